@@ -0,0 +1,84 @@
+use criterion::{black_box, criterion_group, criterion_main, Bencher, BenchmarkId, Criterion};
+use euc::{Buffer2d, Empty, Pipeline, Target, TriangleList};
+use std::time::Duration;
+use vek::*;
+
+struct Quad {
+    offset: Vec2<f32>,
+    color: u32,
+}
+
+impl<'r> Pipeline<'r> for Quad {
+    type Vertex = Vec2<f32>;
+    type VertexData = f32;
+    type Primitives = TriangleList;
+    type Fragment = u32;
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let pos = *pos * 0.05 + self.offset;
+        ([pos.x, pos.y, 0.0, 1.0], 0.0)
+    }
+
+    #[inline(always)]
+    fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+        self.color
+    }
+
+    #[inline(always)]
+    fn blend(&self, _old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+        new
+    }
+}
+
+const QUAD: &[Vec2<f32>] = &[
+    Vec2::new(-1.0, -1.0),
+    Vec2::new(1.0, -1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(-1.0, -1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(-1.0, 1.0),
+];
+
+// Many small draw calls per frame, as is typical of UIs. This measures the fixed overhead of each call to `render`.
+fn small_draws_benchmark(b: &mut Bencher, &draws: &usize) {
+    let [w, h] = [640, 480];
+
+    let mut color = Buffer2d::fill([w, h], 0x0);
+
+    b.iter(|| {
+        color.clear(0x0);
+
+        for i in 0..draws {
+            let t = i as f32 / draws as f32;
+            Quad {
+                offset: Vec2::new(t * 1.8 - 0.9, (t * 37.0).sin() * 0.9),
+                color: i as u32,
+            }
+            .render(QUAD, &mut color, &mut Empty::depth());
+        }
+
+        black_box(&mut color);
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    for draws in [1, 10, 200] {
+        c.bench_with_input(
+            BenchmarkId::new("small_draws", draws),
+            &draws,
+            |b, draws| small_draws_benchmark(b, draws),
+        );
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .sample_size(32)
+        .warm_up_time(Duration::from_millis(1000));
+    targets = criterion_benchmark
+}
+
+criterion_main!(benches);
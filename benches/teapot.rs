@@ -2,7 +2,7 @@ use criterion::{black_box, criterion_group, criterion_main, Bencher, BenchmarkId
 use derive_more::{Add, Mul};
 use euc::{
     Buffer2d, Clamped, CullMode, DepthMode, Empty, Linear, Pipeline, PixelMode, Sampler, Target,
-    Texture, TriangleList, Unit,
+    Texture, Tiling, TriangleList, Unit,
 };
 use std::time::Duration;
 use vek::*;
@@ -58,6 +58,7 @@ struct Teapot<'r> {
     shadow: Clamped<Linear<&'r Buffer2d<f32>>>,
     light_vp: Mat4<f32>,
     cam_pos: Vec3<f32>,
+    tiling: Option<Tiling>,
 }
 
 #[derive(Add, Mul, Clone)]
@@ -79,6 +80,11 @@ impl<'r> Pipeline<'r> for Teapot<'r> {
         DepthMode::LESS_WRITE
     }
 
+    #[inline(always)]
+    fn tiling(&self) -> Option<Tiling> {
+        self.tiling
+    }
+
     #[inline(always)]
     fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
         let wpos = self.m * Vec4::from_point(Vec3::from(vertex.position()));
@@ -141,7 +147,7 @@ impl<'r> Pipeline<'r> for Teapot<'r> {
     }
 }
 
-fn teapot_benchmark(b: &mut Bencher, &[width, height]: &[usize; 2]) {
+fn teapot_benchmark(b: &mut Bencher, &[width, height]: &[usize; 2], tiling: Option<Tiling>) {
     let [w, h] = [width, height];
 
     let mut color = Buffer2d::fill([w, h], 0x0);
@@ -200,6 +206,7 @@ fn teapot_benchmark(b: &mut Bencher, &[width, height]: &[usize; 2]) {
             shadow: (&shadow).linear().clamped(),
             light_vp: light_vp,
             cam_pos: v.inverted().mul_point(Vec3::zero()),
+            tiling,
         }
         .render(model.vertices(), &mut color, &mut depth);
 
@@ -220,7 +227,16 @@ fn criterion_benchmark(c: &mut Criterion) {
         c.bench_with_input(
             BenchmarkId::new("teapot", format!("{size:?}")),
             &size,
-            |b, size| teapot_benchmark(b, size),
+            |b, size| teapot_benchmark(b, size, None),
+        );
+    }
+
+    // Large targets are where rasterizing one tile at a time should help the most
+    for (name, tiling) in [("scanline", None), ("tiled", Some(Tiling::DEFAULT))] {
+        c.bench_with_input(
+            BenchmarkId::new("teapot_2048x1600", name),
+            &tiling,
+            |b, tiling| teapot_benchmark(b, &[2048, 1600], *tiling),
         );
     }
 }
@@ -0,0 +1,240 @@
+use derive_more::{Add, Mul};
+use euc::{
+    Buffer2d, CubeMap, CullMode, DepthMode, Empty, Linear, PackedFormat, Pipeline, Sampler, Target,
+    Texture, ToPacked, TriangleList,
+};
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+use vek::*;
+
+type Environment<'r> = CubeMap<Linear<&'r Buffer2d<Rgba<f32>>>>;
+
+/// Find the direction that a point on a face of a cube map corresponds to (the inverse of
+/// [`CubeMap::face_coords`]).
+fn face_dir(face: usize, s: f32, t: f32) -> Vec3<f32> {
+    let (sc, tc) = (s * 2.0 - 1.0, t * 2.0 - 1.0);
+    match face {
+        0 => Vec3::new(1.0, -tc, -sc),
+        1 => Vec3::new(-1.0, -tc, sc),
+        2 => Vec3::new(sc, 1.0, tc),
+        3 => Vec3::new(sc, -1.0, -tc),
+        4 => Vec3::new(sc, -tc, 1.0),
+        _ => Vec3::new(-sc, -tc, -1.0),
+    }
+}
+
+/// A procedural sky with a sun, above a checkered floor.
+fn environment(dir: Vec3<f32>) -> Rgba<f32> {
+    let dir = dir.normalized();
+    let sun_dir = Vec3::new(0.4, 0.6, -0.7).normalized();
+    if dir.y > 0.0 {
+        let sky = Rgba::lerp(
+            Rgba::new(0.8, 0.9, 1.0, 1.0),
+            Rgba::new(0.2, 0.4, 0.9, 1.0),
+            dir.y.sqrt(),
+        );
+        sky + Rgba::new(1.0, 0.9, 0.6, 0.0) * dir.dot(sun_dir).max(0.0).powf(200.0) * 4.0
+    } else {
+        // Project the direction onto a floor below the origin
+        let floor = Vec2::new(dir.x, dir.z) / -dir.y;
+        let checker = ((floor.x.floor() + floor.y.floor()) as i32).rem_euclid(2) as f32;
+        let fog = (-dir.y).powf(0.3);
+        Rgba::lerp(
+            Rgba::new(0.8, 0.9, 1.0, 1.0),
+            Rgba::new(0.3, 0.3, 0.35, 1.0) + Rgba::new(0.4, 0.4, 0.4, 0.0) * checker,
+            fog,
+        )
+    }
+}
+
+fn to_bgra(rgba: Rgba<f32>) -> u32 {
+    // The window's framebuffer uses BGRA format
+    rgba.into_array().to_packed(PackedFormat::Bgra8)
+}
+
+/// Draws the environment behind everything else with a single triangle covering the screen.
+struct Skybox<'r> {
+    inv_vp: Mat4<f32>,
+    env: &'r Environment<'r>,
+}
+
+impl<'r> Pipeline<'r> for Skybox<'r> {
+    type Vertex = [f32; 2];
+    type VertexData = Vec2<f32>;
+    type Primitives = TriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn rasterizer_config(&self) -> CullMode {
+        CullMode::None
+    }
+
+    #[inline(always)]
+    fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        ([pos[0], pos[1], 1.0, 1.0], Vec2::from(*pos))
+    }
+
+    #[inline(always)]
+    fn fragment(&self, ndc: Self::VertexData) -> Self::Fragment {
+        let near = self.inv_vp * Vec4::new(ndc.x, ndc.y, 0.0, 1.0);
+        let far = self.inv_vp * Vec4::new(ndc.x, ndc.y, 1.0, 1.0);
+        let dir = far.xyz() / far.w - near.xyz() / near.w;
+        self.env.sample(dir.into_array())
+    }
+
+    #[inline(always)]
+    fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
+        to_bgra(rgba)
+    }
+}
+
+struct Teapot<'r> {
+    m: Mat4<f32>,
+    vp: Mat4<f32>,
+    cam_pos: Vec3<f32>,
+    env: &'r Environment<'r>,
+}
+
+#[derive(Add, Mul, Clone)]
+struct VertexData {
+    wpos: Vec3<f32>,
+    wnorm: Vec3<f32>,
+}
+
+impl<'r> Pipeline<'r> for Teapot<'r> {
+    type Vertex = wavefront::Vertex<'r>;
+    type VertexData = VertexData;
+    type Primitives = TriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn depth_mode(&self) -> DepthMode {
+        DepthMode::LESS_WRITE
+    }
+
+    #[inline(always)]
+    fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let wpos = self.m * Vec4::from_point(Vec3::from(vertex.position()));
+        let wnorm = self.m * Vec4::from_direction(-Vec3::from(vertex.normal().unwrap()));
+        (
+            (self.vp * wpos).into_array(),
+            VertexData {
+                wpos: wpos.xyz(),
+                wnorm: wnorm.xyz(),
+            },
+        )
+    }
+
+    #[inline(always)]
+    fn fragment(&self, VertexData { wpos, wnorm }: Self::VertexData) -> Self::Fragment {
+        let wnorm = wnorm.normalized();
+        let view_dir = (wpos - self.cam_pos).normalized();
+
+        // A metallic surface that reflects more of the environment at glancing angles (Schlick's approximation)
+        let reflected = self.env.sample(view_dir.reflected(wnorm).into_array());
+        let fresnel = 0.6 + 0.4 * (1.0 + view_dir.dot(wnorm)).max(0.0).powf(5.0);
+        let tint = Rgba::new(1.0, 0.85, 0.6, 1.0);
+        reflected * tint * fresnel
+    }
+
+    #[inline(always)]
+    fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
+        to_bgra(rgba)
+    }
+}
+
+fn main() {
+    let [w, h] = [1280, 960];
+
+    let mut color = Buffer2d::fill([w, h], 0x0);
+    let mut depth = Buffer2d::fill([w, h], 1.0);
+
+    // Generate each face of the environment cube map
+    let res = 256;
+    let faces = (0..6)
+        .map(|face| {
+            let mut tex = Buffer2d::fill([res; 2], Rgba::zero());
+            for y in 0..res {
+                for x in 0..res {
+                    let [s, t] = [x, y].map(|e| (e as f32 + 0.5) / res as f32);
+                    *tex.get_mut([x, y]) = environment(face_dir(face, s, t));
+                }
+            }
+            tex
+        })
+        .collect::<Vec<_>>();
+    let env = CubeMap::new(std::array::from_fn(|face| (&faces[face]).linear()));
+
+    let model = wavefront::Obj::from_file("examples/data/teapot.obj").unwrap();
+
+    let mut win = Window::new("Cube map", w, h, WindowOptions::default()).unwrap();
+
+    let mut ori = Vec2::new(-0.25, 0.0);
+    let mut dist = 4.5;
+    let mut old_mouse_pos = (0.0, 0.0);
+
+    let mut i = 0;
+    let init = std::time::Instant::now();
+    while win.is_open() && !win.is_key_down(Key::Escape) {
+        let start_time = std::time::Instant::now();
+
+        // Clear the depth target ready for the next frame (the skybox covers the colour target)
+        depth.clear(1.0);
+
+        // Update camera as the mouse moves
+        let mouse_pos = win.get_mouse_pos(MouseMode::Pass).unwrap_or_default();
+        if win.get_mouse_down(MouseButton::Left) {
+            ori -= Vec2::new(mouse_pos.1 - old_mouse_pos.1, mouse_pos.0 - old_mouse_pos.0) * 0.003;
+        }
+        if win.get_mouse_down(MouseButton::Right) {
+            dist = (dist + (mouse_pos.1 - old_mouse_pos.1) as f32 * 0.01)
+                .max(1.0)
+                .min(20.0);
+        }
+        old_mouse_pos = mouse_pos;
+
+        // Set up the camera matrix
+        let p = Mat4::perspective_fov_lh_zo(1.3, w as f32, h as f32, 0.01, 100.0);
+        let v = Mat4::<f32>::identity()
+            * Mat4::translation_3d(Vec3::new(0.0, 0.0, dist))
+            * Mat4::rotation_x(ori.x)
+            * Mat4::rotation_y(ori.y);
+        let vp = p * v;
+        // Set up the teapot matrix
+        let m = Mat4::rotation_y(init.elapsed().as_secs_f32() * 0.5)
+            * Mat4::rotation_x(core::f32::consts::PI);
+
+        // Environment pass
+        Skybox {
+            inv_vp: vp.inverted(),
+            env: &env,
+        }
+        .render(
+            &[[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]],
+            &mut color,
+            &mut Empty::depth(),
+        );
+
+        // Teapot pass
+        Teapot {
+            m,
+            vp,
+            cam_pos: v.inverted().mul_point(Vec3::zero()),
+            env: &env,
+        }
+        .render(model.vertices(), &mut color, &mut depth);
+
+        win.update_with_buffer(color.raw(), w, h).unwrap();
+
+        if i % 60 == 0 {
+            let elapsed = start_time.elapsed();
+            win.set_title(&format!(
+                "Cube map (Time = {:?}, FPS = {})",
+                elapsed,
+                1.0 / elapsed.as_secs_f32()
+            ));
+        }
+        i += 1;
+    }
+}
@@ -0,0 +1,167 @@
+use derive_more::{Add, Mul};
+use euc::{
+    motion_blur::{blur_into, neighbor_max, tile_max, MotionBlurParams},
+    Buffer2d, DepthMode, IndexedVertices, PackedFormat, Pipeline, Target, Texture, ToPacked,
+    TriangleList,
+};
+use minifb::{Key, Window, WindowOptions};
+use vek::*;
+
+struct Cube {
+    mvp: Mat4<f32>,
+    prev_mvp: Mat4<f32>,
+    size: Vec2<f32>,
+}
+
+#[derive(Add, Mul, Clone)]
+struct VertexData {
+    color: Rgba<f32>,
+    pos: Vec4<f32>,
+    prev_pos: Vec4<f32>,
+}
+
+#[derive(Add, Mul, Clone)]
+struct GBuffer {
+    color: Rgba<f32>,
+    vel: Vec2<f32>,
+}
+
+impl<'r> Pipeline<'r> for Cube {
+    type Vertex = (Vec4<f32>, Rgba<f32>);
+    type VertexData = VertexData;
+    type Primitives = TriangleList;
+    type Pixel = GBuffer;
+    type Fragment = GBuffer;
+
+    #[inline(always)]
+    fn depth_mode(&self) -> DepthMode {
+        DepthMode::LESS_WRITE
+    }
+
+    #[inline(always)]
+    fn vertex(&self, (pos, color): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let prev_pos = self.prev_mvp * *pos;
+        let pos = self.mvp * *pos;
+        (
+            pos.into_array(),
+            VertexData {
+                color: *color,
+                pos,
+                prev_pos,
+            },
+        )
+    }
+
+    #[inline(always)]
+    fn fragment(
+        &self,
+        VertexData {
+            color,
+            pos,
+            prev_pos,
+        }: Self::VertexData,
+    ) -> Self::Fragment {
+        // Screen-space velocity, in pixels, since the previous frame
+        let ndc_vel = pos.xy() / pos.w - prev_pos.xy() / prev_pos.w;
+        GBuffer {
+            color,
+            vel: ndc_vel * Vec2::new(0.5, -0.5) * self.size,
+        }
+    }
+
+    fn blend(&self, _: Self::Pixel, frag: Self::Fragment) -> Self::Pixel {
+        frag
+    }
+}
+
+const R: Rgba<f32> = Rgba::new(1.0, 0.0, 0.0, 1.0);
+const Y: Rgba<f32> = Rgba::new(1.0, 1.0, 0.0, 1.0);
+const G: Rgba<f32> = Rgba::new(0.0, 1.0, 0.0, 1.0);
+const B: Rgba<f32> = Rgba::new(0.0, 0.0, 1.0, 1.0);
+
+const VERTICES: &[(Vec4<f32>, Rgba<f32>)] = &[
+    (Vec4::new(-1.0, -1.0, -1.0, 1.0), R),
+    (Vec4::new(-1.0, -1.0, 1.0, 1.0), Y),
+    (Vec4::new(-1.0, 1.0, -1.0, 1.0), G),
+    (Vec4::new(-1.0, 1.0, 1.0, 1.0), B),
+    (Vec4::new(1.0, -1.0, -1.0, 1.0), B),
+    (Vec4::new(1.0, -1.0, 1.0, 1.0), G),
+    (Vec4::new(1.0, 1.0, -1.0, 1.0), Y),
+    (Vec4::new(1.0, 1.0, 1.0, 1.0), R),
+];
+
+const INDICES: &[usize] = &[
+    0, 3, 2, 0, 1, 3, // -x
+    7, 4, 6, 5, 4, 7, // +x
+    5, 0, 4, 1, 0, 5, // -y
+    2, 7, 6, 2, 3, 7, // +y
+    0, 6, 4, 0, 2, 6, // -z
+    7, 1, 5, 3, 1, 7, // +z
+];
+
+fn main() {
+    let [w, h]: [usize; 2] = [800, 600];
+    let params = MotionBlurParams::default();
+    let tiles = [w.div_ceil(params.tile), h.div_ceil(params.tile)];
+
+    let background = GBuffer {
+        color: Rgba::new(0.1, 0.1, 0.1, 1.0),
+        vel: Vec2::zero(),
+    };
+    let mut gbuffer = Buffer2d::fill([w, h], background.clone());
+    let mut depth = Buffer2d::fill([w, h], 1.0);
+    let mut tile_vel = Buffer2d::fill(tiles, [0.0; 2]);
+    let mut neighbor_vel = Buffer2d::fill(tiles, [0.0; 2]);
+    let mut blurred = Buffer2d::fill([w, h], Rgba::zero());
+    let mut color = Buffer2d::fill([w, h], 0);
+
+    let mut win = Window::new("Motion Blur", w, h, WindowOptions::default()).unwrap();
+
+    let mvp_at = |i: usize| {
+        // The camera sways from side to side, quickly enough to produce visible blur
+        let t = i as f32 * 0.05;
+        Mat4::perspective_fov_lh_zo(1.3, w as f32, h as f32, 0.01, 100.0)
+            * Mat4::translation_3d(Vec3::new(t.sin() * 1.5, 0.0, 4.0))
+            * Mat4::rotation_x(t * 0.3)
+            * Mat4::rotation_y(t * 0.7)
+            * Mat4::scaling_3d(Vec3::new(1.0, -1.0, 1.0))
+    };
+
+    let mut i = 0;
+    while win.is_open() && !win.is_key_down(Key::Escape) {
+        gbuffer.clear(background.clone());
+        depth.clear(1.0);
+
+        Cube {
+            mvp: mvp_at(i + 1),
+            prev_mvp: mvp_at(i),
+            size: Vec2::new(w as f32, h as f32),
+        }
+        .render(
+            IndexedVertices::new(INDICES, VERTICES),
+            &mut gbuffer,
+            &mut depth,
+        );
+
+        // Split the G-buffer into its components
+        let frame = Buffer2d::from_texture(&(&gbuffer).map(|g: GBuffer| g.color));
+        let vel = Buffer2d::from_texture(&(&gbuffer).map(|g: GBuffer| g.vel.into_array()));
+
+        tile_max(&vel, params.tile, &mut tile_vel);
+        neighbor_max(&tile_vel, &mut neighbor_vel);
+        blur_into(&frame, &depth, &vel, &neighbor_vel, &mut blurred, params);
+
+        color
+            .raw_mut()
+            .iter_mut()
+            .zip(blurred.raw())
+            .for_each(|(px, rgba)| {
+                // The window's framebuffer uses BGRA format
+                *px = rgba.into_array().to_packed(PackedFormat::Bgra8);
+            });
+
+        win.update_with_buffer(color.raw(), w, h).unwrap();
+
+        i += 1;
+    }
+}
@@ -0,0 +1,122 @@
+use euc::{
+    primitives::PrimitiveKind, Buffer2d, Empty, Geometry, LineList, LineTriangleList, PackedFormat,
+    Pipeline, Target, ToPacked, TriangleList, Unit,
+};
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+use vek::*;
+
+struct Wireframe {
+    mvp: Mat4<f32>,
+}
+
+impl<'r> Pipeline<'r> for Wireframe {
+    type Vertex = wavefront::Vertex<'r>;
+    type VertexData = Unit;
+    type Primitives = LineTriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let pos = Vec4::from_point(Vec3::from(vertex.position()));
+        ((self.mvp * pos).into_array(), Unit)
+    }
+
+    #[inline(always)]
+    fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+        Rgba::new(0.3, 0.3, 0.3, 1.0)
+    }
+
+    #[inline(always)]
+    fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
+        // The window's framebuffer uses BGRA format
+        rgba.into_array().to_packed(PackedFormat::Bgra8)
+    }
+}
+
+/// Draws the normal of each face of a mesh as a line from its centre, using the geometry stage to turn each triangle
+/// into a line.
+struct Normals {
+    mvp: Mat4<f32>,
+    length: f32,
+}
+
+impl<'r> Pipeline<'r> for Normals {
+    type Vertex = wavefront::Vertex<'r>;
+    // The model-space position of the vertex, from which the normal of each face is found
+    type VertexData = Vec3<f32>;
+    type Primitives = Geometry<TriangleList, LineList>;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let pos = Vec3::from(vertex.position());
+        ((self.mvp * Vec4::from_point(pos)).into_array(), pos)
+    }
+
+    #[inline(always)]
+    fn geometry<O>(&self, [(_, a), (_, b), (_, c)]: [([f32; 4], Vec3<f32>); 3], output: O)
+    where
+        O: FnMut([([f32; 4], Vec3<f32>); 2]),
+    {
+        let centre = (a + b + c) / 3.0;
+        let normal = (b - a).cross(c - a).try_normalized().unwrap_or_default();
+        let end = centre + normal * self.length;
+        let vertex = |pos: Vec3<f32>| ((self.mvp * Vec4::from_point(pos)).into_array(), pos);
+        LineList::assemble([vertex(centre), vertex(end)], output);
+    }
+
+    #[inline(always)]
+    fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+        Rgba::red()
+    }
+
+    #[inline(always)]
+    fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
+        rgba.into_array().to_packed(PackedFormat::Bgra8)
+    }
+}
+
+fn main() {
+    let [w, h] = [1280, 960];
+
+    let mut color = Buffer2d::fill([w, h], 0x0);
+
+    let model = wavefront::Obj::from_file("examples/data/teapot.obj").unwrap();
+
+    let mut win = Window::new("Normals", w, h, WindowOptions::default()).unwrap();
+
+    let mut ori = Vec2::new(0.0, 0.0);
+    let mut dist = 6.0;
+    let mut old_mouse_pos = (0.0, 0.0);
+
+    while win.is_open() && !win.is_key_down(Key::Escape) {
+        // Clear the render targets ready for the next frame
+        color.clear(0x0);
+
+        // Update camera as the mouse moves
+        let mouse_pos = win.get_mouse_pos(MouseMode::Pass).unwrap_or_default();
+        if win.get_mouse_down(MouseButton::Left) {
+            ori -= Vec2::new(mouse_pos.1 - old_mouse_pos.1, mouse_pos.0 - old_mouse_pos.0) * 0.003;
+        }
+        if win.get_mouse_down(MouseButton::Right) {
+            dist = (dist + (mouse_pos.1 - old_mouse_pos.1) as f32 * 0.01).clamp(1.0, 20.0);
+        }
+        old_mouse_pos = mouse_pos;
+
+        // Set up the camera and teapot matrices
+        let p = Mat4::perspective_fov_lh_zo(1.3, w as f32, h as f32, 0.01, 100.0);
+        let v = Mat4::<f32>::identity()
+            * Mat4::translation_3d(Vec3::new(0.0, 0.0, dist))
+            * Mat4::rotation_x(ori.x)
+            * Mat4::rotation_y(ori.y);
+        let m = Mat4::<f32>::rotation_x(core::f32::consts::PI);
+        let mvp = p * v * m;
+
+        Wireframe { mvp }.render(model.vertices(), &mut color, &mut Empty::depth());
+        Normals { mvp, length: 0.1 }.render(model.vertices(), &mut color, &mut Empty::depth());
+
+        win.update_with_buffer(color.raw(), w, h).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use derive_more::{Add, Mul};
+use euc::{
+    Buffer2d, CullMode, DepthMode, FromPacked, Nearest, PackedFormat, Pipeline, ReadOnly, Sampler,
+    Target, Texture, ToPacked, TriangleList,
+};
+use minifb::{Key, Window, WindowOptions};
+use vek::*;
+
+const NEAR: f32 = 0.1;
+const FAR: f32 = 100.0;
+
+/// Convert a depth from the depth target back into a distance from the camera.
+fn linearize_depth(z: f32) -> f32 {
+    NEAR * FAR / (FAR - z * (FAR - NEAR))
+}
+
+/// The opaque floor, which particles intersect with.
+struct Floor {
+    vp: Mat4<f32>,
+}
+
+impl<'r> Pipeline<'r> for Floor {
+    type Vertex = Vec3<f32>;
+    type VertexData = Vec2<f32>;
+    type Primitives = TriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    fn depth_mode(&self) -> DepthMode {
+        DepthMode::LESS_WRITE
+    }
+
+    fn rasterizer_config(&self) -> CullMode {
+        CullMode::None
+    }
+
+    #[inline(always)]
+    fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        (
+            (self.vp * Vec4::from_point(*pos)).into_array(),
+            Vec2::new(pos.x, pos.z),
+        )
+    }
+
+    #[inline(always)]
+    fn fragment(&self, pos: Self::VertexData) -> Self::Fragment {
+        let checker = (pos.x.floor() + pos.y.floor()).rem_euclid(2.0);
+        Rgba::new(0.3, 0.35, 0.4, 1.0) + Rgba::new(0.1, 0.1, 0.1, 0.0) * checker
+    }
+
+    fn blend(&self, _: Self::Pixel, color: Self::Fragment) -> Self::Pixel {
+        color.into_array().to_packed(PackedFormat::Bgra8)
+    }
+}
+
+#[derive(Add, Mul, Clone)]
+struct ParticleData {
+    /// The x, y, and w clip-space coordinates of the fragment.
+    clip: Vec3<f32>,
+    /// The position of the fragment within the particle, from -1 to 1 along each axis.
+    offset: Vec2<f32>,
+}
+
+/// Camera-facing puffs of smoke that fade out as they approach the scene behind them.
+struct Particles<'a> {
+    view: Mat4<f32>,
+    proj: Mat4<f32>,
+    size: f32,
+    /// The depth of the opaque scene, which is also the depth target that particles are tested against.
+    scene_depth: Nearest<&'a Buffer2d<f32>>,
+}
+
+impl<'r> Pipeline<'r> for Particles<'_> {
+    type Vertex = (Vec3<f32>, Vec2<f32>);
+    type VertexData = ParticleData;
+    type Primitives = TriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    fn depth_mode(&self) -> DepthMode {
+        // Particles are hidden by the scene, but don't hide each other
+        DepthMode::LESS_PASS
+    }
+
+    fn rasterizer_config(&self) -> CullMode {
+        CullMode::None
+    }
+
+    #[inline(always)]
+    fn vertex(&self, (center, offset): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        // Offset the corners in view space, so that the quad faces the camera
+        let view_pos = self.view * Vec4::from_point(*center)
+            + Vec4::new(offset.x, offset.y, 0.0, 0.0) * self.size;
+        let clip = self.proj * view_pos;
+        (
+            clip.into_array(),
+            ParticleData {
+                clip: Vec3::new(clip.x, clip.y, clip.w),
+                offset: *offset,
+            },
+        )
+    }
+
+    #[inline(always)]
+    fn fragment(&self, ParticleData { clip, offset }: Self::VertexData) -> Self::Fragment {
+        // Find the depth of the scene behind the fragment (for a perspective projection, w is the distance from the
+        // camera)
+        let uv = clip.xy() / clip.z * 0.5 + 0.5;
+        let scene_dist = linearize_depth(self.scene_depth.sample(uv.into_array()));
+        let softness = ((scene_dist - clip.z) / 0.3).clamp(0.0, 1.0);
+
+        let falloff = (1.0 - offset.magnitude()).max(0.0);
+        Rgba::new(0.9, 0.9, 0.95, falloff * softness * 0.8)
+    }
+
+    fn blend(&self, old: Self::Pixel, color: Self::Fragment) -> Self::Pixel {
+        let old = Rgba::from(<[f32; 4]>::from_packed(old, PackedFormat::Bgra8));
+        (old + (color - old) * color.a)
+            .into_array()
+            .to_packed(PackedFormat::Bgra8)
+    }
+}
+
+fn main() {
+    let [w, h] = [800, 600];
+
+    let mut color = Buffer2d::fill([w, h], 0);
+    let mut depth = Buffer2d::fill([w, h], 1.0);
+
+    let floor = [
+        Vec3::new(-8.0, 0.0, -8.0),
+        Vec3::new(8.0, 0.0, -8.0),
+        Vec3::new(8.0, 0.0, 8.0),
+        Vec3::new(-8.0, 0.0, -8.0),
+        Vec3::new(8.0, 0.0, 8.0),
+        Vec3::new(-8.0, 0.0, 8.0),
+    ];
+    let corners = [
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, -1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, 1.0),
+    ];
+
+    // Flip the y axis, since +y points down the screen
+    let proj = Mat4::scaling_3d(Vec3::new(1.0, -1.0, 1.0))
+        * Mat4::perspective_fov_lh_zo(1.3, w as f32, h as f32, NEAR, FAR);
+
+    let mut win = Window::new("Soft particles", w, h, WindowOptions::default()).unwrap();
+
+    let mut i = 0;
+    while win.is_open() && !win.is_key_down(Key::Escape) {
+        let t = i as f32 * 0.01;
+        let cam_pos = Vec3::new(t.cos() * 5.0, 1.5, t.sin() * 5.0);
+        let view = Mat4::look_at_lh(cam_pos, Vec3::zero(), Vec3::unit_y());
+
+        color.clear(0xFF101820);
+        depth.clear(1.0);
+
+        // Opaque pass, which writes the depth of the scene
+        Floor { vp: proj * view }.render(&floor, &mut color, &mut depth);
+
+        // Particles that bob up and down through the floor, each drawn as a camera-facing quad
+        let particles = (0..24).flat_map(|j| {
+            let a = j as f32 * 0.9;
+            let center = Vec3::new(
+                a.cos() * (1.0 + j as f32 * 0.12),
+                (t * 2.0 + a).sin() * 0.4,
+                a.sin() * 2.5,
+            );
+            corners.map(|corner| (center, corner))
+        });
+
+        // The particles sample the depth target while they're tested against it, so it's only borrowed immutably
+        Particles {
+            view,
+            proj,
+            size: 0.6,
+            scene_depth: (&depth).nearest(),
+        }
+        .render(particles, &mut color, &mut ReadOnly::new(&depth));
+
+        win.update_with_buffer(color.raw(), w, h).unwrap();
+
+        i += 1;
+    }
+}
@@ -1,4 +1,4 @@
-use euc::{Buffer2d, IndexedVertices, Pipeline, Target, TriangleList};
+use euc::{Buffer2d, IndexedVertices, PackedFormat, Pipeline, Target, ToPacked, TriangleList};
 use minifb::{Key, Window, WindowOptions};
 use vek::*;
 
@@ -24,7 +24,8 @@ impl<'r> Pipeline<'r> for Cube {
     }
 
     fn blend(&self, _: Self::Pixel, color: Self::Fragment) -> Self::Pixel {
-        u32::from_le_bytes((color * 255.0).as_().into_array())
+        // The window's framebuffer uses BGRA format
+        color.into_array().to_packed(PackedFormat::Bgra8)
     }
 }
 
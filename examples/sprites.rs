@@ -0,0 +1,108 @@
+use euc::{
+    Buffer2d, Empty, PackedFormat, Pipeline, Sampler, Target, Texture, ToPacked, TriangleList,
+};
+use minifb::{Key, Window, WindowOptions};
+use vek::{Rgba, Vec2};
+
+struct Sprite<S> {
+    pos: Vec2<f32>,
+    size: f32,
+    sampler: S,
+}
+
+impl<'r, S: Sampler<2, Index = f32, Sample = Rgba<f32>>> Pipeline<'r> for Sprite<S> {
+    type Vertex = Vec2<f32>;
+    type VertexData = Vec2<f32>;
+    type Primitives = TriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    #[inline]
+    fn vertex(&self, corner: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        // Sprites are square, so they're narrower in clip space than they are tall
+        let pos = self.pos + (*corner * 2.0 - 1.0) * self.size * Vec2::new(0.75, 1.0);
+        (
+            [pos.x, pos.y, 0.0, 1.0],
+            Vec2::new(corner.x, 1.0 - corner.y),
+        )
+    }
+
+    #[inline]
+    fn fragment(&self, uv: Self::VertexData) -> Self::Fragment {
+        self.sampler.sample(uv.into_array())
+    }
+
+    fn blend(&self, old: Self::Pixel, color: Self::Fragment) -> Self::Pixel {
+        // Transparent texels of the sprite leave the background as it is
+        if color.a < 0.5 {
+            old
+        } else {
+            color.into_array().to_packed(PackedFormat::Bgra8)
+        }
+    }
+}
+
+fn main() {
+    let [w, h] = [800, 600];
+
+    let mut color = Buffer2d::fill([w, h], 0);
+
+    // Draw a sprite sheet of four 16x16 sprites, arranged in a 2x2 grid
+    const SPRITE: usize = 16;
+    let mut atlas = Buffer2d::fill([SPRITE * 2; 2], Rgba::<f32>::zero());
+    for y in 0..SPRITE * 2 {
+        for x in 0..SPRITE * 2 {
+            let [sx, sy] = [x % SPRITE, y % SPRITE].map(|e| e as f32 + 0.5 - SPRITE as f32 / 2.0);
+            let texel = match (x / SPRITE, y / SPRITE) {
+                // A red circle
+                (0, 0) if sx * sx + sy * sy < 49.0 => Rgba::new(1.0, 0.2, 0.2, 1.0),
+                // A green diamond
+                (1, 0) if sx.abs() + sy.abs() < 7.5 => Rgba::new(0.2, 1.0, 0.2, 1.0),
+                // A blue cross
+                (0, 1) if sx.abs().min(sy.abs()) < 2.0 => Rgba::new(0.2, 0.4, 1.0, 1.0),
+                // A yellow and white checkerboard
+                (1, 1) if (x / 4 + y / 4) % 2 == 0 => Rgba::new(1.0, 1.0, 0.2, 1.0),
+                (1, 1) => Rgba::white(),
+                _ => Rgba::zero(),
+            };
+            atlas.write(x, y, texel);
+        }
+    }
+
+    // Each sprite is a slice of the sprite sheet, so texture coordinates between 0 and 1 cover just that sprite
+    let sprites = [[0, 0], [1, 0], [0, 1], [1, 1]].map(|[x, y]| {
+        (&atlas)
+            .slice([x * SPRITE, y * SPRITE], [SPRITE; 2])
+            .nearest()
+    });
+
+    let corners = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+    ];
+
+    let mut win = Window::new("Sprites", w, h, WindowOptions::default()).unwrap();
+
+    let mut i = 0;
+    while win.is_open() && !win.is_key_down(Key::Escape) {
+        color.clear(0xFF203040);
+
+        for (j, sampler) in sprites.iter().enumerate() {
+            let t = i as f32 * 0.02 + j as f32 * core::f32::consts::FRAC_PI_2;
+            Sprite {
+                pos: Vec2::new(t.cos(), t.sin()) * 0.5,
+                size: 0.2,
+                sampler,
+            }
+            .render(&corners, &mut color, &mut Empty::depth());
+        }
+
+        win.update_with_buffer(color.raw(), w, h).unwrap();
+
+        i += 1;
+    }
+}
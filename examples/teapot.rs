@@ -1,9 +1,9 @@
-use derive_more::{Add, Mul};
 use euc::{
-    Buffer2d, Clamped, CullMode, DepthMode, Empty, Linear, Pipeline, PixelMode, Sampler, Target,
-    Texture, TriangleList, Unit,
+    impl_weighted_sum, Buffer2d, Comparison, CullMode, DepthBias, DepthMode, Empty, PackedFormat,
+    Pipeline, PixelMode, Target, Texture, ToPacked, TriangleList, Unit,
 };
 use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+use std::cmp::Ordering;
 use vek::*;
 
 struct TeapotShadow {
@@ -27,6 +27,12 @@ impl<'r> Pipeline<'r> for TeapotShadow {
         DepthMode::LESS_WRITE
     }
 
+    // Push the shadow map away from the light to avoid shadow acne, particularly at glancing angles
+    #[inline(always)]
+    fn depth_bias(&self) -> DepthBias {
+        DepthBias::new(0.0001, 1.5)
+    }
+
     #[inline(always)]
     fn rasterizer_config(&self) -> CullMode {
         CullMode::None
@@ -54,18 +60,24 @@ struct Teapot<'r> {
     v: Mat4<f32>,
     p: Mat4<f32>,
     light_pos: Vec3<f32>,
-    shadow: Clamped<Linear<&'r Buffer2d<f32>>>,
+    shadow: Comparison<&'r Buffer2d<f32>>,
     light_vp: Mat4<f32>,
     cam_pos: Vec3<f32>,
 }
 
-#[derive(Add, Mul, Clone)]
+#[derive(Clone)]
 struct VertexData {
     wpos: Vec3<f32>,
     wnorm: Vec3<f32>,
     light_view_pos: Vec3<f32>,
 }
 
+impl_weighted_sum!(VertexData {
+    wpos,
+    wnorm,
+    light_view_pos
+});
+
 impl<'r> Pipeline<'r> for Teapot<'r> {
     type Vertex = wavefront::Vertex<'r>;
     type VertexData = VertexData;
@@ -120,23 +132,19 @@ impl<'r> Pipeline<'r> for Teapot<'r> {
             * 3.0;
 
         // Shadow-mapping
-        let light_depth = self
+        let shadow_uv = light_view_pos.xy() * Vec2::new(1.0, -1.0) * 0.5 + 0.5;
+        let in_light = self
             .shadow
-            .sample((light_view_pos.xy() * Vec2::new(1.0, -1.0) * 0.5 + 0.5).into_array())
-            + 0.0001;
-        let depth = light_view_pos.z;
-        let in_light = depth < light_depth;
+            .sample_compare(shadow_uv.into_array(), light_view_pos.z);
 
-        let light = ambient + if in_light { diffuse + specular } else { 0.0 };
+        let light = ambient + (diffuse + specular) * in_light;
         surf_color * light
     }
 
     #[inline(always)]
     fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
-        let rgba = rgba.map(|e| e.max(0.0).min(1.0) * 255.0).as_();
         // The window's framebuffer uses BGRA format
-        let bgra = Rgba::new(rgba.b, rgba.g, rgba.r, rgba.a);
-        u32::from_le_bytes(bgra.into_array())
+        rgba.into_array().to_packed(PackedFormat::Bgra8)
     }
 }
 
@@ -215,7 +223,7 @@ fn main() {
             v,
             p,
             light_pos,
-            shadow: (&shadow).linear().clamped(),
+            shadow: (&shadow).pcf(Ordering::Less),
             light_vp,
             cam_pos: v.inverted().mul_point(Vec3::zero()),
         }
@@ -1,5 +1,5 @@
-use euc::{Buffer2d, Pipeline, Sampler, Target, Texture, TriangleList};
-use minifb::{Key, Window, WindowOptions};
+use euc::{Buffer2d, PackedFormat, Pipeline, Sampler, Target, Texture, ToPacked, TriangleList};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use vek::{Mat4, Rgba, Vec2, Vec3, Vec4};
 
 struct Cube<'r, S> {
@@ -7,6 +7,7 @@ struct Cube<'r, S> {
     positions: &'r [Vec4<f32>],
     uvs: &'r [Vec2<f32>],
     sampler: S,
+    srgb: bool,
 }
 
 impl<'r, S: Sampler<2, Index = f32, Sample = Rgba<f32>>> Pipeline<'r> for Cube<'r, S> {
@@ -30,7 +31,12 @@ impl<'r, S: Sampler<2, Index = f32, Sample = Rgba<f32>>> Pipeline<'r> for Cube<'
     }
 
     fn blend(&self, _: Self::Pixel, color: Self::Fragment) -> Self::Pixel {
-        u32::from_le_bytes(color.map(|e| e as u8).into_array())
+        // The window's framebuffer uses BGRA format
+        if self.srgb {
+            color.into_array().to_packed_srgb(PackedFormat::Bgra8)
+        } else {
+            color.into_array().to_packed(PackedFormat::Bgra8)
+        }
     }
 }
 
@@ -115,15 +121,38 @@ fn main() {
     let texture = Buffer2d::from_texture(&texture);
 
     // Create a sampler from the texture. Because the underlying texture is a bitmap, we map its texels to a
-    // floating-point color. From here, we allow it to be bilinearly interpolated by the shader.
-    let sampler = texture
-        .map(|pixel| Rgba::from(pixel.0).map(|e: u8| e as f32))
+    // floating-point color (between 0 and 1). From here, we allow it to be bilinearly interpolated by the shader.
+    let sampler = (&texture)
+        .map(|pixel| Rgba::from(pixel.0).map(|e: u8| e as f32 / 255.0))
+        .linear();
+
+    // The texels are actually sRGB-encoded, so the sampler above blends them in the wrong space (darkening the edges
+    // between light and dark regions). Decoding them to linear colour first, then encoding the final colour when
+    // writing to the framebuffer, gives the correct result.
+    let srgb_sampler = (&texture)
+        .map(|pixel| pixel.0)
+        .srgb8::<Rgba<f32>>()
         .linear();
 
     let mut win = Window::new("Texture Mapping", w, h, WindowOptions::default()).unwrap();
 
+    let title = |srgb| {
+        format!(
+            "Texture Mapping ({} sampling, press space to toggle)",
+            if srgb { "sRGB-aware" } else { "naive" }
+        )
+    };
+
+    let mut srgb = true;
+    win.set_title(&title(srgb));
+
     let mut i = 0;
     while win.is_open() && !win.is_key_down(Key::Escape) {
+        if win.is_key_pressed(Key::Space, KeyRepeat::No) {
+            srgb = !srgb;
+            win.set_title(&title(srgb));
+        }
+
         let p = Mat4::perspective_fov_rh_no(1.4, w as f32, h as f32, 0.01, 100.0);
         let v = Mat4::<f32>::translation_3d(Vec3::new(0.0, 0.0, -2.0))
             * Mat4::<f32>::scaling_3d(0.6)
@@ -135,20 +164,29 @@ fn main() {
         color.clear(180);
         depth.clear(1.0);
 
-        let cube = Cube {
-            mvp: p * v * m,
-            positions: &positions,
-            uvs: &uvs,
-            sampler: &sampler,
-        };
-        cube.render(
-            &[
-                0, 3, 1, 1, 3, 2, 4, 5, 7, 5, 6, 7, 8, 11, 9, 9, 11, 10, 12, 13, 15, 13, 14, 15,
-                16, 17, 19, 17, 18, 19, 20, 23, 21, 21, 23, 22,
-            ],
-            &mut color,
-            &mut depth,
-        );
+        let indices = [
+            0, 3, 1, 1, 3, 2, 4, 5, 7, 5, 6, 7, 8, 11, 9, 9, 11, 10, 12, 13, 15, 13, 14, 15, 16,
+            17, 19, 17, 18, 19, 20, 23, 21, 21, 23, 22,
+        ];
+        if srgb {
+            Cube {
+                mvp: p * v * m,
+                positions: &positions,
+                uvs: &uvs,
+                sampler: &srgb_sampler,
+                srgb,
+            }
+            .render(&indices, &mut color, &mut depth);
+        } else {
+            Cube {
+                mvp: p * v * m,
+                positions: &positions,
+                uvs: &uvs,
+                sampler: &sampler,
+                srgb,
+            }
+            .render(&indices, &mut color, &mut depth);
+        }
 
         win.update_with_buffer(color.raw(), w, h).unwrap();
 
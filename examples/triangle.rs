@@ -1,5 +1,4 @@
-use euc::{Buffer2d, Empty, Pipeline, TriangleList};
-use minifb::{Key, Window, WindowOptions};
+use euc::{Empty, ImageTarget, Pipeline, TriangleList};
 use vek::*;
 
 struct Triangle;
@@ -9,7 +8,7 @@ impl<'r> Pipeline<'r> for Triangle {
     type VertexData = Rgba<f32>;
     type Primitives = TriangleList;
     type Fragment = Rgba<f32>;
-    type Pixel = u32;
+    type Pixel = image::Rgba<u8>;
 
     fn vertex(&self, (pos, col): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
         ([pos[0], pos[1], 0.0, 1.0], *col)
@@ -20,25 +19,25 @@ impl<'r> Pipeline<'r> for Triangle {
     }
 
     fn blend(&self, _: Self::Pixel, col: Self::Fragment) -> Self::Pixel {
-        u32::from_le_bytes(col.map(|e| (e * 255.0) as u8).into_array())
+        image::Rgba(col.map(|e| (e.clamp(0.0, 1.0) * 255.0) as u8).into_array())
     }
 }
+
 fn main() {
     let [w, h] = [640, 480];
-    let mut color = Buffer2d::fill([w, h], 0);
-    let mut win = Window::new("Triangle", w, h, WindowOptions::default()).unwrap();
+    let mut image = image::RgbaImage::from_pixel(w, h, image::Rgba([0, 0, 0, 255]));
 
+    // Render straight into the image, without converting from an intermediate buffer
     Triangle.render(
         &[
             ([-1.0, -1.0], Rgba::red()),
             ([1.0, -1.0], Rgba::green()),
             ([0.0, 1.0], Rgba::blue()),
         ],
-        &mut color,
-        &mut Empty::default(),
+        &mut ImageTarget::new(&mut image),
+        &mut Empty::depth(),
     );
 
-    while win.is_open() && !win.is_key_down(Key::Escape) {
-        win.update_with_buffer(color.raw(), w, h).unwrap();
-    }
+    image.save("triangle.png").unwrap();
+    println!("Saved the rendered triangle to triangle.png");
 }
@@ -0,0 +1,171 @@
+use derive_more::{Add, Mul};
+use euc::{
+    procedural::{fbm_with_gradient, gerstner_wave, GerstnerParams},
+    Buffer2d, CullMode, DepthMode, IndexedVertices, PackedFormat, Pipeline, Target, ToPacked,
+    TriangleList,
+};
+use minifb::{Key, Window, WindowOptions};
+use vek::*;
+
+const WAVES: &[GerstnerParams] = &[
+    GerstnerParams {
+        direction: [1.0, 0.2],
+        wavelength: 6.0,
+        amplitude: 0.25,
+        steepness: 0.5,
+        speed: 1.5,
+    },
+    GerstnerParams {
+        direction: [0.3, 1.0],
+        wavelength: 3.1,
+        amplitude: 0.12,
+        steepness: 0.4,
+        speed: 1.1,
+    },
+    GerstnerParams {
+        direction: [-0.7, 0.6],
+        wavelength: 1.7,
+        amplitude: 0.05,
+        steepness: 0.3,
+        speed: 0.8,
+    },
+];
+
+struct Water {
+    vp: Mat4<f32>,
+    t: f32,
+    cam_pos: Vec3<f32>,
+    light_dir: Vec3<f32>,
+}
+
+#[derive(Add, Mul, Clone)]
+struct VertexData {
+    wpos: Vec3<f32>,
+    wnorm: Vec3<f32>,
+}
+
+impl<'r> Pipeline<'r> for Water {
+    type Vertex = Vec2<f32>;
+    type VertexData = VertexData;
+    type Primitives = TriangleList;
+    type Fragment = Rgba<f32>;
+    type Pixel = u32;
+
+    #[inline(always)]
+    fn depth_mode(&self) -> DepthMode {
+        DepthMode::LESS_WRITE
+    }
+
+    #[inline(always)]
+    fn rasterizer_config(&self) -> CullMode {
+        CullMode::None
+    }
+
+    #[inline(always)]
+    fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        // Large-scale swell from the Gerstner waves
+        let (offset, normal) = gerstner_wave(pos.into_array(), self.t, WAVES);
+
+        // Small-scale ripples from noise. Because the noise gradient is analytic, we can perturb the normal without
+        // knowing anything about neighbouring vertices.
+        const RIPPLE_FREQ: f32 = 1.5;
+        const RIPPLE_AMP: f32 = 0.04;
+        let (ripple, grad) = fbm_with_gradient(
+            [pos.x * RIPPLE_FREQ, pos.y * RIPPLE_FREQ, self.t * 0.5],
+            0,
+            3,
+            2.0,
+            0.5,
+        );
+
+        let wpos = Vec3::new(pos.x, 0.0, pos.y)
+            + Vec3::from(offset)
+            + Vec3::unit_y() * ripple * RIPPLE_AMP;
+        let wnorm = (Vec3::from(normal)
+            - Vec3::new(grad[0], 0.0, grad[1]) * RIPPLE_AMP * RIPPLE_FREQ)
+            .normalized();
+
+        (
+            (self.vp * Vec4::from_point(wpos)).into_array(),
+            VertexData { wpos, wnorm },
+        )
+    }
+
+    #[inline(always)]
+    fn fragment(&self, VertexData { wpos, wnorm }: Self::VertexData) -> Self::Fragment {
+        let wnorm = wnorm.normalized();
+        let cam_dir = (self.cam_pos - wpos).normalized();
+
+        let deep = Rgba::new(0.02, 0.12, 0.22, 1.0);
+        let sky = Rgba::new(0.55, 0.75, 0.95, 1.0);
+
+        let diffuse = wnorm.dot(-self.light_dir).max(0.0);
+        let specular = (-self.light_dir)
+            .reflected(wnorm)
+            .dot(-cam_dir)
+            .max(0.0)
+            .powf(40.0);
+        // Schlick's approximation for the amount of sky reflected
+        let fresnel = 0.02 + 0.98 * (1.0 - wnorm.dot(cam_dir).max(0.0)).powf(5.0);
+
+        deep * (0.4 + diffuse * 0.6) * (1.0 - fresnel) + sky * fresnel + Rgba::one() * specular
+    }
+
+    #[inline(always)]
+    fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
+        // The window's framebuffer uses BGRA format
+        rgba.into_array().to_packed(PackedFormat::Bgra8)
+    }
+}
+
+fn main() {
+    let [w, h] = [800, 600];
+
+    let mut color = Buffer2d::fill([w, h], 0);
+    let mut depth = Buffer2d::fill([w, h], 1.0);
+
+    // A flat grid of vertices on the xz plane, displaced by the vertex shader
+    const GRID: usize = 96;
+    const EXTENT: f32 = 24.0;
+    let vertices = (0..GRID * GRID)
+        .map(|i| {
+            Vec2::new((i % GRID) as f32, (i / GRID) as f32) / (GRID - 1) as f32 * EXTENT
+                - EXTENT * 0.5
+        })
+        .collect::<Vec<_>>();
+    let indices = (0..GRID - 1)
+        .flat_map(|z| (0..GRID - 1).map(move |x| z * GRID + x))
+        .flat_map(|i| [i, i + 1, i + GRID, i + 1, i + GRID + 1, i + GRID])
+        .collect::<Vec<_>>();
+
+    let mut win = Window::new("Water", w, h, WindowOptions::default()).unwrap();
+
+    let mut i = 0;
+    while win.is_open() && !win.is_key_down(Key::Escape) {
+        let t = i as f32 / 60.0;
+        let cam_pos = Vec3::new((t * 0.1).sin() * 10.0, 4.0, (t * 0.1).cos() * 10.0);
+
+        let vp = Mat4::<f32>::scaling_3d(Vec3::new(1.0, -1.0, 1.0))
+            * Mat4::perspective_fov_lh_zo(1.3, w as f32, h as f32, 0.01, 100.0)
+            * Mat4::look_at_lh(cam_pos, Vec3::zero(), Vec3::unit_y());
+
+        color.clear(0);
+        depth.clear(1.0);
+
+        Water {
+            vp,
+            t,
+            cam_pos,
+            light_dir: Vec3::new(-1.0, -0.6, 0.4).normalized(),
+        }
+        .render(
+            IndexedVertices::new(&indices, &vertices[..]),
+            &mut color,
+            &mut depth,
+        );
+
+        win.update_with_buffer(color.raw(), w, h).unwrap();
+
+        i += 1;
+    }
+}
@@ -1,4 +1,4 @@
-use euc::{Buffer2d, Empty, LineTriangleList, Pipeline, Target, Unit};
+use euc::{Buffer2d, Empty, LineTriangleList, PackedFormat, Pipeline, Target, ToPacked, Unit};
 use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
 use vek::*;
 
@@ -29,10 +29,8 @@ impl<'r> Pipeline<'r> for Teapot {
 
     #[inline(always)]
     fn blend(&self, _old: Self::Pixel, rgba: Self::Fragment) -> Self::Pixel {
-        let rgba = rgba.map(|e| e.clamped(0.0, 1.0) * 255.0).as_();
         // The window's framebuffer uses BGRA format
-        let bgra = Rgba::new(rgba.b, rgba.g, rgba.r, rgba.a);
-        u32::from_le_bytes(bgra.into_array())
+        rgba.into_array().to_packed(PackedFormat::Bgra8)
     }
 }
 
@@ -81,7 +79,7 @@ fn main() {
         let m = Mat4::<f32>::translation_3d(-teapot_pos) * Mat4::rotation_x(core::f32::consts::PI);
 
         // Colour pass
-        Teapot { m, v, p }.render(model.vertices(), &mut color, &mut Empty::default());
+        Teapot { m, v, p }.render(model.vertices(), &mut color, &mut Empty::depth());
 
         win.update_with_buffer(color.raw(), w, h).unwrap();
 
@@ -0,0 +1,346 @@
+use crate::{
+    buffer::Buffer2d,
+    math::Unit,
+    pipeline::{DepthMode, Parallelism, Pipeline, PixelMode},
+    primitives::TriangleList,
+    rasterizer::CullMode,
+    texture::{Empty, Target, Texture},
+};
+use alloc::vec::Vec;
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// Parameters that control the ambient occlusion produced by [`bake_vertex_ao`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AoBakeParams {
+    /// The width and height, in pixels, of each face of the hemicube rendered around each vertex. Larger values
+    /// produce more accurate results, but take longer to bake.
+    pub resolution: usize,
+    /// The distance that each probe is moved along the normal of its vertex to avoid the vertex being occluded by the
+    /// surface that it belongs to (self-shadowing 'acne').
+    pub bias: f32,
+    /// The distance beyond which occluders are ignored.
+    pub max_distance: f32,
+}
+
+impl Default for AoBakeParams {
+    fn default() -> Self {
+        Self {
+            resolution: 32,
+            bias: 0.001,
+            max_distance: 10.0,
+        }
+    }
+}
+
+/// Bake ambient occlusion for each of the given vertices.
+///
+/// For each vertex, a hemicube (the half of a cube that lies above the surface) of depth-only views is rendered from
+/// the vertex's position along its normal, and the cosine-weighted proportion of the hemisphere that is not occluded
+/// within [`AoBakeParams::max_distance`] is found. The result for each vertex lies between `0.0` (fully occluded) and
+/// `1.0` (unoccluded), and may be multiplied into vertex colours.
+///
+/// `occluder_mesh` should call the function it is given with every triangle (in world space) that may occlude the
+/// vertices. It is called once, before baking begins. Normals do not need to be normalised.
+///
+/// Results are approximate: with the default parameters, they are typically within `0.05` of the exact value.
+/// Vertices are baked in parallel if the `par` feature is enabled.
+///
+/// # Panics
+///
+/// Panics if `positions` and `normals` do not have the same length.
+pub fn bake_vertex_ao(
+    positions: &[[f32; 3]],
+    normals: &[[f32; 3]],
+    occluder_mesh: &impl Fn(&mut dyn FnMut([[f32; 3]; 3])),
+    params: AoBakeParams,
+) -> Vec<f32> {
+    assert_eq!(
+        positions.len(),
+        normals.len(),
+        "Each vertex must have a normal"
+    );
+
+    let mut occluders = Vec::new();
+    occluder_mesh(&mut |tri| occluders.extend(tri));
+
+    let res = params.resolution.max(1);
+    let mut ao = alloc::vec![0.0; positions.len()];
+    let vertices = positions.iter().zip(normals).zip(ao.iter_mut());
+
+    let bake = |depth: &mut Buffer2d<f32>,
+                ((pos, norm), ao): ((&[f32; 3], &[f32; 3]), &mut f32)| {
+        *ao = bake_one(*pos, *norm, &occluders, params, depth);
+    };
+    let scratch = || Buffer2d::fill([res; 2], 1.0);
+
+    #[cfg(not(feature = "par"))]
+    {
+        let mut depth = scratch();
+        vertices.for_each(|v| bake(&mut depth, v));
+    }
+
+    #[cfg(feature = "par")]
+    crate::par::for_each_init(vertices, scratch, bake);
+
+    ao
+}
+
+/// Bake the ambient occlusion of a single vertex, using `depth` as a scratch buffer.
+fn bake_one(
+    pos: [f32; 3],
+    norm: [f32; 3],
+    occluders: &[[f32; 3]],
+    params: AoBakeParams,
+    depth: &mut Buffer2d<f32>,
+) -> f32 {
+    let len = dot(norm, norm).sqrt();
+    if len == 0.0 || !len.is_finite() {
+        return 1.0;
+    }
+    let n = norm.map(|e| e / len);
+    let origin = add(pos, n.map(|e| e * params.bias));
+
+    // Find two tangents that, with the normal, form an orthonormal basis
+    let t = if n[0].abs() < 0.5 {
+        normalize(cross(n, [1.0, 0.0, 0.0]))
+    } else {
+        normalize(cross(n, [0.0, 1.0, 0.0]))
+    };
+    let b = cross(n, t);
+
+    let res = depth.size()[0];
+    let mut visible = 0.0;
+    let mut total = 0.0;
+    // The top face of the hemicube, followed by the four sides (only the upper half of which lies above the surface)
+    for (right, up, forward) in [
+        (t, b, n),
+        (b, n, t),
+        (neg(b), n, neg(t)),
+        (neg(t), n, b),
+        (t, n, neg(b)),
+    ] {
+        depth.clear(1.0);
+        Probe {
+            origin,
+            basis: [right, up, forward],
+            near: params.max_distance * 1.0e-4,
+            far: params.max_distance,
+        }
+        .render(occluders, &mut Empty::default(), depth);
+
+        for y in 0..res {
+            for x in 0..res {
+                // Find the (unnormalised) direction of the pixel in world space
+                let [u, v] = [x, y].map(|e| e as f32 * 2.0 / res as f32 - 1.0);
+                let dir = add(add(right.map(|e| e * u), up.map(|e| e * -v)), forward);
+
+                // The solid angle subtended by a pixel falls off with the cube of its distance and the projected area
+                // with its cosine to the normal
+                let w = dot(dir, n).max(0.0) / (dot(dir, dir) * dot(dir, dir));
+                total += w;
+                if depth.read([x, y]) >= 1.0 {
+                    visible += w;
+                }
+            }
+        }
+    }
+
+    if total > 0.0 {
+        visible / total
+    } else {
+        1.0
+    }
+}
+
+/// A depth-only pipeline that renders one face of a hemicube.
+struct Probe {
+    origin: [f32; 3],
+    basis: [[f32; 3]; 3],
+    near: f32,
+    far: f32,
+}
+
+impl<'r> Pipeline<'r> for Probe {
+    type Vertex = [f32; 3];
+    type VertexData = Unit;
+    type Primitives = TriangleList;
+    type Fragment = Unit;
+    type Pixel = ();
+
+    fn pixel_mode(&self) -> PixelMode {
+        PixelMode::PASS
+    }
+
+    fn depth_mode(&self) -> DepthMode {
+        DepthMode::LESS_WRITE
+    }
+
+    fn rasterizer_config(&self) -> CullMode {
+        CullMode::None
+    }
+
+    fn parallelism(&self) -> Parallelism {
+        // Probes are tiny, so it's better to parallelise across vertices instead
+        Parallelism::DEFAULT.with_max_threads(1)
+    }
+
+    fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let p = sub(*pos, self.origin);
+        let [x, y, z] = self.basis.map(|axis| dot(p, axis));
+        // A 90 degree perspective projection, mapping `near..far` to a depth of `0..1`
+        let depth = self.far * (z - self.near) / (self.far - self.near);
+        ([x, y, depth, z], Unit)
+    }
+
+    fn geometry<O>(&self, [a, b, c]: [([f32; 4], Unit); 3], mut output: O)
+    where
+        O: FnMut([([f32; 4], Unit); 3]),
+    {
+        // Occluders often pass behind the probe, so clip triangles against the near plane to keep every vertex in
+        // front of it
+        let verts = [a.0, b.0, c.0];
+        let dist = verts.map(|v| v[3] - self.near);
+        let mut clipped = [[0.0; 4]; 4];
+        let mut n = 0;
+        for i in 0..3 {
+            let j = (i + 1) % 3;
+            if dist[i] >= 0.0 {
+                clipped[n] = verts[i];
+                n += 1;
+            }
+            if (dist[i] >= 0.0) != (dist[j] >= 0.0) {
+                let t = dist[i] / (dist[i] - dist[j]);
+                clipped[n] =
+                    core::array::from_fn(|k| verts[i][k] + (verts[j][k] - verts[i][k]) * t);
+                n += 1;
+            }
+        }
+        (2..n).for_each(|i| output([clipped[0], clipped[i - 1], clipped[i]].map(|v| (v, Unit))));
+    }
+
+    fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+        Unit
+    }
+
+    fn blend(&self, _: Self::Pixel, _: Self::Fragment) {}
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn neg(a: [f32; 3]) -> [f32; 3] {
+    a.map(|e| -e)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = dot(a, a).sqrt();
+    a.map(|e| e / len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A quad, as two triangles.
+    fn quad(mesh: &mut dyn FnMut([[f32; 3]; 3]), [a, b, c, d]: [[f32; 3]; 4]) {
+        mesh([a, b, c]);
+        mesh([a, c, d]);
+    }
+
+    fn bake(mesh: impl Fn(&mut dyn FnMut([[f32; 3]; 3])), normal: [f32; 3]) -> f32 {
+        bake_vertex_ao(&[[0.0; 3]], &[normal], &mesh, AoBakeParams::default())[0]
+    }
+
+    #[test]
+    fn isolated_vertex_is_unoccluded() {
+        assert_eq!(bake(|_| {}, [0.0, 1.0, 0.0]), 1.0);
+        // Occluders behind the vertex have no effect
+        let behind = |mesh: &mut dyn FnMut(_)| {
+            let s = 5.0;
+            quad(
+                mesh,
+                [[-s, -1.0, -s], [s, -1.0, -s], [s, -1.0, s], [-s, -1.0, s]],
+            );
+        };
+        assert_eq!(bake(behind, [0.0, 1.0, 0.0]), 1.0);
+    }
+
+    #[test]
+    fn enclosed_vertex_is_occluded() {
+        let cube = |mesh: &mut dyn FnMut(_)| {
+            for axis in 0..3 {
+                for side in [-1.0, 1.0] {
+                    let corner = |u: f32, v: f32| {
+                        let mut p = [0.0; 3];
+                        p[axis] = side;
+                        p[(axis + 1) % 3] = u;
+                        p[(axis + 2) % 3] = v;
+                        p
+                    };
+                    quad(
+                        mesh,
+                        [
+                            corner(-1.0, -1.0),
+                            corner(1.0, -1.0),
+                            corner(1.0, 1.0),
+                            corner(-1.0, 1.0),
+                        ],
+                    );
+                }
+            }
+        };
+        for normal in [[0.0, 1.0, 0.0], [1.0, 2.0, -3.0]] {
+            assert_eq!(bake(cube, normal), 0.0);
+        }
+    }
+
+    #[test]
+    fn vertex_on_plane_is_half_occluded() {
+        // A vertex resting on a floor, with a normal parallel to the floor (such as at the base of a wall). The floor
+        // extends well beyond the maximum distance, so is effectively infinite.
+        let floor = |mesh: &mut dyn FnMut(_)| {
+            let (s, y) = (100.0, -0.01);
+            quad(mesh, [[-s, y, -s], [s, y, -s], [s, y, s], [-s, y, s]]);
+        };
+        for normal in [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [1.0, 0.0, 1.0]] {
+            let ao = bake(floor, normal);
+            assert!((ao - 0.5).abs() < 0.05, "{:?}: {}", normal, ao);
+        }
+    }
+
+    #[test]
+    fn bias_avoids_self_occlusion() {
+        // A vertex in the middle of a surface should not be occluded by that surface
+        let surface = |mesh: &mut dyn FnMut(_)| {
+            quad(
+                mesh,
+                [
+                    [-1.0, 0.0, -1.0],
+                    [1.0, 0.0, -1.0],
+                    [1.0, 0.0, 1.0],
+                    [-1.0, 0.0, 1.0],
+                ],
+            );
+        };
+        assert_eq!(bake(surface, [0.0, 1.0, 0.0]), 1.0);
+    }
+}
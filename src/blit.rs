@@ -0,0 +1,254 @@
+use crate::{
+    buffer::Buffer2d,
+    texture::{Target, Texture},
+};
+use core::ops::Range;
+
+/// The number of texels above which copies are split between threads.
+#[cfg(feature = "par")]
+const PAR_THRESHOLD: usize = 1 << 14;
+
+/// The rows of a region that get copied into a target, once clipped against the bounds of both.
+struct Clipped {
+    src_origin: [usize; 2],
+    /// The height of the region after clipping against the source, which decides where rows end up when flipping.
+    src_height: usize,
+    dst_origin: [usize; 2],
+    /// The size of the part of the region that gets copied.
+    size: [usize; 2],
+    flip_y: bool,
+}
+
+impl Clipped {
+    fn new(
+        src_size: [usize; 2],
+        (src_origin, region_size): ([usize; 2], [usize; 2]),
+        dst_size: [usize; 2],
+        dst_origin: [usize; 2],
+        flip_y: bool,
+    ) -> Self {
+        let src_region =
+            [0, 1].map(|i| region_size[i].min(src_size[i].saturating_sub(src_origin[i])));
+        Self {
+            src_origin,
+            src_height: src_region[1],
+            dst_origin,
+            size: [0, 1].map(|i| src_region[i].min(dst_size[i].saturating_sub(dst_origin[i]))),
+            flip_y,
+        }
+    }
+
+    fn texels(&self) -> usize {
+        self.size[0] * self.size[1]
+    }
+
+    /// The row of the source that gets copied into the given row of the destination.
+    fn src_row(&self, dst_y: usize) -> usize {
+        let y = dst_y - self.dst_origin[1];
+        if self.flip_y {
+            self.src_origin[1] + self.src_height - 1 - y
+        } else {
+            self.src_origin[1] + y
+        }
+    }
+
+    fn dst_rows(&self) -> Range<usize> {
+        self.dst_origin[1]..self.dst_origin[1] + self.size[1]
+    }
+}
+
+/// Copy a rectangular region of a texture (given by its origin and size) into a target, with its first texel at
+/// `dst_origin`, converting each texel with `convert`.
+///
+/// This is useful for packing shadow maps into an atlas, keeping the previous frame around for temporal effects, or
+/// uploading images decoded elsewhere (converting their texels to the format of the target as they're copied).
+///
+/// The region is clipped against the bounds of both the source and the destination, so texels that lie beyond either
+/// are skipped rather than causing a panic. When `flip_y` is set, the rows of the region (once clipped against the
+/// source) are copied in reverse order, which is useful when the source is stored with its last row first (as
+/// framebuffers often are, see [`YAxisDirection`](crate::YAxisDirection)).
+///
+/// Large copies are split between threads when the `par` feature is enabled. When both the source and destination are
+/// [`Buffer2d`]s of the same type, [`copy`] is faster.
+pub fn blit<S, D, F>(
+    src: &S,
+    src_region: ([usize; 2], [usize; 2]),
+    dst: &mut D,
+    dst_origin: [usize; 2],
+    flip_y: bool,
+    convert: F,
+) where
+    S: Texture<2, Index = usize> + Sync,
+    D: Target + Send + Sync,
+    F: Fn(S::Texel) -> D::Texel + Sync,
+{
+    let clipped = Clipped::new(src.size(), src_region, dst.size(), dst_origin, flip_y);
+    let dst = &*dst;
+
+    let copy_row = |y: usize| {
+        let src_y = clipped.src_row(y);
+        for x in 0..clipped.size[0] {
+            // Safety: both positions lie within their textures after clipping, and each row of the destination is only
+            // written by a single thread
+            unsafe {
+                let texel = src.read_unchecked([clipped.src_origin[0] + x, src_y]);
+                dst.write_exclusive_unchecked(clipped.dst_origin[0] + x, y, convert(texel));
+            }
+        }
+    };
+
+    #[cfg(feature = "par")]
+    if clipped.texels() >= PAR_THRESHOLD {
+        crate::par::for_each(clipped.dst_rows(), copy_row);
+        return;
+    }
+
+    clipped.dst_rows().for_each(copy_row);
+}
+
+/// Copy a rectangular region of a buffer (given by its origin and size) into another buffer of the same type, with its
+/// first texel at `dst_origin`.
+///
+/// This behaves exactly like [`blit`] without any conversion, but copies whole rows of texels at a time.
+pub fn copy<T>(
+    src: &Buffer2d<T>,
+    src_region: ([usize; 2], [usize; 2]),
+    dst: &mut Buffer2d<T>,
+    dst_origin: [usize; 2],
+    flip_y: bool,
+) where
+    T: Copy + Send + Sync,
+{
+    let clipped = Clipped::new(src.size(), src_region, dst.size(), dst_origin, flip_y);
+    if clipped.texels() == 0 {
+        return;
+    }
+    let [w, dst_x] = [clipped.size[0], clipped.dst_origin[0]];
+
+    let copy_row = |y: usize, row: &mut [T]| {
+        if clipped.dst_rows().contains(&y) {
+            let src_start = src.linear_index2(clipped.src_origin[0], clipped.src_row(y));
+            row[dst_x..dst_x + w].copy_from_slice(&src.raw()[src_start..src_start + w]);
+        }
+    };
+
+    #[cfg(feature = "par")]
+    if clipped.texels() >= PAR_THRESHOLD {
+        dst.for_each_row_mut(copy_row);
+        return;
+    }
+
+    dst.rows_mut()
+        .enumerate()
+        .skip(clipped.dst_origin[1])
+        .take(clipped.size[1])
+        .for_each(|(y, row)| copy_row(y, row));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A buffer where each texel holds its own position.
+    fn coords(size: [usize; 2]) -> Buffer2d<[usize; 2]> {
+        let mut buf = Buffer2d::fill(size, [0; 2]);
+        buf.iter_mut_2d().for_each(|(pos, texel)| *texel = pos);
+        buf
+    }
+
+    /// Check that each texel of the destination holds the expected texel, or `[9; 2]` where nothing is expected.
+    fn check(dst: &Buffer2d<[usize; 2]>, expected: impl Fn([usize; 2]) -> Option<[usize; 2]>) {
+        for (pos, texel) in dst.enumerate_pixels() {
+            assert_eq!(*texel, expected(pos).unwrap_or([9; 2]), "at {:?}", pos);
+        }
+    }
+
+    #[test]
+    fn blit_converts() {
+        let src = coords([5, 4]);
+        let mut dst = Buffer2d::fill([6, 6], 0);
+        blit(&src, ([1, 2], [3, 2]), &mut dst, [2, 1], false, |[x, y]| {
+            x * 10 + y
+        });
+        for (pos, texel) in dst.enumerate_pixels() {
+            let expected = match pos {
+                [x @ 2..=4, y @ 1..=2] => (x - 1) * 10 + y + 1,
+                _ => 0,
+            };
+            assert_eq!(*texel, expected, "at {:?}", pos);
+        }
+    }
+
+    #[test]
+    fn clipped() {
+        let src = coords([5, 4]);
+        for flip_y in [false, true] {
+            // The region extends beyond the source, and only 2x2 texels of what remains fit in the destination
+            let mut by_texel = Buffer2d::fill([4, 3], [9; 2]);
+            blit(&src, ([3, 1], [4, 4]), &mut by_texel, [2, 1], flip_y, |t| t);
+            let mut by_row = Buffer2d::fill([4, 3], [9; 2]);
+            copy(&src, ([3, 1], [4, 4]), &mut by_row, [2, 1], flip_y);
+
+            for dst in [&by_texel, &by_row] {
+                check(dst, |[x, y]| {
+                    // Once clipped against the source, the region is 2x3 texels
+                    (x >= 2 && y >= 1).then(|| {
+                        let y = y - 1;
+                        [x + 1, 1 + if flip_y { 2 - y } else { y }]
+                    })
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn flip_y() {
+        let src = coords([3, 4]);
+        let mut dst = Buffer2d::fill([3, 4], [9; 2]);
+        copy(&src, ([0, 0], [3, 4]), &mut dst, [0, 0], true);
+        assert_eq!(dst.raw(), src.flipped_vertical().raw());
+
+        let mut dst = Buffer2d::fill([3, 4], [9; 2]);
+        blit(&src, ([0, 0], [3, 4]), &mut dst, [0, 0], true, |t| t);
+        assert_eq!(dst.raw(), src.flipped_vertical().raw());
+    }
+
+    #[test]
+    fn outside() {
+        let src = coords([5, 4]);
+        let mut dst = Buffer2d::fill([4, 3], [9; 2]);
+        for (region, dst_origin) in [
+            (([0, 0], [5, 4]), [4, 0]),
+            (([0, 0], [5, 4]), [0, usize::MAX]),
+            (([5, 0], [2, 2]), [0, 0]),
+            (([usize::MAX, 0], [2, 2]), [0, 0]),
+            (([0, 0], [0, 4]), [0, 0]),
+        ] {
+            blit(&src, region, &mut dst, dst_origin, false, |t| t);
+            copy(&src, region, &mut dst, dst_origin, true);
+        }
+        check(&dst, |_| None);
+    }
+
+    #[test]
+    fn large() {
+        // Large enough to be split between threads with the `par` feature
+        let src = coords([300, 200]);
+        let mut by_texel = Buffer2d::fill([256, 256], [9; 2]);
+        blit(
+            &src,
+            ([20, 10], [280, 190]),
+            &mut by_texel,
+            [0, 3],
+            true,
+            |t| t,
+        );
+        let mut by_row = Buffer2d::fill([256, 256], [9; 2]);
+        copy(&src, ([20, 10], [280, 190]), &mut by_row, [0, 3], true);
+
+        assert_eq!(by_texel.raw(), by_row.raw());
+        check(&by_row, |[x, y]| {
+            (3..193).contains(&y).then(|| [x + 20, 10 + 189 - (y - 3)])
+        });
+    }
+}
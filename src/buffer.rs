@@ -1,9 +1,15 @@
-use crate::texture::{Target, Texture};
-use alloc::{boxed::Box, vec::Vec};
-use core::cell::UnsafeCell;
+use crate::{
+    sampler::{Linear, Mipmapped},
+    texture::{Target, Texture},
+};
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    ops::{Add, Mul},
+};
 
 /// A generic 1-dimensional buffer that may be used as a texture.
-pub type Buffer1d<T> = Buffer<T, 2>;
+pub type Buffer1d<T> = Buffer<T, 1>;
 
 /// A generic 2-dimensional buffer that may be used both as a texture and as a render target.
 pub type Buffer2d<T> = Buffer<T, 2>;
@@ -17,7 +23,7 @@ pub type Buffer4d<T> = Buffer<T, 4>;
 /// A generic N-dimensional buffer that may be used both as a texture and as a render target.
 #[derive(Debug)]
 pub struct Buffer<T, const N: usize> {
-    items: Box<[UnsafeCell<T>]>,
+    items: Vec<UnsafeCell<T>>,
     size: [usize; N],
 }
 
@@ -28,9 +34,11 @@ unsafe impl<T: Sync, const N: usize> Sync for Buffer<T, N> {}
 impl<T, const N: usize> Buffer<T, N> {
     /// Copy the texels of an existing [`Texture`] into a new [`Buffer`].
     ///
-    /// This is useful if the original texture has slow access times or isn't usable as a render target.
+    /// This is useful if the original texture has slow access times (such as a [`Texture::map`]ped texture, or an
+    /// image) or isn't usable as a render target.
     pub fn from_texture<U: Texture<N, Index = usize, Texel = T>>(tex: &U) -> Self {
         let tex_size = tex.size();
+        // Indices are produced in the same order as `linear_index`, with the first axis varying fastest
         let mut idx = [0; N];
         let iter = core::iter::once([0; N]).chain(core::iter::from_fn(move || {
             let mut i = 0;
@@ -49,15 +57,18 @@ impl<T, const N: usize> Buffer<T, N> {
 
         Self {
             size: tex_size,
+            // Safety: every index is within the bounds of the texture (textures with a zero-sized axis have no texels)
             items: unsafe {
-                iter.map(|idx| UnsafeCell::new(tex.read_unchecked(idx)))
-                    .collect::<Vec<_>>()
-                    .into_boxed_slice()
+                iter.take(tex_size.iter().product())
+                    .map(|idx| UnsafeCell::new(tex.read_unchecked(idx)))
+                    .collect()
             },
         }
     }
 
     /// Create a new buffer with the given size, filled with duplicates of the given element.
+    ///
+    /// Be careful not to fill depth buffers with NaN, which fails every depth test (see [`Buffer2d::clear_depth`]).
     #[inline]
     pub fn fill(size: [usize; N], item: T) -> Self
     where
@@ -75,13 +86,98 @@ impl<T, const N: usize> Buffer<T, N> {
         (0..N).for_each(|i| len = len.checked_mul(size[i]).unwrap());
         Self {
             size,
-            items: (0..len)
-                .map(|_| UnsafeCell::new(f()))
-                .collect::<Vec<_>>()
-                .into_boxed_slice(),
+            items: (0..len).map(|_| UnsafeCell::new(f())).collect(),
+        }
+    }
+
+    /// Resize the buffer, preserving the texels within both the old and new sizes and filling any others with
+    /// duplicates of the given element.
+    ///
+    /// The existing allocation is reused if it has the capacity for the new size (as it always does when shrinking, so
+    /// shrinking and growing back again doesn't allocate). If the contents aren't needed, [`Buffer::clear_and_resize`]
+    /// avoids moving them.
+    pub fn resize(&mut self, new_size: [usize; N], fill: T)
+    where
+        T: Clone,
+    {
+        let mut len = 1usize;
+        (0..N).for_each(|i| len = len.checked_mul(new_size[i]).unwrap());
+        // Resize one axis at a time, shrinking before growing such that the length never exceeds the larger of the
+        // old and new lengths
+        let old_size = self.size;
+        let axes = (0..N)
+            .filter(|&i| new_size[i] < old_size[i])
+            .chain((0..N).filter(|&i| new_size[i] > old_size[i]));
+        for axis in axes {
+            self.resize_axis(axis, new_size[axis], &fill);
+        }
+        debug_assert_eq!(self.items.len(), len);
+    }
+
+    /// Resize a single axis of the buffer, moving texels to their new positions.
+    fn resize_axis(&mut self, axis: usize, new: usize, fill: &T)
+    where
+        T: Clone,
+    {
+        let old = core::mem::replace(&mut self.size[axis], new);
+        // Texels are stored in `groups` runs of `old` rows along the axis (each made of `block` contiguous texels)
+        let block = self.size[..axis].iter().product::<usize>();
+        let groups = self.size[axis + 1..].iter().product::<usize>();
+        let kept = old.min(new) * block;
+        let [old, new] = [old * block, new * block];
+
+        if new > old {
+            self.items
+                .resize_with(groups * new, || UnsafeCell::new(fill.clone()));
+            // Texels move towards the end of the buffer, so start at the end to avoid overwriting any
+            for group in (0..groups).rev() {
+                for i in (0..kept).rev() {
+                    self.items.swap(group * old + i, group * new + i);
+                }
+                self.items[group * new + kept..(group + 1) * new]
+                    .iter_mut()
+                    .for_each(|item| *item.get_mut() = fill.clone());
+            }
+        } else {
+            // Texels move towards the start of the buffer, so start at the start
+            for group in 0..groups {
+                for i in 0..kept {
+                    self.items.swap(group * old + i, group * new + i);
+                }
+            }
+            self.items.truncate(groups * new);
         }
     }
 
+    /// Resize the buffer and fill it with duplicates of the given element, discarding its contents.
+    ///
+    /// Like [`Buffer::resize`], the existing allocation is reused if it has the capacity for the new size.
+    pub fn clear_and_resize(&mut self, new_size: [usize; N], fill: T)
+    where
+        T: Clone,
+    {
+        let mut len = 1usize;
+        (0..N).for_each(|i| len = len.checked_mul(new_size[i]).unwrap());
+        self.items.truncate(len);
+        self.items
+            .iter_mut()
+            .for_each(|item| *item.get_mut() = fill.clone());
+        self.items
+            .resize_with(len, || UnsafeCell::new(fill.clone()));
+        self.size = new_size;
+    }
+
+    /// Resize the buffer and fill it by calling the function for each element, discarding its contents.
+    ///
+    /// Like [`Buffer::resize`], the existing allocation is reused if it has the capacity for the new size.
+    pub fn clear_and_resize_with<F: FnMut() -> T>(&mut self, new_size: [usize; N], mut f: F) {
+        let mut len = 1usize;
+        (0..N).for_each(|i| len = len.checked_mul(new_size[i]).unwrap());
+        self.items.clear();
+        self.items.extend((0..len).map(|_| UnsafeCell::new(f())));
+        self.size = new_size;
+    }
+
     /// Convert the given index into a linear index that can be used to index into the raw data of this buffer.
     #[inline(always)]
     pub fn linear_index(&self, index: [usize; N]) -> usize {
@@ -147,6 +243,762 @@ impl<T> Buffer<T, 2> {
     pub(crate) fn linear_index2(&self, x: usize, y: usize) -> usize {
         y * self.size[0] + x
     }
+
+    /// Get mutable access to `len` consecutive texels of a row, starting at the given position.
+    ///
+    /// # Safety
+    ///
+    /// The texels must lie within the buffer, and the caller must guarantee exclusive access to them for as long as the
+    /// returned slice is alive (as with [`Target::write_exclusive_unchecked`]).
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    pub(crate) unsafe fn texels_exclusive_unchecked(
+        &self,
+        x: usize,
+        y: usize,
+        len: usize,
+    ) -> &mut [T] {
+        let start = self.linear_index2(x, y);
+        let items = self.items.get_unchecked(start..start + len);
+        core::slice::from_raw_parts_mut(UnsafeCell::raw_get(items.as_ptr()), len)
+    }
+
+    /// Call the function with the index and texels of each row of the buffer, in parallel if the `par` feature is
+    /// enabled.
+    pub(crate) fn for_each_row_mut<F>(&mut self, f: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        #[cfg(not(feature = "par"))]
+        self.rows_mut().enumerate().for_each(|(y, row)| f(y, row));
+
+        #[cfg(feature = "par")]
+        self.par_rows_mut().for_each(f);
+    }
+
+    /// Iterate over the texels of the buffer along with their positions, in row-major order.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = ([usize; 2], &T)> + '_ {
+        self.rows().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, texel)| ([x, y], texel))
+        })
+    }
+
+    /// Iterate mutably over the texels of the buffer along with their positions, in row-major order.
+    pub fn iter_mut_2d(&mut self) -> impl Iterator<Item = ([usize; 2], &mut T)> + '_ {
+        self.rows_mut().enumerate().flat_map(|(y, row)| {
+            row.iter_mut()
+                .enumerate()
+                .map(move |(x, texel)| ([x, y], texel))
+        })
+    }
+
+    /// Iterate over the rows of the buffer, from the first row to the last.
+    pub fn rows(&self) -> core::slice::ChunksExact<'_, T> {
+        let width = self.size[0];
+        self.raw().chunks_exact(width.max(1))
+    }
+
+    /// Iterate mutably over the rows of the buffer, from the first row to the last.
+    ///
+    /// Rows are contiguous in memory, so passes over a whole buffer are fastest when performed a row at a time. With
+    /// the `par` feature, [`Buffer2d::par_rows_mut`] processes the rows in parallel instead.
+    ///
+    /// ```
+    /// # use euc::Buffer2d;
+    /// let mut color = Buffer2d::fill([64, 48], [0.25f32; 3]);
+    /// let gamma = |rgb: &mut [f32; 3]| *rgb = rgb.map(|c| c.powf(1.0 / 2.2));
+    ///
+    /// for row in color.rows_mut() {
+    ///     row.iter_mut().for_each(gamma);
+    /// }
+    ///
+    /// // The same pass, in parallel
+    /// # #[cfg(feature = "par")]
+    /// color.par_rows_mut().for_each(|_y, row| row.iter_mut().for_each(gamma));
+    ///
+    /// # #[cfg(feature = "par")]
+    /// assert!(color.raw().iter().all(|rgb| (rgb[0] - 0.25f32.powf(1.0 / 4.84)).abs() < 1.0e-5));
+    /// ```
+    pub fn rows_mut(&mut self) -> core::slice::ChunksExactMut<'_, T> {
+        let width = self.size[0];
+        self.raw_mut().chunks_exact_mut(width.max(1))
+    }
+
+    /// Process the rows of the buffer in parallel, as the renderer does, such as for post-processing passes.
+    ///
+    /// Each row is given to exactly one thread, so the rows may be mutated freely.
+    #[cfg(feature = "par")]
+    pub fn par_rows_mut(&mut self) -> ParRowsMut<'_, T>
+    where
+        T: Send,
+    {
+        ParRowsMut {
+            rows: self.rows_mut().enumerate(),
+        }
+    }
+
+    /// Flip the buffer vertically in place, such that its first row becomes its last.
+    pub fn flip_vertical(&mut self) {
+        let [w, h] = self.size;
+        if w == 0 {
+            return;
+        }
+        let (top, bottom) = self.raw_mut().split_at_mut(h / 2 * w);
+        // The middle row of buffers with an odd height stays where it is
+        top.chunks_exact_mut(w)
+            .zip(bottom.rchunks_exact_mut(w))
+            .for_each(|(a, b)| a.swap_with_slice(b));
+    }
+
+    /// Create a vertically flipped copy of the buffer (see [`Buffer2d::flip_vertical`]).
+    pub fn flipped_vertical(&self) -> Self
+    where
+        T: Clone,
+    {
+        let w = self.size[0].max(1);
+        Self {
+            size: self.size,
+            items: self
+                .raw()
+                .rchunks_exact(w)
+                .flatten()
+                .map(|item| UnsafeCell::new(item.clone()))
+                .collect(),
+        }
+    }
+
+    /// Copy a rectangular region of another buffer (given by its origin and size) into this buffer, with its first
+    /// texel at `dst_origin`.
+    ///
+    /// Texels that would be copied beyond the edges of this buffer are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region does not lie within `src`.
+    pub fn blit_from(
+        &mut self,
+        src: &Buffer2d<T>,
+        (src_origin, src_size): ([usize; 2], [usize; 2]),
+        dst_origin: [usize; 2],
+    ) where
+        T: Copy,
+    {
+        assert!(
+            (0..2).all(|i| src_origin[i]
+                .checked_add(src_size[i])
+                .is_some_and(|end| end <= src.size[i])),
+            "Blit region at {:?} of size {:?} does not lie within buffer of size {:?}",
+            src_origin,
+            src_size,
+            src.size
+        );
+        // Clip the region to the destination
+        let [w, h] = [0, 1].map(|i| src_size[i].min(self.size[i].saturating_sub(dst_origin[i])));
+        if w == 0 {
+            return;
+        }
+        for y in 0..h {
+            let src_start = src.linear_index2(src_origin[0], src_origin[1] + y);
+            let dst_start = self.linear_index2(dst_origin[0], dst_origin[1] + y);
+            self.raw_mut()[dst_start..dst_start + w]
+                .copy_from_slice(&src.raw()[src_start..src_start + w]);
+        }
+    }
+
+    /// View a rectangular region of the buffer (starting at `origin`, with the given `size`) as a target of its own,
+    /// without copying it.
+    ///
+    /// Texel `[0, 0]` of the view is texel `origin` of the buffer. The view borrows the buffer mutably, so nothing
+    /// else may access the buffer while it exists: use [`Buffer2d::sub_views_mut`] for several views at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region does not lie within the buffer.
+    pub fn sub_view_mut(&mut self, origin: [usize; 2], size: [usize; 2]) -> SubView<'_, T> {
+        let [view] = self.sub_views_mut([(origin, size)]);
+        view
+    }
+
+    /// View several rectangular regions of the buffer (each given by its origin and size) as targets of their own, as
+    /// with [`Buffer2d::sub_view_mut`].
+    ///
+    /// Each view has exclusive access to its texels (and views may be sent to other threads), so the regions must not
+    /// overlap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any region does not lie within the buffer, or if any regions overlap.
+    pub fn sub_views_mut<const K: usize>(
+        &mut self,
+        regions: [([usize; 2], [usize; 2]); K],
+    ) -> [SubView<'_, T>; K] {
+        for (i, (origin, size)) in regions.iter().enumerate() {
+            assert!(
+                (0..2).all(|j| origin[j]
+                    .checked_add(size[j])
+                    .is_some_and(|end| end <= self.size[j])),
+                "Sub-view at {:?} of size {:?} does not lie within buffer of size {:?}",
+                origin,
+                size,
+                self.size
+            );
+            assert!(
+                regions[..i].iter().all(|(other_origin, other_size)| {
+                    let is_empty = |size: &[usize; 2]| size.contains(&0);
+                    is_empty(size)
+                        || is_empty(other_size)
+                        || (0..2).any(|j| {
+                            origin[j] + size[j] <= other_origin[j]
+                                || other_origin[j] + other_size[j] <= origin[j]
+                        })
+                }),
+                "Sub-view at {:?} of size {:?} overlaps another sub-view",
+                origin,
+                size
+            );
+        }
+        let (items, stride) = (&*self.items, self.size[0]);
+        regions.map(|(origin, size)| SubView {
+            items,
+            stride,
+            origin,
+            size,
+        })
+    }
+}
+
+/// The rows of a [`Buffer2d`], which may be processed in parallel (see [`Buffer2d::par_rows_mut`]).
+#[cfg(feature = "par")]
+pub struct ParRowsMut<'a, T> {
+    rows: core::iter::Enumerate<core::slice::ChunksExactMut<'a, T>>,
+}
+
+#[cfg(feature = "par")]
+impl<T: Send> ParRowsMut<'_, T> {
+    /// Call the function with the index and texels of each row, using as many threads as
+    /// [`threads`](crate::par::threads) allows.
+    pub fn for_each<F>(self, f: F)
+    where
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        crate::par::for_each(self.rows, |(y, row)| f(y, row));
+    }
+
+    /// Like [`ParRowsMut::for_each`], but `init` is called once on each thread to create state (such as a scratch
+    /// buffer) that is passed to every call to `f` on that thread.
+    pub fn for_each_init<S, G, F>(self, init: G, f: F)
+    where
+        G: Fn() -> S + Sync,
+        F: Fn(&mut S, usize, &mut [T]) + Sync,
+    {
+        crate::par::for_each_init(self.rows, init, |state, (y, row)| f(state, y, row));
+    }
+}
+
+/// A pair of same-sized [`Buffer2d`]s for iterative passes, each of which reads the result of the previous pass from
+/// the front buffer while writing its own result to the back buffer.
+///
+/// ```
+/// # use euc::{PingPong, Target, Texture};
+/// // Diffuse a single hot texel over its neighbours
+/// let mut heat = PingPong::fill([16, 16], 0.0f32);
+/// heat.back().write(8, 8, 1.0);
+/// heat.swap();
+/// for _ in 0..4 {
+///     heat.run_pass(|front, back| {
+///         let [w, h] = front.size();
+///         for y in 0..h {
+///             for x in 0..w {
+///                 let at = |dx: usize, dy: usize| front.read([(x + w + dx - 1) % w, (y + h + dy - 1) % h]);
+///                 back.write(x, y, (at(1, 1) * 4.0 + at(0, 1) + at(2, 1) + at(1, 0) + at(1, 2)) / 8.0);
+///             }
+///         }
+///     });
+/// }
+/// // The heat has spread out, but none of it has been lost
+/// assert!(heat.front().read([8, 8]) < 0.5);
+/// assert!(heat.front().read([6, 8]) > 0.0);
+/// assert!((heat.front().raw().iter().sum::<f32>() - 1.0).abs() < 1.0e-5);
+/// ```
+#[derive(Debug)]
+pub struct PingPong<T> {
+    front: Buffer2d<T>,
+    back: Buffer2d<T>,
+}
+
+impl<T> PingPong<T> {
+    /// Create a new pair of buffers with the given size, both filled with duplicates of the given element.
+    pub fn fill(size: [usize; 2], item: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            front: Buffer2d::fill(size, item.clone()),
+            back: Buffer2d::fill(size, item),
+        }
+    }
+
+    /// The size of both buffers.
+    #[inline]
+    pub fn size(&self) -> [usize; 2] {
+        self.front.size
+    }
+
+    /// The front buffer, which holds the result of the last pass.
+    #[inline]
+    pub fn front(&self) -> &Buffer2d<T> {
+        &self.front
+    }
+
+    /// The back buffer, to which the next pass writes its result.
+    #[inline]
+    pub fn back(&mut self) -> &mut Buffer2d<T> {
+        &mut self.back
+    }
+
+    /// Swap the front and back buffers, such that the result of the last pass becomes the front buffer.
+    #[inline]
+    pub fn swap(&mut self) {
+        core::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Run a pass, which reads from the front buffer and writes to the back buffer, and then swap the buffers such
+    /// that its result is in the front buffer.
+    pub fn run_pass<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&Buffer2d<T>, &mut Buffer2d<T>) -> R,
+    {
+        let r = f(&self.front, &mut self.back);
+        self.swap();
+        r
+    }
+
+    /// Resize both buffers, as with [`Buffer::resize`].
+    pub fn resize(&mut self, new_size: [usize; 2], fill: T)
+    where
+        T: Clone,
+    {
+        self.front.resize(new_size, fill.clone());
+        self.back.resize(new_size, fill);
+    }
+}
+
+/// A chain of mip levels: buffers that each have half the size of the last (rounded down, but no smaller than 1),
+/// down to a single texel.
+///
+/// Level `0` is the full-resolution buffer. Sample the chain with [`MipChain::linear`] to select between levels with
+/// [`Sampler::sample_lod`](crate::Sampler::sample_lod).
+#[derive(Debug)]
+pub struct MipChain<T> {
+    levels: Vec<Buffer2d<T>>,
+}
+
+impl<T> MipChain<T> {
+    /// Generate a chain of mip levels from the given full-resolution buffer, where each texel of each smaller level is
+    /// the average of the (up to) 2x2 texels that it covers in the level before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has no size.
+    pub fn generate(base: Buffer2d<T>) -> Self
+    where
+        T: Clone + Mul<f32, Output = T> + Add<Output = T>,
+    {
+        assert!(
+            base.size.iter().all(|e| *e >= 1),
+            "Cannot generate mip levels for a buffer with no size",
+        );
+        let mut levels = alloc::vec![base];
+        while let Some(last) = levels.last().filter(|last| last.size != [1; 2]) {
+            let size = next_level_size(last.size);
+            let mut level = Vec::with_capacity(size[0] * size[1]);
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let xs = [x * 2, (x * 2 + 1).min(last.size[0] - 1)];
+                    let ys = [y * 2, (y * 2 + 1).min(last.size[1] - 1)];
+                    let [a, b, c, d] = [[0, 0], [1, 0], [0, 1], [1, 1]]
+                        .map(|[i, j]| last.read([xs[i], ys[j]]) * 0.25);
+                    level.push(a + b + c + d);
+                }
+            }
+            levels.push(Buffer2d {
+                size,
+                items: level.into_iter().map(UnsafeCell::new).collect(),
+            });
+        }
+        Self { levels }
+    }
+
+    /// Create a chain of mip levels with the given full-resolution buffer as level `0` (without copying it), with every
+    /// smaller level filled with `fill`.
+    ///
+    /// This is useful for filling the levels by rendering into each in turn, such as with a pass that downsamples the
+    /// level before it (see [`MipChain::for_each_level_pair`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has no size.
+    pub fn new(base: Buffer2d<T>, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        assert!(
+            base.size.iter().all(|e| *e >= 1),
+            "Cannot create mip levels for a buffer with no size",
+        );
+        let mut levels = alloc::vec![base];
+        while let Some(last) = levels.last().filter(|last| last.size != [1; 2]) {
+            levels.push(Buffer2d::fill(next_level_size(last.size), fill.clone()));
+        }
+        Self { levels }
+    }
+
+    /// The number of levels in the chain.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The buffer holding the given level, where level `0` is the full-resolution buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the level is not in the chain.
+    #[inline]
+    pub fn level(&self, level: usize) -> &Buffer2d<T> {
+        &self.levels[level]
+    }
+
+    /// The buffer holding the given level, which may be rendered into like any other target.
+    ///
+    /// Resizing the buffer would leave the chain with levels of the wrong size, so this should be avoided.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the level is not in the chain.
+    #[inline]
+    pub fn level_mut(&mut self, level: usize) -> &mut Buffer2d<T> {
+        &mut self.levels[level]
+    }
+
+    /// All of the levels in the chain, from the full-resolution buffer to the smallest.
+    #[inline]
+    pub fn levels(&self) -> &[Buffer2d<T>] {
+        &self.levels
+    }
+
+    /// Call the function with each level of the chain, from the full-resolution buffer onwards, along with the level
+    /// after it to be rendered into.
+    ///
+    /// Each level is only given to the function after it has been rendered into as the level after the one before it,
+    /// so this can be used to fill the chain by downsampling each level into the next, such as with a
+    /// [`fullscreen`](crate::fullscreen) pass.
+    pub fn for_each_level_pair<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Buffer2d<T>, &mut Buffer2d<T>),
+    {
+        for i in 1..self.levels.len() {
+            let (src, dst) = self.levels.split_at_mut(i);
+            f(&src[i - 1], &mut dst[0]);
+        }
+    }
+
+    /// Create a sampler that filters each level bilinearly, and blends between levels (see [`Mipmapped`]).
+    pub fn linear(&self) -> Mipmapped<Linear<&Buffer2d<T>>>
+    where
+        T: Clone,
+    {
+        Mipmapped::new(self.levels.iter().map(|level| level.linear()).collect())
+    }
+}
+
+/// The size of the mip level after one of the given size.
+#[inline]
+fn next_level_size(size: [usize; 2]) -> [usize; 2] {
+    size.map(|e| (e / 2).max(1))
+}
+
+/// A rectangular region of a [`Buffer2d`] that may be used as a render target of its own (see
+/// [`Buffer2d::sub_view_mut`]).
+#[derive(Debug)]
+pub struct SubView<'a, T> {
+    // Only the texels within the region may be accessed through this view, since other views may share the buffer
+    items: &'a [UnsafeCell<T>],
+    stride: usize,
+    origin: [usize; 2],
+    size: [usize; 2],
+}
+
+// SAFETY: Views have exclusive access to their texels, like a mutable slice
+unsafe impl<T: Send> Send for SubView<'_, T> {}
+unsafe impl<T: Sync> Sync for SubView<'_, T> {}
+
+impl<T> SubView<'_, T> {
+    #[inline(always)]
+    fn linear_index2(&self, x: usize, y: usize) -> usize {
+        (self.origin[1] + y) * self.stride + self.origin[0] + x
+    }
+}
+
+impl<T: Clone> Texture<2> for SubView<'_, T> {
+    type Index = usize;
+
+    type Texel = T;
+
+    #[inline]
+    fn size(&self) -> [Self::Index; 2] {
+        self.size
+    }
+
+    #[inline]
+    fn read(&self, [x, y]: [Self::Index; 2]) -> Self::Texel {
+        assert!(
+            x < self.size[0] && y < self.size[1],
+            "Attempted to read sub-view of size {:?} at out-of-bounds location {:?}",
+            self.size,
+            [x, y]
+        );
+        // SAFETY: The index is within the view, and so within the buffer
+        unsafe { self.read_unchecked([x, y]) }
+    }
+
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, [x, y]: [Self::Index; 2]) -> Self::Texel {
+        let item = self.items.get_unchecked(self.linear_index2(x, y));
+        // SAFETY: Invariants can only be violated by `write_exclusive_unchecked`
+        unsafe { (*item.get()).clone() }
+    }
+}
+
+impl<T: Clone> Target for SubView<'_, T> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        self.read_unchecked([x, y])
+    }
+
+    #[inline(always)]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        let item = self.items.get_unchecked(self.linear_index2(x, y));
+        // This is safe to do provided the caller has guaranteed exclusive access to the texels being written to, as
+        // per the contractual obligations of this method.
+        unsafe {
+            item.get().write(texel);
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        if x < self.size[0] && y < self.size[1] {
+            // SAFETY: The index is within the view, which has exclusive access to its texels
+            unsafe { self.write_exclusive_unchecked(x, y, texel) }
+        }
+    }
+}
+
+/// A 2-dimensional buffer that stores its texels column by column, rather than row by row like [`Buffer2d`].
+///
+/// The buffer reports [`Texture::preferred_axes`] of `[1, 0]`, so the renderer visits the fragments of each primitive
+/// column by column when it is used as the depth target (or as the pixel target, if the depth target has no
+/// preference). This keeps accesses to tall, thin targets cache-friendly. Rendering produces exactly the same result
+/// regardless of the layout of the targets.
+#[derive(Debug)]
+pub struct ColumnMajor<T>(
+    // The transpose of the buffer, such that each row of it is a column of this buffer
+    Buffer2d<T>,
+);
+
+impl<T> ColumnMajor<T> {
+    /// Create a new buffer with the given size, filled with duplicates of the given element.
+    pub fn fill(size: [usize; 2], item: T) -> Self
+    where
+        T: Clone,
+    {
+        Self(Buffer2d::fill([size[1], size[0]], item))
+    }
+
+    /// Copy the texels of a row-major buffer into a new column-major buffer.
+    pub fn from_row_major(buffer: &Buffer2d<T>) -> Self
+    where
+        T: Clone,
+    {
+        let [w, h] = buffer.size();
+        let mut i = 0;
+        Self(Buffer2d::fill_with([h, w], || {
+            let texel = buffer.raw()[(i % h) * w + i / h].clone();
+            i += 1;
+            texel
+        }))
+    }
+
+    /// Copy the texels of this buffer into a new row-major buffer.
+    pub fn to_row_major(&self) -> Buffer2d<T>
+    where
+        T: Clone,
+    {
+        Buffer2d::from_texture(self)
+    }
+
+    /// Get a slice of the texels of this buffer, column by column.
+    pub fn raw(&self) -> &[T] {
+        self.0.raw()
+    }
+
+    /// Get a mutable slice of the texels of this buffer, column by column.
+    pub fn raw_mut(&mut self) -> &mut [T] {
+        self.0.raw_mut()
+    }
+}
+
+impl<T: Clone> Texture<2> for ColumnMajor<T> {
+    type Index = usize;
+    type Texel = T;
+
+    #[inline]
+    fn size(&self) -> [usize; 2] {
+        let [h, w] = self.0.size();
+        [w, h]
+    }
+
+    #[inline]
+    fn preferred_axes(&self) -> Option<[usize; 2]> {
+        Some([1, 0])
+    }
+
+    #[inline]
+    fn read(&self, [x, y]: [usize; 2]) -> Self::Texel {
+        let size = self.size();
+        assert!(
+            x < size[0] && y < size[1],
+            "Attempted to read buffer of size {:?} at out-of-bounds location {:?}",
+            size,
+            [x, y]
+        );
+        self.0.read([y, x])
+    }
+
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, [x, y]: [usize; 2]) -> Self::Texel {
+        self.0.read_unchecked([y, x])
+    }
+}
+
+impl<T: Clone> Target for ColumnMajor<T> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        self.0.read_exclusive_unchecked(y, x)
+    }
+
+    #[inline(always)]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        self.0.write_exclusive_unchecked(y, x, texel)
+    }
+
+    #[inline(always)]
+    unsafe fn write_unchecked(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        self.0.write_unchecked(y, x, texel)
+    }
+
+    #[inline]
+    fn write(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        let size = self.size();
+        assert!(
+            x < size[0] && y < size[1],
+            "Attempted to write buffer of size {:?} at out-of-bounds location {:?}",
+            size,
+            [x, y]
+        );
+        self.0.write(y, x, texel)
+    }
+
+    #[inline]
+    fn clear(&mut self, texel: Self::Texel) {
+        self.0.clear(texel)
+    }
+}
+
+/// A pixel type that may be converted to and from the pixels of an [`image::RgbaImage`].
+#[cfg(feature = "image")]
+pub trait ImagePixel {
+    /// Convert this pixel into red, green, blue, and alpha channels.
+    fn to_rgba(&self) -> [u8; 4];
+
+    /// Create a pixel from red, green, blue, and alpha channels.
+    fn from_rgba(rgba: [u8; 4]) -> Self;
+}
+
+/// Channels are stored in the order red, green, blue, alpha.
+#[cfg(feature = "image")]
+impl ImagePixel for [u8; 4] {
+    fn to_rgba(&self) -> [u8; 4] {
+        *self
+    }
+    fn from_rgba(rgba: [u8; 4]) -> Self {
+        rgba
+    }
+}
+
+/// Channels are stored as the little-endian bytes of the integer (i.e: red in the least significant byte), such that
+/// the in-memory layout of the buffer matches that of the image.
+#[cfg(feature = "image")]
+impl ImagePixel for u32 {
+    fn to_rgba(&self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+    fn from_rgba(rgba: [u8; 4]) -> Self {
+        u32::from_le_bytes(rgba)
+    }
+}
+
+impl Buffer<f32, 2> {
+    /// Clear a depth buffer, setting every texel to the given depth.
+    ///
+    /// Comparisons against NaN never succeed, so a depth buffer containing NaN fails every test in
+    /// [`DepthMode::test`](crate::DepthMode) and nothing gets drawn. Prefer this to [`Target::clear`] for depth buffers:
+    /// it debug-asserts that the depth is finite.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the depth is not finite.
+    #[inline]
+    pub fn clear_depth(&mut self, value: f32) {
+        debug_assert!(
+            value.is_finite(),
+            "depth buffer cleared with a non-finite depth ({value}), which fails every depth test",
+        );
+        self.clear(value);
+    }
+}
+
+#[cfg(feature = "image")]
+impl<T: ImagePixel> Buffer<T, 2> {
+    /// Copy the pixels of this buffer into a new [`image::RgbaImage`] of the same size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension of the buffer does not fit within a `u32`.
+    pub fn to_image(&self) -> image::RgbaImage {
+        let [w, h] = self
+            .size
+            .map(|e| u32::try_from(e).expect("Buffer is too large for an image"));
+        let bytes = self.raw().iter().flat_map(T::to_rgba).collect();
+        // Both buffers and images are stored in row-major order
+        image::RgbaImage::from_raw(w, h, bytes).unwrap()
+    }
+
+    /// Copy the pixels of an [`image::RgbaImage`] into a new buffer of the same size.
+    pub fn from_image(image: &image::RgbaImage) -> Self {
+        Self {
+            size: [image.width() as usize, image.height() as usize],
+            items: image
+                .pixels()
+                .map(|px| UnsafeCell::new(T::from_rgba(px.0)))
+                .collect(),
+        }
+    }
 }
 
 impl<T: Clone, const N: usize> Texture<N> for Buffer<T, N> {
@@ -217,3 +1069,423 @@ impl<T: Clone> Target for Buffer<T, 2> {
             .for_each(|item| *item = UnsafeCell::new(texel.clone()));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_mapped_texture() {
+        let src = Buffer2d::fill_with([5, 3], {
+            let mut i = 0u32;
+            move || {
+                i += 1;
+                i
+            }
+        });
+        let mapped = (&src).map(|x| x as f32 * 0.5);
+        let buf = Buffer2d::from_texture(&mapped);
+        assert_eq!(buf.size(), [5, 3]);
+        for y in 0..3 {
+            for x in 0..5 {
+                assert_eq!(buf.read([x, y]), mapped.read([x, y]));
+                assert_eq!(
+                    buf.raw()[buf.linear_index([x, y])],
+                    src.read([x, y]) as f32 * 0.5
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn from_texture_3d() {
+        let coords = Buffer3d::from_texture(&CoordTexture([2, 3, 4]));
+        assert_eq!(coords.size(), [2, 3, 4]);
+        assert_eq!(coords.raw().len(), 24);
+        for (i, c) in coords.raw().iter().enumerate() {
+            assert_eq!(coords.linear_index(*c), i);
+        }
+    }
+
+    #[test]
+    fn from_empty_texture() {
+        let buf = Buffer2d::from_texture(&CoordTexture([0, 4]));
+        assert_eq!(buf.size(), [0, 4]);
+        assert!(buf.raw().is_empty());
+    }
+
+    #[test]
+    fn sub_views() {
+        let mut buf = Buffer2d::fill([6, 4], 0);
+        let [mut a, mut b] = buf.sub_views_mut([([1, 1], [2, 3]), ([3, 0], [3, 2])]);
+        assert_eq!(a.size(), [2, 3]);
+        a.clear(1);
+        b.write(2, 1, 2);
+        // Writes outside of a view are ignored
+        b.write(3, 0, 3);
+        assert_eq!(a.read([1, 2]), 1);
+        assert_eq!(b.read([2, 1]), 2);
+        #[rustfmt::skip]
+        assert_eq!(buf.raw(), &[
+            0, 0, 0, 0, 0, 0,
+            0, 1, 1, 0, 0, 2,
+            0, 1, 1, 0, 0, 0,
+            0, 1, 1, 0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn blit() {
+        let src = coords([5, 4]);
+        let mut dst = Buffer2d::fill([6, 6], [9; 2]);
+        dst.blit_from(&src, ([1, 2], [3, 2]), [2, 1]);
+        for y in 0..6 {
+            for x in 0..6 {
+                let expected = if (2..5).contains(&x) && (1..3).contains(&y) {
+                    [x - 1, y + 1]
+                } else {
+                    [9; 2]
+                };
+                assert_eq!(dst.read([x, y]), expected, "at {:?}", [x, y]);
+            }
+        }
+    }
+
+    #[test]
+    fn blit_clipped() {
+        let src = coords([5, 4]);
+        let mut dst = Buffer2d::fill([4, 3], [9; 2]);
+        // Only the top-left 2x2 texels of the region fit in the destination
+        dst.blit_from(&src, ([0, 0], [5, 4]), [2, 1]);
+        for y in 0..3 {
+            for x in 0..4 {
+                let expected = if x >= 2 && y >= 1 {
+                    [x - 2, y - 1]
+                } else {
+                    [9; 2]
+                };
+                assert_eq!(dst.read([x, y]), expected, "at {:?}", [x, y]);
+            }
+        }
+        // Regions entirely outside of the destination copy nothing
+        dst.blit_from(&src, ([0, 0], [5, 4]), [7, 0]);
+        dst.blit_from(&src, ([0, 0], [5, 4]), [0, usize::MAX]);
+        assert_eq!(dst.read([3, 2]), [1, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn blit_out_of_bounds() {
+        Buffer2d::fill([4, 4], 0).blit_from(&Buffer2d::fill([4, 4], 1), ([2, 0], [3, 1]), [0, 0]);
+    }
+
+    #[test]
+    fn flip_vertical() {
+        let mut buf = Buffer2d::fill([1, 3], 0);
+        buf.raw_mut().copy_from_slice(&[1, 2, 3]);
+        assert_eq!(buf.flipped_vertical().raw(), &[3, 2, 1]);
+        buf.flip_vertical();
+        assert_eq!(buf.raw(), &[3, 2, 1]);
+
+        let mut buf = Buffer2d::fill([2, 2], 0);
+        buf.raw_mut().copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(buf.flipped_vertical().raw(), &[3, 4, 1, 2]);
+        buf.flip_vertical();
+        assert_eq!(buf.raw(), &[3, 4, 1, 2]);
+
+        let mut empty = Buffer2d::<u8>::fill([0, 2], 0);
+        empty.flip_vertical();
+        assert_eq!(empty.flipped_vertical().size(), [0, 2]);
+    }
+
+    #[cfg(feature = "par")]
+    #[test]
+    fn par_rows() {
+        let mut buf = coords([37, 23]);
+        buf.par_rows_mut().for_each(|y, row| {
+            row.iter_mut().for_each(|[x, ty]| {
+                assert_eq!(*ty, y);
+                *x *= 2;
+                *ty *= 2;
+            })
+        });
+        assert!(buf
+            .raw()
+            .iter()
+            .enumerate()
+            .all(|(i, [x, y])| [*x, *y] == [i % 37 * 2, i / 37 * 2]));
+
+        // Each thread gets its own state
+        let mut counts = Buffer2d::fill([4, 64], 0);
+        counts.par_rows_mut().for_each_init(
+            || 0,
+            |n, _, row| {
+                *n += 1;
+                row.iter_mut().for_each(|c| *c = *n);
+            },
+        );
+        assert!(counts.raw().iter().all(|c| *c >= 1));
+        assert!(counts.raw().iter().filter(|c| **c == 1).count() >= 4);
+    }
+
+    #[test]
+    fn ping_pong() {
+        let mut buf = PingPong::fill([3, 2], 0);
+        // Each pass adds one to the result of the last
+        for i in 0..3 {
+            let read = buf.run_pass(|front, back| {
+                back.raw_mut()
+                    .iter_mut()
+                    .zip(front.raw())
+                    .for_each(|(b, f)| *b = f + 1);
+                front.read([2, 1])
+            });
+            assert_eq!(read, i);
+            assert_eq!(buf.front().raw(), &[i + 1; 6]);
+        }
+        buf.resize([4, 2], 9);
+        assert_eq!(buf.size(), [4, 2]);
+        assert_eq!(buf.front().raw(), &[3, 3, 3, 9, 3, 3, 3, 9]);
+        assert_eq!(buf.back().read([0, 1]), 2);
+        buf.swap();
+        assert_eq!(buf.front().read([0, 1]), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sub_view_out_of_bounds() {
+        Buffer2d::fill([6, 4], 0).sub_view_mut([4, 1], [3, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps another sub-view")]
+    fn overlapping_sub_views() {
+        Buffer2d::fill([6, 4], 0).sub_views_mut([([1, 1], [2, 3]), ([2, 0], [3, 2])]);
+    }
+
+    /// A 2D buffer whose texels are their own indices.
+    fn coords(size: [usize; 2]) -> Buffer2d<[usize; 2]> {
+        Buffer2d::from_texture(&CoordTexture(size))
+    }
+
+    #[test]
+    fn resize_grow() {
+        let mut buf = coords([3, 2]);
+        buf.resize([5, 4], [9; 2]);
+        assert_eq!(buf.size(), [5, 4]);
+        for y in 0..4 {
+            for x in 0..5 {
+                let expected = if x < 3 && y < 2 { [x, y] } else { [9; 2] };
+                assert_eq!(buf.read([x, y]), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn resize_shrink() {
+        let mut buf = coords([5, 4]);
+        let ptr = buf.raw().as_ptr();
+        buf.resize([2, 3], [9; 2]);
+        assert_eq!(buf.size(), [2, 3]);
+        assert_eq!(buf.raw(), coords([2, 3]).raw());
+        // Growing back to the original size reuses the allocation
+        buf.resize([3, 4], [9; 2]);
+        assert_eq!(buf.raw().as_ptr(), ptr);
+        assert_eq!(buf.read([1, 2]), [1, 2]);
+        assert_eq!(buf.read([2, 1]), [9; 2]);
+        assert_eq!(buf.read([0, 3]), [9; 2]);
+    }
+
+    #[test]
+    fn resize_same_size() {
+        let mut buf = coords([4, 3]);
+        let ptr = buf.raw().as_ptr();
+        buf.resize([4, 3], [9; 2]);
+        assert_eq!(buf.raw(), coords([4, 3]).raw());
+        assert_eq!(buf.raw().as_ptr(), ptr);
+    }
+
+    #[test]
+    fn resize_mixed_3d() {
+        let mut buf = Buffer3d::from_texture(&CoordTexture([4, 2, 3]));
+        buf.resize([2, 5, 0], [9; 3]);
+        assert!(buf.raw().is_empty());
+
+        let mut buf = Buffer3d::from_texture(&CoordTexture([4, 2, 3]));
+        buf.resize([2, 5, 2], [9; 3]);
+        assert_eq!(buf.size(), [2, 5, 2]);
+        assert_eq!(buf.raw().len(), 20);
+        for (i, c) in buf.raw().iter().enumerate() {
+            if *c == [9; 3] {
+                assert!(i % 10 >= 4, "{}", i);
+            } else {
+                assert_eq!(buf.linear_index(*c), i);
+            }
+        }
+    }
+
+    #[test]
+    fn clear_and_resize() {
+        let mut buf = Buffer2d::fill([4, 4], 1u8);
+        let ptr = buf.raw().as_ptr();
+        buf.clear_and_resize([2, 3], 2);
+        assert_eq!(buf.size(), [2, 3]);
+        assert_eq!(buf.raw(), &[2; 6]);
+        buf.clear_and_resize([3, 5], 3);
+        assert_eq!(buf.raw(), &[3; 15]);
+        assert_eq!(buf.raw().as_ptr(), ptr);
+    }
+
+    /// A texture whose texels are their own indices.
+    struct CoordTexture<const N: usize>([usize; N]);
+
+    impl<const N: usize> Texture<N> for CoordTexture<N> {
+        type Index = usize;
+        type Texel = [usize; N];
+        fn size(&self) -> [usize; N] {
+            self.0
+        }
+        fn read(&self, index: [usize; N]) -> [usize; N] {
+            assert!(index.iter().zip(&self.0).all(|(i, s)| i < s));
+            index
+        }
+    }
+
+    #[cfg(feature = "image")]
+    /// An image in which every byte is distinct, such that any change in layout or channel order is detected.
+    fn test_image() -> image::RgbaImage {
+        image::RgbaImage::from_fn(7, 5, |x, y| {
+            let i = (y * 7 + x) as u8 * 4;
+            image::Rgba([i, i + 1, i + 2, i + 3])
+        })
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_round_trip_bytes() {
+        let img = test_image();
+        let buf = Buffer2d::<[u8; 4]>::from_image(&img);
+        assert_eq!(buf.size(), [7, 5]);
+        assert_eq!(buf.read([3, 2]), img.get_pixel(3, 2).0);
+        assert_eq!(buf.to_image().as_raw(), img.as_raw());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_round_trip_u32() {
+        let img = test_image();
+        let buf = Buffer2d::<u32>::from_image(&img);
+        assert_eq!(buf.read([1, 0]), u32::from_le_bytes([4, 5, 6, 7]));
+        assert_eq!(buf.to_image().as_raw(), img.as_raw());
+
+        let buf = Buffer2d::fill([3, 2], 0x44332211u32);
+        assert_eq!(buf.to_image().get_pixel(2, 1).0, [0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(
+            Buffer2d::<u32>::from_image(&buf.to_image()).raw(),
+            buf.raw()
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn empty_image() {
+        let buf = Buffer2d::<u32>::from_image(&image::RgbaImage::new(0, 3));
+        assert_eq!(buf.size(), [0, 3]);
+        assert_eq!(buf.to_image().dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn iter_2d() {
+        let mut buf = Buffer2d::fill([3, 2], [0; 2]);
+        buf.iter_mut_2d().for_each(|(pos, texel)| *texel = pos);
+        assert_eq!(buf.raw(), [[0, 0], [1, 0], [2, 0], [0, 1], [1, 1], [2, 1]]);
+        assert!(buf.enumerate_pixels().all(|(pos, texel)| pos == *texel));
+        assert!(buf
+            .rows()
+            .enumerate()
+            .all(|(y, row)| row.iter().all(|t| t[1] == y)));
+
+        // Buffers without any texels have no rows
+        let mut empty = Buffer2d::fill([0, 4], 0);
+        assert_eq!(empty.rows_mut().count(), 0);
+        assert_eq!(empty.enumerate_pixels().count(), 0);
+    }
+
+    #[test]
+    fn column_major() {
+        let row_major = Buffer2d::from_texture(&CoordTexture([5, 3]));
+        let mut column_major = ColumnMajor::from_row_major(&row_major);
+        assert_eq!(column_major.size(), [5, 3]);
+        assert_eq!(column_major.raw()[..4], [[0, 0], [0, 1], [0, 2], [1, 0]]);
+        assert!(row_major
+            .enumerate_pixels()
+            .all(|(pos, texel)| column_major.read(pos) == *texel));
+
+        column_major.write(4, 1, [9, 9]);
+        assert_eq!(column_major.raw()[13], [9, 9]);
+        assert_eq!(column_major.to_row_major().read([4, 1]), [9, 9]);
+    }
+
+    #[test]
+    fn clear_depth() {
+        let mut depth = Buffer2d::fill([3, 2], 0.0f32);
+        depth.clear_depth(1.0);
+        assert!(depth.raw().iter().all(|&z| z == 1.0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "non-finite depth")]
+    fn clear_depth_nan() {
+        Buffer2d::fill([3, 2], 1.0f32).clear_depth(f32::NAN);
+    }
+
+    #[test]
+    fn mip_chain_render_levels() {
+        use crate::fullscreen::{self, FullscreenPass};
+
+        /// Averages the (up to) 2x2 texels of the level before that each texel covers.
+        struct Downsample<'a>(&'a Buffer2d<f32>);
+
+        impl FullscreenPass for Downsample<'_> {
+            type Texel = f32;
+
+            fn pixel(&self, _: [f32; 2], [x, y]: [usize; 2]) -> Self::Texel {
+                let [w, h] = self.0.size();
+                let xs = [x * 2, (x * 2 + 1).min(w - 1)];
+                let ys = [y * 2, (y * 2 + 1).min(h - 1)];
+                [[0, 0], [1, 0], [0, 1], [1, 1]]
+                    .map(|[i, j]| self.0.read([xs[i], ys[j]]) * 0.25)
+                    .into_iter()
+                    .sum()
+            }
+        }
+
+        let base = Buffer2d::fill_with([7, 5], {
+            let mut i = 0.0;
+            move || {
+                i += 1.0;
+                i
+            }
+        });
+        let generated = MipChain::generate(Buffer2d::from_texture(&base));
+
+        // The base level isn't copied
+        let ptr = base.raw().as_ptr();
+        let mut chain = MipChain::new(base, -1.0);
+        assert_eq!(chain.level(0).raw().as_ptr(), ptr);
+        // Odd sizes are rounded down
+        let sizes = chain.levels().iter().map(|l| l.size()).collect::<Vec<_>>();
+        assert_eq!(sizes, [[7, 5], [3, 2], [1, 1]]);
+
+        chain.for_each_level_pair(|src, dst| fullscreen::run(&Downsample(src), dst));
+        assert_eq!(chain.len(), generated.len());
+        for (level, expected) in chain.levels().iter().zip(generated.levels()) {
+            assert_eq!(level.raw(), expected.raw());
+        }
+
+        // Levels can be rendered into individually
+        chain.level_mut(2).clear(0.0);
+        assert_eq!(chain.level(2).read([0, 0]), 0.0);
+    }
+}
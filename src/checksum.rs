@@ -0,0 +1,675 @@
+use crate::{
+    rasterizer::Blitter,
+    texture::{Target, Texture},
+    Unit,
+};
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Types that have a canonical encoding as a sequence of words, such that equal values always produce the same
+/// encoding.
+///
+/// Floating-point values are canonicalised such that `-0.0` and `0.0` encode identically, as do all NaNs.
+pub trait CanonicalEncoding {
+    /// Emit the canonical encoding of this value.
+    fn encode<F: FnMut(u64)>(&self, write: &mut F);
+}
+
+macro_rules! impl_canonical_int {
+    ($($ty:ty),*) => {
+        $(impl CanonicalEncoding for $ty {
+            #[inline]
+            fn encode<F: FnMut(u64)>(&self, write: &mut F) {
+                write(*self as u64);
+            }
+        })*
+    };
+}
+
+impl_canonical_int!(bool, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl CanonicalEncoding for f32 {
+    #[inline]
+    fn encode<F: FnMut(u64)>(&self, write: &mut F) {
+        write(canonical_f32(*self) as u64);
+    }
+}
+
+impl CanonicalEncoding for f64 {
+    #[inline]
+    fn encode<F: FnMut(u64)>(&self, write: &mut F) {
+        write(if self.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            (*self + 0.0).to_bits()
+        });
+    }
+}
+
+impl CanonicalEncoding for () {
+    #[inline]
+    fn encode<F: FnMut(u64)>(&self, _: &mut F) {}
+}
+
+impl CanonicalEncoding for Unit {
+    #[inline]
+    fn encode<F: FnMut(u64)>(&self, _: &mut F) {}
+}
+
+impl<T: CanonicalEncoding, const N: usize> CanonicalEncoding for [T; N] {
+    #[inline]
+    fn encode<F: FnMut(u64)>(&self, write: &mut F) {
+        self.iter().for_each(|e| e.encode(write));
+    }
+}
+
+impl<T: CanonicalEncoding> CanonicalEncoding for Option<T> {
+    #[inline]
+    fn encode<F: FnMut(u64)>(&self, write: &mut F) {
+        match self {
+            Some(x) => {
+                write(1);
+                x.encode(write);
+            }
+            None => write(0),
+        }
+    }
+}
+
+macro_rules! impl_canonical_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: CanonicalEncoding),*> CanonicalEncoding for ($($name,)*) {
+            #[inline]
+            #[allow(non_snake_case)]
+            fn encode<F: FnMut(u64)>(&self, write: &mut F) {
+                let ($($name,)*) = self;
+                $($name.encode(write);)*
+            }
+        }
+    };
+}
+
+impl_canonical_tuple!(A);
+impl_canonical_tuple!(A, B);
+impl_canonical_tuple!(A, B, C);
+impl_canonical_tuple!(A, B, C, D);
+
+/// An order-independent checksum of a set of fragments.
+///
+/// Each fragment contributes a strong hash of its position, depth, and (canonically encoded) data. Contributions are
+/// combined with commutative operations, so two checksums are equal if the same fragments were added to them,
+/// regardless of the order in which they were added or how they were split between [`Checksum::merge`]d parts.
+/// Unlike comparing the final contents of a target, fragments that are emitted twice change the checksum even if they
+/// write the same value.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    count: u64,
+    sum: u64,
+    xor: u64,
+}
+
+impl Checksum {
+    /// Add a fragment to the checksum.
+    pub fn add<T: CanonicalEncoding + ?Sized>(&mut self, x: usize, y: usize, z: f32, frag: &T) {
+        let (sum, xor) = fragment_hash(x, y, z, frag);
+        self.count += 1;
+        self.sum = self.sum.wrapping_add(sum);
+        self.xor ^= xor;
+    }
+
+    /// The number of fragments added to the checksum.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Combine two checksums, as if every fragment added to either had been added to one.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            count: self.count + other.count,
+            sum: self.sum.wrapping_add(other.sum),
+            xor: self.xor ^ other.xor,
+        }
+    }
+}
+
+/// A [`Blitter`] that emits no fragments to any target, instead adding every fragment it is given to a [`Checksum`].
+///
+/// Fragments are checksummed using their position, depth, and vertex data interpolated at the fragment's position.
+/// Because it can be given to a [`Rasterizer`](crate::rasterizer::Rasterizer) directly, this allows the output of
+/// different rasterization strategies (or of the same rasterizer over different sub-regions of the target) to be
+/// compared without involving a pipeline.
+pub struct ChecksumBlitter<'a, V> {
+    tgt_size: [usize; 2],
+    tgt_min: [usize; 2],
+    tgt_max: [usize; 2],
+    checksum: &'a mut Checksum,
+    phantom: PhantomData<V>,
+}
+
+impl<'a, V> ChecksumBlitter<'a, V> {
+    /// Create a blitter for a target of the given size that accepts fragments within the given region.
+    pub fn new(
+        tgt_size: [usize; 2],
+        (tgt_min, tgt_max): ([usize; 2], [usize; 2]),
+        checksum: &'a mut Checksum,
+    ) -> Self {
+        assert!(
+            (0..2).all(|i| tgt_min[i] <= tgt_max[i] && tgt_max[i] <= tgt_size[i]),
+            "Region must be within the target",
+        );
+        Self {
+            tgt_size,
+            tgt_min,
+            tgt_max,
+            checksum,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<V: CanonicalEncoding> Blitter<V> for ChecksumBlitter<'_, V> {
+    fn target_size(&self) -> [usize; 2] {
+        self.tgt_size
+    }
+    fn target_min(&self) -> [usize; 2] {
+        self.tgt_min
+    }
+    fn target_max(&self) -> [usize; 2] {
+        self.tgt_max
+    }
+
+    fn begin_primitive(&mut self) {}
+
+    unsafe fn test_fragment(&mut self, _x: usize, _y: usize, _z: f32) -> bool {
+        true
+    }
+
+    unsafe fn emit_fragment<F: FnMut(f32, f32) -> ([f32; 3], V)>(
+        &mut self,
+        x: usize,
+        y: usize,
+        mut get_v_data: F,
+        z: f32,
+    ) {
+        debug_assert!(
+            (self.tgt_min[0]..self.tgt_max[0]).contains(&x)
+                && (self.tgt_min[1]..self.tgt_max[1]).contains(&y),
+            "Fragment at {:?} emitted outside of region {:?}..{:?}",
+            [x, y],
+            self.tgt_min,
+            self.tgt_max,
+        );
+        self.checksum
+            .add(x, y, z, &get_v_data(x as f32, y as f32).1);
+    }
+}
+
+/// A render target that discards every texel written to it, instead adding each write to a [`Checksum`].
+///
+/// Writes may come from many threads at once, so this may be used to compare the output of a pipeline between
+/// different thread counts or scheduling strategies. Reads always produce the default texel.
+pub struct ChecksumTarget<T> {
+    size: [usize; 2],
+    count: AtomicU64,
+    sum: AtomicU64,
+    xor: AtomicU64,
+    phantom: PhantomData<T>,
+}
+
+impl<T> ChecksumTarget<T> {
+    /// Create a new checksum target with the given size.
+    pub fn new(size: [usize; 2]) -> Self {
+        Self {
+            size,
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            xor: AtomicU64::new(0),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Get the checksum of every texel written to this target so far.
+    pub fn checksum(&self) -> Checksum {
+        Checksum {
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum.load(Ordering::Relaxed),
+            xor: self.xor.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T: Clone + Default> Texture<2> for ChecksumTarget<T> {
+    type Index = usize;
+    type Texel = T;
+
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 2] {
+        self.size
+    }
+
+    #[inline(always)]
+    fn read(&self, _: [Self::Index; 2]) -> Self::Texel {
+        T::default()
+    }
+}
+
+impl<T: Clone + Default + CanonicalEncoding> Target for ChecksumTarget<T> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, _: usize, _: usize) -> Self::Texel {
+        T::default()
+    }
+
+    #[inline]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        // Targets don't know the depth of the fragment that produced a texel
+        let (sum, xor) = fragment_hash(x, y, 0.0, &texel);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(sum, Ordering::Relaxed);
+        self.xor.fetch_xor(xor, Ordering::Relaxed);
+    }
+}
+
+fn canonical_f32(x: f32) -> u32 {
+    if x.is_nan() {
+        f32::NAN.to_bits()
+    } else {
+        // Adding zero turns `-0.0` into `0.0`
+        (x + 0.0).to_bits()
+    }
+}
+
+/// Hash a fragment into two independent 64-bit words.
+fn fragment_hash<T: CanonicalEncoding + ?Sized>(
+    x: usize,
+    y: usize,
+    z: f32,
+    frag: &T,
+) -> (u64, u64) {
+    let mut a = 0x243f_6a88_85a3_08d3u64;
+    let mut b = 0x1319_8a2e_0370_7344u64;
+    let mut write = |word: u64| {
+        a = mix(a ^ word);
+        b = mix(b.wrapping_add(word).rotate_left(23));
+    };
+    write(x as u64);
+    write(y as u64);
+    write(canonical_f32(z) as u64);
+    frag.encode(&mut write);
+    (mix(a), mix(b ^ 0xa409_3822_299f_31d0))
+}
+
+/// The splitmix64 finaliser.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::Buffer2d,
+        pipeline::{AaMode, Parallelism, Pipeline},
+        primitives::TriangleList,
+        procedural::hash,
+        rasterizer::{CullMode, LineMode, Lines, Rasterizer, Triangles},
+        texture::Empty,
+        CoordinateMode,
+    };
+    use alloc::vec::Vec;
+
+    const SIZE: [usize; 2] = [53, 211];
+
+    /// Overlapping triangles that are larger than the target, some of which cross the near plane.
+    fn triangles() -> Vec<([f32; 4], f32)> {
+        (0..90)
+            .map(|i| {
+                let f = i as f32;
+                let w = if i % 13 == 0 { -0.4 } else { 1.0 };
+                (
+                    [
+                        (f * 0.41).sin() * 1.3,
+                        (f * 0.67).cos() * 1.3,
+                        (f * 0.19).sin() * 0.5 + 0.5,
+                        w,
+                    ],
+                    (f * 1.7).cos(),
+                )
+            })
+            .collect()
+    }
+
+    fn rasterize<B: Blitter<f32>>(blitter: B) {
+        unsafe {
+            Triangles.rasterize(
+                triangles().into_iter(),
+                true,
+                CoordinateMode::default(),
+                CullMode::None,
+                blitter,
+            )
+        }
+    }
+
+    fn rasterize_region(region: ([usize; 2], [usize; 2])) -> Checksum {
+        let mut checksum = Checksum::default();
+        rasterize(ChecksumBlitter::new(SIZE, region, &mut checksum));
+        checksum
+    }
+
+    #[test]
+    fn order_independent() {
+        let frags = (0..100)
+            .map(|i| (i % 7, i / 7, i as f32 * 0.01, [i as f32; 2]))
+            .collect::<Vec<_>>();
+        let add = |checksum: &mut Checksum, &(x, y, z, frag): &(usize, usize, f32, [f32; 2])| {
+            checksum.add(x, y, z, &frag)
+        };
+
+        let mut forward = Checksum::default();
+        frags.iter().for_each(|f| add(&mut forward, f));
+        let mut reverse = Checksum::default();
+        frags.iter().rev().for_each(|f| add(&mut reverse, f));
+        let (mut a, mut b) = (Checksum::default(), Checksum::default());
+        frags.iter().step_by(2).for_each(|f| add(&mut a, f));
+        frags.iter().skip(1).step_by(2).for_each(|f| add(&mut b, f));
+
+        assert_eq!(forward.count(), 100);
+        assert_eq!(forward, reverse);
+        assert_eq!(forward, a.merge(b));
+        assert_eq!(forward, b.merge(a));
+    }
+
+    #[test]
+    fn canonical_floats() {
+        let hash = |z: f32, frag: f32| {
+            let mut checksum = Checksum::default();
+            checksum.add(1, 2, z, &frag);
+            checksum
+        };
+        assert_eq!(hash(0.0, -0.0), hash(-0.0, 0.0));
+        assert_eq!(hash(f32::NAN, 1.0), hash(f32::from_bits(0xffc0_1234), 1.0));
+        assert_eq!(hash(0.5, f32::NAN), hash(0.5, -f32::NAN));
+        assert_ne!(hash(0.5, 1.0), hash(0.5, -1.0));
+        assert_ne!(hash(0.5, 1.0), hash(1.0, 0.5));
+    }
+
+    #[test]
+    fn regions_match_full_target() {
+        let full = rasterize_region(([0; 2], SIZE));
+        assert!(full.count() > 0);
+
+        // Rows
+        for rows in [1, 7, 64] {
+            let split = (0..SIZE[1])
+                .step_by(rows)
+                .map(|y| rasterize_region(([0, y], [SIZE[0], (y + rows).min(SIZE[1])])))
+                .fold(Checksum::default(), Checksum::merge);
+            assert_eq!(split, full, "{} rows per region", rows);
+        }
+
+        // Tiles
+        let tile = [8, 32];
+        let split = (0..SIZE[1])
+            .step_by(tile[1])
+            .flat_map(|y| (0..SIZE[0]).step_by(tile[0]).map(move |x| [x, y]))
+            .map(|[x, y]| {
+                rasterize_region((
+                    [x, y],
+                    [(x + tile[0]).min(SIZE[0]), (y + tile[1]).min(SIZE[1])],
+                ))
+            })
+            .fold(Checksum::default(), Checksum::merge);
+        assert_eq!(split, full);
+    }
+
+    /// The ways in which a single fragment may be perturbed.
+    #[derive(Copy, Clone, Debug)]
+    enum Perturbation {
+        Drop,
+        Duplicate,
+        Move,
+        Depth,
+        Data,
+    }
+
+    /// A blitter that perturbs the `n`th fragment emitted to it.
+    struct Perturb<'a> {
+        inner: ChecksumBlitter<'a, f32>,
+        n: usize,
+        perturbation: Perturbation,
+    }
+
+    impl Blitter<f32> for Perturb<'_> {
+        fn target_size(&self) -> [usize; 2] {
+            self.inner.target_size()
+        }
+        fn target_min(&self) -> [usize; 2] {
+            self.inner.target_min()
+        }
+        fn target_max(&self) -> [usize; 2] {
+            self.inner.target_max()
+        }
+
+        fn begin_primitive(&mut self) {}
+
+        unsafe fn test_fragment(&mut self, x: usize, y: usize, z: f32) -> bool {
+            self.inner.test_fragment(x, y, z)
+        }
+
+        unsafe fn emit_fragment<F: FnMut(f32, f32) -> ([f32; 3], f32)>(
+            &mut self,
+            x: usize,
+            y: usize,
+            mut get_v_data: F,
+            z: f32,
+        ) {
+            let n = self.n;
+            self.n = self.n.wrapping_sub(1);
+            if n != 0 {
+                return self.inner.emit_fragment(x, y, get_v_data, z);
+            }
+
+            let next_up = |x: f32| f32::from_bits(x.to_bits() + 1);
+            match self.perturbation {
+                Perturbation::Drop => {}
+                Perturbation::Duplicate => {
+                    self.inner.emit_fragment(x, y, &mut get_v_data, z);
+                    self.inner.emit_fragment(x, y, get_v_data, z);
+                }
+                Perturbation::Move => self
+                    .inner
+                    .emit_fragment((x + 1) % SIZE[0], y, get_v_data, z),
+                Perturbation::Depth => self.inner.emit_fragment(x, y, get_v_data, next_up(z)),
+                Perturbation::Data => self.inner.emit_fragment(
+                    x,
+                    y,
+                    |x, y| {
+                        let (bary, v_data) = get_v_data(x, y);
+                        (bary, next_up(v_data))
+                    },
+                    z,
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn perturbation_is_detected() {
+        let full = rasterize_region(([0; 2], SIZE));
+        for n in [0, 1, 1000, full.count() as usize - 1] {
+            for perturbation in [
+                Perturbation::Drop,
+                Perturbation::Duplicate,
+                Perturbation::Move,
+                Perturbation::Depth,
+                Perturbation::Data,
+            ] {
+                let mut checksum = Checksum::default();
+                rasterize(Perturb {
+                    inner: ChecksumBlitter::new(SIZE, ([0; 2], SIZE), &mut checksum),
+                    n,
+                    perturbation,
+                });
+                assert_ne!(checksum, full, "{:?} of fragment {}", perturbation, n);
+            }
+        }
+    }
+
+    struct Fragments {
+        aa_mode: AaMode,
+        parallelism: Parallelism,
+    }
+
+    impl<'r> Pipeline<'r> for Fragments {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn aa_mode(&self) -> AaMode {
+            self.aa_mode
+        }
+        fn parallelism(&self) -> Parallelism {
+            self.parallelism
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, (pos, x): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, *x)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            x
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    fn render(aa_mode: AaMode, parallelism: Parallelism) -> Checksum {
+        let mut target = ChecksumTarget::new(SIZE);
+        Fragments {
+            aa_mode,
+            parallelism,
+        }
+        .render(triangles(), &mut target, &mut Empty::depth());
+        target.checksum()
+    }
+
+    #[test]
+    fn render_paths_match() {
+        // Without depth testing, every rasterized fragment is written to the target
+        let sequential = Parallelism::DEFAULT
+            .with_max_threads(1)
+            .with_fragments_per_group(usize::MAX);
+        assert_eq!(
+            render(AaMode::None, sequential).count(),
+            rasterize_region(([0; 2], SIZE)).count(),
+        );
+
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 2,
+                centroid: false,
+            },
+        ] {
+            let expected = render(aa_mode, sequential);
+            assert!(expected.count() > 0);
+            for fragments_per_group in [1, 500, 20_000, usize::MAX] {
+                for max_threads in [1, 2, 5] {
+                    let parallelism = Parallelism::DEFAULT
+                        .with_fragments_per_group(fragments_per_group)
+                        .with_max_threads(max_threads);
+                    assert_eq!(
+                        render(aa_mode, parallelism),
+                        expected,
+                        "{:?} with {:?}",
+                        aa_mode,
+                        parallelism,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Vertices with a mixture of ordinary, tiny, huge, infinite, and NaN coordinates.
+    fn degenerate_vertices() -> Vec<([f32; 4], f32)> {
+        const VALUES: [f32; 10] = [
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            1.0e-30,
+            -1.0e-30,
+            1.0e30,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+        ];
+        (0..3000)
+            .map(|i| {
+                let e = |j| match hash(i, j, 0, 0) {
+                    h if h % 2 == 0 => VALUES[(h >> 1) as usize % VALUES.len()],
+                    h => (h >> 8) as f32 / (1 << 24) as f32 * 4.0 - 2.0,
+                };
+                ([e(0), e(1), e(2), e(3)], e(4))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn degenerate_vertices_stay_in_bounds() {
+        // The blitter asserts that every fragment lies within its region
+        let region = ([5, 20], [40, 150]);
+        for cull_mode in [CullMode::None, CullMode::Back] {
+            let mut checksum = Checksum::default();
+            unsafe {
+                Triangles.rasterize(
+                    degenerate_vertices().into_iter(),
+                    true,
+                    CoordinateMode::default(),
+                    cull_mode,
+                    ChecksumBlitter::new(SIZE, region, &mut checksum),
+                )
+            }
+        }
+        for line_mode in [LineMode::Aliased, LineMode::AntiAliased] {
+            let mut checksum = Checksum::default();
+            unsafe {
+                Lines.rasterize(
+                    degenerate_vertices().into_iter(),
+                    true,
+                    CoordinateMode::default(),
+                    line_mode,
+                    ChecksumBlitter::new(SIZE, region, &mut checksum),
+                )
+            }
+        }
+
+        // Pipelines also divide primitives between groups according to their bounds
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 4,
+                centroid: false,
+            },
+        ] {
+            Fragments {
+                aa_mode,
+                parallelism: Parallelism::DEFAULT.with_fragments_per_group(500),
+            }
+            .render(
+                degenerate_vertices(),
+                &mut Buffer2d::fill(SIZE, 0.0),
+                &mut Empty::depth(),
+            );
+        }
+    }
+}
@@ -0,0 +1,48 @@
+/// The reason that a primitive (or part of one) produced no fragments.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CullReason {
+    /// The triangle faces in the direction culled by its [`CullMode`](crate::CullMode).
+    Facing,
+    /// The triangle has no area on the screen (or has non-finite vertices), or less than that required by
+    /// [`CullMode::MinArea`](crate::CullMode::MinArea).
+    ZeroArea,
+    /// The primitive lies entirely beyond one of the planes of [`CoordinateMode::z_clip_range`](crate::CoordinateMode).
+    ZClip,
+    /// The primitive lies entirely outside of the target, or behind the camera.
+    OffScreen,
+    /// The primitive is entirely hidden behind the contents of the depth target (see
+    /// [`Pipeline::use_hi_z`](crate::Pipeline::use_hi_z)).
+    Occluded,
+}
+
+/// Callbacks that observe the work done by the rasterizer, for debugging (see
+/// [`Pipeline::render_debug`](crate::Pipeline::render_debug)).
+///
+/// Every method does nothing by default.
+pub trait DebugHooks {
+    /// Called when a primitive begins to be rasterized, with the clip-space positions of its vertices.
+    fn on_primitive(&self, index: usize, clip_positions: &[[f32; 4]]) {
+        let _ = (index, clip_positions);
+    }
+
+    /// Called when a primitive produces no fragments, with the reason that it was culled.
+    ///
+    /// Triangles that extend far beyond the target get clipped into several triangles before being rasterized, each of
+    /// which may be culled separately.
+    fn on_primitive_culled(&self, index: usize, reason: CullReason) {
+        let _ = (index, reason);
+    }
+
+    /// Called when a fragment covered by a primitive is tested, with whether it passed depth and stencil testing.
+    ///
+    /// When using [`AaMode::Ssaa`](crate::AaMode::Ssaa), fragments are positioned within the supersampled target.
+    fn on_fragment(&self, x: usize, y: usize, z: f32, passed_depth: bool) {
+        let _ = (x, y, z, passed_depth);
+    }
+}
+
+/// Debug hooks that do nothing, used when rendering without them.
+pub(crate) struct NoHooks;
+
+impl DebugHooks for NoHooks {}
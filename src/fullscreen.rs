@@ -0,0 +1,163 @@
+use crate::texture::Target;
+
+/// A pass that computes each pixel of a target independently of the others, such as tone mapping, blits, or the
+/// per-cell updates of a simulation (see [`run`]).
+///
+/// Unlike drawing a pair of triangles that cover the target with a [`Pipeline`](crate::Pipeline), no vertices or
+/// primitives are involved: the pass is simply called once for every pixel.
+///
+/// ```
+/// # use euc::{fullscreen::{self, FullscreenPass}, Buffer2d, Texture};
+/// /// Applies the Reinhard tone mapping operator to HDR colours.
+/// struct ToneMap;
+///
+/// impl FullscreenPass for ToneMap {
+///     type Texel = [f32; 3];
+///
+///     fn pixel(&self, _uv: [f32; 2], _frag_coord: [usize; 2]) -> Self::Texel {
+///         unreachable!("Tone mapping only depends on the existing value of each pixel")
+///     }
+///
+///     fn blend(&self, old: Self::Texel, _uv: [f32; 2], _frag_coord: [usize; 2]) -> Self::Texel {
+///         old.map(|c| c / (1.0 + c))
+///     }
+/// }
+///
+/// let mut hdr = Buffer2d::fill([64, 48], [3.0, 1.0, 0.0]);
+/// fullscreen::run(&ToneMap, &mut hdr);
+/// assert_eq!(hdr.read([10, 20]), [0.75, 0.5, 0.0]);
+/// ```
+pub trait FullscreenPass: Sync {
+    /// The type of the pixels of the target.
+    type Texel: Clone;
+
+    /// Compute the value of the pixel at `frag_coord`.
+    ///
+    /// `uv` is the position of the centre of the pixel, between `0.0` and `1.0` on both axes, such that sampling a
+    /// texture of the same size at `uv` with [`Nearest`](crate::Nearest) reads the texel at `frag_coord`.
+    fn pixel(&self, uv: [f32; 2], frag_coord: [usize; 2]) -> Self::Texel;
+
+    /// Compute the new value of the pixel at `frag_coord` from its existing value.
+    ///
+    /// By default, the existing value is replaced by [`FullscreenPass::pixel`]. Passes that blend with or transform
+    /// the existing value (such as additive blending or tone mapping) may override this instead.
+    #[inline]
+    fn blend(&self, old: Self::Texel, uv: [f32; 2], frag_coord: [usize; 2]) -> Self::Texel {
+        let _ = old;
+        self.pixel(uv, frag_coord)
+    }
+}
+
+/// Run a [`FullscreenPass`] over every pixel of the target, in parallel if the `par` feature is enabled.
+///
+/// Pixels are visited along the preferred axes of the target (see [`Texture::preferred_axes`](crate::Texture)), and
+/// each row (or column) is processed by a single thread.
+pub fn run<P, T>(pass: &P, target: &mut T)
+where
+    P: FullscreenPass,
+    T: Target<Texel = P::Texel> + Send + Sync,
+{
+    let size = target.size();
+    let [inner, outer] = match target.preferred_axes() {
+        Some([1, 0]) => [1, 0],
+        _ => [0, 1],
+    };
+    let target = &*target;
+
+    let run_line = |o: usize| {
+        for i in 0..size[inner] {
+            let mut frag_coord = [0; 2];
+            frag_coord[inner] = i;
+            frag_coord[outer] = o;
+            let [x, y] = frag_coord;
+            let uv = [
+                (x as f32 + 0.5) / size[0] as f32,
+                (y as f32 + 0.5) / size[1] as f32,
+            ];
+            // Safety: the position lies within the target, and each line is only processed by a single thread
+            unsafe {
+                let old = target.read_exclusive_unchecked(x, y);
+                target.write_exclusive_unchecked(x, y, pass.blend(old, uv, frag_coord));
+            }
+        }
+    };
+
+    #[cfg(not(feature = "par"))]
+    (0..size[outer]).for_each(run_line);
+
+    #[cfg(feature = "par")]
+    crate::par::for_each(0..size[outer], run_line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::Buffer2d, sampler::Sampler, texture::Texture};
+
+    /// Copies a texture through a sampler, optionally adding it to the existing contents of the target.
+    struct Blit<S> {
+        sampler: S,
+        add: bool,
+    }
+
+    impl<S: Sampler<2, Index = f32, Sample = f32> + Sync> FullscreenPass for Blit<S> {
+        type Texel = f32;
+
+        fn pixel(&self, uv: [f32; 2], _: [usize; 2]) -> Self::Texel {
+            self.sampler.sample(uv)
+        }
+
+        fn blend(&self, old: Self::Texel, uv: [f32; 2], frag_coord: [usize; 2]) -> Self::Texel {
+            let new = self.pixel(uv, frag_coord);
+            if self.add {
+                old + new
+            } else {
+                new
+            }
+        }
+    }
+
+    #[test]
+    fn blit() {
+        let src = Buffer2d::fill_with([37, 23], {
+            let mut i = 0;
+            move || {
+                i += 1;
+                (i * 7 % 31) as f32
+            }
+        });
+
+        // Sampling at the UV of each pixel reads the corresponding texel
+        let mut dst = Buffer2d::fill([37, 23], -1.0);
+        for add in [false, true] {
+            let blit = Blit {
+                sampler: (&src).nearest(),
+                add,
+            };
+            run(&blit, &mut dst);
+        }
+        assert!(dst.raw().iter().zip(src.raw()).all(|(d, s)| *d == *s * 2.0));
+    }
+
+    #[test]
+    fn frag_coords() {
+        struct Coords;
+
+        impl FullscreenPass for Coords {
+            type Texel = [usize; 2];
+
+            fn pixel(&self, uv: [f32; 2], frag_coord: [usize; 2]) -> Self::Texel {
+                let [x, y] = frag_coord.map(|e| e as f32);
+                assert_eq!(uv, [(x + 0.5) / 5.0, (y + 0.5) / 3.0]);
+                frag_coord
+            }
+        }
+
+        let mut coords = Buffer2d::fill([5, 3], [usize::MAX; 2]);
+        run(&Coords, &mut coords);
+        assert!(coords.enumerate_pixels().all(|(pos, texel)| pos == *texel));
+
+        // Targets without any pixels are left alone
+        run(&Coords, &mut Buffer2d::fill([0, 3], [0; 2]));
+    }
+}
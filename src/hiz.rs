@@ -0,0 +1,349 @@
+use crate::texture::{Target, Texture};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// The size, in texels, of the tiles in the finest level of a [`HiZ`] pyramid.
+const TILE_SIZE: usize = 8;
+
+/// The maximum number of tiles that are inspected when determining the bounds of a region.
+const MAX_QUERY_TILES: usize = 16;
+
+/// A depth target that keeps track of the range of depths within coarse tiles, allowing the renderer to skip
+/// primitives that are entirely hidden without testing each of their fragments.
+///
+/// `HiZ` wraps another depth target, and is used in its place. It maintains a pyramid of tiles, from 8x8 texels up
+/// to a single tile covering the whole target, each of which stores the lowest and highest depths within it.
+/// Pipelines opt into using it with [`crate::Pipeline::use_hi_z`]. This never changes the rendered image, but can
+/// avoid a great deal of work in scenes with heavy overdraw, particularly when primitives are drawn front-to-back.
+///
+/// Depth writes made through `HiZ` keep the tiles up to date, but only ever widen their ranges: as depths are
+/// replaced by nearer depths (as with [`crate::DepthMode::LESS_WRITE`]), the ranges become increasingly
+/// conservative. [`HiZ::rebuild`] recalculates the ranges exactly, and is worth calling between passes.
+pub struct HiZ<D> {
+    depth: D,
+    // Each level has tiles twice the size of the level before it, and the last level has a single tile
+    levels: Vec<Level>,
+}
+
+struct Level {
+    size: [usize; 2],
+    // The lowest and highest depths of each tile, as the bits of an `f32`
+    bounds: Vec<[AtomicU32; 2]>,
+}
+
+/// Widen a depth range to include the given depth. Since NaN depths are not ordered, they make the range unbounded.
+fn widen([min, max]: [f32; 2], z: f32) -> [f32; 2] {
+    if z.is_nan() {
+        [f32::NEG_INFINITY, f32::INFINITY]
+    } else {
+        [min.min(z), max.max(z)]
+    }
+}
+
+impl<D: Target<Texel = f32>> HiZ<D> {
+    /// Wrap a depth target, calculating the depth ranges of its contents.
+    pub fn new(depth: D) -> Self {
+        let mut levels = Vec::new();
+        let [w, h] = depth.size();
+        loop {
+            let tile_size = TILE_SIZE << levels.len();
+            let size = [w.div_ceil(tile_size).max(1), h.div_ceil(tile_size).max(1)];
+            levels.push(Level {
+                size,
+                bounds: (0..size[0] * size[1])
+                    .map(|_| [AtomicU32::new(0), AtomicU32::new(0)])
+                    .collect(),
+            });
+            if size == [1, 1] {
+                break;
+            }
+        }
+        let mut this = Self { depth, levels };
+        this.rebuild();
+        this
+    }
+
+    /// Recalculate the exact depth range of each tile from the contents of the depth target.
+    pub fn rebuild(&mut self) {
+        let [w, h] = self.depth.size();
+        let mut finer = Vec::new();
+        let mut finer_size = [0; 2];
+        for (i, level) in self.levels.iter_mut().enumerate() {
+            let mut ranges = Vec::with_capacity(level.bounds.len());
+            for ty in 0..level.size[1] {
+                for tx in 0..level.size[0] {
+                    let mut range = [f32::INFINITY, f32::NEG_INFINITY];
+                    if i == 0 {
+                        for y in ty * TILE_SIZE..((ty + 1) * TILE_SIZE).min(h) {
+                            for x in tx * TILE_SIZE..((tx + 1) * TILE_SIZE).min(w) {
+                                range = widen(range, self.depth.read([x, y]));
+                            }
+                        }
+                    } else {
+                        // Combine the (up to) 4 tiles of the finer level that this tile covers
+                        for y in ty * 2..(ty * 2 + 2).min(finer_size[1]) {
+                            for x in tx * 2..(tx * 2 + 2).min(finer_size[0]) {
+                                let [min, max]: [f32; 2] = finer[y * finer_size[0] + x];
+                                range = [range[0].min(min), range[1].max(max)];
+                            }
+                        }
+                    }
+                    ranges.push(range);
+                }
+            }
+            for (tile, range) in level.bounds.iter_mut().zip(&ranges) {
+                *tile[0].get_mut() = range[0].to_bits();
+                *tile[1].get_mut() = range[1].to_bits();
+            }
+            finer = ranges;
+            finer_size = level.size;
+        }
+    }
+
+    /// Get a reference to the underlying depth target.
+    pub fn inner(&self) -> &D {
+        &self.depth
+    }
+
+    /// Unwrap the underlying depth target.
+    pub fn into_inner(self) -> D {
+        self.depth
+    }
+
+    /// Widen the range of every tile containing the given texel to include the given depth.
+    fn update(&self, x: usize, y: usize, z: f32) {
+        let [min, max] = widen([z; 2], z);
+        for (i, level) in self.levels.iter().enumerate() {
+            let tile_size = TILE_SIZE << i;
+            let [tile_min, tile_max] =
+                &level.bounds[(y / tile_size) * level.size[0] + x / tile_size];
+            // Other threads may be widening the same tile at the same time
+            let _ = tile_min.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+                (min < f32::from_bits(old)).then_some(min.to_bits())
+            });
+            let _ = tile_max.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+                (max > f32::from_bits(old)).then_some(max.to_bits())
+            });
+        }
+    }
+}
+
+impl<D: Target<Texel = f32>> Texture<2> for HiZ<D> {
+    type Index = usize;
+    type Texel = f32;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 2] {
+        self.depth.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; 2]> {
+        self.depth.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; 2]) -> Self::Texel {
+        self.depth.read(index)
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; 2]) -> Self::Texel {
+        self.depth.read_unchecked(index)
+    }
+}
+
+impl<D: Target<Texel = f32>> Target for HiZ<D> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        self.depth.read_exclusive_unchecked(x, y)
+    }
+    #[inline]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        self.update(x, y, texel);
+        self.depth.write_exclusive_unchecked(x, y, texel);
+    }
+    #[inline]
+    unsafe fn write_unchecked(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        self.update(x, y, texel);
+        self.depth.write_unchecked(x, y, texel);
+    }
+    fn clear(&mut self, texel: Self::Texel) {
+        self.depth.clear(texel);
+        let [min, max] = widen([texel; 2], texel);
+        for tile in self.levels.iter_mut().flat_map(|level| &mut level.bounds) {
+            *tile[0].get_mut() = min.to_bits();
+            *tile[1].get_mut() = max.to_bits();
+        }
+    }
+    fn texel_bounds(&self, min: [usize; 2], max: [usize; 2]) -> Option<[Self::Texel; 2]> {
+        if min[0] >= max[0] || min[1] >= max[1] {
+            return None;
+        }
+        // Use the finest level at which only a handful of tiles cover the region
+        let (i, level) = self.levels.iter().enumerate().find(|(i, level)| {
+            let tile_size = TILE_SIZE << i;
+            let tiles = |axis: usize| (max[axis] - 1) / tile_size - min[axis] / tile_size + 1;
+            tiles(0) * tiles(1) <= MAX_QUERY_TILES || level.size == [1, 1]
+        })?;
+        let tile_size = TILE_SIZE << i;
+        let mut range = [f32::INFINITY, f32::NEG_INFINITY];
+        for ty in min[1] / tile_size..=((max[1] - 1) / tile_size).min(level.size[1] - 1) {
+            for tx in min[0] / tile_size..=((max[0] - 1) / tile_size).min(level.size[0] - 1) {
+                let [min, max] = level.bounds[ty * level.size[0] + tx]
+                    .each_ref()
+                    .map(|bound| f32::from_bits(bound.load(Ordering::Relaxed)));
+                range = [range[0].min(min), range[1].max(max)];
+            }
+        }
+        Some(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::Buffer2d,
+        pipeline::{DepthMode, Pipeline},
+        primitives::TriangleList,
+        rasterizer::CullMode,
+    };
+    use core::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn bounds_track_writes() {
+        let mut depth = HiZ::new(Buffer2d::fill([20, 13], 1.0));
+        assert_eq!(depth.texel_bounds([0, 0], [20, 13]), Some([1.0, 1.0]));
+
+        depth.write(3, 4, 0.25);
+        assert_eq!(depth.texel_bounds([0, 0], [8, 8]), Some([0.25, 1.0]));
+        assert_eq!(depth.texel_bounds([2, 3], [5, 6]), Some([0.25, 1.0]));
+        assert_eq!(depth.texel_bounds([8, 0], [16, 8]), Some([1.0, 1.0]));
+        assert_eq!(depth.texel_bounds([0, 0], [20, 13]), Some([0.25, 1.0]));
+
+        // Ranges are only widened by writes, until they are rebuilt
+        depth.write(3, 4, 1.0);
+        assert_eq!(depth.texel_bounds([0, 0], [8, 8]), Some([0.25, 1.0]));
+        depth.rebuild();
+        assert_eq!(depth.texel_bounds([0, 0], [8, 8]), Some([1.0, 1.0]));
+
+        // NaN depths can't be bounded
+        depth.write(19, 12, f32::NAN);
+        assert_eq!(
+            depth.texel_bounds([16, 8], [20, 13]),
+            Some([f32::NEG_INFINITY, f32::INFINITY])
+        );
+
+        depth.clear(0.5);
+        assert_eq!(depth.texel_bounds([0, 0], [20, 13]), Some([0.5, 0.5]));
+        assert_eq!(depth.texel_bounds([4, 4], [4, 9]), None);
+    }
+
+    /// A depth target that counts the number of times that it is tested against.
+    struct Counting(Buffer2d<f32>, AtomicUsize);
+
+    impl Texture<2> for Counting {
+        type Index = usize;
+        type Texel = f32;
+        fn size(&self) -> [usize; 2] {
+            self.0.size()
+        }
+        fn read(&self, index: [usize; 2]) -> f32 {
+            self.0.read(index)
+        }
+    }
+
+    impl Target for Counting {
+        unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> f32 {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.read_exclusive_unchecked(x, y)
+        }
+        unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: f32) {
+            self.0.write_exclusive_unchecked(x, y, texel)
+        }
+    }
+
+    struct Occluded {
+        hi_z: bool,
+    }
+
+    impl<'r> Pipeline<'r> for Occluded {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn use_hi_z(&self) -> bool {
+            self.hi_z
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, (pos, x): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, *x)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            x
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    /// Triangles at a range of depths, mostly drawn front-to-back.
+    fn triangles() -> impl Iterator<Item = ([f32; 4], f32)> {
+        (0..600).map(|i| {
+            let f = i as f32;
+            (
+                [
+                    (f * 0.37).sin() * 1.2,
+                    (f * 0.71).cos() * 1.2,
+                    f / 600.0 + (f * 0.13).sin() * 0.2,
+                    1.0,
+                ],
+                f,
+            )
+        })
+    }
+
+    #[test]
+    fn hidden_triangles_are_skipped() {
+        let render = |hi_z| {
+            let mut color = Buffer2d::fill([64, 48], 0.0);
+            let mut depth = HiZ::new(Counting(
+                Buffer2d::fill([64, 48], 0.95),
+                AtomicUsize::new(0),
+            ));
+            let triangles = triangles().collect::<Vec<_>>();
+            let (front, back) = triangles.split_at(300);
+            Occluded { hi_z }.render(front, &mut color, &mut depth);
+            depth.rebuild();
+            depth.inner().1.store(0, Ordering::Relaxed);
+            Occluded { hi_z }.render(back, &mut color, &mut depth);
+            let tests = depth.inner().1.swap(0, Ordering::Relaxed);
+
+            // A triangle behind everything in the depth target
+            Occluded { hi_z }.render(
+                [
+                    ([-1.0, -1.0, 0.99, 1.0], -1.0),
+                    ([3.0, -1.0, 0.99, 1.0], -1.0),
+                    ([-1.0, 3.0, 0.99, 1.0], -1.0),
+                ],
+                &mut color,
+                &mut depth,
+            );
+            let hidden_tests = depth.inner().1.load(Ordering::Relaxed);
+            (color, depth.into_inner().0, tests, hidden_tests)
+        };
+
+        let (color, depth, tests, hidden_tests) = render(false);
+        let (hi_z_color, hi_z_depth, hi_z_tests, hi_z_hidden_tests) = render(true);
+        assert!(color.raw().iter().all(|px| *px >= 0.0));
+        assert_eq!(color.raw(), hi_z_color.raw());
+        assert_eq!(depth.raw(), hi_z_depth.raw());
+        assert!(hi_z_tests < tests, "{} < {}", hi_z_tests, tests);
+        assert!(hidden_tests > 0);
+        assert_eq!(hi_z_hidden_tests, 0);
+    }
+}
@@ -1,6 +1,47 @@
 use core::{borrow::Borrow, marker::PhantomData};
 
+/// A type that may be used to index into a vertex buffer (see [`IndexedVertices`]).
+///
+/// This is implemented for `u16`, `u32`, and `usize` (and references to them), so index buffers may be used without
+/// first converting them.
+pub trait Index: Copy {
+    /// Convert the index into a `usize`.
+    fn to_usize(self) -> usize;
+}
+
+impl Index for u16 {
+    #[inline(always)]
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl Index for u32 {
+    #[inline(always)]
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl Index for usize {
+    #[inline(always)]
+    fn to_usize(self) -> usize {
+        self
+    }
+}
+
+impl<T: Index> Index for &T {
+    #[inline(always)]
+    fn to_usize(self) -> usize {
+        (*self).to_usize()
+    }
+}
+
 /// A helper type that makes indexed vertex access easier.
+///
+/// # Panics
+///
+/// Iterating panics if an index is out of range for the vertices.
 pub struct IndexedVertices<'a, Is, Vs, I, V> {
     indices: Is,
     verts: Vs,
@@ -19,7 +60,7 @@ impl<'a, Is, Vs, I, V> IndexedVertices<'a, Is, Vs, I, V> {
 
 impl<'a, Is, Vs, I, V> IntoIterator for IndexedVertices<'a, Is, Vs, I, V>
 where
-    I: Borrow<usize>,
+    I: Index,
     Is: IntoIterator<Item = I> + 'a,
     Vs: Borrow<&'a [V]> + 'a,
 {
@@ -43,13 +84,61 @@ pub struct IndexedVerticesIter<'a, Is: Iterator, Vs, I, V> {
 
 impl<'a, Is: Iterator, Vs, I, V> Iterator for IndexedVerticesIter<'a, Is, Vs, I, V>
 where
-    I: Borrow<usize>,
+    I: Index,
     Is: Iterator<Item = I> + 'a,
     Vs: Borrow<&'a [V]> + 'a,
 {
     type Item = &'a V;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(&self.verts.borrow()[*self.indices.next()?.borrow()])
+        let index = self.indices.next()?.to_usize();
+        let verts: &'a [V] = self.verts.borrow();
+        match verts.get(index) {
+            Some(vert) => Some(vert),
+            None => panic!(
+                "Vertex index {} is out of range for {} vertices",
+                index,
+                verts.len(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const VERTS: &[char] = &['a', 'b', 'c', 'd'];
+
+    fn fetch<I: Index>(indices: &[I]) -> Vec<char> {
+        IndexedVertices::new(indices, VERTS)
+            .into_iter()
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn index_widths() {
+        let expected = ['d', 'a', 'c', 'a'];
+        assert_eq!(fetch::<u16>(&[3, 0, 2, 0]), expected);
+        assert_eq!(fetch::<u32>(&[3, 0, 2, 0]), expected);
+        assert_eq!(fetch::<usize>(&[3, 0, 2, 0]), expected);
+        // Indices may also be given by value
+        let by_value = IndexedVertices::new([3u16, 0, 2, 0], VERTS);
+        assert_eq!(by_value.into_iter().copied().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn empty_indices() {
+        assert!(fetch::<u16>(&[]).is_empty());
+        assert!(fetch::<u32>(&[]).is_empty());
+        assert!(fetch::<usize>(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Vertex index 4 is out of range for 4 vertices")]
+    fn index_out_of_range() {
+        fetch::<u32>(&[0, 4, 1]);
     }
 }
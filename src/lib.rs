@@ -45,16 +45,40 @@ extern crate alloc;
 #[cfg(any(feature = "par", not(feature = "micromath")))]
 extern crate std;
 
+/// Baking of lighting information into meshes.
+pub mod bake;
+/// Copies between textures and targets.
+pub mod blit;
 /// N-dimensional buffers that may be used as textures and render targets.
 pub mod buffer;
+/// Order-independent checksums of rendered fragments, for differential testing.
+#[cfg(all(test, target_has_atomic = "64"))]
+pub(crate) mod checksum;
+/// Hooks for inspecting the work done during rendering.
+pub mod debug;
+/// Passes that compute every pixel of a target, such as for post-processing.
+pub mod fullscreen;
+/// Hierarchical depth targets, for skipping hidden primitives.
+pub mod hiz;
 /// Index buffer features.
 pub mod index;
 /// Math-related functionality.
 pub mod math;
+/// Motion blur post-processing.
+pub mod motion_blur;
+/// Parallel rendering configuration.
+#[cfg(feature = "par")]
+pub mod par;
 /// Pipeline definitions.
 pub mod pipeline;
+/// Conversions between colours and packed pixel formats.
+pub mod pixel;
+/// Utilities for presenting rendered images.
+pub mod present;
 /// Primitive definitions.
 pub mod primitives;
+/// Procedural noise and displacement functions, useful for shaders.
+pub mod procedural;
 /// Rasterization algorithms.
 pub mod rasterizer;
 /// Texture samplers.
@@ -64,14 +88,32 @@ pub mod texture;
 
 // Reexports
 pub use crate::{
-    buffer::{Buffer, Buffer1d, Buffer2d, Buffer3d, Buffer4d},
-    index::IndexedVertices,
-    math::Unit,
+    buffer::{
+        Buffer, Buffer1d, Buffer2d, Buffer3d, Buffer4d, ColumnMajor, MipChain, PingPong, SubView,
+    },
+    debug::{CullReason, DebugHooks},
+    hiz::HiZ,
+    index::{Index, IndexedVertices},
+    math::{Attributes, Flat, NoPerspective, Rounded, Unit},
     pipeline::{
-        AaMode, CoordinateMode, DepthMode, Handedness, Pipeline, PixelMode, YAxisDirection,
+        AaMode, BlendMode, Color, CoordinateMode, DepthBias, DepthMode, DepthTexel, DitherMatrix,
+        Handedness, Parallelism, Pipeline, PixelMode, Scratch, StencilMode, StencilOp, Tiling,
+        TransparencyMode, U24, YAxisDirection,
+    },
+    pixel::{FromPacked, PackedFormat, ToPacked},
+    primitives::{Geometry, LineList, LineTriangleList, PointList, QuadList, TriangleList},
+    rasterizer::{CullMode, LineMode},
+    sampler::{
+        Anisotropic, Atlas, Clamped, Comparison, CubeMap, Cubic, CubicFilter, Linear, LinearLayer, Mipmapped,
+        Mirrored, Nearest, NearestLayer, Sampler, SamplerGrad, TextureArray, Tiled,
+    },
+    texture::{
+        DirtyTracked, Empty, Palette, ReadOnly, Slice, Srgb, Srgb8, SrgbWrite, Swizzle, Target,
+        Texture,
     },
-    primitives::{LineList, LineTriangleList, TriangleList},
-    rasterizer::CullMode,
-    sampler::{Clamped, Linear, Mirrored, Nearest, Sampler, Tiled},
-    texture::{Empty, Target, Texture},
 };
+
+#[cfg(feature = "image")]
+pub use crate::texture::ImageTarget;
+#[cfg(feature = "half")]
+pub use crate::math::Half;
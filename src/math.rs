@@ -1,4 +1,4 @@
-use core::ops::{Add, Mul};
+use core::ops::{Add, Mul, Sub};
 
 pub trait WeightedSum: Sized {
     fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self;
@@ -8,9 +8,42 @@ pub trait WeightedSum: Sized {
     fn weighted_sum3(v0: Self, v1: Self, v2: Self, w0: f32, w1: f32, w2: f32) -> Self {
         Self::weighted_sum([v0, v1, v2], [w0, w1, w2])
     }
+
+    /// Like [`WeightedSum::weighted_sum`], but also given `linear_weights`, which interpolate between the values
+    /// linearly in screen space instead of with perspective correction.
+    ///
+    /// Rasterizers interpolate vertex data with this, so that types like [`NoPerspective`] can choose which weights to
+    /// use. By default, `linear_weights` are ignored.
+    fn weighted_sum_linear<const N: usize>(
+        values: [Self; N],
+        weights: [f32; N],
+        linear_weights: [f32; N],
+    ) -> Self {
+        let _ = linear_weights;
+        Self::weighted_sum(values, weights)
+    }
+
+    /// Find the mean of the given values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    fn mean(values: &[Self]) -> Self
+    where
+        Self: Clone,
+    {
+        let (first, rest) = values
+            .split_first()
+            .expect("Cannot find the mean of no values");
+        let w = 1.0 / values.len() as f32;
+        rest.iter().fold(
+            Self::weighted_sum2(first.clone(), first.clone(), w, 0.0),
+            |mean, v| Self::weighted_sum2(mean, v.clone(), 1.0, w),
+        )
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 pub struct Unit;
 
 impl WeightedSum for Unit {
@@ -20,6 +53,286 @@ impl WeightedSum for Unit {
     }
 }
 
+/// Vertex data that is interpolated linearly in screen space, rather than with perspective correction (like
+/// `noperspective` in GLSL).
+///
+/// This is useful for attributes that are defined in screen space, such as the coordinates of a full-screen gradient.
+/// Types that contain a `NoPerspective` field should forward [`WeightedSum::weighted_sum_linear`] to it.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct NoPerspective<T>(pub T);
+
+impl<T: WeightedSum> WeightedSum for NoPerspective<T> {
+    #[inline(always)]
+    fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+        Self(T::weighted_sum(values.map(|v| v.0), weights))
+    }
+    #[inline(always)]
+    fn weighted_sum_linear<const N: usize>(
+        values: [Self; N],
+        _: [f32; N],
+        linear_weights: [f32; N],
+    ) -> Self {
+        Self(T::weighted_sum(values.map(|v| v.0), linear_weights))
+    }
+}
+
+/// Vertex data that is not interpolated, taking the value of the provoking vertex of each primitive instead (like `flat`
+/// in GLSL).
+///
+/// The provoking vertex is the first vertex of each primitive, in the order that the primitive's vertices were emitted
+/// (so, for an indexed triangle list, the vertex given by the first index of each triangle). Rasterizers preserve it
+/// regardless of the winding of the primitive or how it is clipped. This is useful for per-primitive data, such as a
+/// material index or a face normal.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Flat<T>(pub T);
+
+impl<T> WeightedSum for Flat<T> {
+    #[inline(always)]
+    fn weighted_sum<const N: usize>(values: [Self; N], _: [f32; N]) -> Self {
+        values
+            .into_iter()
+            .next()
+            .expect("Cannot find the weighted sum of no values")
+    }
+}
+
+/// A fixed-size array of vertex data, which is interpolated component-wise.
+///
+/// [`WeightedSum`] is implemented for every type that may be multiplied by `f32` and added together. Arrays could
+/// implement those operations in a future version of Rust, so [`WeightedSum`] can't be implemented for them directly:
+/// this wraps them instead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Attributes<T, const M: usize>(pub [T; M]);
+
+impl<T: Clone + WeightedSum, const M: usize> WeightedSum for Attributes<T, M> {
+    #[inline(always)]
+    fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+        Self(core::array::from_fn(|i| {
+            T::weighted_sum(values.each_ref().map(|v| v.0[i].clone()), weights)
+        }))
+    }
+    #[inline(always)]
+    fn weighted_sum_linear<const N: usize>(
+        values: [Self; N],
+        weights: [f32; N],
+        linear_weights: [f32; N],
+    ) -> Self {
+        Self(core::array::from_fn(|i| {
+            T::weighted_sum_linear(
+                values.each_ref().map(|v| v.0[i].clone()),
+                weights,
+                linear_weights,
+            )
+        }))
+    }
+}
+
+impl<T, const M: usize> From<[T; M]> for Attributes<T, M> {
+    fn from(array: [T; M]) -> Self {
+        Self(array)
+    }
+}
+
+/// Integer colour channels (`u8` or `u16`, or arrays of them), which are interpolated in `f32` and rounded to the
+/// nearest integer.
+///
+/// This allows, for example, Gouraud shading with `[u8; 4]` colours without converting them to and from `f32` in the
+/// pipeline. Like [`Attributes`], this is a wrapper because [`WeightedSum`] can't be implemented for integers directly.
+///
+/// Each interpolation rounds its result, and results beyond the range of the type are clamped to it. Values that are
+/// interpolated more than once (for example, by clipping and then again by rasterization) may therefore be off by one
+/// from the exact result, and fine gradients between similar values appear banded.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rounded<T>(pub T);
+
+macro_rules! impl_rounded {
+    ($ty:ty) => {
+        impl Rounded<$ty> {
+            #[inline(always)]
+            fn round_weighted<const N: usize>(values: [$ty; N], weights: [f32; N]) -> $ty {
+                let sum = values
+                    .into_iter()
+                    .zip(weights)
+                    .fold(0.0, |a, (v, w)| a + v as f32 * w);
+                // Non-negative after clamping, so truncation rounds to the nearest integer (NaN becomes `0`)
+                (sum.clamp(0.0, <$ty>::MAX as f32) + 0.5) as $ty
+            }
+        }
+
+        impl WeightedSum for Rounded<$ty> {
+            #[inline(always)]
+            fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+                Self(Self::round_weighted(values.map(|v| v.0), weights))
+            }
+        }
+
+        impl<const M: usize> WeightedSum for Rounded<[$ty; M]> {
+            #[inline(always)]
+            fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+                Self(core::array::from_fn(|i| {
+                    Rounded::<$ty>::round_weighted(values.map(|v| v.0[i]), weights)
+                }))
+            }
+        }
+    };
+}
+
+impl_rounded!(u8);
+impl_rounded!(u16);
+
+/// Half-precision floating-point texels (`half::f16`, or arrays of them), which are blended in `f32`.
+///
+/// Textures of `f16` texels (such as HDR environment maps) take half the memory of `f32` textures. Like [`Rounded`],
+/// this is a wrapper because the `Mul<f32>` and `Add` operations that samplers such as [`Linear`](crate::Linear) use to
+/// blend texels can't be implemented for `f16` directly. Each operation is performed in `f32`, and its result is
+/// rounded back to `f16`.
+///
+/// ```
+/// # use euc::{Buffer2d, Half, Sampler, Texture};
+/// use half::f16;
+///
+/// let tex = Buffer2d::fill_with([2, 1], {
+///     let mut texels = [0.0, 1.0].into_iter();
+///     move || Half(f16::from_f32(texels.next().unwrap()))
+/// });
+/// let texel = (&tex).linear().sample([0.25, 0.0]);
+/// assert_eq!(f32::from(texel), 0.5);
+/// ```
+#[cfg(feature = "half")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Half<T>(pub T);
+
+#[cfg(feature = "half")]
+mod half_impls {
+    use super::*;
+    use half::f16;
+
+    impl Mul<f32> for Half<f16> {
+        type Output = Self;
+        #[inline(always)]
+        fn mul(self, rhs: f32) -> Self {
+            Self(f16::from_f32(self.0.to_f32() * rhs))
+        }
+    }
+
+    impl Add for Half<f16> {
+        type Output = Self;
+        #[inline(always)]
+        fn add(self, rhs: Self) -> Self {
+            Self(f16::from_f32(self.0.to_f32() + rhs.0.to_f32()))
+        }
+    }
+
+    impl<const M: usize> Mul<f32> for Half<[f16; M]> {
+        type Output = Self;
+        #[inline(always)]
+        fn mul(self, rhs: f32) -> Self {
+            Self(self.0.map(|e| f16::from_f32(e.to_f32() * rhs)))
+        }
+    }
+
+    impl<const M: usize> Add for Half<[f16; M]> {
+        type Output = Self;
+        #[inline(always)]
+        fn add(self, rhs: Self) -> Self {
+            Self(core::array::from_fn(|i| {
+                f16::from_f32(self.0[i].to_f32() + rhs.0[i].to_f32())
+            }))
+        }
+    }
+
+    impl From<f32> for Half<f16> {
+        #[inline(always)]
+        fn from(x: f32) -> Self {
+            Self(f16::from_f32(x))
+        }
+    }
+
+    impl From<Half<f16>> for f32 {
+        #[inline(always)]
+        fn from(x: Half<f16>) -> Self {
+            x.0.to_f32()
+        }
+    }
+
+    impl<const M: usize> From<[f32; M]> for Half<[f16; M]> {
+        #[inline(always)]
+        fn from(x: [f32; M]) -> Self {
+            Self(x.map(f16::from_f32))
+        }
+    }
+
+    impl<const M: usize> From<Half<[f16; M]>> for [f32; M] {
+        #[inline(always)]
+        fn from(x: Half<[f16; M]>) -> Self {
+            x.0.map(f16::to_f32)
+        }
+    }
+}
+
+/// Implement [`WeightedSum`] for a struct by interpolating each of its fields (which must implement [`Clone`] and
+/// [`WeightedSum`]).
+///
+/// This avoids implementing `Add` and `Mul<f32>` for vertex data made of several attributes. Fields marked `#[flat]`
+/// aren't interpolated (like `flat` in GLSL), and so need only implement [`Clone`]: they take the value of the first
+/// vertex that contributes to each fragment, which is only well-defined if every vertex of a primitive shares it (such
+/// as a material index). Fixed-size arrays of attributes can be wrapped in [`Attributes`].
+///
+/// ```
+/// # use euc::{impl_weighted_sum, math::{Attributes, NoPerspective}};
+/// #[derive(Clone)]
+/// struct VertexData {
+///     normal: Attributes<f32, 3>,
+///     screen_pos: NoPerspective<Attributes<f32, 2>>,
+///     material: u32,
+/// }
+///
+/// impl_weighted_sum!(VertexData { normal, screen_pos, #[flat] material });
+/// ```
+#[macro_export]
+macro_rules! impl_weighted_sum {
+    ($ty:ident { $($(#[$flat:ident])? $field:ident),* $(,)? }) => {
+        impl $crate::math::WeightedSum for $ty {
+            #[inline(always)]
+            fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+                Self {
+                    $($field: $crate::impl_weighted_sum!(
+                        @field $($flat)? values.$field, weights
+                    )),*
+                }
+            }
+            #[inline(always)]
+            fn weighted_sum_linear<const N: usize>(
+                values: [Self; N],
+                weights: [f32; N],
+                linear_weights: [f32; N],
+            ) -> Self {
+                Self {
+                    $($field: $crate::impl_weighted_sum!(
+                        @field $($flat)? values.$field, weights, linear_weights
+                    )),*
+                }
+            }
+        }
+    };
+    (@field flat $values:ident.$field:ident, $($weights:ident),*) => {
+        $values[0].$field.clone()
+    };
+    (@field $values:ident.$field:ident, $weights:ident) => {
+        $crate::math::WeightedSum::weighted_sum(
+            $values.each_ref().map(|v| v.$field.clone()),
+            $weights,
+        )
+    };
+    (@field $values:ident.$field:ident, $weights:ident, $linear_weights:ident) => {
+        $crate::math::WeightedSum::weighted_sum_linear(
+            $values.each_ref().map(|v| v.$field.clone()),
+            $weights,
+            $linear_weights,
+        )
+    };
+}
+
 impl<T: Clone + Mul<f32, Output = T> + Add<Output = T>> WeightedSum for T {
     #[inline(always)]
     fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
@@ -40,6 +353,121 @@ impl<T: Clone + Mul<f32, Output = T> + Add<Output = T>> WeightedSum for T {
     }
 }
 
+/// The cross product of two 3D vectors.
+///
+/// ```
+/// # use euc::math::cross;
+/// assert_eq!(cross([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+/// ```
+#[inline]
+pub fn cross([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
+    [
+        a1 * b2 - a2 * b1, // x-component
+        a2 * b0 - a0 * b2, // y-component
+        a0 * b1 - a1 * b0, // z-component
+    ]
+}
+
+/// The component-wise difference of two 3D vectors.
+///
+/// ```
+/// # use euc::math::sub;
+/// assert_eq!(sub([3.0, 2.0, 1.0], [1.0, 1.0, 1.0]), [2.0, 1.0, 0.0]);
+/// ```
+#[inline]
+pub fn sub([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
+    [
+        a0 - b0, // x-component
+        a1 - b1, // y-component
+        a2 - b2, // z-component
+    ]
+}
+
+/// The component-wise sum of two 3D vectors.
+///
+/// ```
+/// # use euc::math::add;
+/// assert_eq!(add([3.0, 2.0, 1.0], [1.0, 1.0, 1.0]), [4.0, 3.0, 2.0]);
+/// ```
+#[inline]
+pub fn add([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
+    [
+        a0 + b0, // x-component
+        a1 + b1, // y-component
+        a2 + b2, // z-component
+    ]
+}
+
+/// The dot product of two 3D vectors.
+///
+/// ```
+/// # use euc::math::dot;
+/// assert_eq!(dot([1.0, 2.0, 3.0], [4.0, 5.0, 6.0]), 32.0);
+/// ```
+#[inline]
+pub fn dot([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> f32 {
+    a0 * b0 + a1 * b1 + a2 * b2
+}
+
+/// The squared length of a 3D vector.
+///
+/// ```
+/// # use euc::math::magnitude_squared;
+/// assert_eq!(magnitude_squared([1.0, 2.0, 2.0]), 9.0);
+/// ```
+#[inline]
+pub fn magnitude_squared([v0, v1, v2]: [f32; 3]) -> f32 {
+    v0 * v0 + v1 * v1 + v2 * v2
+}
+
+/// The product of two 3x3 matrices, which are stored as arrays of rows.
+///
+/// ```
+/// # use euc::math::matmul;
+/// let swap_xy = [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+/// let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+/// assert_eq!(matmul(swap_xy, swap_xy), identity);
+/// ```
+#[inline]
+pub fn matmul(a: [[f32; 3]; 3], b: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut result = [[0.0; 3]; 3]; // Initialize a 3x3 matrix to store the result
+
+    for i in 0..3 {
+        for j in 0..3 {
+            result[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+
+    result
+}
+
+/// The product of a 3x3 matrix, stored as an array of rows, and a 3D column vector.
+///
+/// ```
+/// # use euc::math::mat3_mul_vec3;
+/// let swap_xy = [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+/// assert_eq!(mat3_mul_vec3(swap_xy, [1.0, 2.0, 3.0]), [2.0, 1.0, 3.0]);
+/// ```
+#[inline]
+pub fn mat3_mul_vec3(m: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2], // x-component
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2], // y-component
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2], // z-component
+    ]
+}
+
+/// Linearly interpolate from `a` (when `t` is `0.0`) to `b` (when `t` is `1.0`).
+///
+/// ```
+/// # use euc::math::lerp;
+/// assert_eq!(lerp(2.0, 4.0, 0.25), 2.5);
+/// ```
+#[inline]
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
 pub trait Denormalize<T>: Sized {
     fn denormalize_to(self, scale: T) -> T;
     fn denormalize_array<const N: usize>(this: [Self; N], other: [T; N]) -> [T; N];
@@ -77,3 +505,204 @@ impl_denormalize!(f64, u32);
 impl_denormalize!(f64, u64);
 impl_denormalize!(f64, u128);
 impl_denormalize!(f64, usize);
+
+/// Signed indices are not clamped to the range `0..scale`, so that coordinates outside of `0.0..1.0` produce indices
+/// outside of the texture (for samplers that wrap them around, for example). Coordinates are rounded down, such that
+/// `-0.05` maps to `-1` rather than `0` for a scale of `10`.
+macro_rules! impl_denormalize_signed {
+    ($this:ty, $other:ty) => {
+        impl Denormalize<$other> for $this {
+            fn denormalize_to(self, scale: $other) -> $other {
+                let x = self * scale as $this;
+                // Rounds towards zero, so correct negative values that had a fractional part
+                let i = x as $other;
+                if (i as $this) > x {
+                    i - 1
+                } else {
+                    i
+                }
+            }
+
+            fn denormalize_array<const N: usize>(
+                this: [Self; N],
+                other: [$other; N],
+            ) -> [$other; N] {
+                let mut out = [0; N];
+                (0..N).for_each(|i| out[i] = this[i].denormalize_to(other[i]));
+                out
+            }
+        }
+    };
+}
+
+impl_denormalize_signed!(f32, i32);
+impl_denormalize_signed!(f32, i64);
+
+impl_denormalize_signed!(f64, i32);
+impl_denormalize_signed!(f64, i64);
+
+/// A floating-point type that may be used to index samplers, such as [`crate::Tiled`], that wrap indices around.
+pub trait Float: Copy + PartialOrd + Sub<Output = Self> {
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+
+    /// Restrict the value to the given range.
+    fn clamp_to(self, min: Self, max: Self) -> Self;
+
+    /// Find the least non-negative remainder of `self` divided by `rhs`, like [`f32::rem_euclid`].
+    fn rem_euclid_by(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_float {
+    ($this:ty) => {
+        impl Float for $this {
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+            const TWO: Self = 2.0;
+
+            #[inline(always)]
+            fn clamp_to(self, min: Self, max: Self) -> Self {
+                self.clamp(min, max)
+            }
+
+            #[inline(always)]
+            fn rem_euclid_by(self, rhs: Self) -> Self {
+                // Equivalent to `rem_euclid`, which isn't available without `std`
+                let r = self % rhs;
+                if r >= 0.0 {
+                    r
+                } else if rhs < 0.0 {
+                    r - rhs
+                } else {
+                    r + rhs
+                }
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct VertexData {
+        pos: Attributes<f32, 2>,
+        screen: NoPerspective<f32>,
+        material: u32,
+    }
+
+    impl_weighted_sum!(VertexData {
+        pos,
+        screen,
+        #[flat]
+        material,
+    });
+
+    #[test]
+    fn weighted_sum_macro() {
+        let v = |x: f32, material| VertexData {
+            pos: Attributes([x, x * 2.0]),
+            screen: NoPerspective(x),
+            material,
+        };
+        let (values, weights, linear_weights) = ([v(1.0, 7), v(3.0, 9)], [0.75, 0.25], [0.5, 0.5]);
+        assert_eq!(
+            VertexData::weighted_sum(values.clone(), weights),
+            VertexData {
+                pos: Attributes([1.5, 3.0]),
+                screen: NoPerspective(1.5),
+                material: 7,
+            }
+        );
+        // Only the `NoPerspective` field uses the linear weights
+        assert_eq!(
+            VertexData::weighted_sum_linear(values, weights, linear_weights),
+            VertexData {
+                pos: Attributes([1.5, 3.0]),
+                screen: NoPerspective(2.0),
+                material: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn cross_basis() {
+        let [x, y, z] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        assert_eq!(cross(x, y), z);
+        assert_eq!(cross(y, z), x);
+        assert_eq!(cross(z, x), y);
+        assert_eq!(cross(y, x), [0.0, 0.0, -1.0]);
+        assert_eq!(cross(x, x), [0.0; 3]);
+        // The cross product is perpendicular to both vectors
+        let [a, b] = [[1.0, 2.0, 3.0], [-2.0, 0.5, 4.0]];
+        assert_eq!(dot(cross(a, b), a), 0.0);
+        assert_eq!(dot(cross(a, b), b), 0.0);
+    }
+
+    #[test]
+    fn matrix_products() {
+        let m = [[1.0, 2.0, 0.0], [0.0, 1.0, 3.0], [4.0, 0.0, 1.0]];
+        let n = [[2.0, 0.0, 1.0], [1.0, 1.0, 0.0], [0.0, 2.0, 1.0]];
+        let v = [1.0, -1.0, 2.0];
+        // Multiplying by a product of matrices is the same as multiplying by each in turn
+        assert_eq!(
+            mat3_mul_vec3(matmul(m, n), v),
+            mat3_mul_vec3(m, mat3_mul_vec3(n, v))
+        );
+        assert_eq!(mat3_mul_vec3(m, v), [-1.0, 5.0, 6.0]);
+        assert_eq!(add(sub(v, m[0]), m[0]), v);
+        assert_eq!(magnitude_squared(v), dot(v, v));
+    }
+
+    #[test]
+    fn denormalize_signed() {
+        assert_eq!((-0.5f32).denormalize_to(10i32), -5);
+        assert_eq!((-0.05f32).denormalize_to(10i32), -1);
+        assert_eq!(0.35f32.denormalize_to(10i32), 3);
+        assert_eq!(1.25f64.denormalize_to(8i64), 10);
+        assert_eq!(f32::denormalize_array([-0.25, 0.5], [16i64, 16]), [-4, 8]);
+        // Unsigned indices are still clamped to the texture
+        assert_eq!((-0.5f32).denormalize_to(10u32), 0);
+        assert_eq!(1.0f32.denormalize_to(10usize), 9);
+    }
+
+    #[test]
+    fn flat_takes_first() {
+        let values = [Flat(1.0), Flat(2.0), Flat(4.0)];
+        assert_eq!(Flat::weighted_sum(values, [0.0, 0.5, 0.5]), Flat(1.0));
+        assert_eq!(
+            Flat::weighted_sum_linear(values, [0.0; 3], [0.2, 0.3, 0.5]),
+            Flat(1.0)
+        );
+        assert_eq!(Flat::mean(&values), Flat(1.0));
+    }
+
+    #[test]
+    fn rounded_midpoint() {
+        let [a, b] = [Rounded([0u8, 0, 0, 255]), Rounded([255, 255, 255, 255])];
+        assert_eq!(
+            Rounded::weighted_sum([a, b], [0.5, 0.5]),
+            Rounded([128, 128, 128, 255])
+        );
+        assert_eq!(Rounded::weighted_sum([a, b], [1.0, 0.0]), a);
+        assert_eq!(Rounded::weighted_sum([a, b], [0.0, 1.0]), b);
+    }
+
+    #[test]
+    fn rounded_clamps() {
+        let [a, b] = [Rounded(0u16), Rounded(u16::MAX)];
+        // Weights slightly outside of the triangle extrapolate beyond the range of the type
+        assert_eq!(Rounded::weighted_sum([a, b], [-0.1, 1.1]), b);
+        assert_eq!(Rounded::weighted_sum([a, b], [1.1, -0.1]), a);
+        assert_eq!(Rounded::weighted_sum([a, b], [f32::NAN; 2]), a);
+        assert_eq!(
+            Rounded::weighted_sum3(Rounded(10u8), Rounded(11), Rounded(13), 0.2, 0.3, 0.5),
+            Rounded(12)
+        );
+    }
+}
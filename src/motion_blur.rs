@@ -0,0 +1,393 @@
+use crate::{buffer::Buffer2d, procedural::hash, texture::Texture};
+use core::ops::{Add, Mul};
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// Parameters that control the appearance of motion blur produced by [`blur_into`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MotionBlurParams {
+    /// The size, in pixels, of the tiles passed to [`tile_max`]. This is also the maximum blur radius: longer
+    /// velocities are clamped to this length.
+    pub tile: usize,
+    /// The number of taps taken along the dominant velocity of each pixel. Even values are rounded up to the next odd
+    /// value, such that taps are spread evenly either side of the pixel.
+    pub samples: usize,
+    /// The difference in depth over which two surfaces transition from being considered to overlap to one being in
+    /// front of the other. A softness of zero compares depths exactly.
+    pub depth_softness: f32,
+    /// The seed used to jitter the position of taps, which trades banding for noise.
+    pub seed: u32,
+}
+
+impl Default for MotionBlurParams {
+    fn default() -> Self {
+        Self {
+            tile: 20,
+            samples: 15,
+            depth_softness: 0.01,
+            seed: 0,
+        }
+    }
+}
+
+/// Find the longest velocity (in pixels) within each `tile` x `tile` tile of the velocity buffer.
+///
+/// # Panics
+///
+/// Panics if `out` does not have exactly one texel per tile (i.e: a size of `vel.size() / tile`, rounded up).
+pub fn tile_max(vel: &Buffer2d<[f32; 2]>, tile: usize, out: &mut Buffer2d<[f32; 2]>) {
+    assert!(tile > 0, "Tile size must be non-zero");
+    let [w, h] = vel.size();
+    assert_eq!(
+        out.size(),
+        [w.div_ceil(tile), h.div_ceil(tile)],
+        "Tile buffer must have one texel per tile",
+    );
+
+    let vel = vel.raw();
+    out.for_each_row_mut(|ty, row| {
+        row.iter_mut().enumerate().for_each(|(tx, out)| {
+            *out = (ty * tile..((ty + 1) * tile).min(h))
+                .flat_map(|y| (tx * tile..((tx + 1) * tile).min(w)).map(move |x| vel[y * w + x]))
+                .fold([0.0; 2], longest);
+        })
+    });
+}
+
+/// Find the longest velocity within the 3x3 neighbourhood of each tile produced by [`tile_max`].
+///
+/// This dilates the tile velocities so that blur from fast-moving objects can spread into neighbouring tiles.
+///
+/// # Panics
+///
+/// Panics if `out` is not the same size as `tiles`.
+pub fn neighbor_max(tiles: &Buffer2d<[f32; 2]>, out: &mut Buffer2d<[f32; 2]>) {
+    let [w, h] = tiles.size();
+    assert_eq!(
+        out.size(),
+        [w, h],
+        "Output must be the same size as the tiles"
+    );
+
+    let tiles = tiles.raw();
+    out.for_each_row_mut(|y, row| {
+        row.iter_mut().enumerate().for_each(|(x, out)| {
+            *out = (y.saturating_sub(1)..(y + 2).min(h))
+                .flat_map(|y| (x.saturating_sub(1)..(x + 2).min(w)).map(move |x| tiles[y * w + x]))
+                .fold([0.0; 2], longest);
+        })
+    });
+}
+
+/// Blur `color` along the per-pixel velocities (in pixels) of `vel`, writing the result to `dst`.
+///
+/// This implements the reconstruction filter described by McGuire et al. in *A Reconstruction Filter for Plausible
+/// Motion Blur* (2012). Taps are taken along the dominant velocity of each pixel's neighbourhood (from
+/// [`neighbor_max`]) and weighted by the depth (smaller values being closer) and velocity of each tap, such that
+/// moving foreground objects blur over the background and the background is revealed behind them.
+///
+/// Pixels with no nearby motion are copied to `dst` unchanged.
+///
+/// # Panics
+///
+/// Panics if `params.tile` is zero, if `depth`, `vel`, and `dst` are not the same size as `color`, or if
+/// `neighbor_max` does not have one texel per tile of size `params.tile`.
+pub fn blur_into<C>(
+    color: &Buffer2d<C>,
+    depth: &Buffer2d<f32>,
+    vel: &Buffer2d<[f32; 2]>,
+    neighbor_max: &Buffer2d<[f32; 2]>,
+    dst: &mut Buffer2d<C>,
+    params: MotionBlurParams,
+) where
+    C: Clone + Send + Sync + Mul<f32, Output = C> + Add<Output = C>,
+{
+    let [w, h] = color.size();
+    let tile = params.tile;
+    assert!(tile > 0, "Tile size must be non-zero");
+    assert_eq!(
+        depth.size(),
+        [w, h],
+        "Depth buffer must be the same size as the color buffer"
+    );
+    assert_eq!(
+        vel.size(),
+        [w, h],
+        "Velocity buffer must be the same size as the color buffer"
+    );
+    assert_eq!(
+        dst.size(),
+        [w, h],
+        "Destination must be the same size as the color buffer"
+    );
+    assert_eq!(
+        neighbor_max.size(),
+        [w.div_ceil(tile), h.div_ceil(tile)],
+        "Neighbour buffer must have one texel per tile",
+    );
+
+    // An odd number of taps means that the central tap falls on the pixel itself
+    let samples = params.samples.max(1) | 1;
+    let depth_softness = params.depth_softness.max(f32::MIN_POSITIVE);
+    let max_len = tile as f32;
+    let tiles_w = neighbor_max.size()[0];
+    let (color, depth, vel, neighbor_max) =
+        (color.raw(), depth.raw(), vel.raw(), neighbor_max.raw());
+
+    dst.for_each_row_mut(|y, row| {
+        row.iter_mut().enumerate().for_each(|(x, out)| {
+            let i = y * w + x;
+            let vn = clamp_len(neighbor_max[(y / tile) * tiles_w + x / tile], max_len);
+            let vn_len = len(vn);
+            if vn_len <= 0.5 {
+                // Not enough motion nearby to produce visible blur
+                *out = color[i].clone();
+                return;
+            }
+
+            let x_len = len(clamp_len(vel[i], max_len)).max(0.5);
+            let x_depth = depth[i];
+
+            let jitter = hash(x as i32, y as i32, 0, params.seed) as f32 / u32::MAX as f32 - 0.5;
+
+            let mut weight = 1.0 / x_len;
+            let mut sum = color[i].clone() * weight;
+            for s in 0..samples {
+                // The central tap would sample the pixel itself, which we've already accounted for
+                if s == (samples - 1) / 2 {
+                    continue;
+                }
+                let t = -1.0 + 2.0 * (s as f32 + jitter + 1.0) / (samples + 1) as f32;
+                let tap = [
+                    (x as f32 + vn[0] * t).round().clamp(0.0, (w - 1) as f32),
+                    (y as f32 + vn[1] * t).round().clamp(0.0, (h - 1) as f32),
+                ];
+                let dist = len([tap[0] - x as f32, tap[1] - y as f32]);
+                let j = tap[1] as usize * w + tap[0] as usize;
+
+                let y_len = len(clamp_len(vel[j], max_len)).max(0.5);
+                let y_depth = depth[j];
+
+                // How much the tap is in front of, or behind, the pixel
+                let front = soft_depth_compare(y_depth, x_depth, depth_softness);
+                let back = soft_depth_compare(x_depth, y_depth, depth_softness);
+
+                let alpha = front * cone(dist, y_len)
+                    + back * cone(dist, x_len)
+                    + cylinder(dist, y_len) * cylinder(dist, x_len) * 2.0;
+
+                weight += alpha;
+                sum = sum + color[j].clone() * alpha;
+            }
+
+            *out = sum * (1.0 / weight);
+        })
+    });
+}
+
+fn len([x, y]: [f32; 2]) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+fn longest(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    if b[0] * b[0] + b[1] * b[1] > a[0] * a[0] + a[1] * a[1] {
+        b
+    } else {
+        a
+    }
+}
+
+fn clamp_len(v: [f32; 2], max: f32) -> [f32; 2] {
+    let l = len(v);
+    if l > max {
+        v.map(|e| e * max / l)
+    } else {
+        v
+    }
+}
+
+/// `1.0` if depth `a` is in front of depth `b`, falling off to `0.0` over `softness`.
+fn soft_depth_compare(a: f32, b: f32, softness: f32) -> f32 {
+    (1.0 - (a - b) / softness).clamp(0.0, 1.0)
+}
+
+fn cone(dist: f32, vel_len: f32) -> f32 {
+    (1.0 - dist / vel_len).clamp(0.0, 1.0)
+}
+
+fn cylinder(dist: f32, vel_len: f32) -> f32 {
+    1.0 - smoothstep(0.95 * vel_len, 1.05 * vel_len, dist)
+}
+
+fn smoothstep(a: f32, b: f32, x: f32) -> f32 {
+    let t = ((x - a) / (b - a)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Target;
+
+    fn random(x: usize, y: usize, seed: u32) -> f32 {
+        hash(x as i32, y as i32, 0, seed) as f32 / u32::MAX as f32
+    }
+
+    fn random_velocities(size: [usize; 2], scale: f32) -> Buffer2d<[f32; 2]> {
+        let mut vel = Buffer2d::fill(size, [0.0; 2]);
+        for y in 0..size[1] {
+            for x in 0..size[0] {
+                vel.write(
+                    x,
+                    y,
+                    [
+                        (random(x, y, 1) - 0.5) * scale,
+                        (random(x, y, 2) - 0.5) * scale,
+                    ],
+                );
+            }
+        }
+        vel
+    }
+
+    fn blur(
+        color: &Buffer2d<f32>,
+        depth: &Buffer2d<f32>,
+        vel: &Buffer2d<[f32; 2]>,
+        params: MotionBlurParams,
+    ) -> Buffer2d<f32> {
+        let [w, h] = color.size();
+        let tiles = [w.div_ceil(params.tile), h.div_ceil(params.tile)];
+        let mut max = Buffer2d::fill(tiles, [0.0; 2]);
+        let mut neighbors = Buffer2d::fill(tiles, [0.0; 2]);
+        tile_max(vel, params.tile, &mut max);
+        neighbor_max(&max, &mut neighbors);
+
+        let mut dst = Buffer2d::fill([w, h], 0.0);
+        blur_into(color, depth, vel, &neighbors, &mut dst, params);
+        dst
+    }
+
+    #[test]
+    fn tile_and_neighbor_max_match_brute_force() {
+        for (size, tile) in [([1, 1], 1), ([37, 23], 4), ([64, 64], 8), ([50, 9], 20)] {
+            let vel = random_velocities(size, 40.0);
+            let tiles = [size[0].div_ceil(tile), size[1].div_ceil(tile)];
+
+            let mut max = Buffer2d::fill(tiles, [0.0; 2]);
+            tile_max(&vel, tile, &mut max);
+            for ty in 0..tiles[1] {
+                for tx in 0..tiles[0] {
+                    let expected = (0..size[1])
+                        .flat_map(|y| (0..size[0]).map(move |x| [x, y]))
+                        .filter(|[x, y]| x / tile == tx && y / tile == ty)
+                        .map(|[x, y]| len(vel.read([x, y])))
+                        .fold(0.0, f32::max);
+                    assert_eq!(len(max.read([tx, ty])), expected);
+                }
+            }
+
+            let mut neighbors = Buffer2d::fill(tiles, [0.0; 2]);
+            neighbor_max(&max, &mut neighbors);
+            for ty in 0..tiles[1] {
+                for tx in 0..tiles[0] {
+                    let expected = (0..tiles[1])
+                        .flat_map(|y| (0..tiles[0]).map(move |x| [x, y]))
+                        .filter(|[x, y]| x.abs_diff(tx) <= 1 && y.abs_diff(ty) <= 1)
+                        .map(|[x, y]| len(max.read([x, y])))
+                        .fold(0.0, f32::max);
+                    assert_eq!(len(neighbors.read([tx, ty])), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_velocity_is_identity() {
+        let size = [41, 29];
+        let mut color = Buffer2d::fill(size, 0.0);
+        let mut depth = Buffer2d::fill(size, 0.0);
+        for y in 0..size[1] {
+            for x in 0..size[0] {
+                color.write(x, y, random(x, y, 3));
+                depth.write(x, y, random(x, y, 4));
+            }
+        }
+        let vel = Buffer2d::fill(size, [0.0; 2]);
+
+        for samples in [1, 4, 15] {
+            let params = MotionBlurParams {
+                tile: 8,
+                samples,
+                depth_softness: 0.0,
+                seed: 0,
+            };
+            assert_eq!(blur(&color, &depth, &vel, params).raw(), color.raw());
+        }
+    }
+
+    #[test]
+    fn uniform_velocity_smears_symmetrically() {
+        const SIZE: [usize; 2] = [64, 256];
+        const LINE: usize = 32;
+        const VEL: f32 = 6.0;
+
+        // A vertical line moving horizontally over a flat background
+        let mut color = Buffer2d::fill(SIZE, 0.0);
+        (0..SIZE[1]).for_each(|y| color.write(LINE, y, 1.0));
+        let depth = Buffer2d::fill(SIZE, 0.5);
+        let vel = Buffer2d::fill(SIZE, [VEL, 0.0]);
+
+        for samples in [14, 15] {
+            let params = MotionBlurParams {
+                tile: 8,
+                samples,
+                ..MotionBlurParams::default()
+            };
+            let dst = blur(&color, &depth, &vel, params);
+
+            // Average over rows to remove the effect of jitter
+            let profile = (0..SIZE[0])
+                .map(|x| (0..SIZE[1]).map(|y| dst.read([x, y])).sum::<f32>() / SIZE[1] as f32)
+                .collect::<alloc::vec::Vec<_>>();
+
+            // With uniform velocity and depth, every pixel applies the same 1D filter. Without jitter, its taps fall
+            // at fixed offsets with weights that depend only on their distance from the pixel.
+            let taps = (samples | 1) as i32;
+            let offsets = (0..taps)
+                .filter(|&s| s != taps / 2)
+                .map(|s| (VEL * (-1.0 + 2.0 * (s + 1) as f32 / (taps + 1) as f32)).round() as i32);
+            let alpha =
+                |dist: f32| 2.0 * cone(dist, VEL) + cylinder(dist, VEL) * cylinder(dist, VEL) * 2.0;
+            let total_weight =
+                1.0 / VEL + offsets.clone().map(|o| alpha(o.abs() as f32)).sum::<f32>();
+            let reference = |x: usize| {
+                let centre = if x == LINE { 1.0 / VEL } else { 0.0 };
+                let taps = offsets
+                    .clone()
+                    .filter(|o| x as i32 + o == LINE as i32)
+                    .map(|o| alpha(o.abs() as f32))
+                    .sum::<f32>();
+                (centre + taps) / total_weight
+            };
+
+            for dx in 0..=(VEL as usize + 2) {
+                let (l, r) = (profile[LINE - dx], profile[LINE + dx]);
+                assert!((l - r).abs() < 0.02, "asymmetric at {}: {:?}", dx, profile);
+            }
+            for x in 0..SIZE[0] {
+                assert!(
+                    (profile[x] - reference(x)).abs() < 0.06,
+                    "differs from reference at {}: {:?} vs {:?}",
+                    x,
+                    profile,
+                    (0..SIZE[0]).map(reference).collect::<alloc::vec::Vec<_>>(),
+                );
+            }
+            // The total intensity of the line is preserved
+            let total = profile.iter().sum::<f32>();
+            assert!((total - 1.0).abs() < 0.1, "{}", total);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(not(feature = "rayon"))]
+use alloc::{collections::VecDeque, sync::Arc};
+#[cfg(not(feature = "rayon"))]
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Condvar, OnceLock},
+    thread,
+};
+
+static MAX_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// The available parallelism of the system, which is cached because querying it can be expensive (on Linux, it
+/// involves reading cgroup quotas from the filesystem).
+#[cfg(not(feature = "rayon"))]
+static DEFAULT_THREADS: OnceLock<usize> = OnceLock::new();
+
+#[cfg(not(feature = "rayon"))]
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+/// Set the maximum number of threads (including the calling thread) that may be used to render a single draw call.
+///
+/// Passing `0` restores the default, which is the available parallelism of the system. Worker threads are spawned
+/// lazily and persist between draw calls, so lowering the limit does not stop threads that have already been spawned,
+/// but it does prevent them from being given work.
+///
+/// When the `rayon` feature is enabled, work is run on the current rayon thread pool instead of euc's own workers,
+/// and the default is the number of threads in that pool (so rendering within `rayon::ThreadPool::install` uses
+/// the installed pool).
+pub fn set_threads(n: usize) {
+    MAX_THREADS.store(n, Ordering::Relaxed);
+}
+
+/// Get the maximum number of threads (including the calling thread) that may be used to render a single draw call.
+///
+/// See [`set_threads`].
+pub fn threads() -> usize {
+    match MAX_THREADS.load(Ordering::Relaxed) {
+        #[cfg(not(feature = "rayon"))]
+        0 => *DEFAULT_THREADS.get_or_init(|| {
+            thread::available_parallelism()
+                .map(|cpu| cpu.into())
+                .unwrap_or(1usize)
+        }),
+        #[cfg(feature = "rayon")]
+        0 => rayon::current_num_threads(),
+        n => n,
+    }
+}
+
+/// Run `f` on up to `n` threads at once (including the calling thread), returning once every invocation has finished.
+///
+/// `f` is always run at least once on the calling thread, and may be run any number of times (up to `n - 1`) on
+/// worker threads, so it should pull work from a shared source until there is none left. Because the calling thread
+/// never waits for work that has not yet been started, this may be safely called from within `f` itself.
+///
+/// When the `rayon` feature is enabled, `f` is instead run exactly `n.min(threads())` times within a `rayon::scope`,
+/// which may run several invocations on the same thread.
+///
+/// # Panics
+///
+/// If any invocation of `f` panics, this function panics too.
+pub(crate) fn broadcast<F: Fn() + Sync>(n: usize, f: F) {
+    let helpers = n.min(threads()).saturating_sub(1);
+    if helpers == 0 {
+        f();
+        return;
+    }
+
+    #[cfg(feature = "rayon")]
+    rayon::scope(|s| {
+        (0..helpers).for_each(|_| s.spawn(|_| f()));
+        f();
+    });
+    #[cfg(not(feature = "rayon"))]
+    broadcast_pool(helpers, f);
+}
+
+/// Run `f` on the calling thread and on up to `helpers` of euc's own worker threads.
+#[cfg(not(feature = "rayon"))]
+fn broadcast_pool<F: Fn() + Sync>(helpers: usize, f: F) {
+    let pool = POOL.get_or_init(Pool::default);
+    pool.spawn_workers(helpers);
+
+    let f: &(dyn Fn() + Sync) = &f;
+    let job = Arc::new(Job {
+        // SAFETY: The lifetime of `f` is erased so that it can be sent to the workers. This is sound because `f` is
+        // only called by workers that registered themselves as active before the job was finished, and
+        // `JobGuard::drop` (which runs even if the calling thread panics) waits for all active workers to finish.
+        f: unsafe {
+            core::mem::transmute::<*const (dyn Fn() + Sync + '_), *const (dyn Fn() + Sync + 'static)>(
+                f,
+            )
+        },
+        state: Mutex::new(JobState {
+            slots: helpers,
+            active: 0,
+            panicked: false,
+        }),
+        finished: Condvar::new(),
+    });
+    let guard = JobGuard(job.clone());
+
+    lock(&pool.queue).extend(core::iter::repeat_n(job, helpers));
+    pool.available.notify_all();
+
+    f();
+
+    drop(guard);
+}
+
+#[cfg(not(feature = "rayon"))]
+struct JobState {
+    /// The number of workers that may still begin running the job.
+    slots: usize,
+    /// The number of workers currently running the job.
+    active: usize,
+    panicked: bool,
+}
+
+#[cfg(not(feature = "rayon"))]
+struct Job {
+    f: *const (dyn Fn() + Sync),
+    state: Mutex<JobState>,
+    finished: Condvar,
+}
+
+// SAFETY: `f` is `Sync`, and is only accessed while the job is active (see `broadcast`)
+#[cfg(not(feature = "rayon"))]
+unsafe impl Send for Job {}
+#[cfg(not(feature = "rayon"))]
+unsafe impl Sync for Job {}
+
+#[cfg(not(feature = "rayon"))]
+impl Job {
+    fn help(&self) {
+        {
+            let mut state = lock(&self.state);
+            if state.slots == 0 {
+                return;
+            }
+            state.slots -= 1;
+            state.active += 1;
+        }
+
+        // SAFETY: We registered ourselves as active above, so the caller of `broadcast` is waiting for us
+        let result = catch_unwind(AssertUnwindSafe(|| unsafe { (*self.f)() }));
+
+        let mut state = lock(&self.state);
+        state.active -= 1;
+        state.panicked |= result.is_err();
+        if state.active == 0 {
+            self.finished.notify_all();
+        }
+    }
+}
+
+/// Prevents any further workers from starting a job, and waits for active workers to finish it, when dropped.
+#[cfg(not(feature = "rayon"))]
+struct JobGuard(Arc<Job>);
+
+#[cfg(not(feature = "rayon"))]
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let mut state = lock(&self.0.state);
+        state.slots = 0;
+        while state.active > 0 {
+            state = self
+                .0
+                .finished
+                .wait(state)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        if state.panicked && !thread::panicking() {
+            panic!("A worker thread panicked while rendering");
+        }
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+#[derive(Default)]
+struct Pool {
+    queue: Mutex<VecDeque<Arc<Job>>>,
+    available: Condvar,
+    workers: Mutex<usize>,
+}
+
+#[cfg(not(feature = "rayon"))]
+impl Pool {
+    fn spawn_workers(&'static self, n: usize) {
+        let mut workers = lock(&self.workers);
+        while *workers < n {
+            thread::Builder::new()
+                .name(alloc::format!("euc-worker-{}", *workers))
+                .spawn(move || self.work())
+                .expect("Failed to spawn worker thread");
+            *workers += 1;
+        }
+    }
+
+    fn work(&self) {
+        loop {
+            let job = {
+                let mut queue = lock(&self.queue);
+                loop {
+                    match queue.pop_front() {
+                        Some(job) => break job,
+                        None => {
+                            queue = self
+                                .available
+                                .wait(queue)
+                                .unwrap_or_else(|err| err.into_inner())
+                        }
+                    }
+                }
+            };
+            job.help();
+        }
+    }
+}
+
+/// Call `f` with each item of `iter`, using as many threads as [`threads`] allows.
+///
+/// Items are handed out one at a time, in order, so this is best suited to iterators that produce a modest number of
+/// coarse items, such as the rows of an image.
+pub(crate) fn for_each<I, F>(iter: I, f: F)
+where
+    I: Iterator + Send,
+    F: Fn(I::Item) + Sync,
+{
+    for_each_init(iter, || (), |_, item| f(item));
+}
+
+/// Like [`for_each`], but `init` is called once on each thread that takes part to create state (such as a scratch
+/// buffer) that is then passed to every call to `f` on that thread.
+pub(crate) fn for_each_init<I, S, G, F>(iter: I, init: G, f: F)
+where
+    I: Iterator + Send,
+    G: Fn() -> S + Sync,
+    F: Fn(&mut S, I::Item) + Sync,
+{
+    let n = iter.size_hint().1.unwrap_or(usize::MAX);
+    let iter = Mutex::new(iter);
+    broadcast(n, || {
+        let mut state = None;
+        loop {
+            // The lock must be released before calling `f`, so the item is taken in its own statement
+            let item = lock(&iter).next();
+            match item {
+                Some(item) => f(state.get_or_insert_with(&init), item),
+                None => break,
+            }
+        }
+    });
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::Buffer2d, pipeline::Pipeline, primitives::TriangleList, CullMode};
+    use std::{collections::HashSet, panic::AssertUnwindSafe, sync::Mutex, thread};
+
+    #[test]
+    fn thread_limit() {
+        let invocations = |n| {
+            let threads = Mutex::new(HashSet::new());
+            let calls = AtomicUsize::new(0);
+            broadcast(n, || {
+                calls.fetch_add(1, Ordering::Relaxed);
+                lock(&threads).insert(thread::current().id());
+                // Give workers time to pick up the job
+                thread::sleep(std::time::Duration::from_millis(20));
+            });
+            let distinct = lock(&threads).len();
+            (calls.into_inner(), distinct)
+        };
+
+        set_threads(1);
+        assert_eq!(threads(), 1);
+        assert_eq!(invocations(usize::MAX), (1, 1));
+
+        set_threads(3);
+        assert_eq!(threads(), 3);
+        let (calls, distinct) = invocations(usize::MAX);
+        #[cfg(not(feature = "rayon"))]
+        assert!((1..=3).contains(&calls) && distinct == calls);
+        // Rayon runs every invocation, but may run several of them on the same thread
+        #[cfg(feature = "rayon")]
+        assert!(calls == 3 && (1..=3).contains(&distinct));
+        // The per-call limit applies too
+        assert_eq!(invocations(1), (1, 1));
+
+        // Panics on workers are propagated to the caller
+        let caller = thread::current().id();
+        let worker_ran = AtomicUsize::new(0);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            broadcast(usize::MAX, || {
+                if thread::current().id() != caller {
+                    worker_ran.fetch_add(1, Ordering::Relaxed);
+                    panic!("worker panic");
+                }
+                // Wait for a worker to pick up the job
+                for _ in 0..500 {
+                    if worker_ran.load(Ordering::Relaxed) > 0 {
+                        break;
+                    }
+                    thread::sleep(std::time::Duration::from_millis(10));
+                }
+            })
+        }));
+        assert!(worker_ran.into_inner() >= 1);
+        assert!(result.is_err());
+
+        set_threads(0);
+        assert_eq!(
+            threads(),
+            thread::available_parallelism().map_or(1, |n| n.get())
+        );
+    }
+
+    #[test]
+    fn nested_broadcast() {
+        let total = AtomicUsize::new(0);
+        let outer = AtomicUsize::new(0);
+        broadcast(4, || {
+            while outer.fetch_add(1, Ordering::Relaxed) < 16 {
+                let inner = AtomicUsize::new(0);
+                broadcast(4, || {
+                    while inner.fetch_add(1, Ordering::Relaxed) < 16 {
+                        total.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        assert_eq!(total.into_inner(), 16 * 16);
+    }
+
+    /// Renders a small triangle, optionally rendering another draw call from within its fragment shader.
+    struct Nested {
+        depth: usize,
+    }
+
+    impl<'r> Pipeline<'r> for Nested {
+        type Vertex = [f32; 4];
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, 1.0)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            if self.depth == 0 {
+                x
+            } else {
+                let inner = render_nested(self.depth - 1, [8, 8]);
+                inner.raw().iter().sum::<f32>()
+            }
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    fn render_nested(depth: usize, size: [usize; 2]) -> Buffer2d<f32> {
+        let mut color = Buffer2d::fill(size, 0.0);
+        Nested { depth }.render(
+            [
+                [-1.0, -1.0, 0.5, 1.0],
+                [3.0, -1.0, 0.5, 1.0],
+                [-1.0, 3.0, 0.5, 1.0],
+            ],
+            &mut color,
+            &mut crate::texture::Empty::depth(),
+        );
+        color
+    }
+
+    #[test]
+    fn nested_render() {
+        // Every fragment of the outer draw issues a full-screen inner draw, which must not deadlock
+        let color = render_nested(1, [16, 64]);
+        assert!(color.raw().iter().all(|px| *px == 64.0));
+    }
+}
@@ -1,18 +1,23 @@
 use crate::{
-    buffer::Buffer2d, math::WeightedSum, primitives::PrimitiveKind, rasterizer::Rasterizer,
-    texture::Target,
+    buffer::Buffer2d,
+    debug::{CullReason, DebugHooks, NoHooks},
+    index::{Index, IndexedVertices},
+    math::{Flat, Unit, WeightedSum},
+    primitives::PrimitiveKind,
+    rasterizer::Rasterizer,
+    texture::{Empty, Target, Texture},
 };
-use alloc::collections::VecDeque;
-use core::{borrow::Borrow, cmp::Ordering, ops::Range};
-
-#[cfg(feature = "micromath")]
-use micromath::F32Ext;
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{borrow::Borrow, cmp::Ordering, mem, ops::Range};
 
 /// Defines how a [`Pipeline`] will interact with the depth target.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub struct DepthMode {
     /// The test, if any, that occurs when comparing the depth of the new fragment with that of the current depth.
+    ///
+    /// Comparisons involving NaN always fail, so a depth target cleared with NaN rejects every fragment (see
+    /// [`Buffer2d::clear_depth`](crate::Buffer2d::clear_depth)).
     pub test: Option<Ordering>,
     /// Whether the fragment's depth should be written to the depth target if the test was passed.
     pub write: bool,
@@ -52,6 +57,241 @@ impl DepthMode {
     }
 }
 
+/// A type that may be stored in a depth target.
+///
+/// Depths are converted to their stored representation (after being mapped onto [`CoordinateMode::depth_range`])
+/// before being tested against and written to the depth target. Conversion preserves ordering, so depth tests give
+/// the same results regardless of the representation, up to its precision.
+///
+/// The integer representations (`u16`, [`U24`], and `u32`) store depths between `0.0` and `1.0` as fixed-point values,
+/// clamping depths outside of this range: pipelines with a z clip range of `-1.0..1.0` (such as
+/// [`CoordinateMode::OPENGL`]) should use [`CoordinateMode::with_depth_range`] to map depths onto `0.0..1.0`.
+pub trait DepthTexel: Copy + PartialOrd + Default {
+    /// Convert a depth into its stored representation.
+    fn from_depth(z: f32) -> Self;
+
+    /// Convert the stored representation back into a depth.
+    fn to_depth(self) -> f32;
+}
+
+impl DepthTexel for f32 {
+    #[inline(always)]
+    fn from_depth(z: f32) -> Self {
+        z
+    }
+
+    #[inline(always)]
+    fn to_depth(self) -> f32 {
+        self
+    }
+}
+
+impl DepthTexel for u16 {
+    #[inline(always)]
+    fn from_depth(z: f32) -> Self {
+        // Casting saturates, and truncation of a non-negative value rounds it after adding 0.5
+        (z.clamp(0.0, 1.0) * u16::MAX as f32 + 0.5) as u16
+    }
+
+    #[inline(always)]
+    fn to_depth(self) -> f32 {
+        self as f32 / u16::MAX as f32
+    }
+}
+
+impl DepthTexel for u32 {
+    #[inline(always)]
+    fn from_depth(z: f32) -> Self {
+        // `f32` can't represent every `u32`, so scale with more precision
+        (z.clamp(0.0, 1.0) as f64 * u32::MAX as f64 + 0.5) as u32
+    }
+
+    #[inline(always)]
+    fn to_depth(self) -> f32 {
+        (self as f64 / u32::MAX as f64) as f32
+    }
+}
+
+/// A 24-bit fixed-point depth (see [`DepthTexel`]), which takes 3 bytes rather than the 4 bytes of an `f32` or `u32`.
+///
+/// Depths are stored in big-endian byte order, such that comparing the bytes compares the depths.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U24([u8; 3]);
+
+impl U24 {
+    /// The largest representable value, which corresponds to a depth of `1.0`.
+    pub const MAX: Self = Self([0xFF; 3]);
+
+    /// Create a depth from the given value, of which only the lowest 24 bits are kept.
+    pub fn new(value: u32) -> Self {
+        let [_, a, b, c] = value.to_be_bytes();
+        Self([a, b, c])
+    }
+
+    /// The value of this depth, between `0` and `0xFFFFFF`.
+    pub fn get(self) -> u32 {
+        let [a, b, c] = self.0;
+        u32::from_be_bytes([0, a, b, c])
+    }
+}
+
+impl DepthTexel for U24 {
+    #[inline(always)]
+    fn from_depth(z: f32) -> Self {
+        Self::new((z.clamp(0.0, 1.0) as f64 * Self::MAX.get() as f64 + 0.5) as u32)
+    }
+
+    #[inline(always)]
+    fn to_depth(self) -> f32 {
+        (self.get() as f64 / Self::MAX.get() as f64) as f32
+    }
+}
+
+/// An operation applied to the stencil target when a fragment passes or fails the stencil and depth tests.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StencilOp {
+    /// Keep the existing stencil value.
+    #[default]
+    Keep,
+    /// Replace the stencil value with the reference value of the [`StencilMode`].
+    Replace,
+    /// Increment the stencil value, saturating at `u8::MAX`.
+    Incr,
+    /// Decrement the stencil value, saturating at `0`.
+    Decr,
+}
+
+impl StencilOp {
+    /// Apply this operation to the given stencil value.
+    pub fn apply(self, value: u8, reference: u8) -> u8 {
+        match self {
+            Self::Keep => value,
+            Self::Replace => reference,
+            Self::Incr => value.saturating_add(1),
+            Self::Decr => value.saturating_sub(1),
+        }
+    }
+}
+
+/// Defines how a [`Pipeline`] will interact with the stencil target.
+///
+/// The stencil test happens before the depth test. Fragments that fail either test are discarded.
+///
+/// See [`Pipeline::render_with_stencil`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct StencilMode {
+    /// The test, if any, that occurs when comparing the reference value with the current stencil value. A fragment
+    /// passes the test if the reference has this ordering relative to the current value. If there is no test, every
+    /// fragment passes.
+    pub test: Option<Ordering>,
+    /// The reference value that stencil values are compared against (and that [`StencilOp::Replace`] writes).
+    pub reference: u8,
+    /// The operation applied when a fragment fails the stencil test.
+    pub fail: StencilOp,
+    /// The operation applied when a fragment passes the stencil test but fails the depth test.
+    pub depth_fail: StencilOp,
+    /// The operation applied when a fragment passes both the stencil test and the depth test.
+    pub pass: StencilOp,
+}
+
+impl StencilMode {
+    pub const NONE: Self = Self {
+        test: None,
+        reference: 0,
+        fail: StencilOp::Keep,
+        depth_fail: StencilOp::Keep,
+        pass: StencilOp::Keep,
+    };
+
+    /// Only allow fragments through where the reference has the given ordering relative to the current stencil value.
+    pub fn with_test(self, test: Ordering, reference: u8) -> Self {
+        Self {
+            test: Some(test),
+            reference,
+            ..self
+        }
+    }
+
+    /// Write the reference value to the stencil target wherever a fragment is emitted, without performing a test.
+    pub fn write(reference: u8) -> Self {
+        Self {
+            reference,
+            pass: StencilOp::Replace,
+            ..Self::NONE
+        }
+    }
+
+    /// Apply the given operations to the stencil target when a fragment fails the stencil test, fails the depth
+    /// test, or passes both tests, respectively.
+    pub fn with_ops(self, fail: StencilOp, depth_fail: StencilOp, pass: StencilOp) -> Self {
+        Self {
+            fail,
+            depth_fail,
+            pass,
+            ..self
+        }
+    }
+
+    /// Determine whether the stencil mode needs to interact with the stencil target at all.
+    pub fn uses_stencil(&self) -> bool {
+        self.test.is_some() || self.writes_stencil()
+    }
+
+    /// Determine whether the stencil mode may modify the stencil target.
+    pub fn writes_stencil(&self) -> bool {
+        [self.fail, self.depth_fail, self.pass]
+            .iter()
+            .any(|op| *op != StencilOp::Keep)
+    }
+
+    /// Determine whether a fragment passes the stencil test, given the current stencil value.
+    pub fn passes(&self, value: u8) -> bool {
+        self.test
+            .is_none_or(|test| self.reference.cmp(&value) == test)
+    }
+}
+
+impl Default for StencilMode {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// An offset applied to the depth of each triangle's fragments before they are tested against and written to the depth
+/// target (like `glPolygonOffset`).
+///
+/// The offset for each triangle is `constant + slope_scale * max(|dz/dx|, |dz/dy|)`, where `dz/dx` and `dz/dy` are the
+/// rates at which the triangle's depth changes per pixel. The slope term grows as the triangle approaches a glancing
+/// angle, which is where a constant offset alone fails to prevent shadow acne or z-fighting.
+///
+/// The offset is applied in clip space after clipping (and before any [`CoordinateMode::depth_range`] mapping), so
+/// positive values push fragments away from the viewer. Lines are not affected.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct DepthBias {
+    /// A constant offset added to the depth of every fragment.
+    pub constant: f32,
+    /// A factor by which the depth slope of the triangle is multiplied before being added to the depth.
+    pub slope_scale: f32,
+}
+
+impl DepthBias {
+    pub const NONE: Self = Self {
+        constant: 0.0,
+        slope_scale: 0.0,
+    };
+
+    /// Create a new depth bias with the given constant and slope-scaled components.
+    pub fn new(constant: f32, slope_scale: f32) -> Self {
+        Self {
+            constant,
+            slope_scale,
+        }
+    }
+}
+
 /// Defines how a [`Pipeline`] will interact with the pixel target.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
@@ -73,6 +313,8 @@ impl Default for PixelMode {
 }
 
 /// The handedness of the coordinate space used by a pipeline.
+///
+/// This determines which triangles face towards the camera, for the purposes of [`CullMode`](crate::CullMode).
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Handedness {
     /// Left-handed coordinate space (used by Vulkan and DirectX)
@@ -96,10 +338,140 @@ pub enum YAxisDirection {
 pub struct CoordinateMode {
     pub handedness: Handedness,
     pub y_axis_direction: YAxisDirection,
+    /// The range of z coordinates (after perspective division) outside of which fragments are clipped, inclusive of
+    /// both ends. The range may be reversed (i.e: `1.0..0.0`, see [`CoordinateMode::reverse_z`]).
     pub z_clip_range: Option<Range<f32>>,
+    /// The range onto which depths are mapped before being tested against and written to the depth target (like
+    /// `glDepthRange`). The z clip range (or `0.0..1.0`, if there is none) maps to this range, so `1.0..0.0` may be
+    /// used for reversed-Z rendering. Fragments are clipped before their depth is mapped.
+    ///
+    /// If this is `None`, depths are used without modification.
+    pub depth_range: Option<Range<f32>>,
+    /// Whether the x and y coordinates of vertex positions (after perspective division) are given in pixels, from `0`
+    /// to the width and height of the target, rather than from -1 to 1. The first row of the target is at y = 0, so
+    /// [`CoordinateMode::y_axis_direction`] is ignored.
+    ///
+    /// This is useful for 2D rendering, such as of user interfaces, where positions are already known in pixels. When
+    /// supersampling (see [`AaMode::Ssaa`]), coordinates are still given in the pixels of the target.
+    pub pixel_coordinates: bool,
+}
+
+/// A type that may be treated as a colour with red, green, blue, and alpha channels, for use with [`BlendMode`].
+///
+/// Channels are expected to be linear and (with the exception of [`BlendMode::Over`] with premultiplied alpha) not
+/// premultiplied by alpha.
+pub trait Color: Sized {
+    /// Convert this colour into red, green, blue, and alpha channels.
+    fn to_rgba(&self) -> [f32; 4];
+
+    /// Create a colour from red, green, blue, and alpha channels.
+    fn from_rgba(rgba: [f32; 4]) -> Self;
+}
+
+impl Color for [f32; 4] {
+    #[inline(always)]
+    fn to_rgba(&self) -> [f32; 4] {
+        *self
+    }
+    #[inline(always)]
+    fn from_rgba(rgba: [f32; 4]) -> Self {
+        rgba
+    }
+}
+
+#[cfg(feature = "image")]
+impl Color for image::Rgba<f32> {
+    #[inline(always)]
+    fn to_rgba(&self) -> [f32; 4] {
+        self.0
+    }
+    #[inline(always)]
+    fn from_rgba(rgba: [f32; 4]) -> Self {
+        image::Rgba(rgba)
+    }
+}
+
+/// A common way of blending a new fragment (the source) into an existing pixel (the destination).
+///
+/// See [`Pipeline::blend_mode`] and [`BlendMode::apply`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BlendMode {
+    /// The source replaces the destination.
+    #[default]
+    Replace,
+    /// The source is composited over the destination according to its alpha (the 'source-over' operator).
+    ///
+    /// If `premultiplied` is `true`, the colour channels of both the source and the destination are assumed to have
+    /// already been multiplied by their alpha, and the result is premultiplied too.
+    Over { premultiplied: bool },
+    /// The channels of the source and destination are added together.
+    Add,
+    /// The channels of the source and destination are multiplied together.
+    Multiply,
+    /// The minimum of each channel of the source and destination is taken.
+    Min,
+    /// The maximum of each channel of the source and destination is taken.
+    Max,
+}
+
+impl BlendMode {
+    /// Blend the `new` colour (the source) into the `old` colour (the destination).
+    pub fn apply<P: Color, F: Color>(self, old: P, new: F) -> P {
+        let [d, s] = [old.to_rgba(), new.to_rgba()];
+        let zip = |f: fn(f32, f32) -> f32| core::array::from_fn(|i| f(s[i], d[i]));
+        P::from_rgba(match self {
+            Self::Replace => s,
+            Self::Over {
+                premultiplied: true,
+            } => core::array::from_fn(|i| s[i] + d[i] * (1.0 - s[3])),
+            Self::Over {
+                premultiplied: false,
+            } => {
+                let a = s[3] + d[3] * (1.0 - s[3]);
+                if a > 0.0 {
+                    let [r, g, b] =
+                        core::array::from_fn(|i| (s[i] * s[3] + d[i] * d[3] * (1.0 - s[3])) / a);
+                    [r, g, b, a]
+                } else {
+                    [0.0; 4]
+                }
+            }
+            Self::Add => zip(|s, d| s + d),
+            Self::Multiply => zip(|s, d| s * d),
+            Self::Min => zip(f32::min),
+            Self::Max => zip(f32::max),
+        })
+    }
+}
+
+/// An ordered dithering pattern, used to hide the banding of smooth gradients when fragments are quantized by the
+/// blend stage (see [`Pipeline::dither`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DitherMatrix {
+    /// A 4x4 Bayer matrix, which gives 16 distinct offsets.
+    #[default]
+    Bayer4x4,
+}
+
+impl DitherMatrix {
+    /// The offset of the pixel at the given position, between `-0.5` and `0.5`, in units of one quantization step.
+    ///
+    /// The offsets of each tile of the matrix average to zero.
+    pub fn offset(&self, x: usize, y: usize) -> f32 {
+        const BAYER_4X4: [[u8; 4]; 4] =
+            [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+        match self {
+            Self::Bayer4x4 => (BAYER_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5,
+        }
+    }
 }
 
 /// The anti-aliasing mode used by a pipeline.
+///
+/// Anti-aliased rendering (with either [`AaMode::Msaa`] or [`AaMode::Ssaa`]) resolves its samples into the targets once
+/// every primitive has been rasterized, and is not split between threads (see [`Parallelism`]).
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum AaMode {
@@ -107,9 +479,263 @@ pub enum AaMode {
     None,
     /// Multi-sampling anti-aliasing.
     ///
-    /// This form of anti-aliasing skips evaluating fragments in the middle of primitives while maintaining detail
-    /// along edges. The `level` should be within the range 1 to 6 (inclusive).
-    Msaa { level: u32 },
+    /// The coverage of each primitive (and the depth and stencil tests) is evaluated at `level` sample positions
+    /// within each pixel, but fragments are only evaluated once per pixel and primitive. Each pixel's samples are then
+    /// combined with [`Pipeline::downsample`], smoothing the edges of primitives without blurring their interiors.
+    ///
+    /// If `centroid` is enabled, fragments that are only partially covered by a triangle are evaluated at the centroid
+    /// of their covered samples rather than at the centre of their pixel, so their vertex data is never extrapolated
+    /// beyond the triangle (which may otherwise cause textures to be sampled outside of their region of an atlas).
+    ///
+    /// The `level` should be 2, 4, or 8. Other levels are rounded up to the next supported level (to a maximum of 8),
+    /// and levels of 0 and 1 disable anti-aliasing.
+    ///
+    /// Like the depth target with [`AaMode::Ssaa`], each pixel of the depth target receives the sample that best
+    /// passes the depth test. Multisampled rendering is not split between threads.
+    Msaa { level: u32, centroid: bool },
+    /// Super-sampling anti-aliasing.
+    ///
+    /// The scene is rendered at `factor` times the resolution of the target along each axis, evaluating every
+    /// fragment, and each block of samples is then combined into a single pixel with [`Pipeline::downsample`].
+    ///
+    /// The `factor` should be between 2 and 4 (inclusive). Larger factors are clamped to 4 (so `Ssaa { factor: 100 }`
+    /// renders like `Ssaa { factor: 4 }`), and factors of 0 and 1 disable anti-aliasing.
+    ///
+    /// Each pixel of the depth target receives the sample that best passes the depth test (i.e: the nearest sample,
+    /// for [`DepthMode::LESS_WRITE`]) or, if there is no depth test, the first sample.
+    ///
+    /// Supersampled rendering is not split between threads.
+    Ssaa { factor: u32 },
+}
+
+/// Defines the order in which the fragments that cover each pixel are blended (see [`Pipeline::transparency_mode`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TransparencyMode {
+    /// Fragments are blended as soon as they are emitted, in the order that their primitives were submitted.
+    #[default]
+    Blended,
+    /// Order-independent transparency.
+    ///
+    /// The fragments covering each pixel are kept as primitives are rasterized, then blended from back to front once
+    /// every primitive has been rasterized, so the result doesn't depend on the order that primitives were submitted
+    /// in. Fragments are ordered by the depth test of the [`DepthMode`] (i.e: the furthest fragment comes first, for
+    /// [`DepthMode::LESS_PASS`]), or with the greatest depth first if there is no depth test. Fragments with the same
+    /// depth are blended in the order that they were emitted.
+    ///
+    /// Only the nearest `max_layers` fragments are kept for each pixel (or each sample, when anti-aliasing), and any
+    /// further fragments are dropped. A `max_layers` of 0 is treated as 1. Fragments are still depth tested as they
+    /// are emitted, so transparent primitives are usually drawn after opaque ones, with a [`DepthMode`] that doesn't
+    /// write to the depth target.
+    ///
+    /// The fragments are kept in the [`Scratch`] memory of the draw call (see [`Pipeline::render_with_scratch`]).
+    /// Sorted rendering is not split between threads.
+    Sorted { max_layers: usize },
+}
+
+/// Defines how the work of a [`Pipeline`] is split between threads when the `par` feature is enabled.
+///
+/// The target is divided into groups of rows that are rasterized independently. Smaller groups balance work between
+/// threads better, but larger groups reduce the overhead of scheduling and of setting up primitives for each group.
+///
+/// Pipelines that anti-alias (see [`AaMode`]) or sort transparent fragments (see [`TransparencyMode::Sorted`]) are
+/// always rendered on a single thread.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Parallelism {
+    /// The approximate number of fragments in each group of rows.
+    pub fragments_per_group: usize,
+    /// The maximum number of threads that may render this pipeline at once, if different from the global limit (see
+    /// `par::set_threads`).
+    pub max_threads: Option<usize>,
+}
+
+impl Parallelism {
+    /// Groups of roughly 20,000 fragments, using as many threads as the global limit allows.
+    pub const DEFAULT: Self = Self {
+        fragments_per_group: 20_000,
+        max_threads: None,
+    };
+
+    /// Set the approximate number of fragments in each group of rows.
+    pub fn with_fragments_per_group(self, fragments_per_group: usize) -> Self {
+        Self {
+            fragments_per_group,
+            ..self
+        }
+    }
+
+    /// Limit the number of threads that may render this pipeline at once.
+    pub fn with_max_threads(self, max_threads: usize) -> Self {
+        Self {
+            max_threads: Some(max_threads),
+            ..self
+        }
+    }
+
+    /// Determine the number of rows in each group for a target of the given size.
+    ///
+    /// The result is always at least 1.
+    pub fn group_rows(&self, tgt_size: [usize; 2]) -> usize {
+        // No group needs more rows than the target has
+        (self.fragments_per_group / tgt_size[0].max(1)).clamp(1, tgt_size[1].max(1))
+    }
+
+    /// Iterate over the row ranges of each group for a target of the given size.
+    ///
+    /// The ranges are in order, do not overlap, and together cover every row of the target exactly once.
+    pub fn row_groups(&self, tgt_size: [usize; 2]) -> impl Iterator<Item = Range<usize>> {
+        let group_rows = self.group_rows(tgt_size);
+        (0..tgt_size[1])
+            .step_by(group_rows)
+            .map(move |start| start..(start + group_rows).min(tgt_size[1]))
+    }
+}
+
+impl Default for Parallelism {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Defines how the target is divided into tiles for tiled rendering (see [`Pipeline::tiling`]).
+///
+/// Primitives are binned into the tiles that their bounds overlap, then the tiles are rasterized one at a time, so the
+/// texels of each tile stay in the cache while every primitive that covers it is drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Tiling {
+    /// The width and height of each tile, in pixels.
+    pub tile_size: [usize; 2],
+}
+
+impl Tiling {
+    /// Tiles of 64x64 pixels.
+    pub const DEFAULT: Self = Self {
+        tile_size: [64, 64],
+    };
+
+    /// Set the width and height of each tile, in pixels.
+    pub fn with_tile_size(self, tile_size: [usize; 2]) -> Self {
+        Self { tile_size }
+    }
+
+    /// Iterate over the regions (`min` inclusive, `max` exclusive) of each tile for a target of the given size.
+    ///
+    /// The tiles are in row-major order, do not overlap, and together cover every pixel of the target exactly once.
+    /// Axes of the tile size that are 0 are treated as 1.
+    pub fn tiles(&self, tgt_size: [usize; 2]) -> impl Iterator<Item = ([usize; 2], [usize; 2])> {
+        let [w, h] = self.tile_size.map(|e| e.max(1));
+        (0..tgt_size[1]).step_by(h).flat_map(move |y| {
+            (0..tgt_size[0]).step_by(w).map(move |x| {
+                let max = [
+                    x.saturating_add(w).min(tgt_size[0]),
+                    y.saturating_add(h).min(tgt_size[1]),
+                ];
+                ([x, y], max)
+            })
+        })
+    }
+}
+
+impl Default for Tiling {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Memory that is reused between draw calls, to avoid allocating it for every call (see
+/// [`Pipeline::render_with_scratch`]).
+///
+/// `V` is the [`Pipeline::VertexData`], `P` is the [`Pipeline::Pixel`], `D` is the texel of the depth target, and `F` is
+/// the [`Pipeline::Fragment`]. Each buffer grows as needed, but is never shrunk, so after the first few draw calls
+/// rendering does not allocate. The exceptions are primitives that must be clipped against the guard band (those with
+/// vertices behind the camera or far outside of the target), and handing work to worker threads when the `par` feature
+/// is enabled.
+///
+/// ```
+/// # use euc::{Buffer2d, Pipeline, Scratch, TriangleList, Unit};
+/// # struct White;
+/// # impl<'r> Pipeline<'r> for White {
+/// #     type Vertex = [f32; 4];
+/// #     type VertexData = Unit;
+/// #     type Primitives = TriangleList;
+/// #     type Fragment = f32;
+/// #     type Pixel = f32;
+/// #     fn vertex(&self, pos: &[f32; 4]) -> ([f32; 4], Unit) { (*pos, Unit) }
+/// #     fn fragment(&self, _: Unit) -> f32 { 1.0 }
+/// #     fn blend(&self, _: f32, new: f32) -> f32 { new }
+/// # }
+/// # let triangles = [[-1.0, -1.0, 0.5, 1.0], [1.0, -1.0, 0.5, 1.0], [0.0, 1.0, 0.5, 1.0]];
+/// let mut color = Buffer2d::fill([64, 64], 0.0);
+/// let mut depth = Buffer2d::fill([64, 64], 1.0);
+/// let mut scratch = Scratch::default();
+/// for _frame in 0..3 {
+///     White.render_with_scratch(&mut scratch, &triangles, &mut color, &mut depth);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Scratch<V, P, D = f32, F = P> {
+    /// The vertices produced by the geometry stage that have yet to be rasterized.
+    vertex_queue: VecDeque<([f32; 4], V)>,
+    /// The vertices and primitive bins used to split rendering between threads or tiles.
+    bins: BinScratch<V>,
+    /// The per-sample copies of the targets used for anti-aliasing.
+    resolve: ResolveScratch<P, D>,
+    /// The fragments kept for each pixel with [`TransparencyMode::Sorted`].
+    layers: Layers<F>,
+}
+
+impl<V, P, D, F> Default for Scratch<V, P, D, F> {
+    fn default() -> Self {
+        Self {
+            vertex_queue: VecDeque::new(),
+            bins: BinScratch {
+                vertices: Vec::new(),
+                tiles: Vec::new(),
+                bins: Vec::new(),
+                verts_hom: Vec::new(),
+            },
+            resolve: ResolveScratch {
+                pixels: Buffer2d::fill_with([0; 2], || unreachable!()),
+                depths: Buffer2d::fill_with([0; 2], || unreachable!()),
+                stencils: Buffer2d::fill_with([0; 2], || unreachable!()),
+                samples: Vec::new(),
+            },
+            layers: Buffer2d::fill_with([0; 2], || unreachable!()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BinScratch<V> {
+    vertices: Vec<([f32; 4], V)>,
+    /// The region of the target covered by each tile (or group of rows), in order.
+    tiles: Vec<([usize; 2], [usize; 2])>,
+    /// The indices of the primitives that overlap each tile, of which there may be more than there are tiles.
+    bins: Vec<Vec<usize>>,
+    verts_hom: Vec<[f32; 4]>,
+}
+
+/// The fragments kept for each pixel (or sample) with [`TransparencyMode::Sorted`], stored side-by-side along the x axis
+/// and ordered from back to front, followed by any empty slots.
+type Layers<F> = Buffer2d<Option<Layer<F>>>;
+
+/// A fragment that is blended once every primitive has been rasterized (see [`TransparencyMode::Sorted`]).
+#[derive(Clone, Debug)]
+struct Layer<F> {
+    /// The depth of the fragment, as compared by the depth test.
+    z: f32,
+    coverage: f32,
+    fragment: F,
+}
+
+#[derive(Debug)]
+struct ResolveScratch<P, D> {
+    pixels: Buffer2d<P>,
+    depths: Buffer2d<D>,
+    stencils: Buffer2d<u8>,
+    /// The samples of a single pixel, as given to [`Pipeline::downsample`].
+    samples: Vec<P>,
 }
 
 impl CoordinateMode {
@@ -118,6 +744,8 @@ impl CoordinateMode {
         handedness: Handedness::Right,
         y_axis_direction: YAxisDirection::Up,
         z_clip_range: Some(-1.0..1.0),
+        depth_range: None,
+        pixel_coordinates: false,
     };
 
     /// Vulkan-like coordinates (left-handed, y = down, 0 to 1 z clip range).
@@ -125,6 +753,8 @@ impl CoordinateMode {
         handedness: Handedness::Left,
         y_axis_direction: YAxisDirection::Down,
         z_clip_range: Some(0.0..1.0),
+        depth_range: None,
+        pixel_coordinates: false,
     };
 
     /// Metal-like coordinates (right-handed, y = down, 0 to 1 z clip range).
@@ -132,6 +762,8 @@ impl CoordinateMode {
         handedness: Handedness::Right,
         y_axis_direction: YAxisDirection::Down,
         z_clip_range: Some(0.0..1.0),
+        depth_range: None,
+        pixel_coordinates: false,
     };
 
     /// DirectX-like coordinates (left-handed, y = up, 0 to 1 z clip range).
@@ -139,6 +771,8 @@ impl CoordinateMode {
         handedness: Handedness::Left,
         y_axis_direction: YAxisDirection::Up,
         z_clip_range: Some(0.0..1.0),
+        depth_range: None,
+        pixel_coordinates: false,
     };
 
     pub fn without_z_clip(self) -> Self {
@@ -148,11 +782,56 @@ impl CoordinateMode {
         }
     }
 
+    /// Use reversed-Z coordinates, where the near plane is at the end of the z clip range that was previously the far
+    /// plane, and vice versa (i.e: `0.0..1.0` becomes `1.0..0.0`).
+    ///
+    /// Reversed-Z distributes the precision of floating-point depths far more evenly across large scenes. The
+    /// projection matrix must map the near plane to the new start of the z clip range, the depth target should be
+    /// cleared to the new end (i.e: `0.0`), and the pipeline should use [`DepthMode::GREATER_WRITE`].
+    pub fn reverse_z(self) -> Self {
+        Self {
+            z_clip_range: self.z_clip_range.map(|clip| clip.end..clip.start),
+            ..self
+        }
+    }
+
+    /// Give the x and y coordinates of vertex positions in pixels, rather than from -1 to 1.
+    ///
+    /// See [`CoordinateMode::pixel_coordinates`].
+    pub fn with_pixel_coordinates(self) -> Self {
+        Self {
+            pixel_coordinates: true,
+            ..self
+        }
+    }
+
+    /// Map depths onto the given range before they are tested against and written to the depth target.
+    ///
+    /// See [`CoordinateMode::depth_range`].
+    pub fn with_depth_range(self, depth_range: Range<f32>) -> Self {
+        Self {
+            depth_range: Some(depth_range),
+            ..self
+        }
+    }
+
+    /// The scale and offset that map clip-space depths onto [`CoordinateMode::depth_range`].
+    pub(crate) fn depth_mapping(&self) -> (f32, f32) {
+        match &self.depth_range {
+            Some(range) => {
+                let clip = self.z_clip_range.clone().unwrap_or(0.0..1.0);
+                let scale = (range.end - range.start) / (clip.end - clip.start);
+                (scale, range.start - clip.start * scale)
+            }
+            None => (1.0, 0.0),
+        }
+    }
+
     pub(crate) fn passes_z_clip(&self, z: f32) -> bool {
-        // Don't use `.contains(&z)`, it isn't inclusive
+        // Don't use `.contains(&z)`, it isn't inclusive (and the range may be reversed)
         self.z_clip_range
             .as_ref()
-            .map_or(true, |clip| clip.start <= z && z <= clip.end)
+            .is_none_or(|clip| clip.start.min(clip.end) <= z && z <= clip.start.max(clip.end))
     }
 }
 
@@ -187,6 +866,30 @@ pub trait Pipeline<'r>: Sized {
         DepthMode::NONE
     }
 
+    /// Returns whether primitives that are entirely hidden behind the contents of the depth target should be skipped
+    /// without testing each of their fragments.
+    ///
+    /// This has no effect unless the depth target keeps track of the range of its depths, such as [`crate::HiZ`], and
+    /// never changes the rendered image. Primitives are never skipped while the stencil test is in use.
+    #[inline]
+    fn use_hi_z(&self) -> bool {
+        false
+    }
+
+    /// Returns the [`StencilMode`] of this pipeline.
+    ///
+    /// This has no effect unless the pipeline is rendered with [`Pipeline::render_with_stencil`].
+    #[inline]
+    fn stencil_mode(&self) -> StencilMode {
+        StencilMode::NONE
+    }
+
+    /// Returns the [`DepthBias`] of this pipeline.
+    #[inline]
+    fn depth_bias(&self) -> DepthBias {
+        DepthBias::NONE
+    }
+
     /// Returns the [`CoordinateMode`] of this pipeline.
     #[inline]
     fn coordinate_mode(&self) -> CoordinateMode {
@@ -199,7 +902,34 @@ pub trait Pipeline<'r>: Sized {
         AaMode::None
     }
 
-    /// Returns the rasterizer configuration (usually [`CullMode`], when using [`Triangles`]) of this pipeline.
+    /// Returns the [`TransparencyMode`] of this pipeline, which decides the order in which fragments are blended.
+    #[inline]
+    fn transparency_mode(&self) -> TransparencyMode {
+        TransparencyMode::Blended
+    }
+
+    /// Returns the [`Parallelism`] of this pipeline.
+    #[inline]
+    fn parallelism(&self) -> Parallelism {
+        Parallelism::default()
+    }
+
+    /// Returns the [`Tiling`] of this pipeline, if the target should be rasterized one tile at a time.
+    ///
+    /// By default, each primitive is rasterized in its entirety before moving on to the next, so large primitives
+    /// sweep over large parts of the target. For large targets (such as 4K framebuffers), rasterizing one tile at a
+    /// time keeps the texels being written in the cache, at the cost of binning primitives into tiles up front. The
+    /// result is identical either way. When rendering is split between threads, tiles are handed to threads in place
+    /// of groups of rows.
+    ///
+    /// Tiled rendering is not used with anti-aliasing, nor by [`Pipeline::render_debug`].
+    #[inline]
+    fn tiling(&self) -> Option<Tiling> {
+        None
+    }
+
+    /// Returns the rasterizer configuration (usually [`CullMode`] when using [`Triangles`], or [`LineMode`] when using
+    /// [`Lines`]) of this pipeline.
     #[inline]
     fn rasterizer_config(
         &self,
@@ -214,34 +944,201 @@ pub trait Pipeline<'r>: Sized {
     /// This stage is executed at the beginning of pipeline execution.
     fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData);
 
+    /// Like [`Pipeline::vertex`], but also given the index of the vertex within the vertex stream passed to
+    /// [`Pipeline::render`] (like `gl_VertexIndex` in GLSL).
+    ///
+    /// Vertex indices start at `0` for each call to [`Pipeline::render`]. By default, this forwards to
+    /// [`Pipeline::vertex`].
+    #[inline]
+    fn vertex_indexed(&self, index: usize, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        let _ = index;
+        self.vertex(vertex)
+    }
+
     /// Turn a primitive into many primitives.
     ///
-    /// This stage sits between the vertex shader and the fragment shader.
+    /// This stage sits between the vertex shader and the fragment shader. The primitives emitted are of the kind
+    /// [`PrimitiveKind::Output`], which is the same as that of [`Pipeline::Primitives`] unless a
+    /// [`Geometry`](crate::primitives::Geometry) kind is used to produce primitives of a different kind.
     #[inline]
     fn geometry<O>(
         &self,
         primitive: <Self::Primitives as PrimitiveKind<Self::VertexData>>::Primitive,
-        mut output: O,
+        output: O,
     ) where
-        O: FnMut(<Self::Primitives as PrimitiveKind<Self::VertexData>>::Primitive),
+        O: FnMut(
+            <<Self::Primitives as PrimitiveKind<Self::VertexData>>::Output as PrimitiveKind<
+                Self::VertexData,
+            >>::Primitive,
+        ),
     {
-        output(primitive);
+        Self::Primitives::passthrough(primitive, output);
     }
 
-    /// Transforms a [`Pipeline::VertexData`] into a fragment to be rendered to a pixel target.
+    /// Like [`Pipeline::geometry`], but also given the index of the primitive within the vertex stream passed to
+    /// [`Pipeline::render`].
     ///
-    /// This stage is executed for every fragment generated by the rasterizer.
-    fn fragment(&self, vs_out: Self::VertexData) -> Self::Fragment;
-
-    /// Blend an old fragment with a new fragment.
+    /// Primitive indices start at `0` for each call to [`Pipeline::render`]. By default, this forwards to
+    /// [`Pipeline::geometry`].
+    #[inline]
+    fn geometry_indexed<O>(
+        &self,
+        index: usize,
+        primitive: <Self::Primitives as PrimitiveKind<Self::VertexData>>::Primitive,
+        output: O,
+    ) where
+        O: FnMut(
+            <<Self::Primitives as PrimitiveKind<Self::VertexData>>::Output as PrimitiveKind<
+                Self::VertexData,
+            >>::Primitive,
+        ),
+    {
+        let _ = index;
+        self.geometry(primitive, output);
+    }
+
+    /// Transforms a [`Pipeline::VertexData`] into a fragment to be rendered to a pixel target.
+    ///
+    /// This stage is executed for every fragment generated by the rasterizer.
+    fn fragment(&self, vs_out: Self::VertexData) -> Self::Fragment;
+
+    /// Like [`Pipeline::fragment`], but also given the index of the primitive that the fragment belongs to (like
+    /// `gl_PrimitiveID` in GLSL).
+    ///
+    /// Primitives are numbered in the order that they are produced by [`Pipeline::geometry`], starting at `0` for each
+    /// call to [`Pipeline::render`]. Primitives that get culled or clipped still count towards the index, so when the
+    /// geometry stage emits each primitive unchanged, this is the index of the primitive within the vertex stream. By
+    /// default, this forwards to [`Pipeline::fragment`].
+    #[inline]
+    fn fragment_indexed(&self, primitive: usize, vs_out: Self::VertexData) -> Self::Fragment {
+        let _ = primitive;
+        self.fragment(vs_out)
+    }
+
+    /// Like [`Pipeline::fragment_indexed`], but also given the barycentric coordinates of the fragment within its
+    /// primitive.
+    ///
+    /// `bary` holds the (perspective-correct) weight of each vertex of the primitive at the fragment, in the order that
+    /// the vertices were produced by [`Pipeline::geometry`]. The weights sum to `1.0`, and lines only have two
+    /// vertices, so their third weight is always `0.0`. A weight approaches `0.0` towards the edge opposite its vertex,
+    /// so the smallest weight may be used to draw the edges of triangles (such as for a wireframe over solid geometry).
+    /// By default, this forwards to [`Pipeline::fragment_indexed`].
+    #[inline]
+    fn fragment_bary(
+        &self,
+        primitive: usize,
+        bary: [f32; 3],
+        vs_out: Self::VertexData,
+    ) -> Self::Fragment {
+        let _ = bary;
+        self.fragment_indexed(primitive, vs_out)
+    }
+
+    /// Returns whether fragments override their own depth with [`Pipeline::fragment_with_depth`].
+    ///
+    /// When this returns `true`, the fragment stage is executed before the depth test (rather than only for the
+    /// fragments that pass it), and [`Pipeline::use_hi_z`] has no effect. By default, this returns `false`.
+    #[inline]
+    fn writes_fragment_depth(&self) -> bool {
+        false
+    }
+
+    /// Like [`Pipeline::fragment`], but also produces a depth that overrides the interpolated depth of the fragment for
+    /// the depth test and the depth write, such as for impostors or raymarched surfaces.
+    ///
+    /// The depth is given in normalized device coordinates (like the z coordinate of each vertex position after it is
+    /// divided by w), and replaces the interpolated depth entirely, so [`Pipeline::depth_bias`] is not applied to it.
+    /// Returning `None` keeps the interpolated depth.
+    ///
+    /// This is only used when [`Pipeline::writes_fragment_depth`] returns `true`, in place of
+    /// [`Pipeline::fragment_bary`]. By default, this forwards to [`Pipeline::fragment`] and keeps the interpolated depth.
+    #[inline]
+    fn fragment_with_depth(&self, vs_out: Self::VertexData) -> (Self::Fragment, Option<f32>) {
+        (self.fragment(vs_out), None)
+    }
+
+    /// Like [`Pipeline::fragment_with_depth`], but also given the index of the primitive that the fragment belongs to
+    /// (see [`Pipeline::fragment_indexed`]).
+    ///
+    /// By default, this forwards to [`Pipeline::fragment_with_depth`].
+    #[inline]
+    fn fragment_indexed_with_depth(
+        &self,
+        primitive: usize,
+        vs_out: Self::VertexData,
+    ) -> (Self::Fragment, Option<f32>) {
+        let _ = primitive;
+        self.fragment_with_depth(vs_out)
+    }
+
+    /// Blend an old fragment with a new fragment.
     ///
     /// This stage is executed after rasterization and defines how a fragment may be blended into an existing fragment
     /// from the pixel target.
     ///
-    /// The default implementation simply returns the new fragment and ignores the old one. However, this may be used
-    /// to implement techniques such as alpha blending.
+    /// This may be used to implement techniques such as alpha blending. Pipelines with [`Color`] pixels and fragments
+    /// can use one of the common [`BlendMode`]s by implementing this as `self.blend_mode().apply(old, new)`.
     fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel;
 
+    /// Blend an old fragment with a new fragment that only partially covers the pixel, such as at the edges of
+    /// anti-aliased lines (see [`LineMode::AntiAliased`](crate::LineMode::AntiAliased)).
+    ///
+    /// `coverage` is the proportion of the pixel that is covered by the new fragment, between `0.0` and `1.0`. Fragments
+    /// that cover their pixel entirely are blended with [`Pipeline::blend`] instead.
+    ///
+    /// By default, the coverage is ignored and this forwards to [`Pipeline::blend`]. Pipelines with [`Color`] fragments
+    /// will usually want to scale the alpha of the fragment by `coverage` and blend it with [`BlendMode::Over`].
+    #[inline]
+    fn blend_coverage(&self, old: Self::Pixel, new: Self::Fragment, coverage: f32) -> Self::Pixel {
+        let _ = coverage;
+        self.blend(old, new)
+    }
+
+    /// Returns the [`DitherMatrix`] used to perturb each fragment before it is blended, if any.
+    ///
+    /// Dithering trades the banding of smooth gradients, which appears when the blend stage quantizes fragments into
+    /// pixels with few levels per channel (such as `u32` or `[u8; 4]`), for a fine pattern of noise. When this returns
+    /// `Some`, each fragment is passed through [`Pipeline::dither_fragment`] with the offset of its pixel before it is
+    /// blended. By default, fragments are not dithered.
+    #[inline]
+    fn dither(&self) -> Option<DitherMatrix> {
+        None
+    }
+
+    /// Perturb a fragment by the given offset (see [`DitherMatrix::offset`]) before it is blended.
+    ///
+    /// The offset is in units of one quantization step, so pipelines that quantize each channel to 8 bits will usually
+    /// add `offset / 255.0` to each channel. By default, the fragment is returned unchanged.
+    #[inline]
+    fn dither_fragment(&self, fragment: Self::Fragment, offset: f32) -> Self::Fragment {
+        let _ = offset;
+        fragment
+    }
+
+    /// Returns the [`BlendMode`] of this pipeline, for use by [`Pipeline::blend`].
+    ///
+    /// This is not used by the renderer directly, since the blend stage is free to treat pixels however it likes.
+    #[inline]
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Replace
+    }
+
+    /// Combine the samples that make up a single pixel into one pixel when anti-aliasing (see [`AaMode::Ssaa`] and
+    /// [`AaMode::Msaa`]).
+    ///
+    /// When supersampling, samples are given in row-major order. When multisampling, they are given in the order of
+    /// the sample pattern, and samples that were not covered by any primitive retain the original value of the pixel.
+    ///
+    /// The default implementation performs no filtering and returns a single sample (when supersampling, the sample
+    /// closest to the centre of the pixel): pipelines with a [`Pipeline::Pixel`] type that implements [`WeightedSum`]
+    /// will usually want to override this with [`WeightedSum::mean`], which applies a box filter. Other pixel types
+    /// (such as packed colours) must be unpacked to be averaged.
+    #[inline]
+    fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+        let factor = samples.len().isqrt();
+        samples[(factor / 2) * factor + factor / 2].clone()
+    }
+
     /// Render a stream of vertices to given provided pixel target and depth target using the rasterizer.
     ///
     /// **Do not implement this method**
@@ -251,364 +1148,4596 @@ pub trait Pipeline<'r>: Sized {
         S: IntoIterator<Item = V>,
         V: Borrow<Self::Vertex>,
         P: Target<Texel = Self::Pixel> + Send + Sync,
-        D: Target<Texel = f32> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
     {
-        let target_size = match (self.pixel_mode().write, self.depth_mode().uses_depth()) {
-            (false, false) => return, // No targets actually get written to, don't bother doing anything
-            (true, false) => pixel.size(),
-            (false, true) => depth.size(),
-            (true, true) => {
-                // Ensure that the pixel target and depth target are compatible
-                assert_eq!(
-                    pixel.size(),
-                    depth.size(),
-                    "Pixel target size is compatible with depth target size"
-                );
-                // Prefer
-                pixel.size()
-            }
-        };
+        self.render_with(vertices, pixel, depth, self.parallelism())
+    }
+
+    /// Like [`Pipeline::render`], but with the given [`Parallelism`] instead of that of [`Pipeline::parallelism`].
+    ///
+    /// This is useful for tuning the number of threads or the size of the groups that the target is divided into
+    /// for a particular draw call. Without the `par` feature, `parallelism` has no effect.
+    ///
+    /// **Do not implement this method**
+    fn render_with<S, V, P, D>(
+        &self,
+        vertices: S,
+        pixel: &mut P,
+        depth: &mut D,
+        parallelism: Parallelism,
+    ) where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, &mut Empty::default()),
+            Some(parallelism),
+            TrackStats::<false>,
+            &NoHooks,
+            &mut Scratch::default(),
+        );
+    }
+
+    /// Like [`Pipeline::render`], but reusing the memory in `scratch` instead of allocating it for this draw call.
+    ///
+    /// This is useful when making many draw calls per frame, such as when rendering many small objects or when
+    /// anti-aliasing (which needs per-sample copies of the targets). The same [`Scratch`] may be used for every draw
+    /// call of a pipeline, and for other pipelines with the same vertex data, fragment, pixel, and depth types.
+    ///
+    /// **Do not implement this method**
+    fn render_with_scratch<S, V, P, D>(
+        &self,
+        scratch: &mut Scratch<Self::VertexData, Self::Pixel, D::Texel, Self::Fragment>,
+        vertices: S,
+        pixel: &mut P,
+        depth: &mut D,
+    ) where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, &mut Empty::default()),
+            Some(self.parallelism()),
+            TrackStats::<false>,
+            &NoHooks,
+            scratch,
+        );
+    }
+
+    /// Like [`Pipeline::render`], but collecting the vertices of the draw call into `scratch` instead of allocating
+    /// memory for them.
+    ///
+    /// Vertices are collected up front when rendering is split between threads or tiles (see [`Pipeline::parallelism`]
+    /// and [`Pipeline::tiling`]). `scratch` is cleared, but not freed, once the draw call is done, so reusing it for
+    /// every frame avoids reallocating the vertices each time.
+    ///
+    /// Only the vertices are kept in `scratch`: the rest of the memory needed by the draw call (such as the primitives
+    /// binned into each tile, the per-sample targets used for anti-aliasing, and the layers kept for
+    /// [`TransparencyMode::Sorted`]) is still allocated for every call, as with [`Pipeline::render`]. To reuse all of
+    /// it, see [`Pipeline::render_with_scratch`].
+    ///
+    /// **Do not implement this method**
+    fn render_into_scratch<S, V, P, D>(
+        &self,
+        vertices: S,
+        scratch: &mut Vec<([f32; 4], Self::VertexData)>,
+        pixel: &mut P,
+        depth: &mut D,
+    ) where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        let mut full_scratch = Scratch::default();
+        full_scratch.bins.vertices = mem::take(scratch);
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, &mut Empty::default()),
+            Some(self.parallelism()),
+            TrackStats::<false>,
+            &NoHooks,
+            &mut full_scratch,
+        );
+        *scratch = full_scratch.bins.vertices;
+        scratch.clear();
+    }
+
+    /// Like [`Pipeline::render`], but also returns the bounds of the pixels that rendering may have modified, or
+    /// `None` if no fragments were emitted.
+    ///
+    /// The bounds are given as `[min_x, min_y, max_x, max_y]`, where the maximum is exclusive, and cover every fragment
+    /// that passed depth and stencil testing (including those of anti-aliased lines and partially covered pixels when
+    /// anti-aliasing). This is useful for presenting or invalidating only the part of a window that changed. Tracking
+    /// the bounds has a small cost for each fragment, which [`Pipeline::render`] does not pay.
+    ///
+    /// **Do not implement this method**
+    fn render_bounded<S, V, P, D>(
+        &self,
+        vertices: S,
+        pixel: &mut P,
+        depth: &mut D,
+    ) -> Option<[usize; 4]>
+    where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, &mut Empty::default()),
+            Some(self.parallelism()),
+            TrackStats::<true>,
+            &NoHooks,
+            &mut Scratch::default(),
+        )
+        .bounds
+    }
+
+    /// Like [`Pipeline::render`], but also returns the number of fragments that passed depth and stencil testing.
+    ///
+    /// This can be used for occlusion queries: the pixel target is not required to be written to (see
+    /// [`Pipeline::pixel_mode`]), but its size is still used to rasterize primitives if no other target is in use.
+    /// When using [`AaMode::Ssaa`], each sample is counted as a fragment. Counting fragments has a small cost for each
+    /// fragment, which [`Pipeline::render`] does not pay.
+    ///
+    /// **Do not implement this method**
+    fn render_counted<S, V, P, D>(&self, vertices: S, pixel: &mut P, depth: &mut D) -> usize
+    where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, &mut Empty::default()),
+            Some(self.parallelism()),
+            TrackStats::<true>,
+            &NoHooks,
+            &mut Scratch::default(),
+        )
+        .fragments
+    }
+
+    /// Like [`Pipeline::render`], but calls the given [`DebugHooks`] as primitives are rasterized.
+    ///
+    /// This is useful for finding out why a render does not look as expected, such as by drawing an overlay of the
+    /// primitives that were culled. Rendering happens on the current thread, so that the hooks see every primitive
+    /// exactly once and in order. [`Pipeline::render`] does not pay any cost for the hooks.
+    ///
+    /// **Do not implement this method**
+    fn render_debug<S, V, P, D, H>(&self, vertices: S, pixel: &mut P, depth: &mut D, hooks: &H)
+    where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+        H: DebugHooks,
+    {
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, &mut Empty::default()),
+            None,
+            TrackStats::<false>,
+            hooks,
+            &mut Scratch::default(),
+        );
+    }
+
+    /// Render the index of the primitive that covers each pixel (see [`Pipeline::fragment_indexed`]) to the given ID
+    /// target, instead of rendering fragments to a pixel target.
+    ///
+    /// This is useful for picking: reading the ID target under the cursor gives the primitive that was drawn there.
+    /// Primitives are depth tested as usual, so each ID belongs to the frontmost primitive, and pixels that no primitive
+    /// covers are left untouched (so filling the ID target with `u32::MAX` first distinguishes them). Since the depth
+    /// test is performed against the contents of `depth`, it should be cleared (or separate from that used to render
+    /// the image) beforehand. The fragment shader and blend stage are not used (except to produce the depth of pipelines
+    /// that override it with [`Pipeline::fragment_with_depth`]), and there is no anti-aliasing.
+    ///
+    /// **Do not implement this method**
+    fn render_ids<S, V, I, D>(&self, vertices: S, ids: &mut I, depth: &mut D)
+    where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        I: Target<Texel = u32> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        PrimitiveIds(self).render(vertices, ids, depth)
+    }
+
+    /// Render a stream of vertices to the given depth target only, such as for a shadow map.
+    ///
+    /// This behaves like [`Pipeline::render`] with an [`Empty`] pixel target and [`PixelMode::PASS`], but the pixel
+    /// stage is skipped entirely: [`Pipeline::fragment`] and [`Pipeline::blend`] are never called (whatever the
+    /// pipeline's [`Pipeline::pixel_mode`]), and only the depth test and depth write are performed for each fragment.
+    /// Pipelines that override the depth of their fragments still have [`Pipeline::fragment_with_depth`] (or
+    /// [`Pipeline::fragment_indexed_with_depth`]) called to produce it.
+    ///
+    /// **Do not implement this method**
+    fn render_depth<S, V, D>(&self, vertices: S, depth: &mut D)
+    where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        DepthOnly(self).render(vertices, &mut Empty::default(), depth)
+    }
+
+    /// Like [`Pipeline::render`], but with vertices fetched from `vertices` according to a stream of indices (see
+    /// [`IndexedVertices`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index is out of range for `vertices`.
+    ///
+    /// **Do not implement this method**
+    fn render_indexed<'a, Is, P, D>(
+        &self,
+        indices: Is,
+        vertices: &'a [Self::Vertex],
+        pixel: &mut P,
+        depth: &mut D,
+    ) where
+        Self: Send + Sync,
+        Is: IntoIterator + 'a,
+        Is::Item: Index,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+    {
+        self.render(IndexedVertices::new(indices, vertices), pixel, depth)
+    }
+
+    /// Render a stream of vertices to given provided pixel target, depth target, and stencil target using the
+    /// rasterizer.
+    ///
+    /// The stencil target is tested and updated according to [`Pipeline::stencil_mode`].
+    ///
+    /// **Do not implement this method**
+    fn render_with_stencil<S, V, P, D, St>(
+        &self,
+        vertices: S,
+        pixel: &mut P,
+        depth: &mut D,
+        stencil: &mut St,
+    ) where
+        Self: Send + Sync,
+        S: IntoIterator<Item = V>,
+        V: Borrow<Self::Vertex>,
+        P: Target<Texel = Self::Pixel> + Send + Sync,
+        D: Target + Send + Sync,
+        D::Texel: DepthTexel,
+        St: Target<Texel = u8> + Send + Sync,
+    {
+        render_targets(
+            self,
+            vertices,
+            (pixel, depth, stencil),
+            Some(self.parallelism()),
+            TrackStats::<false>,
+            &NoHooks,
+            &mut Scratch::default(),
+        );
+    }
+}
+
+/// A pipeline that renders the index of each primitive of another pipeline (see [`Pipeline::render_ids`]).
+struct PrimitiveIds<'a, Pipe>(&'a Pipe);
+
+impl<'r, Pipe: Pipeline<'r>> Pipeline<'r> for PrimitiveIds<'_, Pipe> {
+    type Vertex = Pipe::Vertex;
+    type VertexData = Pipe::VertexData;
+    type Primitives = Pipe::Primitives;
+    type Fragment = Flat<u32>;
+    type Pixel = u32;
+
+    #[inline]
+    fn depth_mode(&self) -> DepthMode {
+        self.0.depth_mode()
+    }
+    #[inline]
+    fn use_hi_z(&self) -> bool {
+        self.0.use_hi_z()
+    }
+    #[inline]
+    fn depth_bias(&self) -> DepthBias {
+        self.0.depth_bias()
+    }
+    #[inline]
+    fn coordinate_mode(&self) -> CoordinateMode {
+        self.0.coordinate_mode()
+    }
+    #[inline]
+    fn parallelism(&self) -> Parallelism {
+        self.0.parallelism()
+    }
+    #[inline]
+    fn tiling(&self) -> Option<Tiling> {
+        self.0.tiling()
+    }
+    #[inline]
+    fn rasterizer_config(
+        &self,
+    ) -> <<Self::Primitives as PrimitiveKind<Self::VertexData>>::Rasterizer as Rasterizer>::Config
+    {
+        self.0.rasterizer_config()
+    }
+    #[inline]
+    fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        self.0.vertex(vertex)
+    }
+    #[inline]
+    fn vertex_indexed(&self, index: usize, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        self.0.vertex_indexed(index, vertex)
+    }
+    #[inline]
+    fn geometry_indexed<O>(
+        &self,
+        index: usize,
+        primitive: <Self::Primitives as PrimitiveKind<Self::VertexData>>::Primitive,
+        output: O,
+    ) where
+        O: FnMut(
+            <<Self::Primitives as PrimitiveKind<Self::VertexData>>::Output as PrimitiveKind<
+                Self::VertexData,
+            >>::Primitive,
+        ),
+    {
+        self.0.geometry_indexed(index, primitive, output)
+    }
+    #[inline]
+    fn writes_fragment_depth(&self) -> bool {
+        self.0.writes_fragment_depth()
+    }
+    #[inline]
+    fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+        unreachable!("Primitive IDs are produced by `fragment_indexed`")
+    }
+    #[inline]
+    fn fragment_indexed(&self, primitive: usize, _: Self::VertexData) -> Self::Fragment {
+        Flat(primitive as u32)
+    }
+    #[inline]
+    fn fragment_with_depth(&self, _: Self::VertexData) -> (Self::Fragment, Option<f32>) {
+        unreachable!("Primitive IDs are produced by `fragment_indexed_with_depth`")
+    }
+    #[inline]
+    fn fragment_indexed_with_depth(
+        &self,
+        primitive: usize,
+        vs_out: Self::VertexData,
+    ) -> (Self::Fragment, Option<f32>) {
+        let (_, z) = self.0.fragment_indexed_with_depth(primitive, vs_out);
+        (Flat(primitive as u32), z)
+    }
+    #[inline]
+    fn blend(&self, _: Self::Pixel, Flat(id): Self::Fragment) -> Self::Pixel {
+        id
+    }
+}
+
+/// A pipeline that renders only the depth of another pipeline (see [`Pipeline::render_depth`]).
+struct DepthOnly<'a, Pipe>(&'a Pipe);
+
+impl<'r, Pipe: Pipeline<'r>> Pipeline<'r> for DepthOnly<'_, Pipe> {
+    type Vertex = Pipe::Vertex;
+    type VertexData = Pipe::VertexData;
+    type Primitives = Pipe::Primitives;
+    type Fragment = Unit;
+    type Pixel = Unit;
+
+    #[inline]
+    fn pixel_mode(&self) -> PixelMode {
+        PixelMode::PASS
+    }
+    #[inline]
+    fn depth_mode(&self) -> DepthMode {
+        self.0.depth_mode()
+    }
+    #[inline]
+    fn use_hi_z(&self) -> bool {
+        self.0.use_hi_z()
+    }
+    #[inline]
+    fn depth_bias(&self) -> DepthBias {
+        self.0.depth_bias()
+    }
+    #[inline]
+    fn coordinate_mode(&self) -> CoordinateMode {
+        self.0.coordinate_mode()
+    }
+    #[inline]
+    fn aa_mode(&self) -> AaMode {
+        self.0.aa_mode()
+    }
+    #[inline]
+    fn parallelism(&self) -> Parallelism {
+        self.0.parallelism()
+    }
+    #[inline]
+    fn tiling(&self) -> Option<Tiling> {
+        self.0.tiling()
+    }
+    #[inline]
+    fn rasterizer_config(
+        &self,
+    ) -> <<Self::Primitives as PrimitiveKind<Self::VertexData>>::Rasterizer as Rasterizer>::Config
+    {
+        self.0.rasterizer_config()
+    }
+    #[inline]
+    fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        self.0.vertex(vertex)
+    }
+    #[inline]
+    fn vertex_indexed(&self, index: usize, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+        self.0.vertex_indexed(index, vertex)
+    }
+    #[inline]
+    fn geometry_indexed<O>(
+        &self,
+        index: usize,
+        primitive: <Self::Primitives as PrimitiveKind<Self::VertexData>>::Primitive,
+        output: O,
+    ) where
+        O: FnMut(
+            <<Self::Primitives as PrimitiveKind<Self::VertexData>>::Output as PrimitiveKind<
+                Self::VertexData,
+            >>::Primitive,
+        ),
+    {
+        self.0.geometry_indexed(index, primitive, output)
+    }
+    #[inline]
+    fn writes_fragment_depth(&self) -> bool {
+        self.0.writes_fragment_depth()
+    }
+    #[inline]
+    fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+        unreachable!("Fragments are not evaluated when rendering depth only")
+    }
+    #[inline]
+    fn fragment_with_depth(&self, _: Self::VertexData) -> (Self::Fragment, Option<f32>) {
+        unreachable!("Fragment depths are produced by `fragment_indexed_with_depth`")
+    }
+    #[inline]
+    fn fragment_indexed_with_depth(
+        &self,
+        primitive: usize,
+        vs_out: Self::VertexData,
+    ) -> (Self::Fragment, Option<f32>) {
+        let (_, z) = self.0.fragment_indexed_with_depth(primitive, vs_out);
+        (Unit, z)
+    }
+    #[inline]
+    fn blend(&self, _: Self::Pixel, _: Self::Fragment) -> Self::Pixel {
+        unreachable!("Fragments are not blended when rendering depth only")
+    }
+}
+
+/// Whether to track statistics about the fragments emitted during rendering. This is decided at compile time, so that
+/// rendering without tracking them has no overhead.
+#[derive(Copy, Clone)]
+struct TrackStats<const ENABLED: bool>;
+
+/// Statistics about the fragments emitted during rendering.
+#[derive(Copy, Clone, Default)]
+struct RenderStats {
+    /// The bounds of the emitted fragments (see [`Pipeline::render_bounded`]).
+    bounds: Option<[usize; 4]>,
+    /// The number of emitted fragments (see [`Pipeline::render_counted`]).
+    fragments: usize,
+}
+
+impl RenderStats {
+    fn merge(self, other: Self) -> Self {
+        Self {
+            bounds: union_bounds(self.bounds, other.bounds),
+            fragments: self.fragments + other.fragments,
+        }
+    }
+}
+
+/// The union of two sets of bounds, given as `[min_x, min_y, max_x, max_y]`.
+fn union_bounds(a: Option<[usize; 4]>, b: Option<[usize; 4]>) -> Option<[usize; 4]> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some([
+            a[0].min(b[0]),
+            a[1].min(b[1]),
+            a[2].max(b[2]),
+            a[3].max(b[3]),
+        ]),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Render a stream of vertices to the given targets, splitting the work between threads according to `parallelism`, or
+/// on the current thread if it is `None`.
+///
+/// If `TRACK_STATS` is enabled, this returns statistics about the emitted fragments. Primitives are only given to
+/// `hooks` when rendering on the current thread, where they are rasterized exactly once and in order. Any memory
+/// needed along the way is taken from `scratch`.
+fn render_targets<'r, Pipe, S, V, P, D, St, H, const TRACK_STATS: bool>(
+    pipeline: &Pipe,
+    vertices: S,
+    (pixel, depth, stencil): (&mut P, &mut D, &mut St),
+    parallelism: Option<Parallelism>,
+    track_stats: TrackStats<TRACK_STATS>,
+    hooks: &H,
+    scratch: &mut Scratch<Pipe::VertexData, Pipe::Pixel, D::Texel, Pipe::Fragment>,
+) -> RenderStats
+where
+    Pipe: Pipeline<'r> + Send + Sync,
+    S: IntoIterator<Item = V>,
+    V: Borrow<Pipe::Vertex>,
+    P: Target<Texel = Pipe::Pixel> + Send + Sync,
+    D: Target + Send + Sync,
+    D::Texel: DepthTexel,
+    St: Target<Texel = u8> + Send + Sync,
+    H: DebugHooks,
+{
+    // Ensure that all of the targets that get used are compatible
+    let mut target_size = None;
+    for (used, size, name) in [
+        (pipeline.pixel_mode().write, pixel.size(), "Pixel"),
+        (pipeline.depth_mode().uses_depth(), depth.size(), "Depth"),
+        (
+            pipeline.stencil_mode().uses_stencil(),
+            stencil.size(),
+            "Stencil",
+        ),
+    ] {
+        match target_size {
+            _ if !used => {}
+            None => target_size = Some((size, name)),
+            Some((target_size, target_name)) if target_size != size => {
+                // Passing `Empty` for a target that the pipeline uses is an easy mistake to make, so call it out
+                if let Some((_, name)) = [(size, name), (target_size, target_name)]
+                    .into_iter()
+                    .find(|(size, _)| *size == [0; 2])
+                {
+                    panic!(
+                        "{0} target is empty, but the pipeline's `{0}Mode` uses it (pass a target with the same size \
+                        as the others, or a `{0}Mode` that does not use it)",
+                        name,
+                    );
+                }
+                panic!(
+                    "{} target size {:?} is not compatible with the size of other targets {:?}",
+                    name, size, target_size,
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    // Fragments may be counted without writing to any target, in which case the pixel target determines the size
+    let target_size = target_size
+        .map(|(size, _)| size)
+        .or(TRACK_STATS.then(|| pixel.size()));
+    let Some(target_size) = target_size else {
+        return RenderStats::default(); // No targets actually get written to, don't bother doing anything
+    };
+
+    // Produce an iterator over vertices (using the vertex shader and geometry shader to produce them)
+    let mut vert_outs = vertices
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| pipeline.vertex_indexed(i, v.borrow()))
+        .peekable();
+    let vert_out_queue = &mut scratch.vertex_queue;
+    vert_out_queue.clear();
+    let mut prim_index = 0;
+    let fetch_vertex = core::iter::from_fn(move || loop {
+        match vert_out_queue.pop_front() {
+            Some(v) => break Some(v),
+            None if vert_outs.peek().is_none() => break None,
+            None => {
+                let prim = Pipe::Primitives::collect_primitive(&mut vert_outs)?;
+                pipeline.geometry_indexed(prim_index, prim, |prim| {
+                    <Pipe::Primitives as PrimitiveKind<Pipe::VertexData>>::Output::primitive_vertices(
+                        prim,
+                        |v| vert_out_queue.push_back(v),
+                    )
+                });
+                prim_index += 1;
+            }
+        }
+    });
+
+    let max_layers = match pipeline.transparency_mode() {
+        TransparencyMode::Sorted { max_layers } if pipeline.pixel_mode().write => {
+            Some(max_layers.max(1))
+        }
+        _ => None,
+    };
+    let layers = &mut scratch.layers;
+
+    match pipeline.aa_mode() {
+        AaMode::Msaa { level, .. } if level > 1 => {
+            let samples = msaa_sample_offsets(level);
+            let n = samples.len();
+            return render_resolved(
+                pipeline,
+                fetch_vertex,
+                target_size,
+                (pixel, depth, stencil),
+                (target_size, samples),
+                // The samples of each pixel are stored side-by-side
+                |[x, y]| [x / n, y],
+                |x, y| (0..n).map(move |i| [x * n + i, y]),
+                0,
+                track_stats,
+                hooks,
+                &mut scratch.resolve,
+                prepare_layers(layers, max_layers, [target_size[0] * n, target_size[1]]),
+            );
+        }
+        AaMode::Ssaa { factor } => {
+            let factor = factor.clamp(1, 4) as usize;
+            if factor > 1 {
+                // Positions given in pixels must be scaled up to the pixels of the supersampled targets
+                let scale = if pipeline.coordinate_mode().pixel_coordinates {
+                    factor as f32
+                } else {
+                    1.0
+                };
+                return render_resolved(
+                    pipeline,
+                    fetch_vertex
+                        .map(move |([x, y, z, w], v_data)| ([x * scale, y * scale, z, w], v_data)),
+                    target_size,
+                    (pixel, depth, stencil),
+                    (target_size.map(|e| e * factor), &[[0.0; 2]]),
+                    |pos| pos.map(|e| e / factor),
+                    |x, y| {
+                        (0..factor).flat_map(move |j| {
+                            (0..factor).map(move |i| [x * factor + i, y * factor + j])
+                        })
+                    },
+                    // Like the default downsampling filter, take the sample closest to the centre of the pixel
+                    (factor / 2) * factor + factor / 2,
+                    track_stats,
+                    hooks,
+                    &mut scratch.resolve,
+                    prepare_layers(layers, max_layers, target_size.map(|e| e * factor)),
+                );
+            }
+        }
+        _ => {}
+    }
+
+    // Like splitting rendering between threads, tiling changes the order in which primitives are rasterized, so it only
+    // happens when the caller allows it (see `Pipeline::render_debug`)
+    let tiling = parallelism.and(pipeline.tiling());
+    match (parallelism, tiling) {
+        #[cfg(feature = "par")]
+        (Some(parallelism), _) if max_layers.is_none() => render_par(
+            pipeline,
+            fetch_vertex,
+            target_size,
+            (pixel, depth, stencil),
+            (parallelism, tiling),
+            track_stats,
+            &mut scratch.bins,
+        ),
+        (_, Some(tiling)) => render_tiled(
+            pipeline,
+            fetch_vertex,
+            target_size,
+            (pixel, depth, stencil),
+            tiling,
+            track_stats,
+            &mut scratch.bins,
+            prepare_layers(layers, max_layers, target_size),
+        ),
+        _ => render_seq(
+            pipeline,
+            fetch_vertex,
+            target_size,
+            (pixel, depth, stencil),
+            track_stats,
+            hooks,
+            prepare_layers(layers, max_layers, target_size),
+        ),
+    }
+}
+
+/// Clear the layers of fragments used by [`TransparencyMode::Sorted`] for targets of the given size (in samples), if
+/// `max_layers` fragments are to be kept for each sample.
+fn prepare_layers<F>(
+    layers: &mut Layers<F>,
+    max_layers: Option<usize>,
+    [w, h]: [usize; 2],
+) -> Option<(&Layers<F>, usize)> {
+    let max_layers = max_layers?;
+    layers.clear_and_resize_with([w * max_layers, h], || None);
+    Some((layers, max_layers))
+}
+
+/// The positions of the samples within each pixel used for [`AaMode::Msaa`], relative to the position of the pixel.
+///
+/// These are the standard sample patterns used by most graphics hardware, which are spread out such that edges at any
+/// angle cross as many distinct samples as possible.
+fn msaa_sample_offsets(level: u32) -> &'static [[f32; 2]] {
+    const fn offsets<const N: usize>(sixteenths: [[i8; 2]; N]) -> [[f32; 2]; N] {
+        let mut offsets = [[0.0; 2]; N];
+        let mut i = 0;
+        while i < N {
+            offsets[i] = [
+                sixteenths[i][0] as f32 / 16.0,
+                sixteenths[i][1] as f32 / 16.0,
+            ];
+            i += 1;
+        }
+        offsets
+    }
+    const X2: [[f32; 2]; 2] = offsets([[4, 4], [-4, -4]]);
+    const X4: [[f32; 2]; 4] = offsets([[-2, -6], [6, -2], [-6, 2], [2, 6]]);
+    const X8: [[f32; 2]; 8] = offsets([
+        [1, -3],
+        [-1, 3],
+        [5, 1],
+        [-3, -5],
+        [-5, 5],
+        [-7, -1],
+        [3, 7],
+        [7, -7],
+    ]);
+    match level {
+        0..=2 => &X2,
+        3..=4 => &X4,
+        _ => &X8,
+    }
+}
+
+/// Divide the target into tiles, and bin the primitives produced by `fetch_vertex` into the tiles that their bounds
+/// overlap, returning the number of vertices in each primitive and whether that number is fixed.
+///
+/// Primitives are pushed in submission order, so each tile rasterizes its primitives (and hence blends their fragments)
+/// in the same order as `render_seq`. If the rasterizer can't tell us how to divide the vertex stream into primitives,
+/// the whole stream is treated as a single primitive that covers every tile.
+fn bin_primitives<'r, Pipe, S>(
+    pipeline: &Pipe,
+    fetch_vertex: S,
+    tgt_size: [usize; 2],
+    tiling: Tiling,
+    scratch: &mut BinScratch<Pipe::VertexData>,
+) -> (usize, bool)
+where
+    Pipe: Pipeline<'r>,
+    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
+{
+    let BinScratch {
+        vertices,
+        tiles,
+        bins,
+        verts_hom,
+    } = scratch;
+
+    // Every vertex is kept until the end of the draw call, since each primitive may be rasterized by several tiles
+    vertices.clear();
+    vertices.extend(fetch_vertex);
+
+    tiles.clear();
+    tiles.extend(tiling.tiles(tgt_size));
+    let [tile_w, tile_h] = tiling.tile_size.map(|e| e.max(1));
+    let columns = tgt_size[0].div_ceil(tile_w);
+
+    let rasterizer = <Pipe::Primitives as PrimitiveKind<Pipe::VertexData>>::Rasterizer::default();
+    let fixed_verts_per_prim =
+        <<Pipe::Primitives as PrimitiveKind<Pipe::VertexData>>::Rasterizer as Rasterizer>::VERTICES_PER_PRIMITIVE;
+    let verts_per_prim = fixed_verts_per_prim.unwrap_or(vertices.len()).max(1);
+    let coordinate_mode = pipeline.coordinate_mode();
+    if bins.len() < tiles.len() {
+        bins.resize_with(tiles.len(), Vec::new);
+    }
+    let bins = &mut bins[..tiles.len()];
+    bins.iter_mut().for_each(Vec::clear);
+    for (i, prim) in vertices.chunks_exact(verts_per_prim).enumerate() {
+        let bounds = fixed_verts_per_prim.and_then(|_| {
+            verts_hom.clear();
+            verts_hom.extend(prim.iter().map(|(v_hom, _)| *v_hom));
+            rasterizer.screen_bounds(verts_hom, &coordinate_mode, tgt_size)
+        });
+        match bounds {
+            // No fragments can be produced, so there's no need to rasterize the primitive at all
+            Some((min, max)) if min[1] >= max[1] || min[0] >= max[0] => {}
+            Some((min, max)) => {
+                // Every tile other than those on the last row and column has exactly the tile size
+                for y in min[1] / tile_h..(max[1] - 1) / tile_h + 1 {
+                    bins[y * columns + min[0] / tile_w..y * columns + (max[0] - 1) / tile_w + 1]
+                        .iter_mut()
+                        .for_each(|bin| bin.push(i));
+                }
+            }
+            None => bins.iter_mut().for_each(|bin| bin.push(i)),
+        }
+    }
+
+    (verts_per_prim, fixed_verts_per_prim.is_some())
+}
+
+#[cfg(feature = "par")]
+fn render_par<'r, Pipe, S, P, D, St, const TRACK_STATS: bool>(
+    pipeline: &Pipe,
+    fetch_vertex: S,
+    tgt_size: [usize; 2],
+    (pixel, depth, stencil): (&mut P, &mut D, &mut St),
+    (parallelism, tiling): (Parallelism, Option<Tiling>),
+    track_stats: TrackStats<TRACK_STATS>,
+    scratch: &mut BinScratch<Pipe::VertexData>,
+) -> RenderStats
+where
+    Pipe: Pipeline<'r> + Send + Sync,
+    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
+    P: Target<Texel = Pipe::Pixel> + Send + Sync,
+    D: Target + Send + Sync,
+    D::Texel: DepthTexel,
+    St: Target<Texel = u8> + Send + Sync,
+{
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    // Each group of rows is a tile that spans the width of the target (see `Parallelism::row_groups`), unless the
+    // pipeline asks for smaller tiles
+    let tiling = tiling.unwrap_or_else(|| {
+        Tiling::DEFAULT.with_tile_size([tgt_size[0], parallelism.group_rows(tgt_size)])
+    });
+    let (verts_per_prim, fixed_verts_per_prim) =
+        bin_primitives(pipeline, fetch_vertex, tgt_size, tiling, scratch);
+    let BinScratch {
+        vertices,
+        tiles,
+        bins,
+        ..
+    } = scratch;
+    let group = AtomicUsize::new(0);
+
+    let pixel = &*pixel;
+    let depth = &*depth;
+    let stencil = &*stencil;
+
+    // The statistics of each group are merged as each group finishes
+    let stats = Mutex::new(RenderStats::default());
+
+    let threads = tiles
+        .len()
+        .min(parallelism.max_threads.unwrap_or(usize::MAX));
+    crate::par::broadcast(threads, || loop {
+        let group = group.fetch_add(1, Ordering::Relaxed);
+        let Some((&(tgt_min, tgt_max), bin)) = tiles.get(group).zip(bins.get(group)) else {
+            break;
+        };
+
+        // Safety: we have exclusive access to our specific regions of `pixel`, `depth`, and `stencil`
+        let group_stats = unsafe {
+            render_inner(
+                pipeline,
+                bin.iter().flat_map(|i| {
+                    vertices[i * verts_per_prim..(i + 1) * verts_per_prim]
+                        .iter()
+                        .cloned()
+                }),
+                (tgt_min, tgt_max),
+                tgt_size,
+                (pixel, depth, stencil),
+                // Primitives without fixed bounds are given to every group in their entirety
+                fixed_verts_per_prim.then_some(bin.as_slice()),
+                &[[0.0; 2]],
+                track_stats,
+                &NoHooks,
+                None,
+            )
+        };
+        if TRACK_STATS && group_stats.fragments > 0 {
+            let mut stats = stats.lock().unwrap_or_else(|err| err.into_inner());
+            *stats = stats.merge(group_stats);
+        }
+    });
+
+    // Only the capacity of the vertex buffer needs to be kept for the next draw call
+    vertices.clear();
+
+    stats.into_inner().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Render one tile at a time on the current thread (see [`Pipeline::tiling`]).
+#[allow(clippy::too_many_arguments)]
+fn render_tiled<'r, Pipe, S, P, D, St, const TRACK_STATS: bool>(
+    pipeline: &Pipe,
+    fetch_vertex: S,
+    tgt_size: [usize; 2],
+    (pixel, depth, stencil): (&mut P, &mut D, &mut St),
+    tiling: Tiling,
+    track_stats: TrackStats<TRACK_STATS>,
+    scratch: &mut BinScratch<Pipe::VertexData>,
+    layers: Option<(&Layers<Pipe::Fragment>, usize)>,
+) -> RenderStats
+where
+    Pipe: Pipeline<'r> + Send + Sync,
+    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
+    P: Target<Texel = Pipe::Pixel>,
+    D: Target,
+    D::Texel: DepthTexel,
+    St: Target<Texel = u8>,
+{
+    let (verts_per_prim, fixed_verts_per_prim) =
+        bin_primitives(pipeline, fetch_vertex, tgt_size, tiling, scratch);
+    let BinScratch {
+        vertices,
+        tiles,
+        bins,
+        ..
+    } = scratch;
+
+    let mut stats = RenderStats::default();
+    for (&(tgt_min, tgt_max), bin) in tiles.iter().zip(bins.iter()) {
+        // Safety: we have exclusive access to `pixel`, `depth`, and `stencil`
+        let tile_stats = unsafe {
+            render_inner(
+                pipeline,
+                bin.iter().flat_map(|i| {
+                    vertices[i * verts_per_prim..(i + 1) * verts_per_prim]
+                        .iter()
+                        .cloned()
+                }),
+                (tgt_min, tgt_max),
+                tgt_size,
+                (&*pixel, &*depth, &*stencil),
+                // Primitives without fixed bounds are given to every tile in their entirety
+                fixed_verts_per_prim.then_some(bin.as_slice()),
+                &[[0.0; 2]],
+                track_stats,
+                &NoHooks,
+                layers,
+            )
+        };
+        stats = stats.merge(tile_stats);
+    }
+
+    // Only the capacity of the vertex buffer needs to be kept for the next draw call
+    vertices.clear();
+
+    stats
+}
+
+fn render_seq<'r, Pipe, S, P, D, St, H, const TRACK_STATS: bool>(
+    pipeline: &Pipe,
+    fetch_vertex: S,
+    tgt_size: [usize; 2],
+    (pixel, depth, stencil): (&mut P, &mut D, &mut St),
+    track_stats: TrackStats<TRACK_STATS>,
+    hooks: &H,
+    layers: Option<(&Layers<Pipe::Fragment>, usize)>,
+) -> RenderStats
+where
+    Pipe: Pipeline<'r> + Send + Sync,
+    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
+    P: Target<Texel = Pipe::Pixel> + Send + Sync,
+    D: Target + Send + Sync,
+    D::Texel: DepthTexel,
+    St: Target<Texel = u8> + Send + Sync,
+    H: DebugHooks,
+{
+    // Safety: we have exclusive access to `pixel`, `depth`, and `stencil`
+    unsafe {
+        render_inner(
+            pipeline,
+            fetch_vertex,
+            ([0; 2], tgt_size),
+            tgt_size,
+            (&*pixel, &*depth, &*stencil),
+            None,
+            &[[0.0; 2]],
+            track_stats,
+            hooks,
+            layers,
+        )
+    }
+}
+
+/// Render to per-sample copies of the targets, then combine the samples of each pixel to produce the final pixel.
+///
+/// Rendering happens at `render_size`, with each fragment covering the samples at `sample_offsets` (see
+/// [`Blitter::sample_offsets`]). `sample_pixel` maps the position of a sample to that of the pixel that it belongs to,
+/// `pixel_samples` produces the positions of the samples of a pixel, and the stencil target receives the sample with
+/// index `stencil_sample`.
+#[allow(clippy::too_many_arguments)]
+fn render_resolved<'r, Pipe, S, P, D, St, I, H, const TRACK_STATS: bool>(
+    pipeline: &Pipe,
+    fetch_vertex: S,
+    tgt_size: [usize; 2],
+    (pixel, depth, stencil): (&mut P, &mut D, &mut St),
+    (render_size, sample_offsets): ([usize; 2], &[[f32; 2]]),
+    sample_pixel: impl Fn([usize; 2]) -> [usize; 2],
+    pixel_samples: impl Fn(usize, usize) -> I,
+    stencil_sample: usize,
+    track_stats: TrackStats<TRACK_STATS>,
+    hooks: &H,
+    scratch: &mut ResolveScratch<Pipe::Pixel, D::Texel>,
+    layers: Option<(&Layers<Pipe::Fragment>, usize)>,
+) -> RenderStats
+where
+    Pipe: Pipeline<'r> + Send + Sync,
+    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
+    P: Target<Texel = Pipe::Pixel>,
+    D: Target,
+    D::Texel: DepthTexel,
+    St: Target<Texel = u8>,
+    I: Iterator<Item = [usize; 2]> + Clone,
+    H: DebugHooks,
+{
+    let write_pixels = pipeline.pixel_mode().write;
+    let depth_mode = pipeline.depth_mode();
+    let stencil_mode = pipeline.stencil_mode();
+
+    // Every sample begins with the value of the pixel that it belongs to, so that blending and depth testing behave as
+    // they would without anti-aliasing
+    let samples_size = [render_size[0] * sample_offsets.len(), render_size[1]];
+    let sample_pixel = &sample_pixel;
+    let sample_pos = || {
+        let mut i = 0;
+        move || {
+            let pos = [i % samples_size[0], i / samples_size[0]];
+            i += 1;
+            sample_pixel(pos)
+        }
+    };
+    let ResolveScratch {
+        pixels,
+        depths,
+        stencils,
+        samples,
+    } = scratch;
+    let mut pos = sample_pos();
+    if write_pixels {
+        pixels.clear_and_resize_with(samples_size, || pixel.read(pos()));
+    } else {
+        pixels.clear_and_resize_with([0; 2], || unreachable!());
+    }
+    let mut pos = sample_pos();
+    if depth_mode.uses_depth() {
+        depths.clear_and_resize_with(samples_size, || depth.read(pos()));
+    } else {
+        depths.clear_and_resize_with([0; 2], || unreachable!());
+    }
+    let mut pos = sample_pos();
+    if stencil_mode.uses_stencil() {
+        stencils.clear_and_resize_with(samples_size, || stencil.read(pos()));
+    } else {
+        stencils.clear_and_resize_with([0; 2], || unreachable!());
+    }
+
+    // Safety: we have exclusive access to `pixels`, `depths`, and `stencils`
+    let mut stats = unsafe {
+        render_inner(
+            pipeline,
+            fetch_vertex,
+            ([0; 2], render_size),
+            render_size,
+            (&*pixels, &*depths, &*stencils),
+            None,
+            sample_offsets,
+            track_stats,
+            hooks,
+            layers,
+        )
+    };
+    // Fragments are positioned at the first of their samples, and their bounds are exclusive
+    let n = sample_offsets.len();
+    stats.bounds = stats.bounds.map(|[x0, y0, x1, y1]| {
+        let [x0, y0] = sample_pixel([x0 * n, y0]);
+        let [x1, y1] = sample_pixel([(x1 - 1) * n, y1 - 1]);
+        [x0, y0, x1 + 1, y1 + 1]
+    });
+
+    for y in 0..tgt_size[1] {
+        for x in 0..tgt_size[0] {
+            let sample_pos = pixel_samples(x, y);
+            if write_pixels {
+                samples.clear();
+                samples.extend(sample_pos.clone().map(|pos| pixels.read(pos)));
+                pixel.write(x, y, pipeline.downsample(samples));
+            }
+            if depth_mode.write {
+                let z = sample_pos
+                    .clone()
+                    .map(|pos| depths.read(pos))
+                    .reduce(|a, b| match depth_mode.test {
+                        Some(test) if b.partial_cmp(&a) == Some(test) => b,
+                        _ => a,
+                    });
+                depth.write(x, y, z.unwrap_or_default());
+            }
+            if stencil_mode.writes_stencil() {
+                let pos = sample_pos.clone().nth(stencil_sample).unwrap();
+                stencil.write(x, y, stencils.read(pos));
+            }
+        }
+    }
+
+    stats
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn render_inner<'r, Pipe, S, P, D, St, H, const TRACK_STATS: bool>(
+    pipeline: &Pipe,
+    fetch_vertex: S,
+    (tgt_min, tgt_max): ([usize; 2], [usize; 2]),
+    tgt_size: [usize; 2],
+    (pixel, depth, stencil): (&P, &D, &St),
+    primitive_indices: Option<&[usize]>,
+    sample_offsets: &[[f32; 2]],
+    _: TrackStats<TRACK_STATS>,
+    hooks: &H,
+    layers: Option<(&Layers<Pipe::Fragment>, usize)>,
+) -> RenderStats
+where
+    Pipe: Pipeline<'r> + Send + Sync,
+    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
+    P: Target<Texel = Pipe::Pixel>,
+    D: Target,
+    D::Texel: DepthTexel,
+    St: Target<Texel = u8>,
+    H: DebugHooks,
+{
+    let write_pixels = pipeline.pixel_mode().write;
+    let depth_mode = pipeline.depth_mode();
+    let stencil_mode = pipeline.stencil_mode();
+    let coordinate_mode = pipeline.coordinate_mode();
+    // The samples of each fragment are stored side-by-side along the x axis of each target
+    let scale = [sample_offsets.len(), 1];
+    for i in 0..2 {
+        // Safety check
+        if write_pixels {
+            assert!(
+                tgt_min[i] * scale[i] <= pixel.size()[i],
+                "{}, {}, {}",
+                i,
+                tgt_min[i],
+                pixel.size()[i]
+            );
+            assert!(
+                tgt_max[i] * scale[i] <= pixel.size()[i],
+                "{}, {}, {}",
+                i,
+                tgt_min[i],
+                pixel.size()[i]
+            );
+        }
+        if depth_mode.uses_depth() {
+            assert!(
+                tgt_min[i] * scale[i] <= depth.size()[i],
+                "{}, {}, {}",
+                i,
+                tgt_min[i],
+                depth.size()[i]
+            );
+            assert!(
+                tgt_max[i] * scale[i] <= depth.size()[i],
+                "{}, {}, {}",
+                i,
+                tgt_min[i],
+                depth.size()[i]
+            );
+        }
+        if stencil_mode.uses_stencil() {
+            assert!(
+                tgt_min[i] * scale[i] <= stencil.size()[i]
+                    && tgt_max[i] * scale[i] <= stencil.size()[i],
+                "{}, {}, {}",
+                i,
+                tgt_min[i],
+                stencil.size()[i]
+            );
+        }
+    }
+
+    if let Some((layers, max_layers)) = layers {
+        // Safety check
+        assert!(
+            tgt_max[0] * scale[0] * max_layers <= layers.size()[0]
+                && tgt_max[1] <= layers.size()[1],
+            "{:?}, {:?}",
+            tgt_max,
+            layers.size()
+        );
+    }
+
+    // The layout of the depth target takes precedence, since it's accessed for every fragment (even rejected ones)
+    let principal_x = depth
+        .preferred_axes()
+        .or_else(|| pixel.preferred_axes())
+        .is_none_or(|[a, _]| a == 0);
+
+    use crate::rasterizer::{Blitter, MAX_SAMPLES};
+    use core::{cell::Cell, marker::PhantomData};
+
+    struct BlitterImpl<'a, 'r, Pipe: Pipeline<'r>, P, D, St, H, const TRACK_STATS: bool> {
+        write_pixels: bool,
+        fragment_depth: bool,
+        dither: Option<DitherMatrix>,
+        depth_mode: DepthMode,
+        use_hi_z: bool,
+        stencil_mode: Option<StencilMode>,
+        depth_mapping: (f32, f32),
+        depth_bias: DepthBias,
+
+        tgt_min: [usize; 2],
+        tgt_max: [usize; 2],
+        tgt_size: [usize; 2],
+
+        pipeline: &'a Pipe,
+        pixel: &'a P,
+        depth: &'a D,
+        stencil: &'a St,
+        // The indices of the primitives being rasterized, if they are not simply `0..`
+        primitive_indices: Option<&'a [usize]>,
+        primitive_count: usize,
+        primitive_index: usize,
+
+        sample_offsets: &'a [[f32; 2]],
+        centroid: bool,
+        // The layers of fragments that are blended once rasterization is complete, and the number of layers per sample
+        layers: Option<(&'a Layers<Pipe::Fragment>, usize)>,
+        // Whether fragments with a greater depth lie behind those with a lesser depth, for sorting layers
+        greater_is_further: bool,
+        // The samples of the fragment being rasterized that passed testing, and their depths
+        passed_samples: u32,
+        passed_z: [f32; MAX_SAMPLES],
+        // The statistics of the fragments emitted so far, if `TRACK_STATS` is enabled
+        stats: &'a Cell<RenderStats>,
+        hooks: &'a H,
+        phantom: PhantomData<&'r ()>,
+    }
+
+    impl<'a, 'r, Pipe, P, D, St, H, const TRACK_STATS: bool>
+        BlitterImpl<'a, 'r, Pipe, P, D, St, H, TRACK_STATS>
+    where
+        Pipe: Pipeline<'r> + Send + Sync,
+        P: Target<Texel = Pipe::Pixel>,
+        D: Target,
+        D::Texel: DepthTexel,
+        St: Target<Texel = u8>,
+        H: DebugHooks,
+    {
+        #[inline]
+        unsafe fn update_stencil(&self, x: usize, y: usize, value: u8, op: StencilOp) {
+            if let Some(stencil_mode) = self.stencil_mode.filter(|_| op != StencilOp::Keep) {
+                self.stencil.write_exclusive_unchecked(
+                    x,
+                    y,
+                    op.apply(value, stencil_mode.reference),
+                );
+            }
+        }
+
+        /// Map a depth onto its stored representation in the depth target.
+        #[inline]
+        fn map_depth(&self, z: f32) -> D::Texel {
+            D::Texel::from_depth(z * self.depth_mapping.0 + self.depth_mapping.1)
+        }
+
+        /// Test a single sample, at the given position within the targets.
+        #[inline]
+        unsafe fn test_sample(&self, x: usize, y: usize, z: f32) -> bool {
+            // The stencil test happens first, and fragments that fail it are never depth tested
+            let stencil = self.stencil_mode.map(|stencil_mode| {
+                let value = self.stencil.read_exclusive_unchecked(x, y);
+                (stencil_mode, value)
+            });
+            if let Some((stencil_mode, value)) = stencil {
+                if !stencil_mode.passes(value) {
+                    self.update_stencil(x, y, value, stencil_mode.fail);
+                    return false;
+                }
+            }
+
+            if let Some(test) = self.depth_mode.test {
+                let z = self.map_depth(z);
+                let old_z = self.depth.read_exclusive_unchecked(x, y);
+                if z.partial_cmp(&old_z) != Some(test) {
+                    if let Some((stencil_mode, value)) = stencil {
+                        self.update_stencil(x, y, value, stencil_mode.depth_fail);
+                    }
+                    return false;
+                }
+            }
+            true
+        }
+
+        /// Keep a fragment in the layers of the sample at the given position, which are ordered from back to front.
+        /// When the layers are full, the furthest fragment is dropped.
+        #[inline]
+        unsafe fn push_layer(&self, x: usize, y: usize, layer: Layer<Pipe::Fragment>) {
+            let Some((layers, max_layers)) = self.layers else {
+                return;
+            };
+            let slots = layers.texels_exclusive_unchecked(x * max_layers, y, max_layers);
+            let further = |a: &Layer<Pipe::Fragment>, b: &Layer<Pipe::Fragment>| {
+                if self.greater_is_further {
+                    a.z > b.z
+                } else {
+                    a.z < b.z
+                }
+            };
+            let len = slots.iter().take_while(|slot| slot.is_some()).count();
+            // Fragments at the same depth are blended in the order they were emitted, so they go after existing ones
+            let pos = slots[..len]
+                .iter()
+                .take_while(|slot| slot.as_ref().is_some_and(|l| !further(&layer, l)))
+                .count();
+            if len < max_layers {
+                slots[pos..=len].rotate_right(1);
+                slots[pos] = Some(layer);
+            } else if pos > 0 {
+                // Drop the furthest fragment to make room
+                slots[..pos].rotate_left(1);
+                slots[pos - 1] = Some(layer);
+            }
+        }
+    }
+
+    impl<'a, 'r, Pipe, P, D, St, H, const TRACK_STATS: bool> Blitter<Pipe::VertexData>
+        for BlitterImpl<'a, 'r, Pipe, P, D, St, H, TRACK_STATS>
+    where
+        Pipe: Pipeline<'r> + Send + Sync,
+        P: Target<Texel = Pipe::Pixel>,
+        D: Target,
+        D::Texel: DepthTexel,
+        St: Target<Texel = u8>,
+        H: DebugHooks,
+    {
+        fn target_size(&self) -> [usize; 2] {
+            self.tgt_size
+        }
+        fn target_min(&self) -> [usize; 2] {
+            self.tgt_min
+        }
+        fn target_max(&self) -> [usize; 2] {
+            self.tgt_max
+        }
+        fn depth_bias(&self) -> DepthBias {
+            self.depth_bias
+        }
+        fn sample_offsets(&self) -> &[[f32; 2]] {
+            self.sample_offsets
+        }
+        fn centroid_sampling(&self) -> bool {
+            self.centroid
+        }
+
+        #[inline]
+        fn test_region(&self, min: [usize; 2], max: [usize; 2], [z0, z1]: [f32; 2]) -> bool {
+            // Fragments that fail the depth test still affect the stencil target, so they can't be skipped. Nor can
+            // fragments that override their depth, which may lie anywhere.
+            if !self.use_hi_z || self.stencil_mode.is_some() || self.fragment_depth {
+                return true;
+            }
+            let Some(test) = self.depth_mode.test else {
+                return true;
+            };
+            let Some([old_min, old_max]) = self.depth.texel_bounds(min, max) else {
+                return true;
+            };
+            // The depth mapping may reverse the order of the range
+            let [z0, z1] = [z0, z1].map(|z| self.map_depth(z));
+            let [z_min, z_max] = if z0 <= z1 { [z0, z1] } else { [z1, z0] };
+            match test {
+                Ordering::Less => z_min < old_max,
+                Ordering::Greater => z_max > old_min,
+                Ordering::Equal => z_min <= old_max && z_max >= old_min,
+            }
+        }
+
+        #[inline]
+        fn begin_primitive(&mut self) {
+            self.primitive_index = match self.primitive_indices {
+                Some(indices) => indices[self.primitive_count],
+                None => self.primitive_count,
+            };
+            self.primitive_count += 1;
+        }
+
+        #[inline]
+        fn inspect_primitive(&mut self, verts_hom: &[[f32; 4]]) {
+            self.hooks.on_primitive(self.primitive_index, verts_hom);
+        }
+
+        #[inline]
+        fn primitive_culled(&mut self, reason: CullReason) {
+            self.hooks.on_primitive_culled(self.primitive_index, reason);
+        }
+
+        #[inline]
+        unsafe fn test_fragment(&mut self, x: usize, y: usize, z: f32) -> bool {
+            // Rasterizers that don't evaluate coverage per-sample cover every sample of the fragment
+            let samples = self.sample_offsets.len();
+            self.test_samples(
+                x,
+                y,
+                u32::MAX >> (32 - samples),
+                &[z; MAX_SAMPLES][..samples],
+            )
+        }
+
+        #[inline]
+        unsafe fn test_samples(&mut self, x: usize, y: usize, coverage: u32, z: &[f32]) -> bool {
+            if self.fragment_depth {
+                // Testing is deferred until the fragment has been evaluated, since it may override its depth
+                self.passed_samples = coverage;
+                self.passed_z[..z.len()].copy_from_slice(z);
+                return coverage != 0;
+            }
+
+            let samples = self.sample_offsets.len();
+            self.passed_samples = 0;
+            for (i, z) in z.iter().enumerate() {
+                if coverage & (1 << i) != 0 && self.test_sample(x * samples + i, y, *z) {
+                    self.passed_samples |= 1 << i;
+                    self.passed_z[i] = *z;
+                }
+            }
+            let passed = self.passed_samples != 0;
+            // Report the depth of the first covered sample
+            let first = (coverage.trailing_zeros() as usize).min(z.len() - 1);
+            self.hooks.on_fragment(x, y, z[first], passed);
+            passed
+        }
+
+        #[inline]
+        unsafe fn emit_fragment<F: FnMut(f32, f32) -> ([f32; 3], Pipe::VertexData)>(
+            &mut self,
+            x: usize,
+            y: usize,
+            get_v_data: F,
+            z: f32,
+        ) {
+            self.emit_fragment_with_coverage(x, y, get_v_data, z, 1.0)
+        }
+
+        #[inline]
+        unsafe fn emit_fragment_with_coverage<
+            F: FnMut(f32, f32) -> ([f32; 3], Pipe::VertexData),
+        >(
+            &mut self,
+            x: usize,
+            y: usize,
+            mut get_v_data: F,
+            _z: f32,
+            coverage: f32,
+        ) {
+            let samples = self.sample_offsets.len();
+
+            // The fragment is evaluated once, no matter how many of its samples passed
+            let frag = if self.fragment_depth {
+                let (_, v_data) = get_v_data(x as f32, y as f32);
+                let (frag, frag_z) = self
+                    .pipeline
+                    .fragment_indexed_with_depth(self.primitive_index, v_data);
+
+                // Perform the deferred tests of the covered samples (see `test_samples`)
+                let covered = self.passed_samples;
+                self.passed_samples = 0;
+                for i in (0..samples).filter(|i| covered & (1 << i) != 0) {
+                    let z = frag_z.unwrap_or(self.passed_z[i]);
+                    if self.test_sample(x * samples + i, y, z) {
+                        self.passed_samples |= 1 << i;
+                        self.passed_z[i] = z;
+                    }
+                }
+                let passed = self.passed_samples != 0;
+                let first = (covered.trailing_zeros() as usize).min(samples - 1);
+                self.hooks
+                    .on_fragment(x, y, frag_z.unwrap_or(self.passed_z[first]), passed);
+                if !passed {
+                    return;
+                }
+
+                self.write_pixels.then_some(frag)
+            } else {
+                self.write_pixels.then(|| {
+                    let (bary, v_data) = get_v_data(x as f32, y as f32);
+                    self.pipeline
+                        .fragment_bary(self.primitive_index, bary, v_data)
+                })
+            };
+            let frag = frag.map(|frag| match self.dither {
+                Some(dither) => self.pipeline.dither_fragment(frag, dither.offset(x, y)),
+                None => frag,
+            });
+
+            if TRACK_STATS {
+                self.stats.set(self.stats.get().merge(RenderStats {
+                    bounds: Some([x, y, x + 1, y + 1]),
+                    fragments: 1,
+                }));
+            }
+
+            for i in (0..samples).filter(|i| self.passed_samples & (1 << i) != 0) {
+                let x = x * samples + i;
+
+                if self.depth_mode.write {
+                    let z = self.map_depth(self.passed_z[i]);
+                    self.depth.write_exclusive_unchecked(x, y, z);
+                }
+
+                if let Some(stencil_mode) = self.stencil_mode {
+                    let value = self.stencil.read_exclusive_unchecked(x, y);
+                    self.update_stencil(x, y, value, stencil_mode.pass);
+                }
+
+                if let (Some(frag), Some(_)) = (&frag, self.layers) {
+                    let z = self.passed_z[i] * self.depth_mapping.0 + self.depth_mapping.1;
+                    self.push_layer(
+                        x,
+                        y,
+                        Layer {
+                            z,
+                            coverage,
+                            fragment: frag.clone(),
+                        },
+                    );
+                } else if let Some(frag) = &frag {
+                    let old_px = self.pixel.read_exclusive_unchecked(x, y);
+                    let blended_px = if coverage < 1.0 {
+                        self.pipeline.blend_coverage(old_px, frag.clone(), coverage)
+                    } else {
+                        self.pipeline.blend(old_px, frag.clone())
+                    };
+                    self.pixel.write_exclusive_unchecked(x, y, blended_px);
+                }
+            }
+        }
+    }
+
+    let stats = Cell::new(RenderStats::default());
+    <Pipe::Primitives as PrimitiveKind<Pipe::VertexData>>::Rasterizer::default().rasterize(
+        fetch_vertex,
+        principal_x,
+        coordinate_mode.clone(),
+        pipeline.rasterizer_config(),
+        BlitterImpl::<_, _, _, _, _, TRACK_STATS> {
+            write_pixels,
+            fragment_depth: pipeline.writes_fragment_depth(),
+            dither: pipeline.dither(),
+            depth_mode,
+            use_hi_z: pipeline.use_hi_z(),
+            stencil_mode: Some(stencil_mode).filter(StencilMode::uses_stencil),
+            depth_mapping: coordinate_mode.depth_mapping(),
+            depth_bias: pipeline.depth_bias(),
+
+            tgt_size,
+            tgt_min,
+            tgt_max,
+
+            pipeline,
+            pixel,
+            depth,
+            stencil,
+            primitive_indices,
+            primitive_count: 0,
+            primitive_index: 0,
+
+            sample_offsets,
+            centroid: matches!(pipeline.aa_mode(), AaMode::Msaa { centroid: true, .. }),
+            layers,
+            greater_is_further: depth_mode.test != Some(Ordering::Greater),
+            passed_samples: 0,
+            passed_z: [0.0; MAX_SAMPLES],
+            stats: &stats,
+            hooks,
+            phantom: PhantomData,
+        },
+    );
+
+    // Blend the kept fragments of each sample from back to front (see `TransparencyMode::Sorted`)
+    if let Some((layers, max_layers)) = layers {
+        for y in tgt_min[1]..tgt_max[1] {
+            for x in tgt_min[0] * scale[0]..tgt_max[0] * scale[0] {
+                let slots = layers.texels_exclusive_unchecked(x * max_layers, y, max_layers);
+                let mut px = None;
+                for layer in slots.iter_mut().map_while(Option::take) {
+                    let old_px = px.unwrap_or_else(|| pixel.read_exclusive_unchecked(x, y));
+                    px = Some(if layer.coverage < 1.0 {
+                        pipeline.blend_coverage(old_px, layer.fragment, layer.coverage)
+                    } else {
+                        pipeline.blend(old_px, layer.fragment)
+                    });
+                }
+                if let Some(px) = px {
+                    pixel.write_exclusive_unchecked(x, y, px);
+                }
+            }
+        }
+    }
+
+    stats.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::ColumnMajor,
+        math::{Attributes, Flat, NoPerspective, Unit},
+        primitives::{Geometry, LineList, PointList, QuadList, TriangleList},
+        rasterizer::{CullMode, LineMode, Quads, Triangles},
+        texture::Empty,
+    };
+    use alloc::vec::Vec;
+    use core::marker::PhantomData;
+
+    struct Blend {
+        aa_mode: AaMode,
+        parallelism: Parallelism,
+    }
+
+    impl<'r> Pipeline<'r> for Blend {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn aa_mode(&self) -> AaMode {
+            self.aa_mode
+        }
+        fn parallelism(&self) -> Parallelism {
+            self.parallelism
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, (pos, x): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, *x)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            x
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            // Deliberately order-dependent, so that any change in the order fragments are blended is detected
+            old * 0.5 + new
+        }
+        fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+            WeightedSum::mean(samples)
+        }
+    }
+
+    /// Overlapping triangles that are larger than the target, some of which cross the near plane.
+    pub(crate) fn overlapping_triangles() -> Vec<([f32; 4], f32)> {
+        (0..300)
+            .map(|i| {
+                let f = i as f32;
+                let w = if i % 17 == 0 { -0.3 } else { 1.0 };
+                (
+                    [
+                        (f * 0.37).sin() * 1.4,
+                        (f * 0.71).cos() * 1.4,
+                        (f * 0.13).sin() * 0.5 + 0.5,
+                        w,
+                    ],
+                    (f * 1.3).sin(),
+                )
+            })
+            .collect()
+    }
+
+    fn render(aa_mode: AaMode, parallelism: Parallelism) -> (Buffer2d<f32>, Buffer2d<f32>) {
+        let mut color = Buffer2d::fill([61, 487], 0.0);
+        let mut depth = Buffer2d::fill([61, 487], 0.75);
+        Blend {
+            aa_mode,
+            parallelism,
+        }
+        .render(overlapping_triangles(), &mut color, &mut depth);
+        (color, depth)
+    }
+
+    #[test]
+    fn render_with_parallelism() {
+        let render_with = |parallelism| {
+            let mut color = Buffer2d::fill([61, 487], 0.0);
+            let mut depth = Buffer2d::fill([61, 487], 0.75);
+            Blend {
+                aa_mode: AaMode::None,
+                parallelism: Parallelism::DEFAULT,
+            }
+            .render_with(overlapping_triangles(), &mut color, &mut depth, parallelism);
+            (color, depth)
+        };
+
+        let (color, depth) = render(AaMode::None, Parallelism::DEFAULT);
+        let sequential = render_with(Parallelism::DEFAULT.with_max_threads(1));
+        assert_eq!(sequential.0.raw(), color.raw());
+        assert_eq!(sequential.1.raw(), depth.raw());
+        for max_threads in [1, 2, 5] {
+            let (c, d) = render_with(
+                Parallelism::DEFAULT
+                    .with_fragments_per_group(500)
+                    .with_max_threads(max_threads),
+            );
+            assert_eq!(c.raw(), color.raw(), "{} threads", max_threads);
+            assert_eq!(d.raw(), depth.raw(), "{} threads", max_threads);
+        }
+    }
+
+    /// Renders like [`Blend`], but one tile at a time.
+    struct Tiled(Blend, Tiling, TransparencyMode);
+
+    impl<'r> Pipeline<'r> for Tiled {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            self.0.depth_mode()
+        }
+        fn parallelism(&self) -> Parallelism {
+            self.0.parallelism()
+        }
+        fn tiling(&self) -> Option<Tiling> {
+            Some(self.1)
+        }
+        fn transparency_mode(&self) -> TransparencyMode {
+            self.2
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            self.0.rasterizer_config()
+        }
+        fn vertex(&self, vertex: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            self.0.vertex(vertex)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            self.0.fragment(x)
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            self.0.blend(old, new)
+        }
+        fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+            self.0.downsample(samples)
+        }
+    }
+
+    #[test]
+    fn render_tiled() {
+        let render_tiled = |tile_size, parallelism, transparency_mode| {
+            let mut color = Buffer2d::fill([61, 487], 0.0);
+            let mut depth = Buffer2d::fill([61, 487], 0.75);
+            let pipeline = Blend {
+                aa_mode: AaMode::None,
+                parallelism,
+            };
+            Tiled(
+                pipeline,
+                Tiling::DEFAULT.with_tile_size(tile_size),
+                transparency_mode,
+            )
+            .render(overlapping_triangles(), &mut color, &mut depth);
+            (color, depth)
+        };
+
+        let (color, depth) = render(AaMode::None, Parallelism::DEFAULT);
+        // Tiles that don't divide the target evenly, and tiles that span the whole target along either axis
+        for tile_size in [[64, 64], [7, 13], [0, 1000], [1000, 1]] {
+            for max_threads in [1, 3] {
+                let parallelism = Parallelism::DEFAULT.with_max_threads(max_threads);
+                let (c, d) = render_tiled(tile_size, parallelism, TransparencyMode::Blended);
+                assert_eq!(c.raw(), color.raw(), "{:?}", (tile_size, max_threads));
+                assert_eq!(d.raw(), depth.raw(), "{:?}", (tile_size, max_threads));
+            }
+        }
+
+        // Sorted transparency always renders on the current thread, one tile at a time
+        let sorted = TransparencyMode::Sorted { max_layers: 3 };
+        let (color, depth) = render_tiled([usize::MAX; 2], Parallelism::DEFAULT, sorted);
+        for tile_size in [[64, 64], [7, 13]] {
+            let (c, d) = render_tiled(tile_size, Parallelism::DEFAULT, sorted);
+            assert_eq!(c.raw(), color.raw(), "{:?}", tile_size);
+            assert_eq!(d.raw(), depth.raw(), "{:?}", tile_size);
+        }
+    }
+
+    #[test]
+    fn scratch_vertices_reused() {
+        // Binning primitives into tiles (or groups of rows) collects the vertices of the draw call up front
+        let pipeline = Tiled(
+            Blend {
+                aa_mode: AaMode::None,
+                parallelism: Parallelism::DEFAULT,
+            },
+            Tiling::DEFAULT,
+            TransparencyMode::Blended,
+        );
+        let (color, depth) = render(AaMode::None, Parallelism::DEFAULT);
+
+        let mut scratch = Scratch::default();
+        let mut first_capacity = None;
+        for _ in 0..3 {
+            let mut c = Buffer2d::fill([61, 487], 0.0);
+            let mut d = Buffer2d::fill([61, 487], 0.75);
+            pipeline.render_with_scratch(&mut scratch, overlapping_triangles(), &mut c, &mut d);
+            assert_eq!(c.raw(), color.raw());
+            assert_eq!(d.raw(), depth.raw());
+
+            // The vertices are cleared after each draw call, but their memory is kept for the next
+            let vertices = &scratch.bins.vertices;
+            assert!(vertices.is_empty());
+            let capacity = vertices.capacity();
+            assert!(capacity >= overlapping_triangles().len());
+            assert_eq!(*first_capacity.get_or_insert(capacity), capacity);
+        }
+    }
+
+    #[test]
+    fn render_into_scratch() {
+        // Binning primitives into tiles (or groups of rows) collects the vertices of the draw call up front
+        let pipeline = Tiled(
+            Blend {
+                aa_mode: AaMode::None,
+                parallelism: Parallelism::DEFAULT,
+            },
+            Tiling::DEFAULT,
+            TransparencyMode::Blended,
+        );
+        let (color, depth) = render(AaMode::None, Parallelism::DEFAULT);
+
+        for initial_capacity in [0, 1000] {
+            let mut scratch = Vec::with_capacity(initial_capacity);
+            let mut first_capacity = None;
+            for _ in 0..3 {
+                let mut c = Buffer2d::fill([61, 487], 0.0);
+                let mut d = Buffer2d::fill([61, 487], 0.75);
+                pipeline.render_into_scratch(overlapping_triangles(), &mut scratch, &mut c, &mut d);
+                assert_eq!(c.raw(), color.raw());
+                assert_eq!(d.raw(), depth.raw());
+
+                // The vertices are cleared after each draw call, but their memory is kept for the next
+                assert!(scratch.is_empty());
+                let capacity = scratch.capacity();
+                assert!(capacity >= initial_capacity.max(overlapping_triangles().len()));
+                assert_eq!(*first_capacity.get_or_insert(capacity), capacity);
+            }
+        }
+    }
+
+    /// Counts the heap allocations made by each thread, so that rendering can be checked not to allocate.
+    #[cfg(any(feature = "par", not(feature = "micromath")))]
+    mod allocations {
+        use core::{
+            alloc::{GlobalAlloc, Layout},
+            cell::Cell,
+        };
+        use std::alloc::System;
+
+        std::thread_local! {
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Counting;
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+                System.alloc(layout)
+            }
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+            unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+                let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+                System.realloc(ptr, layout, new_size)
+            }
+        }
+
+        #[global_allocator]
+        static COUNTING: Counting = Counting;
+
+        /// The number of allocations made by the current thread while calling `f`.
+        pub fn count(f: impl FnOnce()) -> usize {
+            let before = ALLOCATIONS.with(Cell::get);
+            f();
+            ALLOCATIONS.with(Cell::get) - before
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "par", not(feature = "micromath")))]
+    fn render_with_scratch() {
+        // Triangles that cross the near plane get clipped, which allocates, so they're left out
+        let triangles = overlapping_triangles()
+            .chunks_exact(3)
+            .filter(|tri| tri.iter().all(|(pos, _)| pos[3] > 0.0))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 4,
+                centroid: false,
+            },
+            AaMode::Ssaa { factor: 2 },
+        ] {
+            // Multiple groups are rendered, but only on this thread, which is the one whose allocations are counted
+            let pipeline = Blend {
+                aa_mode,
+                parallelism: Parallelism::DEFAULT
+                    .with_fragments_per_group(500)
+                    .with_max_threads(1),
+            };
+            let mut color = Buffer2d::fill([61, 487], 0.0);
+            let mut depth = Buffer2d::fill([61, 487], 0.75);
+            pipeline.render(&triangles, &mut color, &mut depth);
+
+            let mut scratch = Scratch::default();
+            let mut scratch_color = Buffer2d::fill([61, 487], 0.0);
+            let mut scratch_depth = Buffer2d::fill([61, 487], 0.75);
+            // The first draw call warms up the scratch memory
+            pipeline.render_with_scratch(
+                &mut scratch,
+                &triangles,
+                &mut scratch_color,
+                &mut scratch_depth,
+            );
+            assert_eq!(scratch_color.raw(), color.raw(), "{:?}", aa_mode);
+            assert_eq!(scratch_depth.raw(), depth.raw(), "{:?}", aa_mode);
+
+            let allocations = allocations::count(|| {
+                for _ in 0..3 {
+                    pipeline.render_with_scratch(
+                        &mut scratch,
+                        &triangles,
+                        &mut scratch_color,
+                        &mut scratch_depth,
+                    );
+                }
+            });
+            assert_eq!(allocations, 0, "{:?}", aa_mode);
+        }
+    }
+
+    #[test]
+    fn column_major_rasterization() {
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 2,
+                centroid: false,
+            },
+        ] {
+            let (color, depth) = render(aa_mode, Parallelism::DEFAULT);
+
+            let mut column_color = Buffer2d::fill([61, 487], 0.0);
+            let mut column_depth = ColumnMajor::fill([61, 487], 0.75);
+            Blend {
+                aa_mode,
+                parallelism: Parallelism::DEFAULT,
+            }
+            .render(
+                overlapping_triangles(),
+                &mut column_color,
+                &mut column_depth,
+            );
+            // Visiting fragments column by column produces exactly the same results
+            assert_eq!(column_color.raw(), color.raw(), "{:?}", aa_mode);
+            assert_eq!(
+                column_depth.to_row_major().raw(),
+                depth.raw(),
+                "{:?}",
+                aa_mode
+            );
+
+            // Without a preference from the depth target, the layout of the pixel target is used instead
+            let mut column_color = ColumnMajor::fill([61, 487], 0.0);
+            let mut depth = Buffer2d::fill([61, 487], 0.75);
+            Blend {
+                aa_mode,
+                parallelism: Parallelism::DEFAULT,
+            }
+            .render(overlapping_triangles(), &mut column_color, &mut depth);
+            assert_eq!(
+                column_color.to_row_major().raw(),
+                color.raw(),
+                "{:?}",
+                aa_mode
+            );
+        }
+    }
+
+    #[test]
+    fn rotated_triangle_iteration_order() {
+        // Both tiny triangles (for which every candidate fragment is tested) and large ones (for which the range of
+        // each row or column is found from the edges) are rotated through a full turn
+        for scale in [0.05, 0.9] {
+            for i in 0..48 {
+                let angle = i as f32 * core::f32::consts::TAU / 48.0;
+                let triangle = [0.0, 2.1, 4.4].map(|a: f32| {
+                    let (s, c) = (angle + a).sin_cos();
+                    [c * scale + 0.03, s * scale * 0.8 - 0.02]
+                });
+
+                let mut rows = Buffer2d::fill([97, 61], 0.0);
+                let mut columns = ColumnMajor::fill([97, 61], 0.0);
+                Overdraw.render(triangle, &mut rows, &mut Empty::depth());
+                Overdraw.render(triangle, &mut columns, &mut Empty::depth());
+
+                assert!(rows.raw().contains(&1.0), "{}, {}", scale, angle);
+                assert!(rows.raw().iter().all(|n| *n <= 1.0), "{}, {}", scale, angle);
+                assert_eq!(
+                    columns.to_row_major().raw(),
+                    rows.raw(),
+                    "{}, {}",
+                    scale,
+                    angle
+                );
+            }
+        }
+    }
+
+    struct Coverage {
+        aa_mode: AaMode,
+        filter: bool,
+    }
+
+    impl<'r> Pipeline<'r> for Coverage {
+        type Vertex = [f32; 4];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn aa_mode(&self) -> AaMode {
+            self.aa_mode
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+        fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+            if self.filter {
+                f32::mean(samples)
+            } else {
+                samples[0]
+            }
+        }
+    }
+
+    /// Render a triangle that covers the lower-left half of a small target.
+    fn render_coverage(aa_mode: AaMode, filter: bool) -> (Buffer2d<f32>, Buffer2d<f32>) {
+        let mut color = Buffer2d::fill([8, 8], 0.0);
+        let mut depth = Buffer2d::fill([8, 8], 1.0);
+        Coverage { aa_mode, filter }.render(
+            [
+                [-1.0, -1.0, 0.5, 1.0],
+                [1.0, 1.0, 0.5, 1.0],
+                [-1.0, 1.0, 0.5, 1.0],
+            ],
+            &mut color,
+            &mut depth,
+        );
+        (color, depth)
+    }
+
+    #[test]
+    fn ssaa_coverage() {
+        let (aliased, _) = render_coverage(AaMode::None, true);
+        assert!(aliased.raw().iter().all(|&c| c == 0.0 || c == 1.0));
+
+        let (color, depth) = render_coverage(AaMode::Ssaa { factor: 4 }, true);
+        // Coverage is measured in sixteenths of a pixel
+        assert!(color
+            .raw()
+            .iter()
+            .all(|&c| (c * 16.0 - (c * 16.0).round()).abs() < 1.0e-4));
+        assert!(color.raw().iter().any(|&c| c > 0.0 && c < 1.0));
+        // Half of the target is covered, plus the samples that lie exactly on the diagonal edge
+        let total = color.raw().iter().sum::<f32>();
+        assert!((total - 32.0).abs() <= 1.5, "{}", total);
+        // Pixels that are partially covered still receive the depth of the triangle
+        for (c, z) in color.raw().iter().zip(depth.raw()) {
+            assert_eq!(*z, if *c > 0.0 { 0.5 } else { 1.0 });
+        }
+
+        // The factor is clamped
+        assert_eq!(
+            render_coverage(AaMode::Ssaa { factor: 100 }, true).0.raw(),
+            color.raw()
+        );
+        assert_eq!(
+            render_coverage(AaMode::Ssaa { factor: 1 }, true).0.raw(),
+            aliased.raw()
+        );
+
+        // Without a filter, only a single sample is used
+        let (unfiltered, _) = render_coverage(AaMode::Ssaa { factor: 4 }, false);
+        assert!(unfiltered.raw().iter().all(|&c| c == 0.0 || c == 1.0));
+    }
+
+    #[test]
+    fn msaa_coverage() {
+        for level in [2, 4, 8] {
+            let aa_mode = AaMode::Msaa {
+                level,
+                centroid: false,
+            };
+
+            // A near-vertical edge that crosses column 8 of the target without leaving it
+            let mut color = Buffer2d::fill([16, 16], 0.0);
+            let mut depth = Buffer2d::fill([16, 16], 1.0);
+            Coverage {
+                aa_mode,
+                filter: true,
+            }
+            .render(
+                [
+                    [-9.0, -3.0, 0.5, 1.0],
+                    [0.05, -3.0, 0.5, 1.0],
+                    [-0.05, 3.0, 0.5, 1.0],
+                ],
+                &mut color,
+                &mut depth,
+            );
+            for y in 0..16 {
+                let c = color.read([8, y]);
+                assert!(c > 0.0 && c < 1.0, "{:?}: {} at row {}", aa_mode, c, y);
+                // Coverage is measured in whole samples
+                assert_eq!(c * level as f32, (c * level as f32).round());
+                // Pixels on either side of the edge are untouched by anti-aliasing
+                assert!((0..8).all(|x| color.read([x, y]) == 1.0));
+                assert!((9..16).all(|x| color.read([x, y]) == 0.0));
+                // Partially covered pixels still receive the depth of the triangle
+                assert!((0..9).all(|x| (depth.read([x, y]) - 0.5).abs() < 1.0e-6));
+            }
+
+            // Fragments are evaluated once per pixel, so the interiors of primitives are identical to an aliased render
+            let render = |aa_mode| {
+                let mut color = Buffer2d::fill([17, 13], f32::NAN);
+                Gradient {
+                    aa_mode,
+                    parallelism: Parallelism::DEFAULT,
+                }
+                .render(
+                    [[-2.0, -2.0], [5.0, -2.0], [-2.0, 5.0]],
+                    &mut color,
+                    &mut Empty::depth(),
+                );
+                color
+            };
+            assert_eq!(render(aa_mode).raw(), render(AaMode::None).raw());
+        }
+    }
+
+    #[test]
+    fn msaa_centroid() {
+        /// Writes the vertex data of each fragment, keeping the sample furthest from `0.5` when downsampling.
+        struct Extremes {
+            aa_mode: AaMode,
+        }
+
+        impl<'r> Pipeline<'r> for Extremes {
+            type Vertex = ([f32; 2], f32);
+            type VertexData = f32;
+            type Primitives = TriangleList;
+            type Fragment = f32;
+            type Pixel = f32;
+
+            fn aa_mode(&self) -> AaMode {
+                self.aa_mode
+            }
+            fn rasterizer_config(&self) -> CullMode {
+                CullMode::None
+            }
+            fn vertex(&self, ([x, y], a): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                ([*x, *y, 0.5, 1.0], *a)
+            }
+            fn fragment(&self, a: Self::VertexData) -> Self::Fragment {
+                a
+            }
+            fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+                new
+            }
+            fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+                samples
+                    .iter()
+                    .copied()
+                    .filter(|s| !s.is_nan())
+                    .fold(f32::NAN, |a, b| {
+                        if a.is_nan() || (b - 0.5).abs() > (a - 0.5).abs() {
+                            b
+                        } else {
+                            a
+                        }
+                    })
+            }
+        }
+
+        // A sliver, across which the vertex data changes quickly
+        let sliver = [([-0.9, -0.8], 0.0), ([0.9, -0.7], 0.0), ([0.8, -0.55], 1.0)];
+        for level in [2, 4, 8] {
+            let aa_mode = AaMode::Msaa {
+                level,
+                centroid: true,
+            };
+            let mut color = Buffer2d::fill([32, 32], f32::NAN);
+            Extremes { aa_mode }.render(sliver, &mut color, &mut Empty::depth());
+
+            // Partially covered fragments are evaluated within the primitive, so the vertex data is never extrapolated
+            let written = color.raw().iter().filter(|a| !a.is_nan());
+            assert!(written.clone().count() > 20, "{:?}", aa_mode);
+            for a in written {
+                assert!((-1.0e-5..=1.0 + 1.0e-5).contains(a), "{:?}: {}", aa_mode, a);
+            }
+
+            // Fully covered fragments are still evaluated at the centre of their pixel
+            let big = [([-5.0, -3.0], 0.0), ([5.0, -3.0], 0.0), ([0.0, 5.0], 1.0)];
+            let render = |aa_mode| {
+                let mut color = Buffer2d::fill([32, 32], f32::NAN);
+                Extremes { aa_mode }.render(big, &mut color, &mut Empty::depth());
+                color
+            };
+            assert_eq!(render(aa_mode).raw(), render(AaMode::None).raw());
+        }
+
+        // Without centroid sampling, partially covered fragments are evaluated at the centre of their pixel
+        let aa_mode = AaMode::Msaa {
+            level: 4,
+            centroid: false,
+        };
+        let mut color = Buffer2d::fill([32, 32], f32::NAN);
+        Extremes { aa_mode }.render(sliver, &mut color, &mut Empty::depth());
+        assert!(color
+            .raw()
+            .iter()
+            .any(|a| !(0.0..=1.0).contains(a) && !a.is_nan()));
+    }
+
+    #[test]
+    fn msaa_atlas_cell() {
+        use crate::sampler::{Atlas, Nearest, Sampler};
+
+        /// Draws a sprite from the left half of an atlas, whose texels are `1.0`, beside a cell of `0.0` texels.
+        struct Sprite {
+            sampler: Atlas<Nearest<Buffer2d<f32>>, 2>,
+            level: u32,
+            centroid: bool,
+        }
+
+        impl<'r> Pipeline<'r> for Sprite {
+            type Vertex = ([f32; 2], f32);
+            type VertexData = f32;
+            type Primitives = TriangleList;
+            type Fragment = f32;
+            type Pixel = f32;
+
+            fn aa_mode(&self) -> AaMode {
+                AaMode::Msaa {
+                    level: self.level,
+                    centroid: self.centroid,
+                }
+            }
+            fn rasterizer_config(&self) -> CullMode {
+                CullMode::None
+            }
+            fn vertex(&self, ([x, y], u): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                ([*x, *y, 0.5, 1.0], *u)
+            }
+            fn fragment(&self, u: Self::VertexData) -> Self::Fragment {
+                self.sampler.sample([u, 0.5])
+            }
+            fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+                new
+            }
+            fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+                // Keep any sample that bled in from the neighbouring cell
+                samples.iter().copied().fold(f32::NAN, f32::min)
+            }
+        }
+
+        let atlas = || {
+            let mut atlas = Buffer2d::fill([4, 1], 0.0);
+            atlas.write(0, 0, 1.0);
+            atlas.write(1, 0, 1.0);
+            atlas.nearest().atlas([0.0, 0.0], [0.5, 1.0])
+        };
+        // The sprite's texture coordinates reach almost to the edge of its cell, across the thin side of a sliver
+        let sliver = [
+            ([-0.9, -0.8], 0.0),
+            ([0.9, -0.7], 0.0),
+            ([0.8, -0.55], 0.99),
+        ];
+        let render = |level, centroid| {
+            let sprite = Sprite {
+                sampler: atlas(),
+                level,
+                centroid,
+            };
+            let mut color = Buffer2d::fill([32, 32], f32::NAN);
+            sprite.render(sliver, &mut color, &mut Empty::depth());
+            color
+        };
+        for level in [2, 4, 8] {
+            let color = render(level, true);
+            let written = color.raw().iter().filter(|c| !c.is_nan());
+            assert!(written.clone().count() > 20, "{}", level);
+            assert!(written.clone().all(|c| *c == 1.0), "{}", level);
+        }
+    }
+
+    #[test]
+    fn row_groups_cover_target() {
+        for w in [0, 1, 7, 64, 1000, 4096] {
+            for h in [0, 1, 2, 13, 480, 2161] {
+                for fragments_per_group in [0, 1, 100, 20_000, usize::MAX] {
+                    let parallelism =
+                        Parallelism::DEFAULT.with_fragments_per_group(fragments_per_group);
+                    let group_rows = parallelism.group_rows([w, h]);
+                    assert!(group_rows >= 1);
+
+                    let mut next = 0;
+                    for (i, rows) in parallelism.row_groups([w, h]).enumerate() {
+                        assert_eq!(rows.start, next, "groups must be contiguous");
+                        assert_eq!(rows.start, i * group_rows);
+                        assert!(rows.start < rows.end && rows.end <= h);
+                        next = rows.end;
+                    }
+                    assert_eq!(next, h, "groups must cover every row");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn groups_match_single_group() {
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 2,
+                centroid: false,
+            },
+            AaMode::Msaa {
+                level: 8,
+                centroid: false,
+            },
+        ] {
+            let single = render(
+                aa_mode,
+                Parallelism::DEFAULT.with_fragments_per_group(usize::MAX),
+            );
+            assert!(single.0.raw().iter().any(|px| *px != 0.0));
+            for fragments_per_group in [1, 61 * 3, 1000, 20_000] {
+                for max_threads in [1, 2, 7] {
+                    let grouped = render(
+                        aa_mode,
+                        Parallelism::DEFAULT
+                            .with_fragments_per_group(fragments_per_group)
+                            .with_max_threads(max_threads),
+                    );
+                    // Compare bits, since depth must match exactly
+                    let bits = |(color, depth): &(Buffer2d<f32>, Buffer2d<f32>)| {
+                        color
+                            .raw()
+                            .iter()
+                            .chain(depth.raw())
+                            .map(|e| e.to_bits())
+                            .collect::<Vec<_>>()
+                    };
+                    assert!(
+                        bits(&single) == bits(&grouped),
+                        "{:?} with {} fragments per group on {} threads differs from a single group",
+                        aa_mode,
+                        fragments_per_group,
+                        max_threads,
+                    );
+                }
+            }
+        }
+    }
+
+    struct Gradient {
+        aa_mode: AaMode,
+        parallelism: Parallelism,
+    }
+
+    impl<'r> Pipeline<'r> for Gradient {
+        type Vertex = [f32; 2];
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn aa_mode(&self) -> AaMode {
+            self.aa_mode
+        }
+        fn parallelism(&self) -> Parallelism {
+            self.parallelism
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([pos[0], pos[1], 0.5, 1.0], pos[0] * 3.0 + pos[1])
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            x
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn msaa_odd_sizes() {
+        // A triangle that covers the entire target, with no edges passing through any pixel
+        let tri = [[-2.0, -2.0], [5.0, -2.0], [-2.0, 5.0]];
+        for (size, levels) in [
+            ([1, 1], 1..=3),
+            ([3, 5], 1..=3),
+            ([17, 61], 1..=4),
+            ([129, 97], 1..=4),
+            ([1280, 961], 2..=2),
+        ] {
+            for level in levels {
+                let aa_mode = AaMode::Msaa {
+                    level,
+                    centroid: false,
+                };
+                let render = |parallelism| {
+                    // Pixels that are never written remain NaN
+                    let mut color = Buffer2d::fill(size, f32::NAN);
+                    Gradient {
+                        aa_mode,
+                        parallelism,
+                    }
+                    .render(tri, &mut color, &mut Empty::depth());
+                    color
+                };
+
+                let single = render(Parallelism::DEFAULT.with_fragments_per_group(usize::MAX));
+                assert!(
+                    single.raw().iter().all(|px| px.is_finite()),
+                    "{:?} at {:?} left pixels unwritten",
+                    aa_mode,
+                    size,
+                );
+                for fragments_per_group in [1, size[0] * 3, 1000] {
+                    let grouped = render(
+                        Parallelism::DEFAULT
+                            .with_fragments_per_group(fragments_per_group)
+                            .with_max_threads(3),
+                    );
+                    assert!(
+                        single.raw() == grouped.raw(),
+                        "{:?} at {:?} with {} fragments per group differs from a single group",
+                        aa_mode,
+                        size,
+                        fragments_per_group,
+                    );
+                }
+            }
+        }
+    }
+
+    struct Layers {
+        coordinate_mode: CoordinateMode,
+        depth_mode: DepthMode,
+    }
+
+    impl<'r> Pipeline<'r> for Layers {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn coordinate_mode(&self) -> CoordinateMode {
+            self.coordinate_mode.clone()
+        }
+        fn depth_mode(&self) -> DepthMode {
+            self.depth_mode
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, (pos, color): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, *color)
+        }
+        fn fragment(&self, color: Self::VertexData) -> Self::Fragment {
+            color
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    /// Render full-screen triangles at each of the given depths, in order, with the depth as their colour.
+    fn render_layers(pipeline: Layers, clear_depth: f32, depths: &[f32]) -> (f32, f32) {
+        let mut color = Buffer2d::fill([4, 4], f32::NAN);
+        let mut depth = Buffer2d::fill([4, 4], clear_depth);
+        let tris = depths
+            .iter()
+            .flat_map(|&z| {
+                [[-2.0, -2.0], [5.0, -2.0], [-2.0, 5.0]].map(|[x, y]| ([x, y, z, 1.0], z))
+            })
+            .collect::<Vec<_>>();
+        pipeline.render(tris, &mut color, &mut depth);
+        (color.read([2, 2]), depth.read([2, 2]))
+    }
+
+    #[test]
+    fn reversed_z() {
+        let reversed = || Layers {
+            coordinate_mode: CoordinateMode::VULKAN.with_depth_range(1.0..0.0),
+            depth_mode: DepthMode::GREATER_WRITE,
+        };
+        // Depths are exactly representable, so that they are unchanged by interpolation and mapping
+        // The nearest layer is visible regardless of the order in which layers are drawn, and its depth is reversed
+        assert_eq!(render_layers(reversed(), 0.0, &[0.25, 0.75]), (0.25, 0.75));
+        assert_eq!(render_layers(reversed(), 0.0, &[0.75, 0.25]), (0.25, 0.75));
+        // Clipping happens before mapping, so layers outside of the clip range are never drawn
+        assert!(render_layers(reversed(), 0.0, &[-0.5, 1.5]).0.is_nan());
+        assert_eq!(
+            render_layers(reversed(), 0.0, &[-0.5, 0.625, 1.5]),
+            (0.625, 0.375)
+        );
+
+        // The clip range is mapped onto the depth range
+        let opengl = Layers {
+            coordinate_mode: CoordinateMode::OPENGL.with_depth_range(0.0..1.0),
+            depth_mode: DepthMode::LESS_WRITE,
+        };
+        assert_eq!(render_layers(opengl, 1.0, &[0.5, -0.5]), (-0.5, 0.25));
+
+        // Without a depth range, depths are written unmodified
+        let unmapped = Layers {
+            coordinate_mode: CoordinateMode::VULKAN,
+            depth_mode: DepthMode::LESS_WRITE,
+        };
+        assert_eq!(
+            render_layers(unmapped, 1.0, &[0.75, 0.25, 0.625]),
+            (0.25, 0.25)
+        );
+    }
+
+    /// Counts the number of fragments that are blended into each pixel.
+    struct Overdraw;
+
+    impl<'r> Pipeline<'r> for Overdraw {
+        type Vertex = [f32; 2];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, 0.5, 1.0], Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            old + new
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Depth target is empty, but the pipeline's `DepthMode` uses it")]
+    fn empty_depth_target() {
+        Coverage {
+            aa_mode: AaMode::None,
+            filter: false,
+        }
+        .render(
+            [
+                [-1.0, -1.0, 0.5, 1.0],
+                [1.0, -1.0, 0.5, 1.0],
+                [-1.0, 1.0, 0.5, 1.0],
+            ],
+            &mut Buffer2d::fill([8, 8], 0.0),
+            &mut Empty::depth(),
+        );
+    }
+
+    #[test]
+    fn render_into_sub_view() {
+        let tri = [[-0.8, -0.9], [0.9, -0.5], [-0.2, 0.8]];
+        let mut expected = Buffer2d::fill([100, 100], 0.0);
+        Overdraw.render(tri, &mut expected, &mut Empty::depth());
+
+        let mut color = Buffer2d::fill([400, 400], 0.0);
+        let mut depth = Buffer2d::fill([400, 400], 1.0);
+        let origin = [150, 220];
+        Overdraw.render(
+            tri,
+            &mut color.sub_view_mut(origin, [100, 100]),
+            &mut depth.sub_view_mut(origin, [100, 100]),
+        );
+        for y in 0..400usize {
+            for x in 0..400usize {
+                let [vx, vy] = [x.wrapping_sub(origin[0]), y.wrapping_sub(origin[1])];
+                let inside = vx < 100 && vy < 100;
+                let c = if inside { expected.read([vx, vy]) } else { 0.0 };
+                assert_eq!(color.read([x, y]), c, "at {:?}", [x, y]);
+                if !inside {
+                    assert_eq!(depth.read([x, y]), 1.0, "at {:?}", [x, y]);
+                }
+            }
+        }
+        assert!(expected.raw().iter().sum::<f32>() > 1000.0);
+    }
+
+    #[test]
+    fn guard_band_clipping() {
+        // A quad covering the target with one corner far beyond its edges, such that its diagonal crosses the target
+        let [a, b, c, d] = [[3.0, 2.5], [-1.5, 2.5], [-3.5, -2.5], [5.0e4, -1.0e5]];
+        let mut color = Buffer2d::fill([64, 64], 0.0);
+        Overdraw.render(&[a, b, c, a, c, d], &mut color, &mut Empty::depth());
+        for y in 0..64 {
+            for x in 0..64 {
+                assert_eq!(color.read([x, y]), 1.0, "at {:?}", [x, y]);
+            }
+        }
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        // Vertices far enough beyond the target that their screen coordinates can't be represented as integers
+        let triangle = [[-1.5, -1.5], [1.0e9, -1.5], [-1.5, 1.0e9]];
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 4,
+                centroid: false,
+            },
+        ] {
+            for parallelism in [
+                Parallelism::DEFAULT,
+                Parallelism::DEFAULT.with_max_threads(1),
+            ] {
+                let mut color = Buffer2d::fill([64, 64], 0.0);
+                let mut depth = Buffer2d::fill([64, 64], 1.0);
+                Blend {
+                    aa_mode,
+                    parallelism,
+                }
+                .render(
+                    triangle.map(|[x, y]| ([x, y, 0.5, 1.0], 1.0)),
+                    &mut color,
+                    &mut depth,
+                );
+                assert!(
+                    color.raw().iter().all(|c| (c - 1.0).abs() < 1.0e-5),
+                    "{:?}",
+                    aa_mode
+                );
+            }
+        }
+
+        // Lines are clipped to the target before being drawn
+        let line = [[-1.5, 0.34375], [1.0e9, 0.34375]];
+        let mut color = Buffer2d::fill([32, 32], 0.0);
+        Line(LineMode::Aliased).render(line, &mut color, &mut Empty::depth());
+        for x in 0..32 {
+            assert_eq!(
+                (0..32).map(|y| color.read([x, y])).sum::<f32>(),
+                1.0,
+                "column {}",
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn z_clip_whole_triangle() {
+        let coverage = Coverage {
+            aa_mode: AaMode::None,
+            filter: false,
+        };
+        let render = |z: [f32; 3], w: f32| {
+            let mut color = Buffer2d::fill([8, 8], 0.0);
+            coverage.render(
+                z.map(|z| [-1.0, -1.0, z, 1.0])
+                    .iter()
+                    .zip([[0.0, 0.0], [2.0, 2.0], [0.0, 2.0]])
+                    .map(|(v, [dx, dy])| [(v[0] + dx) * w, (v[1] + dy) * w, v[2] * w, w])
+                    .collect::<Vec<_>>(),
+                &mut color,
+                // Every fragment passes the depth test, so only the z clip test may reject them
+                &mut Buffer2d::fill([8, 8], f32::INFINITY),
+            );
+            color.raw().iter().filter(|c| **c == 1.0).count()
+        };
+        // Triangles entirely beyond the far plane, or in front of the near plane, produce no fragments
+        assert_eq!(render([1.2, 1.5, 3.0], 1.0), 0);
+        assert_eq!(render([1.2, 1.5, 3.0], 4.0), 0);
+        assert_eq!(render([-0.5, -0.1, -2.0], 1.0), 0);
+        // ...while those that straddle a plane still produce the fragments within it
+        assert_eq!(render([0.5, 0.5, 0.5], 1.0), 36);
+        let straddling = render([0.5, 1.5, 1.5], 1.0);
+        assert!(straddling > 0 && straddling < 36);
+        let straddling = render([-0.5, 0.5, -0.5], 2.0);
+        assert!(straddling > 0 && straddling < 36);
+    }
+
+    #[test]
+    fn render_bounds() {
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Msaa {
+                level: 4,
+                centroid: false,
+            },
+            AaMode::Ssaa { factor: 2 },
+        ] {
+            for max_threads in [1, 4] {
+                let pipeline = Blend {
+                    aa_mode,
+                    parallelism: Parallelism::DEFAULT.with_max_threads(max_threads),
+                };
+
+                // Only a corner of the target is drawn to
+                let mut color = Buffer2d::fill([61, 487], 0.0);
+                let mut depth = Buffer2d::fill([61, 487], 0.75);
+                let triangles = || {
+                    overlapping_triangles()
+                        .into_iter()
+                        .map(|([x, y, z, _], c)| ([x * 0.2 - 0.5, y * 0.2 + 0.5, z, 1.0], c))
+                };
+                let [x0, y0, x1, y1] = pipeline
+                    .render_bounded(triangles(), &mut color, &mut depth)
+                    .unwrap();
+                assert!(x0 < x1 && x1 <= 61 && y0 < y1 && y1 <= 487);
+                assert!(x1 - x0 < 61 && y1 - y0 < 487);
+
+                // Every pixel that was written to lies within the bounds
+                let mut written = None;
+                for y in 0..487 {
+                    for x in 0..61 {
+                        if depth.read([x, y]) != 0.75 {
+                            assert!((x0..x1).contains(&x) && (y0..y1).contains(&y));
+                            written = union_bounds(written, Some([x, y, x + 1, y + 1]));
+                        }
+                    }
+                }
+                if aa_mode == AaMode::None {
+                    assert_eq!(written, Some([x0, y0, x1, y1]));
+                }
+                // Rendering is otherwise unaffected
+                let mut unbounded = Buffer2d::fill([61, 487], 0.0);
+                pipeline.render(
+                    triangles(),
+                    &mut unbounded,
+                    &mut Buffer2d::fill([61, 487], 0.75),
+                );
+                assert_eq!(unbounded.raw(), color.raw());
+
+                // Nothing is drawn
+                assert_eq!(
+                    pipeline.render_bounded(Vec::<([f32; 4], f32)>::new(), &mut color, &mut depth),
+                    None
+                );
+            }
+        }
+    }
+
+    /// Counts the fragments of triangles that pass the depth test, without writing to any target.
+    struct Occlusion {
+        depth_mode: DepthMode,
+    }
+
+    impl<'r> Pipeline<'r> for Occlusion {
+        type Vertex = [f32; 4];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn pixel_mode(&self) -> PixelMode {
+            PixelMode::PASS
+        }
+        fn depth_mode(&self) -> DepthMode {
+            self.depth_mode
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            unreachable!("fragments are only counted")
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn render_counted() {
+        // Half of a 16x16 target, plus the pixels whose centres lie exactly on the diagonal edge
+        let triangle = |z| {
+            [
+                [-1.0, -1.0, z, 1.0],
+                [1.0, 1.0, z, 1.0],
+                [-1.0, 1.0, z, 1.0],
+            ]
+        };
+        let mut color = Buffer2d::fill([16, 16], 0.0);
+        let count = Occlusion {
+            depth_mode: DepthMode::NONE,
+        }
+        .render_counted(triangle(0.5), &mut color, &mut Empty::depth());
+        assert!((128..=144).contains(&count), "{}", count);
+        assert!(color.raw().iter().all(|c| *c == 0.0));
+
+        // Fragments that fail the depth test are not counted
+        let mut depth = Buffer2d::fill([16, 16], 1.0);
+        Coverage {
+            aa_mode: AaMode::None,
+            filter: false,
+        }
+        .render(triangle(0.5), &mut color, &mut depth);
+        let occlusion = Occlusion {
+            depth_mode: DepthMode::LESS_PASS,
+        };
+        let mut empty = Empty::default();
+        assert_eq!(
+            occlusion.render_counted(triangle(0.75), &mut empty, &mut depth),
+            0
+        );
+        assert_eq!(
+            occlusion.render_counted(triangle(0.25), &mut empty, &mut depth),
+            count
+        );
+        assert_eq!(
+            occlusion.render_counted(Vec::<[f32; 4]>::new(), &mut empty, &mut depth),
+            0
+        );
+    }
+
+    #[test]
+    fn render_depth() {
+        // Two triangles that intersect each other, so that each is in front of the other over part of the target
+        let triangles = [
+            [-1.0, -1.0, 0.2, 1.0],
+            [1.0, -1.0, 0.8, 1.0],
+            [0.0, 1.0, 0.5, 1.0],
+            [-1.0, 1.0, 0.5, 1.0],
+            [1.0, 1.0, 0.5, 1.0],
+            [0.0, -1.0, 0.5, 1.0],
+        ];
+        let occlusion = Occlusion {
+            depth_mode: DepthMode::LESS_WRITE,
+        };
+
+        let mut expected = Buffer2d::fill([23, 17], 1.0);
+        occlusion.render(triangles, &mut Empty::depth(), &mut expected);
+        let mut depth = Buffer2d::fill([23, 17], 1.0);
+        occlusion.render_depth(triangles, &mut depth);
+        assert!(depth.raw().iter().any(|z| *z < 0.5));
+        assert_eq!(depth.raw(), expected.raw());
+
+        // Integer depth targets work too
+        let mut depth = Buffer2d::fill([23, 17], u16::MAX);
+        occlusion.render_depth(triangles, &mut depth);
+        assert!(depth.raw().iter().any(|z| *z < u16::MAX / 2));
+    }
+
+    /// Draws triangles in a flat colour, at a depth equal to their colour regardless of the depth of their vertices.
+    struct ForcedDepth {
+        writes_depth: bool,
+    }
+
+    impl<'r> Pipeline<'r> for ForcedDepth {
+        type Vertex = ([f32; 3], f32);
+        type VertexData = Flat<f32>;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, ([x, y, z], color): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, *z, 1.0], Flat(*color))
+        }
+        fn writes_fragment_depth(&self) -> bool {
+            self.writes_depth
+        }
+        fn fragment(&self, Flat(color): Self::VertexData) -> Self::Fragment {
+            color
+        }
+        fn fragment_with_depth(
+            &self,
+            Flat(color): Self::VertexData,
+        ) -> (Self::Fragment, Option<f32>) {
+            (color, Some(color))
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn fragment_depth() {
+        // Triangles covering the whole target, with vertices at depth `z`
+        let triangle =
+            |z, color| [[-1.0, -1.0, z], [3.0, -1.0, z], [-1.0, 3.0, z]].map(|pos| (pos, color));
+        // The far triangle is nearer when its forced depth is used
+        let far = triangle(0.9, 0.1);
+        let near = triangle(0.1, 0.9);
+
+        for (first, second) in [(far, near), (near, far)] {
+            for writes_depth in [false, true] {
+                let mut color = Buffer2d::fill([8, 8], 0.0);
+                let mut depth = Buffer2d::fill([8, 8], 1.0);
+                let pipeline = ForcedDepth { writes_depth };
+                pipeline.render(first, &mut color, &mut depth);
+                pipeline.render(second, &mut color, &mut depth);
+
+                let (expected_color, expected_depth) =
+                    if writes_depth { (0.1, 0.1) } else { (0.9, 0.1) };
+                assert!(
+                    color.raw().iter().all(|c| *c == expected_color),
+                    "{}",
+                    writes_depth
+                );
+                assert!(
+                    depth
+                        .raw()
+                        .iter()
+                        .all(|z| (z - expected_depth).abs() < 1.0e-6),
+                    "{}",
+                    writes_depth,
+                );
+
+                // Depth-only rendering uses the forced depth too
+                let mut shadow = Buffer2d::fill([8, 8], 1.0);
+                pipeline.render_depth(first, &mut shadow);
+                pipeline.render_depth(second, &mut shadow);
+                assert_eq!(shadow.raw(), depth.raw());
+
+                // So does rendering primitive IDs, so the ID is that of the primitive with the nearest forced depth
+                let mut ids = Buffer2d::fill([8, 8], u32::MAX);
+                let mut depth = Buffer2d::fill([8, 8], 1.0);
+                pipeline.render_ids([first, second].concat(), &mut ids, &mut depth);
+                let expected_id = if (first == far) == writes_depth { 0 } else { 1 };
+                assert!(
+                    ids.raw().iter().all(|id| *id == expected_id),
+                    "{}",
+                    writes_depth
+                );
+                assert_eq!(shadow.raw(), depth.raw());
+            }
+        }
+    }
+
+    /// Draws triangles in a flat colour taken from their provoking vertex.
+    struct FlatColors;
+
+    impl<'r> Pipeline<'r> for FlatColors {
+        type Vertex = ([f32; 3], f32);
+        type VertexData = Flat<f32>;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, ([x, y, w], color): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([x * w, y * w, 0.5 * w, *w], Flat(*color))
+        }
+        fn fragment(&self, Flat(color): Self::VertexData) -> Self::Fragment {
+            color
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn flat_provoking_vertex() {
+        // A quad covering the target, made of two triangles that face opposite ways, with one corner far beyond the
+        // edges of the target and vertices at different depths (so that they get reordered in every way)
+        let [a, b, c, d] = [
+            [3.0, 2.5, 2.0],
+            [-1.5, 2.5, 1.0],
+            [-3.5, -2.5, 0.5],
+            [5.0e4, -1.0e5, 1.5],
+        ];
+        let tris = [
+            [(a, 1.0), (b, 10.0), (c, 100.0)],
+            [(d, 2.0), (c, 20.0), (a, 200.0)],
+            [(b, 3.0), (c, 30.0), (a, 300.0)],
+            [(c, 4.0), (a, 40.0), (d, 400.0)],
+        ];
+        for quad in [[tris[0], tris[1]], [tris[2], tris[3]]] {
+            let [first, second] = quad.map(|tri| tri[0].1);
+            let mut color = Buffer2d::fill([64, 64], 0.0);
+            FlatColors.render(quad.concat(), &mut color, &mut Empty::depth());
+            let count = |c| color.raw().iter().filter(|e| **e == c).count();
+            assert_eq!(count(first) + count(second), 64 * 64);
+            assert!(count(first) > 500 && count(second) > 500);
+        }
+    }
+
+    /// Expands each point into a square sprite, made of two triangles, with sides `2 * self.0` long.
+    struct Sprites(f32);
+
+    impl<'r> Pipeline<'r> for Sprites {
+        type Vertex = [f32; 2];
+        type VertexData = Unit;
+        type Primitives = Geometry<PointList, TriangleList>;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, 0.5, 1.0], Unit)
+        }
+        fn geometry<O>(&self, ([x, y, z, w], _): ([f32; 4], Unit), mut output: O)
+        where
+            O: FnMut([([f32; 4], Unit); 3]),
+        {
+            let corner =
+                |dx: f32, dy: f32| ([x + dx * self.0 * w, y + dy * self.0 * w, z, w], Unit);
+            let [a, b, c, d] = [
+                corner(-1.0, -1.0),
+                corner(1.0, -1.0),
+                corner(1.0, 1.0),
+                corner(-1.0, 1.0),
+            ];
+            output([a, b, c]);
+            output([a, c, d]);
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            old + new
+        }
+    }
+
+    #[test]
+    fn point_sprites() {
+        let points = [[-0.5, -0.5], [0.5, 0.25], [0.0, 0.75]];
+        let mut color = Buffer2d::fill([32, 32], 0.0);
+        Sprites(0.25).render(points, &mut color, &mut Empty::depth());
+        // Each point becomes an 8x8 square, which the triangles it's made of cover exactly once
+        assert!(color.raw().iter().all(|c| *c == 0.0 || *c == 1.0));
+        assert_eq!(color.raw().iter().sum::<f32>(), 3.0 * 64.0);
+    }
+
+    /// Draws quads, either as a [`QuadList`] or as pairs of triangles.
+    struct Quad<P>(PhantomData<P>);
+
+    impl<'r, P: PrimitiveKind<f32>> Pipeline<'r> for Quad<P> {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = f32;
+        type Primitives = P;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn vertex(&self, (pos, x): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, *x)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            x
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn quad_list() {
+        // A quad that recedes into the distance, so that it's interpolated with perspective
+        let v = |x: f32, y: f32, w: f32| [x * w, y * w, 0.5, w];
+        let [a, b, c, d] = [
+            (v(-0.8, -0.7, 1.0), 0.0),
+            (v(0.8, -0.7, 1.0), 0.25),
+            (v(0.3, 0.7, 4.0), 1.0),
+            (v(-0.3, 0.7, 4.0), 0.5),
+        ];
+
+        let mut quads = Buffer2d::fill([64, 64], -1.0);
+        Quad::<QuadList>(PhantomData).render([a, b, c, d], &mut quads, &mut Empty::depth());
+        let mut triangles = Buffer2d::fill([64, 64], -1.0);
+        Quad::<TriangleList>(PhantomData).render(
+            [a, b, c, a, c, d],
+            &mut triangles,
+            &mut Empty::depth(),
+        );
+        assert!(quads.raw().iter().filter(|x| **x >= 0.0).count() > 1000);
+        assert_eq!(quads.raw(), triangles.raw());
+
+        // Both of the quad's triangles belong to the same primitive
+        let mut ids = Buffer2d::fill([64, 64], u32::MAX);
+        Quad::<QuadList>(PhantomData).render_ids([a, b, c, d], &mut ids, &mut Empty::depth());
+        assert!(ids.raw().iter().all(|id| *id == 0 || *id == u32::MAX));
+        assert_eq!(
+            ids.raw().iter().filter(|id| **id == 0).count(),
+            quads.raw().iter().filter(|x| **x >= 0.0).count(),
+        );
+
+        // The quad is bounded as a whole, covering the bounds of both of its triangles
+        let coords = CoordinateMode::default();
+        let (min0, max0) = Triangles
+            .screen_bounds(&[a.0, b.0, c.0], &coords, [64, 64])
+            .unwrap();
+        let (min1, max1) = Triangles
+            .screen_bounds(&[a.0, c.0, d.0], &coords, [64, 64])
+            .unwrap();
+        assert_eq!(
+            Quads.screen_bounds(&[a.0, b.0, c.0, d.0], &coords, [64, 64]),
+            Some((
+                [min0[0].min(min1[0]), min0[1].min(min1[1])],
+                [max0[0].max(max1[0]), max0[1].max(max1[1])],
+            )),
+        );
+    }
+
+    #[test]
+    fn full_screen_quad() {
+        // A quad that extends beyond every edge of the target by less than a pixel, or by a lot
+        for margin in [0.01, 0.3, 20.0] {
+            let v = |x: f32, y: f32| ([x * (1.0 + margin), y * (1.0 + margin), 0.5, 1.0], 1.0);
+            let quad = [v(-1.0, -1.0), v(1.0, -1.0), v(1.0, 1.0), v(-1.0, 1.0)];
+            for size in [[1, 1], [64, 64], [61, 37], [317, 2]] {
+                let mut color = Buffer2d::fill(size, 0.0);
+                let fragments = Quad::<QuadList>(PhantomData).render_counted(
+                    quad,
+                    &mut color,
+                    &mut Empty::depth(),
+                );
+                // Every pixel of the target is covered exactly once
+                assert!(
+                    color.raw().iter().all(|x| *x > 0.5),
+                    "{:?} {}",
+                    size,
+                    margin
+                );
+                assert_eq!(fragments, size[0] * size[1], "{:?} {}", size, margin);
+
+                let verts = quad.map(|(pos, _)| pos);
+                assert_eq!(
+                    Quads.screen_bounds(&verts, &CoordinateMode::default(), size),
+                    Some(([0; 2], size)),
+                    "{:?} {}",
+                    size,
+                    margin
+                );
+            }
+        }
+    }
+
+    /// Turns each triangle into the three lines along its edges.
+    struct Edges;
+
+    impl<'r> Pipeline<'r> for Edges {
+        type Vertex = [f32; 2];
+        type VertexData = Unit;
+        type Primitives = Geometry<TriangleList, LineList>;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, 0.5, 1.0], Unit)
+        }
+        fn geometry<O>(&self, [a, b, c]: [([f32; 4], Unit); 3], output: O)
+        where
+            O: FnMut([([f32; 4], Unit); 2]),
+        {
+            LineList::assemble([a, b, b, c, c, a], output);
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            old + new
+        }
+    }
+
+    #[test]
+    fn triangles_to_lines() {
+        let mut color = Buffer2d::fill([32, 32], 0.0);
+        Edges.render(
+            [[-0.75, -0.75], [0.75, -0.75], [-0.75, 0.75]],
+            &mut color,
+            &mut Empty::depth(),
+        );
+        // The edges along each axis are drawn...
+        let lit = |pixels: &mut dyn Iterator<Item = [usize; 2]>| {
+            pixels.filter(|pos| color.read(*pos) > 0.0).count()
+        };
+        assert!((0..32).any(|y| lit(&mut (0..32).map(|x| [x, y])) >= 20));
+        assert!((0..32).any(|x| lit(&mut (0..32).map(|y| [x, y])) >= 20));
+        // ...as is the diagonal, but the inside of the triangle isn't filled
+        assert!(lit(&mut (0..32).map(|i| [i, i])) > 0);
+        assert_eq!(color.read([12, 20]), 0.0);
+    }
+
+    struct Dots;
+
+    impl<'r> Pipeline<'r> for Dots {
+        type Vertex = [f32; 3];
+        type VertexData = Unit;
+        type Primitives = PointList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn vertex(&self, [x, y, z]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, *z, 1.0], Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            old + new
+        }
+    }
+
+    #[test]
+    fn points() {
+        let points = [
+            [-0.5, -0.5, 0.5],
+            [0.5, 0.25, 0.5],
+            // Hidden behind the first point
+            [-0.5, -0.5, 0.75],
+            // Outside of the target or depth range
+            [1.0, 0.0, 0.5],
+            [0.0, -1.5, 0.5],
+            [0.0, 0.0, 1.5],
+        ];
+        let mut color = Buffer2d::fill([32, 32], 0.0);
+        let mut depth = Buffer2d::fill([32, 32], 1.0);
+        Dots.render(points, &mut color, &mut depth);
+        assert!(color.raw().iter().all(|c| *c == 0.0 || *c == 1.0));
+        assert_eq!(color.raw().iter().sum::<f32>(), 2.0);
+    }
+
+    #[test]
+    fn render_indexed() {
+        const QUAD: [[f32; 2]; 4] = [[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+        fn render<I: Index>(indices: &[I]) -> Buffer2d<f32> {
+            let mut color = Buffer2d::fill([16, 16], 0.0);
+            Overdraw.render_indexed(indices, &QUAD, &mut color, &mut Empty::depth());
+            color
+        }
+
+        let mut expected = Buffer2d::fill([16, 16], 0.0);
+        Overdraw.render(
+            [0, 1, 2, 0, 2, 3].map(|i| QUAD[i]),
+            &mut expected,
+            &mut Empty::depth(),
+        );
+        assert_eq!(render::<u16>(&[0, 1, 2, 0, 2, 3]).raw(), expected.raw());
+        assert_eq!(render::<u32>(&[0, 1, 2, 0, 2, 3]).raw(), expected.raw());
+        assert_eq!(render::<usize>(&[0, 1, 2, 0, 2, 3]).raw(), expected.raw());
+        assert!(render::<u16>(&[]).raw().iter().all(|c| *c == 0.0));
+    }
+
+    #[test]
+    fn top_left_fill_convention() {
+        // A quad whose edges, and the diagonals that split it into triangles, pass exactly through fragment positions
+        let [a, b, c, d] = [[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+        for quad in [
+            [a, b, c, a, c, d],
+            [a, c, b, a, d, c],
+            [b, c, d, b, d, a],
+            [b, d, c, b, a, d],
+        ] {
+            // Small triangles skip the calculation of the bounds of each row
+            for size in [8, 64] {
+                let mut color = Buffer2d::fill([size, size], 0.0);
+                Overdraw.render(&quad, &mut color, &mut Empty::depth());
+                // Fragments on the top and left edges of the quad are covered, and those on the bottom and right are not
+                let covered = size / 4..size * 3 / 4;
+                for y in 0..size {
+                    for x in 0..size {
+                        let expected = if covered.contains(&x) && covered.contains(&y) {
+                            1.0
+                        } else {
+                            0.0
+                        };
+                        assert_eq!(color.read([x, y]), expected, "at {:?}", [x, y]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integer_depth_targets() {
+        // Overlapping triangles, each at a constant depth that is well-separated from the others
+        let tris = (0..20)
+            .flat_map(|i| {
+                let f = i as f32;
+                let [cx, cy] = [(f * 1.7).sin() * 0.6, (f * 2.3).cos() * 0.6];
+                let z = ((i * 7) % 20) as f32 / 20.0 + 0.025;
+                [[-0.8, -0.8], [0.8, -0.6], [-0.2, 0.9]].map(|[x, y]| ([cx + x, cy + y, z, 1.0], f))
+            })
+            .collect::<Vec<_>>();
+        fn render<T: DepthTexel + Send + Sync>(
+            tris: &[([f32; 4], f32)],
+        ) -> (Buffer2d<f32>, Buffer2d<T>) {
+            let mut color = Buffer2d::fill([64, 48], -1.0);
+            let mut depth = Buffer2d::fill([64, 48], T::from_depth(1.0));
+            Layers {
+                coordinate_mode: CoordinateMode::VULKAN,
+                depth_mode: DepthMode::LESS_WRITE,
+            }
+            .render(tris, &mut color, &mut depth);
+            (color, depth)
+        }
+
+        let (color_f32, depth_f32) = render::<f32>(&tris);
+        let (color_u16, depth_u16) = render::<u16>(&tris);
+        let (color_u24, depth_u24) = render::<U24>(&tris);
+        let (color_u32, depth_u32) = render::<u32>(&tris);
+        assert!(color_f32.raw() == color_u16.raw());
+        assert!(color_f32.raw() == color_u24.raw());
+        assert!(color_f32.raw() == color_u32.raw());
+        for (i, z) in depth_f32.raw().iter().enumerate() {
+            assert!((depth_u16.raw()[i].to_depth() - z).abs() <= 0.5 / u16::MAX as f32);
+            assert!((depth_u24.raw()[i].to_depth() - z).abs() <= 0.5 / U24::MAX.get() as f32);
+            assert!((depth_u32.raw()[i].to_depth() - z).abs() <= f32::EPSILON);
+        }
+
+        // Depths outside of the representable range are clamped
+        assert_eq!(u16::from_depth(-0.5), 0);
+        assert_eq!(u16::from_depth(1.5), u16::MAX);
+        assert_eq!(u32::from_depth(1.0), u32::MAX);
+        assert_eq!(U24::from_depth(1.5), U24::MAX);
+        assert_eq!(U24::new(0x123456).get(), 0x123456);
+        assert!(U24::new(0x0100FF) < U24::new(0x010100));
+    }
+
+    #[test]
+    fn reverse_z_clip_range() {
+        let reversed = CoordinateMode::VULKAN.reverse_z();
+        assert_eq!(reversed.z_clip_range, Some(1.0..0.0));
+        assert!([0.0, 0.5, 1.0].iter().all(|z| reversed.passes_z_clip(*z)));
+        assert!(![-0.01, 1.01].iter().any(|z| reversed.passes_z_clip(*z)));
+
+        // Two overlapping quads, each with their own colour
+        let quads = |[near, far]: [f32; 2], near_first: bool| {
+            let quad = |[x0, x1]: [f32; 2], z: f32, color: f32| {
+                [
+                    [x0, -1.0],
+                    [x1, -1.0],
+                    [x1, 1.0],
+                    [x0, -1.0],
+                    [x1, 1.0],
+                    [x0, 1.0],
+                ]
+                .map(|[x, y]| ([x, y, z, 1.0], color))
+            };
+            let (near, far) = (quad([-1.0, 0.5], near, 1.0), quad([-0.5, 1.0], far, 2.0));
+            if near_first {
+                [near, far].concat()
+            } else {
+                [far, near].concat()
+            }
+        };
+        let render =
+            |coordinate_mode: CoordinateMode, depth_mode, clear_depth, depths, near_first| {
+                let mut color = Buffer2d::fill([16, 4], 0.0);
+                let mut depth = Buffer2d::fill([16, 4], clear_depth);
+                Layers {
+                    coordinate_mode,
+                    depth_mode,
+                }
+                .render(quads(depths, near_first), &mut color, &mut depth);
+                color
+            };
+
+        for near_first in [true, false] {
+            let standard = render(
+                CoordinateMode::VULKAN,
+                DepthMode::LESS_WRITE,
+                1.0,
+                [0.25, 0.75],
+                near_first,
+            );
+            // The near quad is in front where the two overlap
+            assert_eq!(standard.read([8, 2]), 1.0);
+            assert_eq!(standard.read([14, 2]), 2.0);
+
+            let reversed = render(
+                CoordinateMode::VULKAN.reverse_z(),
+                DepthMode::GREATER_WRITE,
+                0.0,
+                [0.75, 0.25],
+                near_first,
+            );
+            assert_eq!(standard.raw(), reversed.raw());
+        }
+    }
+
+    struct Culled {
+        coordinate_mode: CoordinateMode,
+        cull_mode: CullMode,
+    }
+
+    impl<'r> Pipeline<'r> for Culled {
+        type Vertex = [f32; 2];
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn coordinate_mode(&self) -> CoordinateMode {
+            self.coordinate_mode.clone()
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            self.cull_mode
+        }
+        fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, 0.5, 1.0], 1.0)
+        }
+        fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+            x
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn cull_mode_follows_handedness() {
+        // Counter-clockwise when +y is up
+        let ccw = [[-0.5, -0.5], [0.5, -0.5], [0.0, 0.5]];
+        let visible = |coordinate_mode: &CoordinateMode, cull_mode| {
+            let mut color = Buffer2d::fill([8, 8], 0.0);
+            Culled {
+                coordinate_mode: coordinate_mode.clone(),
+                cull_mode,
+            }
+            .render(&ccw, &mut color, &mut Empty::depth());
+            color.raw().iter().any(|x| *x != 0.0)
+        };
+
+        for (coordinate_mode, faces_camera) in [
+            (CoordinateMode::OPENGL, true),
+            (CoordinateMode::VULKAN, true),
+            (CoordinateMode::METAL, false),
+            (CoordinateMode::DIRECTX, false),
+        ] {
+            assert!(visible(&coordinate_mode, CullMode::None));
+            assert_eq!(
+                visible(&coordinate_mode, CullMode::Back),
+                faces_camera,
+                "{:?}",
+                coordinate_mode
+            );
+            assert_eq!(
+                visible(&coordinate_mode, CullMode::Front),
+                !faces_camera,
+                "{:?}",
+                coordinate_mode
+            );
+        }
+    }
+
+    #[test]
+    fn pixel_coordinates() {
+        // Fragments lie at integer positions, so no fragment lies exactly on an edge of the triangle
+        let triangle = [[1.5, 1.5], [11.0, 1.5], [1.5, 11.0]];
+        for coordinate_mode in [CoordinateMode::OPENGL, CoordinateMode::VULKAN] {
+            let mut color = Buffer2d::fill([16, 16], 0.0);
+            Culled {
+                coordinate_mode: coordinate_mode.with_pixel_coordinates(),
+                cull_mode: CullMode::None,
+            }
+            .render(&triangle, &mut color, &mut Empty::depth());
+            for y in 0..16 {
+                for x in 0..16 {
+                    let covered = x >= 2 && y >= 2 && x + y <= 12;
+                    assert_eq!(color.read([x, y]) != 0.0, covered, "{}, {}", x, y);
+                }
+            }
+        }
+    }
+
+    struct Biased(DepthBias);
+
+    impl<'r> Pipeline<'r> for Biased {
+        type Vertex = ([f32; 3], f32);
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn depth_bias(&self) -> DepthBias {
+            self.0
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, ([x, y, z], color): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            // A perspective projection, such that depth varies non-linearly across the plane
+            ([*x * *z, *y * *z, *z - 0.9, *z], *color)
+        }
+        fn fragment(&self, color: Self::VertexData) -> Self::Fragment {
+            color
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    /// Render a base quad with the given bias, followed by a coplanar decal quad (triangulated differently) without
+    /// bias, returning the proportion of base pixels that the decal covers.
+    fn decal_coverage(bias: DepthBias) -> f32 {
+        // A plane at a glancing angle, receding into the distance
+        let corners = [
+            [-1.0, -1.0, 1.0],
+            [1.0, -1.0, 1.0],
+            [1.0, 1.0, 4.0],
+            [-1.0, 1.0, 4.0],
+        ];
+        let quad =
+            |[a, b, c, d]: [usize; 4], color| [a, b, c, a, c, d].map(|i| (corners[i], color));
+
+        let mut color = Buffer2d::fill([64, 64], f32::NAN);
+        let mut depth = Buffer2d::fill([64, 64], 1.0);
+        Biased(bias).render(quad([0, 1, 2, 3], 0.0), &mut color, &mut depth);
+        Biased(DepthBias::NONE).render(quad([1, 2, 3, 0], 1.0), &mut color, &mut depth);
+
+        let base = color.raw().iter().filter(|c| !c.is_nan());
+        base.clone().sum::<f32>() / base.count() as f32
+    }
+
+    #[test]
+    fn depth_bias_avoids_z_fighting() {
+        // Without bias, the coplanar quads fight
+        let fighting = decal_coverage(DepthBias::NONE);
+        assert!(fighting < 0.99, "{}", fighting);
+        // A slope-scaled bias on the base quad resolves the fight in favour of the decal
+        assert_eq!(decal_coverage(DepthBias::new(0.0, 1.0)), 1.0);
+        assert_eq!(decal_coverage(DepthBias::new(1.0e-5, 0.5)), 1.0);
+        // A constant bias large enough to fix the steepest part of the plane works too, but must be much larger
+        assert_eq!(decal_coverage(DepthBias::new(1.0e-2, 0.0)), 1.0);
+    }
+
+    /// A colour that, unlike `[f32; 4]`, may be interpolated.
+    #[derive(Copy, Clone)]
+    struct Rgba([f32; 4]);
+
+    impl core::ops::Mul<f32> for Rgba {
+        type Output = Self;
+        fn mul(self, w: f32) -> Self {
+            Self(self.0.map(|e| e * w))
+        }
+    }
+
+    impl core::ops::Add for Rgba {
+        type Output = Self;
+        fn add(self, other: Self) -> Self {
+            Self(core::array::from_fn(|i| self.0[i] + other.0[i]))
+        }
+    }
+
+    impl Color for Rgba {
+        fn to_rgba(&self) -> [f32; 4] {
+            self.0
+        }
+        fn from_rgba(rgba: [f32; 4]) -> Self {
+            Self(rgba)
+        }
+    }
+
+    struct Composite {
+        mode: BlendMode,
+        color: [f32; 4],
+    }
+
+    impl<'r> Pipeline<'r> for Composite {
+        type Vertex = [f32; 4];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = Rgba;
+        type Pixel = [f32; 4];
+
+        fn blend_mode(&self) -> BlendMode {
+            self.mode
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            Rgba(self.color)
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            self.blend_mode().apply(old, new)
+        }
+    }
+
+    #[test]
+    fn blend_over() {
+        let (red, blue) = ([1.0, 0.0, 0.0, 0.5], [0.0, 0.0, 1.0, 1.0]);
+        let over = BlendMode::Over {
+            premultiplied: false,
+        };
+        assert_eq!(over.apply(blue, red), [0.5, 0.0, 0.5, 1.0]);
+        // Premultiplied colours produce the same (premultiplied) result
+        let over = BlendMode::Over {
+            premultiplied: true,
+        };
+        assert_eq!(over.apply(blue, [0.5, 0.0, 0.0, 0.5]), [0.5, 0.0, 0.5, 1.0]);
+        // Compositing over a transparent destination leaves the source unchanged
+        let over = BlendMode::Over {
+            premultiplied: false,
+        };
+        assert_eq!(over.apply([0.0; 4], red), red);
+        assert_eq!(over.apply([0.0; 4], [0.0; 4]), [0.0; 4]);
+
+        // Blending happens as part of rendering
+        let mut color = Buffer2d::fill([4, 4], blue);
+        let tri = [[-2.0, -2.0], [5.0, -2.0], [-2.0, 5.0]].map(|[x, y]| [x, y, 0.5, 1.0]);
+        Composite {
+            mode: over,
+            color: red,
+        }
+        .render(tri, &mut color, &mut Empty::depth());
+        assert!(color.raw().iter().all(|px| *px == [0.5, 0.0, 0.5, 1.0]));
+    }
+
+    #[test]
+    fn blend_channelwise() {
+        let (a, b) = ([0.25, 0.5, 1.0, 0.5], [0.5, 0.25, 0.0, 1.0]);
+        assert_eq!(BlendMode::Replace.apply(a, b), b);
+        assert_eq!(BlendMode::Add.apply(a, b), [0.75, 0.75, 1.0, 1.5]);
+        assert_eq!(BlendMode::Multiply.apply(a, b), [0.125, 0.125, 0.0, 0.5]);
+        assert_eq!(BlendMode::Min.apply(a, b), [0.25, 0.25, 0.0, 0.5]);
+        assert_eq!(BlendMode::Max.apply(a, b), [0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(BlendMode::default(), BlendMode::Replace);
+    }
+
+    struct Stenciled {
+        stencil_mode: StencilMode,
+        pixel_mode: PixelMode,
+        color: f32,
+    }
+
+    impl<'r> Pipeline<'r> for Stenciled {
+        type Vertex = [f32; 4];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn pixel_mode(&self) -> PixelMode {
+            self.pixel_mode
+        }
+        fn stencil_mode(&self) -> StencilMode {
+            self.stencil_mode
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            self.color
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn stencil_mask() {
+        let size = [8, 8];
+        let full = [[-2.0, -2.0], [5.0, -2.0], [-2.0, 5.0]].map(|[x, y]| [x, y, 0.5, 1.0]);
+        // Covers only the left half of the target
+        let left =
+            [[-2.0, -2.0], [-0.1, -2.0], [-0.1, 5.0], [-2.0, 5.0]].map(|[x, y]| [x, y, 0.5, 1.0]);
+        let left = [left[0], left[1], left[2], left[0], left[2], left[3]];
+
+        let mut color = Buffer2d::fill(size, 0.0);
+        let mut stencil = Buffer2d::fill(size, 0u8);
+
+        // Write a mask into the stencil target without touching the pixel target
+        Stenciled {
+            stencil_mode: StencilMode::write(1),
+            pixel_mode: PixelMode::PASS,
+            color: 0.5,
+        }
+        .render_with_stencil(&left, &mut color, &mut Empty::depth(), &mut stencil);
+        assert!(color.raw().iter().all(|c| *c == 0.0));
+        assert_eq!(stencil.read([0, 0]), 1);
+        assert_eq!(stencil.read([7, 7]), 0);
+
+        // Only render where the mask was written, counting fragments that fail the test
+        Stenciled {
+            stencil_mode: StencilMode::NONE.with_test(Ordering::Equal, 1).with_ops(
+                StencilOp::Incr,
+                StencilOp::Keep,
+                StencilOp::Keep,
+            ),
+            pixel_mode: PixelMode::WRITE,
+            color: 1.0,
+        }
+        .render_with_stencil(&full, &mut color, &mut Empty::depth(), &mut stencil);
+        for y in 0..size[1] {
+            for x in 0..size[0] {
+                let masked = x < size[0] / 2;
+                assert_eq!(
+                    color.read([x, y]),
+                    if masked { 1.0 } else { 0.0 },
+                    "{} {}",
+                    x,
+                    y
+                );
+                assert_eq!(stencil.read([x, y]), 1, "{} {}", x, y);
+            }
+        }
+
+        // Without a stencil test, `render` behaves as before
+        Stenciled {
+            stencil_mode: StencilMode::NONE,
+            pixel_mode: PixelMode::WRITE,
+            color: 0.25,
+        }
+        .render(&full, &mut color, &mut Empty::depth());
+        assert!(color.raw().iter().all(|c| *c == 0.25));
+    }
+
+    #[test]
+    fn stencil_ops() {
+        assert_eq!(StencilOp::Keep.apply(3, 7), 3);
+        assert_eq!(StencilOp::Replace.apply(3, 7), 7);
+        assert_eq!(StencilOp::Incr.apply(255, 7), 255);
+        assert_eq!(StencilOp::Decr.apply(0, 7), 0);
+        assert!(!StencilMode::NONE.uses_stencil());
+        assert!(StencilMode::write(1).uses_stencil());
+        assert!(StencilMode::NONE.with_test(Ordering::Less, 2).passes(3));
+        assert!(!StencilMode::NONE.with_test(Ordering::Less, 2).passes(1));
+    }
+
+    /// Generates the positions of its vertices from their indices, and colours each primitive by its index.
+    struct Indexed;
+
+    impl<'r> Pipeline<'r> for Indexed {
+        type Vertex = ();
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, _: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            unreachable!("Vertices are only processed through `vertex_indexed`")
+        }
+        fn vertex_indexed(&self, index: usize, _: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            // The top half of the screen, followed by the bottom half
+            let [x, y] = [
+                [-2.0, 0.0],
+                [5.0, 0.0],
+                [-2.0, 5.0],
+                [-2.0, -5.0],
+                [5.0, 0.0],
+                [-2.0, 0.0],
+            ][index];
+            ([x, y, 0.5, 1.0], Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            unreachable!("Fragments are only processed through `fragment_indexed`")
+        }
+        fn fragment_indexed(&self, primitive: usize, _: Self::VertexData) -> Self::Fragment {
+            primitive as f32
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
 
-        // Produce an iterator over vertices (using the vertex shader and geometry shader to produce them)
-        let mut vert_outs = vertices
-            .into_iter()
-            .map(|v| self.vertex(v.borrow()))
-            .peekable();
-        let mut vert_out_queue = VecDeque::new();
-        let fetch_vertex = core::iter::from_fn(move || loop {
-            match vert_out_queue.pop_front() {
-                Some(v) => break Some(v),
-                None if vert_outs.peek().is_none() => break None,
-                None => {
-                    let prim = Self::Primitives::collect_primitive(&mut vert_outs)?;
-                    self.geometry(prim, |prim| {
-                        Self::Primitives::primitive_vertices(prim, |v| vert_out_queue.push_back(v))
-                    });
-                }
+    #[test]
+    fn primitive_index() {
+        let mut color = Buffer2d::fill([64, 64], -1.0);
+        // Indices restart for each call to `render`
+        for _ in 0..2 {
+            Indexed.render(&[(); 6], &mut color, &mut Empty::depth());
+            // Skip the row that lies exactly on the edge shared by both triangles
+            for y in (0..64).filter(|y| *y != 32) {
+                let expected = if y < 32 { 0.0 } else { 1.0 };
+                assert!((0..64).all(|x| color.read([x, y]) == expected), "row {}", y);
             }
-        });
+        }
+    }
 
-        let msaa_level = match self.aa_mode() {
-            AaMode::None => 0,
-            AaMode::Msaa { level } => level.max(0).min(6) as usize,
-        };
+    struct Line(LineMode);
 
-        #[cfg(not(feature = "par"))]
-        render_seq(self, fetch_vertex, target_size, pixel, depth, msaa_level);
-        #[cfg(feature = "par")]
-        render_par(self, fetch_vertex, target_size, pixel, depth, msaa_level);
+    impl<'r> Pipeline<'r> for Line {
+        type Vertex = [f32; 2];
+        type VertexData = Unit;
+        type Primitives = LineList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> LineMode {
+            self.0
+        }
+        fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, 0.5, 1.0], Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            old + new
+        }
+        fn blend_coverage(
+            &self,
+            old: Self::Pixel,
+            new: Self::Fragment,
+            coverage: f32,
+        ) -> Self::Pixel {
+            old + new * coverage
+        }
     }
-}
 
-#[cfg(feature = "par")]
-fn render_par<'r, Pipe, S, P, D>(
-    pipeline: &Pipe,
-    fetch_vertex: S,
-    tgt_size: [usize; 2],
-    pixel: &mut P,
-    depth: &mut D,
-    msaa_level: usize,
-) where
-    Pipe: Pipeline<'r> + Send + Sync,
-    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
-    P: Target<Texel = Pipe::Pixel> + Send + Sync,
-    D: Target<Texel = f32> + Send + Sync,
-{
-    use alloc::vec::Vec;
-    use core::sync::atomic::{AtomicUsize, Ordering};
-    use std::thread;
+    #[test]
+    fn anti_aliased_lines() {
+        // From pixel (2, 10) to pixel (29, 16.75), so the line crosses a quarter of a pixel per column
+        let line = [[-0.875, 0.375], [0.8125, -0.046875]];
+        let render = |mode| {
+            let mut color = Buffer2d::fill([32, 32], 0.0);
+            Line(mode).render(line, &mut color, &mut Empty::depth());
+            color
+        };
 
-    // TODO: Don't pull all vertices at once
-    let vertices = fetch_vertex.collect::<Vec<_>>();
-    let threads = std::thread::available_parallelism()
-        .map(|cpu| cpu.into())
-        .unwrap_or(1usize);
-    let row = AtomicUsize::new(0);
+        let aliased = render(LineMode::Aliased);
+        assert!(aliased.raw().iter().all(|c| *c == 0.0 || *c == 1.0));
 
-    const FRAGMENTS_PER_GROUP: usize = 20_000; // Magic number, maybe make this configurable?
-    let group_rows = FRAGMENTS_PER_GROUP * (1 << msaa_level) / tgt_size[0].max(1);
-    let needed_threads = (tgt_size[1] / group_rows).min(threads);
+        let aa = render(LineMode::AntiAliased);
+        let column = |x| (0..32).map(|y| aa.read([x, y])).sum::<f32>();
+        // The pixels either side of the line share its coverage...
+        for x in 3..29 {
+            assert!((column(x) - 1.0).abs() < 1.0e-6, "column {}", x);
+        }
+        // ...which is fractional wherever the line doesn't cross the centre of a pixel (in 21 of its 28 columns, plus
+        // the first pixel)
+        let partial = aa.raw().iter().filter(|c| **c > 0.0 && **c < 1.0).count();
+        assert_eq!(partial, 2 * 21 + 1);
+        // Only half of the pixels at either end of the line are covered
+        assert_eq!(column(2), 0.5);
+        assert_eq!(column(29), 0.5);
+        assert!((0..32).all(|x| (2..=29).contains(&x) || column(x) == 0.0));
+    }
 
-    let vertices = &vertices;
-    let pixel = &*pixel;
-    let depth = &*depth;
+    #[test]
+    fn anti_aliased_connected_lines() {
+        // Pixels (2, 10) to (16.3, 13) to (29, 11), meeting part of the way across a pixel
+        let [a, b, c] = [[-0.875, 0.375], [0.01875, 0.1875], [0.8125, 0.3125]];
+        // The order of the vertices of each line shouldn't matter
+        for lines in [[a, b, b, c], [b, a, c, b]] {
+            let mut color = Buffer2d::fill([32, 32], 0.0);
+            Line(LineMode::AntiAliased).render(lines, &mut color, &mut Empty::depth());
+            let column = |x| (0..32).map(|y| color.read([x, y])).sum::<f32>();
+            // The pixels where the lines meet are covered no more than any other
+            for x in 3..29 {
+                assert!((column(x) - 1.0).abs() < 1.0e-5, "column {}", x);
+            }
+        }
+    }
 
-    thread::scope(|s| {
-        for _ in 0..needed_threads {
-            // TODO: Respawning them each time is dumb
-            s.spawn(|| {
-                loop {
-                    let row_start = row.fetch_add(group_rows, Ordering::Relaxed);
-                    let row_end = if row_start >= tgt_size[1] {
-                        break;
-                    } else {
-                        (row_start + group_rows).min(tgt_size[1])
-                    };
+    #[test]
+    fn blend_coverage_lerp() {
+        /// Blends partially covered fragments over the background in proportion to their coverage.
+        struct Lerped;
 
-                    let tgt_min = [0, row_start];
-                    let tgt_max = [tgt_size[0], row_end];
-                    // Safety: we have exclusive access to our specific regions of `pixel` and `depth`
-                    unsafe {
-                        render_inner(
-                            pipeline,
-                            vertices.iter().cloned(),
-                            (tgt_min, tgt_max),
-                            tgt_size,
-                            pixel,
-                            depth,
-                            msaa_level,
-                        )
-                    }
-                }
-            });
+        impl<'r> Pipeline<'r> for Lerped {
+            type Vertex = [f32; 2];
+            type VertexData = Unit;
+            type Primitives = LineList;
+            type Fragment = f32;
+            type Pixel = f32;
+
+            fn rasterizer_config(&self) -> LineMode {
+                LineMode::AntiAliased
+            }
+            fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                ([*x, *y, 0.5, 1.0], Unit)
+            }
+            fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+                1.0
+            }
+            fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+                new
+            }
+            fn blend_coverage(
+                &self,
+                old: Self::Pixel,
+                new: Self::Fragment,
+                coverage: f32,
+            ) -> Self::Pixel {
+                old + (new - old) * coverage
+            }
         }
-    });
-}
 
-#[cfg(not(feature = "par"))]
-fn render_seq<'r, Pipe, S, P, D>(
-    pipeline: &Pipe,
-    fetch_vertex: S,
-    tgt_size: [usize; 2],
-    pixel: &mut P,
-    depth: &mut D,
-    msaa_level: usize,
-) where
-    Pipe: Pipeline<'r> + Send + Sync,
-    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
-    P: Target<Texel = Pipe::Pixel> + Send + Sync,
-    D: Target<Texel = f32> + Send + Sync,
-{
-    // Safety: we have exclusive access to `pixel` and `depth`
-    unsafe {
-        render_inner(
-            pipeline,
-            fetch_vertex,
-            ([0; 2], tgt_size),
-            tgt_size,
-            pixel,
-            depth,
-            msaa_level,
-        )
+        // From the centre of pixel (2, 10) to that of pixel (29, 10), so only half of each end pixel is covered
+        let mut color = Buffer2d::fill([32, 32], 0.2);
+        Lerped.render(
+            [[-0.875, 0.375], [0.8125, 0.375]],
+            &mut color,
+            &mut Empty::depth(),
+        );
+        for x in [2, 29] {
+            let c = color.read([x, 10]);
+            assert!((c - 0.6).abs() < 1.0e-6, "{}", c);
+        }
+        assert!((3..29).all(|x| color.read([x, 10]) == 1.0));
+        assert!((0..32).all(|x| color.read([x, 9]) == 0.2 && color.read([x, 11]) == 0.2));
     }
-}
 
-unsafe fn render_inner<'r, Pipe, S, P, D>(
-    pipeline: &Pipe,
-    fetch_vertex: S,
-    (tgt_min, tgt_max): ([usize; 2], [usize; 2]),
-    tgt_size: [usize; 2],
-    pixel: &P,
-    depth: &D,
-    msaa_level: usize,
-) where
-    Pipe: Pipeline<'r> + Send + Sync,
-    S: Iterator<Item = ([f32; 4], Pipe::VertexData)>,
-    P: Target<Texel = Pipe::Pixel> + Send + Sync,
-    D: Target<Texel = f32> + Send + Sync,
-{
-    let write_pixels = pipeline.pixel_mode().write;
-    let depth_mode = pipeline.depth_mode();
-    for i in 0..2 {
-        // Safety check
-        if write_pixels {
-            assert!(
-                tgt_min[i] <= pixel.size()[i],
-                "{}, {}, {}",
-                i,
-                tgt_min[i],
-                pixel.size()[i]
-            );
-            assert!(
-                tgt_max[i] <= pixel.size()[i],
-                "{}, {}, {}",
-                i,
-                tgt_min[i],
-                pixel.size()[i]
-            );
+    #[test]
+    fn transparency_sorted() {
+        struct Layered(TransparencyMode, AaMode);
+
+        impl<'r> Pipeline<'r> for Layered {
+            type Vertex = ([f32; 4], f32);
+            type VertexData = f32;
+            type Primitives = TriangleList;
+            type Fragment = f32;
+            type Pixel = f32;
+
+            fn depth_mode(&self) -> DepthMode {
+                DepthMode::LESS_PASS
+            }
+            fn aa_mode(&self) -> AaMode {
+                self.1
+            }
+            fn transparency_mode(&self) -> TransparencyMode {
+                self.0
+            }
+            fn rasterizer_config(&self) -> CullMode {
+                CullMode::None
+            }
+            fn vertex(&self, (pos, x): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                (*pos, *x)
+            }
+            fn fragment(&self, x: Self::VertexData) -> Self::Fragment {
+                x
+            }
+            fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+                // Deliberately order-dependent, so that the order fragments are blended in is detected
+                old * 0.5 + new
+            }
+            fn downsample(&self, samples: &[Self::Pixel]) -> Self::Pixel {
+                WeightedSum::mean(samples)
+            }
         }
-        if depth_mode.uses_depth() {
-            assert!(
-                tgt_min[i] <= depth.size()[i],
-                "{}, {}, {}",
-                i,
-                tgt_min[i],
-                depth.size()[i]
+
+        // The same triangle at differing depths, ordered from back to front
+        let tris = [(0.8, 4.0), (0.5, 2.0), (0.2, 1.0)].map(|(z, c)| {
+            [[-0.7, -0.8], [0.9, -0.3], [-0.2, 0.6]].map(|[x, y]| ([x, y, z, 1.0], c))
+        });
+        let render = |mode, aa_mode, order: &[usize]| {
+            let mut color = Buffer2d::fill([16, 16], 0.0);
+            let mut depth = Buffer2d::fill([16, 16], 1.0);
+            Layered(mode, aa_mode).render(
+                order.iter().flat_map(|i| tris[*i]),
+                &mut color,
+                &mut depth,
             );
-            assert!(
-                tgt_max[i] <= depth.size()[i],
-                "{}, {}, {}",
-                i,
-                tgt_min[i],
-                depth.size()[i]
+            color
+        };
+
+        for aa_mode in [
+            AaMode::None,
+            AaMode::Ssaa { factor: 2 },
+            AaMode::Msaa {
+                level: 4,
+                centroid: false,
+            },
+        ] {
+            let expected = render(TransparencyMode::Blended, aa_mode, &[0, 1, 2]);
+            assert_ne!(
+                expected.raw(),
+                render(TransparencyMode::Blended, aa_mode, &[2, 0, 1]).raw()
             );
+            let sorted = TransparencyMode::Sorted { max_layers: 4 };
+            for order in [[0, 1, 2], [2, 1, 0], [1, 2, 0], [2, 0, 1]] {
+                assert_eq!(expected.raw(), render(sorted, aa_mode, &order).raw());
+            }
+
+            // Only the nearest two fragments of each pixel are kept
+            let expected = render(TransparencyMode::Blended, aa_mode, &[1, 2]);
+            let sorted = TransparencyMode::Sorted { max_layers: 2 };
+            for order in [[0, 1, 2], [2, 1, 0], [1, 0, 2]] {
+                assert_eq!(expected.raw(), render(sorted, aa_mode, &order).raw());
+            }
         }
     }
 
-    let principal_x = depth.preferred_axes().map_or(true, |[a, _]| a == 0);
+    /// Quantizes a horizontal gradient into 8-bit pixels.
+    struct Dithered(Option<DitherMatrix>);
 
-    use crate::rasterizer::Blitter;
+    impl<'r> Pipeline<'r> for Dithered {
+        type Vertex = [f32; 2];
+        type VertexData = f32;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = u8;
 
-    struct BlitterImpl<'a, 'r, Pipe: Pipeline<'r>, P, D> {
-        write_pixels: bool,
-        depth_mode: DepthMode,
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, [x, y]: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            // A shallow gradient, spanning a few quantization steps
+            ([*x, *y, 0.5, 1.0], 0.2 + *x * 0.025)
+        }
+        fn fragment(&self, value: Self::VertexData) -> Self::Fragment {
+            value
+        }
+        fn dither(&self) -> Option<DitherMatrix> {
+            self.0
+        }
+        fn dither_fragment(&self, fragment: Self::Fragment, offset: f32) -> Self::Fragment {
+            fragment + offset / 255.0
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            (new * 255.0).round() as u8
+        }
+    }
 
-        tgt_min: [usize; 2],
-        tgt_max: [usize; 2],
-        tgt_size: [usize; 2],
+    #[test]
+    fn dithering() {
+        let [w, h] = [64, 16];
+        let render = |dither| {
+            let mut color = Buffer2d::fill([w, h], 0);
+            let quad = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+            let verts = [0, 1, 2, 0, 2, 3].map(|i| quad[i]);
+            Dithered(dither).render(verts, &mut color, &mut Empty::depth());
+            color
+        };
+        let changes = |color: &Buffer2d<u8>, y| {
+            (1..w)
+                .filter(|x| color.read([*x, y]) != color.read([x - 1, y]))
+                .count()
+        };
 
-        pipeline: &'a Pipe,
-        pixel: &'a P,
-        depth: &'a D,
-        primitive_count: u64,
+        // Without dithering, each row is made up of a few wide bands...
+        let banded = render(None);
+        for y in 0..h {
+            assert!(changes(&banded, y) <= 14, "row {}", y);
+        }
 
-        msaa_level: usize,
-        msaa_buf: Option<Buffer2d<(u64, Option<Pipe::Fragment>)>>,
-        msaa_div: f32,
+        // ...which dithering breaks up into adjacent pixels that alternate between values
+        let dithered = render(Some(DitherMatrix::Bayer4x4));
+        for y in 0..h {
+            assert!(changes(&dithered, y) >= 24, "row {}", y);
+        }
+        // The dithered pixels still average to the gradient over each tile of the matrix
+        for tile_x in (0..w).step_by(4) {
+            let mean = |color: &Buffer2d<u8>| {
+                (tile_x..tile_x + 4)
+                    .flat_map(|x| (0..4).map(move |y| color.read([x, y]) as f32))
+                    .sum::<f32>()
+                    / 16.0
+            };
+            let exact = 255.0 * (0.2 + ((tile_x as f32 + 1.5) / w as f32 * 2.0 - 1.0) * 0.025);
+            assert!((mean(&dithered) - exact).abs() < 0.25, "tile {}", tile_x);
+        }
     }
 
-    impl<'a, 'r, Pipe, P, D> BlitterImpl<'a, 'r, Pipe, P, D>
-    where
-        Pipe: Pipeline<'r> + Send + Sync,
-        P: Target<Texel = Pipe::Pixel> + Send + Sync,
-        D: Target<Texel = f32> + Send + Sync,
-    {
-        #[inline]
-        unsafe fn msaa_fragment<F: FnMut(usize, usize) -> Pipe::VertexData>(
-            &mut self,
-            x: usize,
-            y: usize,
-            mut get_v_data: F,
-        ) -> Pipe::Fragment {
-            // Safety: MSAA buffer will always be large enough
-            let texel = self.msaa_buf.as_mut().unwrap().get_mut([x + 1, y + 1]);
-            if texel.0 != self.primitive_count {
-                texel.0 = self.primitive_count;
-                texel.1 = Some(self.pipeline.fragment(get_v_data(x, y)));
-            }
-            // Safety: We know this entry will always be occupied due to the code above
-            texel
-                .1
-                .clone()
-                .unwrap_or_else(|| core::hint::unreachable_unchecked())
+    /// Shades each fragment with its smallest barycentric coordinate, which is darkest at the edges of the triangle.
+    struct Bary;
+
+    impl<'r> Pipeline<'r> for Bary {
+        type Vertex = [f32; 4];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            unreachable!("Fragments are only processed through `fragment_bary`")
+        }
+        fn fragment_bary(&self, _: usize, bary: [f32; 3], _: Self::VertexData) -> Self::Fragment {
+            assert!((bary.iter().sum::<f32>() - 1.0).abs() < 1.0e-5);
+            bary[0].min(bary[1]).min(bary[2])
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
         }
     }
 
-    impl<'a, 'r, Pipe, P, D> Blitter<Pipe::VertexData> for BlitterImpl<'a, 'r, Pipe, P, D>
-    where
-        Pipe: Pipeline<'r> + Send + Sync,
-        P: Target<Texel = Pipe::Pixel> + Send + Sync,
-        D: Target<Texel = f32> + Send + Sync,
-    {
-        fn target_size(&self) -> [usize; 2] {
-            self.tgt_size
+    /// Records the calls made to each debug hook.
+    #[derive(Default)]
+    struct Recorder {
+        primitives: core::cell::RefCell<Vec<(usize, Vec<[f32; 4]>)>>,
+        culled: core::cell::RefCell<Vec<(usize, CullReason)>>,
+        fragments: core::cell::RefCell<Vec<([usize; 2], bool)>>,
+    }
+
+    impl DebugHooks for Recorder {
+        fn on_primitive(&self, index: usize, clip_positions: &[[f32; 4]]) {
+            self.primitives
+                .borrow_mut()
+                .push((index, clip_positions.to_vec()));
         }
-        fn target_min(&self) -> [usize; 2] {
-            self.tgt_min
+        fn on_primitive_culled(&self, index: usize, reason: CullReason) {
+            self.culled.borrow_mut().push((index, reason));
         }
-        fn target_max(&self) -> [usize; 2] {
-            self.tgt_max
+        fn on_fragment(&self, x: usize, y: usize, _z: f32, passed_depth: bool) {
+            self.fragments.borrow_mut().push(([x, y], passed_depth));
         }
+    }
 
-        #[inline]
-        fn begin_primitive(&mut self) {
-            self.primitive_count = self.primitive_count.wrapping_add(1);
+    struct Debugged(CullMode);
+
+    impl<'r> Pipeline<'r> for Debugged {
+        type Vertex = [f32; 4];
+        type VertexData = Unit;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn depth_mode(&self) -> DepthMode {
+            DepthMode::LESS_WRITE
+        }
+        fn rasterizer_config(&self) -> CullMode {
+            self.0
         }
+        fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Unit)
+        }
+        fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+            1.0
+        }
+        fn blend(&self, old: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            old + new
+        }
+    }
 
-        #[inline]
-        unsafe fn test_fragment(&mut self, x: usize, y: usize, z: f32) -> bool {
-            if let Some(test) = self.depth_mode.test {
-                let old_z = self.depth.read_exclusive_unchecked(x, y);
-                z.partial_cmp(&old_z) == Some(test)
-            } else {
-                true
-            }
+    #[test]
+    fn debug_hooks() {
+        let visible = [
+            [-1.0, -1.0, 0.5, 1.0],
+            [1.0, 1.0, 0.5, 1.0],
+            [-1.0, 1.0, 0.5, 1.0],
+        ];
+        let triangles = [
+            visible,
+            // Zero area
+            [
+                [-1.0, -1.0, 0.5, 1.0],
+                [0.0, 0.0, 0.5, 1.0],
+                [1.0, 1.0, 0.5, 1.0],
+            ],
+            // Beyond the far plane
+            visible.map(|[x, y, _, w]| [x, y, 2.0, w]),
+            // Beyond the right of the target
+            visible.map(|[x, y, z, w]| [x + 3.0, y, z, w]),
+            // Hidden behind the first triangle
+            visible.map(|[x, y, _, w]| [x, y, 0.75, w]),
+        ];
+        let mut color = Buffer2d::fill([16, 16], 0.0);
+        let mut depth = Buffer2d::fill([16, 16], 1.0);
+        let recorder = Recorder::default();
+        Debugged(CullMode::None).render_debug(
+            triangles.iter().flatten(),
+            &mut color,
+            &mut depth,
+            &recorder,
+        );
+
+        let primitives = recorder.primitives.into_inner();
+        assert_eq!(primitives.len(), triangles.len());
+        for (i, (index, verts)) in primitives.into_iter().enumerate() {
+            assert_eq!(index, i);
+            assert_eq!(verts, triangles[i]);
+        }
+        assert_eq!(
+            recorder.culled.into_inner(),
+            [
+                (1, CullReason::ZeroArea),
+                (2, CullReason::ZClip),
+                (3, CullReason::OffScreen)
+            ]
+        );
+        // The hidden triangle covers the same pixels as the visible one, all of which fail the depth test
+        let fragments = recorder.fragments.into_inner();
+        let drawn = color.raw().iter().filter(|c| **c == 1.0).count();
+        assert!(drawn > 0 && color.raw().iter().all(|c| *c <= 1.0));
+        assert_eq!(fragments.len(), drawn * 2);
+        assert!(fragments[..drawn].iter().all(|(_, passed)| *passed));
+        assert!(fragments[drawn..].iter().all(|(_, passed)| !*passed));
+        for ([x, y], _) in fragments {
+            assert_eq!(color.read([x, y]), 1.0);
         }
 
-        #[inline]
-        unsafe fn emit_fragment<F: FnMut(f32, f32) -> Pipe::VertexData>(
-            &mut self,
-            x: usize,
-            y: usize,
-            mut get_v_data: F,
-            z: f32,
-        ) {
-            if self.depth_mode.write {
-                self.depth.write_exclusive_unchecked(x, y, z);
-            }
+        // Exactly one of the windings of a triangle gets culled
+        let recorder = Recorder::default();
+        let mut reversed = visible;
+        reversed.reverse();
+        Debugged(CullMode::Back).render_debug(
+            visible.iter().chain(&reversed),
+            &mut Buffer2d::fill([16, 16], 0.0),
+            &mut Buffer2d::fill([16, 16], 1.0),
+            &recorder,
+        );
+        assert_eq!(recorder.culled.into_inner().len(), 1);
 
-            if self.write_pixels {
-                let frag = if self.msaa_level == 0 {
-                    self.pipeline.fragment(get_v_data(x as f32, y as f32))
-                } else {
-                    let (fractx, fracty) = (
-                        ((x - self.tgt_min[0]) as f32 * self.msaa_div).fract(),
-                        ((y - self.tgt_min[1]) as f32 * self.msaa_div).fract(),
-                    );
+        // Lines are inspected too
+        let recorder = Recorder::default();
+        Line(LineMode::Aliased).render_debug(
+            [[-0.5, -0.5], [0.5, 0.5], [2.0, 2.0], [3.0, 2.5]],
+            &mut Buffer2d::fill([16, 16], 0.0),
+            &mut Empty::depth(),
+            &recorder,
+        );
+        assert_eq!(recorder.primitives.into_inner().len(), 2);
+        assert_eq!(recorder.culled.into_inner(), [(1, CullReason::OffScreen)]);
+        let fragments = recorder.fragments.into_inner();
+        assert!(!fragments.is_empty() && fragments.iter().all(|(_, passed)| *passed));
+    }
+
+    #[test]
+    fn cull_min_area() {
+        let visible = [
+            [-1.0, -1.0, 0.5, 1.0],
+            [1.0, 1.0, 0.5, 1.0],
+            [-1.0, 1.0, 0.5, 1.0],
+        ];
+        // The other half of the target
+        let other = [
+            [-1.0, -1.0, 0.5, 1.0],
+            [1.0, -1.0, 0.5, 1.0],
+            [1.0, 1.0, 0.5, 1.0],
+        ];
+        let [a, b, c] = other;
+        let triangles = [
+            visible,
+            // Zero area
+            [
+                [-1.0, -1.0, 0.5, 1.0],
+                [0.0, 0.0, 0.5, 1.0],
+                [1.0, 1.0, 0.5, 1.0],
+            ],
+            // A sliver with an area of 0.64 square pixels along the bottom of the target
+            [
+                [-1.0, -1.0, 0.5, 1.0],
+                [1.0, -1.0, 0.5, 1.0],
+                [1.0, -0.99, 0.5, 1.0],
+            ],
+            // Triangles are culled by their area whichever way they face
+            other,
+            [c, b, a],
+        ];
+        let culled = |cull_mode| {
+            let mut color = Buffer2d::fill([16, 16], 0.0);
+            let recorder = Recorder::default();
+            Debugged(cull_mode).render_debug(
+                triangles.iter().flatten(),
+                &mut color,
+                &mut Buffer2d::fill([16, 16], 1.0),
+                &recorder,
+            );
+            // The large triangles are always drawn
+            assert!(color.raw().iter().all(|c| *c >= 1.0));
+            recorder.culled.into_inner()
+        };
 
-                    let posix = (x - self.tgt_min[0]) >> self.msaa_level;
-                    let posiy = (y - self.tgt_min[1]) >> self.msaa_level;
+        assert_eq!(
+            culled(CullMode::MinArea(1.0)),
+            [(1, CullReason::ZeroArea), (2, CullReason::ZeroArea)]
+        );
+        assert_eq!(culled(CullMode::MinArea(0.5)), [(1, CullReason::ZeroArea)]);
+        assert_eq!(culled(CullMode::None), [(1, CullReason::ZeroArea)]);
 
-                    let tgt_min = self.tgt_min;
-                    let msaa_level = self.msaa_level;
-                    let mut get_v_data = |x: usize, y: usize| {
-                        get_v_data(
-                            (tgt_min[0] + (x << msaa_level)) as f32,
-                            (tgt_min[1] + (y << msaa_level)) as f32,
-                        )
-                    };
+        // Triangles that extend far beyond the target are drawn in several parts, which are never culled by their own
+        // area (here, every part lies within the guard band, which is smaller than the minimum area)
+        let huge = [
+            [-1.0e6, -1.0e6, 0.5, 1.0],
+            [1.0e6, -1.0, 0.5, 1.0],
+            [-1.0, 1.0e6, 0.5, 1.0],
+        ];
+        let render = |cull_mode| {
+            let mut color = Buffer2d::fill([16, 16], 0.0);
+            Debugged(cull_mode).render(huge, &mut color, &mut Buffer2d::fill([16, 16], 1.0));
+            color
+        };
+        let color = render(CullMode::MinArea(1.0e5));
+        assert!(color.raw().iter().all(|c| *c == 1.0));
+        assert_eq!(color.raw(), render(CullMode::None).raw());
+    }
 
-                    let t00 = self.msaa_fragment(posix + 0, posiy + 0, &mut get_v_data);
-                    let t10 = self.msaa_fragment(posix + 1, posiy + 0, &mut get_v_data);
-                    let t01 = self.msaa_fragment(posix + 0, posiy + 1, &mut get_v_data);
-                    let t11 = self.msaa_fragment(posix + 1, posiy + 1, &mut get_v_data);
+    #[test]
+    fn render_ids() {
+        // The left half of the target, and the top half of the target
+        let left = |z| {
+            [
+                [-1.0, -1.0, z, 1.0],
+                [0.0, -1.0, z, 1.0],
+                [0.0, 1.0, z, 1.0],
+                [-1.0, -1.0, z, 1.0],
+                [0.0, 1.0, z, 1.0],
+                [-1.0, 1.0, z, 1.0],
+            ]
+        };
+        let top = |z| {
+            [
+                [-1.0, 0.0, z, 1.0],
+                [1.0, 0.0, z, 1.0],
+                [1.0, 1.0, z, 1.0],
+                [-1.0, 0.0, z, 1.0],
+                [1.0, 1.0, z, 1.0],
+                [-1.0, 1.0, z, 1.0],
+            ]
+        };
+        for (first, second, overlap) in [(0.25, 0.5, [0, 1]), (0.5, 0.25, [2, 3])] {
+            let mut ids = Buffer2d::fill([16, 16], u32::MAX);
+            Debugged(CullMode::None).render_ids(
+                left(first).iter().chain(&top(second)),
+                &mut ids,
+                &mut Buffer2d::fill([16, 16], 1.0),
+            );
+            // The nearer primitive is visible where they overlap
+            assert!(overlap.contains(&ids.read([4, 4])));
+            // Elsewhere, each primitive is visible or the target is untouched
+            assert!([0, 1].contains(&ids.read([4, 12])));
+            assert!([2, 3].contains(&ids.read([12, 4])));
+            assert_eq!(ids.read([12, 12]), u32::MAX);
+        }
+    }
 
-                    let t0 = Pipe::Fragment::weighted_sum2(t00, t01, 1.0 - fracty, fracty);
-                    let t1 = Pipe::Fragment::weighted_sum2(t10, t11, 1.0 - fracty, fracty);
+    #[test]
+    fn barycentric_edges() {
+        // With and without perspective, and with a vertex far enough away for the triangle to be clipped (in which case
+        // the coordinates are still relative to the original triangle, so the centre of the triangle is off-screen)
+        let v = |x, y, w| [x, y, 0.5, w];
+        for (tri, brightest) in [
+            (
+                [v(-0.8, -0.8, 1.0), v(0.8, -0.8, 1.0), v(0.0, 0.8, 1.0)],
+                0.3..0.33334,
+            ),
+            (
+                [v(-1.6, -1.6, 2.0), v(0.4, -0.4, 0.5), v(0.0, 0.8, 1.0)],
+                0.3..0.33334,
+            ),
+            (
+                [v(-0.8, -0.8, 1.0), v(1.0e5, -0.8, 1.0), v(0.0, 0.8, 1.0)],
+                0.0..1.0e-4,
+            ),
+        ] {
+            let mut color = Buffer2d::fill([64, 64], -1.0);
+            Bary.render(tri, &mut color, &mut Empty::depth());
+            // The centre of the triangle is brightest...
+            let max = color.raw().iter().copied().fold(0.0, f32::max);
+            assert!(brightest.contains(&max), "{}", max);
+            // ...and the ends of every row and column of the triangle are darkest
+            let lines = (0..64)
+                .map(|y| (0..64).map(|x| color.read([x, y])).collect::<Vec<_>>())
+                .chain((0..64).map(|x| (0..64).map(|y| color.read([x, y])).collect()));
+            for line in lines {
+                let covered = line.into_iter().filter(|c| *c >= 0.0).collect::<Vec<_>>();
+                if let (Some(first), Some(last)) = (covered.first(), covered.last()) {
+                    let darkest = first.min(*last);
+                    assert!(covered.iter().all(|c| *c >= darkest), "{:?}", covered);
+                }
+            }
+        }
+    }
 
-                    let t = Pipe::Fragment::weighted_sum2(t0, t1, 1.0 - fractx, fractx);
-                    t
+    /// The same coordinate, interpolated both with and without perspective correction.
+    #[derive(Clone)]
+    struct Interpolated {
+        correct: f32,
+        linear: NoPerspective<f32>,
+    }
 
-                    //self.fetch_pixel([posi[0] + 0, posi[1] + 0], v_data.clone())
-                };
-                let old_px = self.pixel.read_exclusive_unchecked(x, y);
-                let blended_px = self.pipeline.blend(old_px, frag);
-                self.pixel.write_exclusive_unchecked(x, y, blended_px);
+    impl WeightedSum for Interpolated {
+        fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+            Self::weighted_sum_linear(values, weights, weights)
+        }
+        fn weighted_sum_linear<const N: usize>(
+            values: [Self; N],
+            weights: [f32; N],
+            linear_weights: [f32; N],
+        ) -> Self {
+            Self {
+                correct: f32::weighted_sum(values.clone().map(|v| v.correct), weights),
+                linear: NoPerspective::weighted_sum_linear(
+                    values.map(|v| v.linear),
+                    weights,
+                    linear_weights,
+                ),
             }
         }
     }
 
-    <Pipe::Primitives as PrimitiveKind<Pipe::VertexData>>::Rasterizer::default().rasterize(
-        fetch_vertex,
-        principal_x,
-        pipeline.coordinate_mode(),
-        pipeline.rasterizer_config(),
-        BlitterImpl {
-            write_pixels,
-            depth_mode,
+    struct Foreshortened;
 
-            tgt_size,
-            tgt_min,
-            tgt_max,
+    impl<'r> Pipeline<'r> for Foreshortened {
+        type Vertex = ([f32; 4], f32);
+        type VertexData = Interpolated;
+        type Primitives = TriangleList;
+        type Fragment = Interpolated;
+        type Pixel = [f32; 2];
 
-            pipeline,
-            pixel,
-            depth,
-            primitive_count: 0,
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, (pos, v): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            let linear = NoPerspective(*v);
+            (
+                *pos,
+                Interpolated {
+                    correct: *v,
+                    linear,
+                },
+            )
+        }
+        fn fragment(&self, v: Self::VertexData) -> Self::Fragment {
+            v
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            [new.correct, new.linear.0]
+        }
+    }
 
-            msaa_level,
-            msaa_buf: if msaa_level > 0 {
-                Some(Buffer2d::fill_with(
-                    [
-                        ((tgt_max[0] - tgt_min[0]) >> msaa_level) + 3,
-                        ((tgt_max[1] - tgt_min[1]) >> msaa_level) + 3,
-                    ],
-                    || (u64::MAX, None),
-                ))
-            } else {
-                None
-            },
-            msaa_div: 1.0 / (1 << msaa_level) as f32,
-        },
-    );
+    #[test]
+    fn no_perspective_interpolation() {
+        // A quad that recedes into the distance, with its far edge 8 times further away than its near edge
+        let [near, far] = [1.0, 8.0];
+        let v = |x: f32, y: f32, w: f32| [x * w, y * w, 0.5 * w, w];
+        let [a, b, c, d] = [
+            (v(-0.75, -0.7, near), 0.0),
+            (v(0.75, -0.7, near), 0.0),
+            (v(0.25, 0.7, far), 1.0),
+            (v(-0.25, 0.7, far), 1.0),
+        ];
+        let mut color = Buffer2d::fill([64, 64], [-1.0; 2]);
+        Foreshortened.render([a, b, c, a, c, d], &mut color, &mut Empty::depth());
+
+        let column = (0..64)
+            .map(|y| color.read([32, y]))
+            .filter(|[correct, _]| *correct > -1.0)
+            .collect::<Vec<_>>();
+        assert_eq!(column.len(), 45);
+        // Without perspective correction, the coordinate changes by the same amount from row to row (the quad is 44.8
+        // rows tall)
+        let step = column[1][1] - column[0][1];
+        assert!((step.abs() - 1.0 / 44.8).abs() < 1.0e-4, "{}", step);
+        for rows in column.windows(2) {
+            assert!(((rows[1][1] - rows[0][1]) - step).abs() < 1.0e-4);
+        }
+        // With perspective correction, it's skewed towards the near edge
+        for [correct, linear] in column {
+            let expected = (linear / far) / ((1.0 - linear) / near + linear / far);
+            assert!(
+                (correct - expected).abs() < 1.0e-4,
+                "{} {}",
+                correct,
+                expected
+            );
+        }
+    }
+
+    struct Colored;
+
+    impl<'r> Pipeline<'r> for Colored {
+        type Vertex = ([f32; 4], [f32; 4]);
+        type VertexData = Attributes<f32, 4>;
+        type Primitives = TriangleList;
+        type Fragment = Attributes<f32, 4>;
+        type Pixel = [f32; 4];
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, (pos, rgba): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            (*pos, Attributes(*rgba))
+        }
+        fn fragment(&self, rgba: Self::VertexData) -> Self::Fragment {
+            rgba
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new.0
+        }
+    }
+
+    #[test]
+    fn array_vertex_data() {
+        // A red, a green, and a blue vertex, with some perspective
+        let tri = [
+            ([-0.8, -0.8, 0.5, 1.0], [1.0, 0.0, 0.0, 1.0]),
+            ([1.6, -1.6, 1.0, 2.0], [0.0, 1.0, 0.0, 1.0]),
+            ([0.0, 0.4, 0.25, 0.5], [0.0, 0.0, 1.0, 1.0]),
+        ];
+        let mut color = Buffer2d::fill([64, 64], [0.0; 4]);
+        Colored.render(tri, &mut color, &mut Empty::depth());
+
+        let covered = color
+            .raw()
+            .iter()
+            .filter(|[.., a]| *a > 0.0)
+            .collect::<Vec<_>>();
+        assert!(covered.len() > 500);
+        for [r, g, b, a] in covered.iter().copied() {
+            // Each component is interpolated with the same weights
+            assert!((r + g + b - 1.0).abs() < 1.0e-5, "{:?}", [r, g, b]);
+            assert!((a - 1.0).abs() < 1.0e-5);
+        }
+        // Fragments near each vertex mostly take its colour
+        for i in 0..3 {
+            assert!(covered.iter().any(|c| c[i] > 0.9));
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use crate::{buffer::Buffer2d, texture::Texture};
+
+/// The placement of an image that was upscaled by [`integer_upscale_into`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UpscaleInfo {
+    /// The integer factor by which the source image was scaled. This is `0` if the source image did not fit within
+    /// the destination at all.
+    pub factor: usize,
+    /// The position of the top-left corner of the scaled image within the destination.
+    pub offset: [usize; 2],
+    /// The size of the scaled image within the destination.
+    pub size: [usize; 2],
+}
+
+impl UpscaleInfo {
+    /// Compute the placement of an image of size `src_size` upscaled into a destination of size `dst_size` by the
+    /// largest integer factor that fits, centred within the destination.
+    pub fn new(src_size: [usize; 2], dst_size: [usize; 2]) -> Self {
+        let [sw, sh] = src_size;
+        let [dw, dh] = dst_size;
+        let factor = if sw == 0 || sh == 0 {
+            0
+        } else {
+            (dw / sw).min(dh / sh)
+        };
+        let size = [sw * factor, sh * factor];
+        Self {
+            factor,
+            offset: [(dw - size[0]) / 2, (dh - size[1]) / 2],
+            size,
+        }
+    }
+
+    /// Map a position in the destination (such as the mouse position within a window) back to the corresponding
+    /// texel of the source image.
+    ///
+    /// Returns `None` if the position falls within the letterbox bars, outside the scaled image.
+    pub fn window_to_internal(&self, [x, y]: [usize; 2]) -> Option<[usize; 2]> {
+        let x = x
+            .checked_sub(self.offset[0])
+            .filter(|x| *x < self.size[0])?;
+        let y = y
+            .checked_sub(self.offset[1])
+            .filter(|y| *y < self.size[1])?;
+        Some([x / self.factor, y / self.factor])
+    }
+
+    /// Map a texel of the source image to the top-left corner of the region it covers in the destination.
+    pub fn internal_to_window(&self, [x, y]: [usize; 2]) -> [usize; 2] {
+        [
+            self.offset[0] + x * self.factor,
+            self.offset[1] + y * self.factor,
+        ]
+    }
+}
+
+/// Upscale `src` into `dst` by the largest integer factor that fits, centring the result and filling the remaining
+/// letterbox bars with `background`.
+///
+/// Upscaling uses nearest-neighbour filtering, so every source texel becomes an exact `factor` x `factor` square of
+/// pixels. This is ideal for presenting low-resolution pixel art without blurring or uneven texel sizes. If `src`
+/// does not fit within `dst` at all, `dst` is simply filled with `background`.
+pub fn integer_upscale_into<T>(
+    src: &Buffer2d<T>,
+    dst: &mut Buffer2d<T>,
+    background: T,
+) -> UpscaleInfo
+where
+    T: Copy + Send + Sync,
+{
+    let [sw, _] = src.size();
+    let [dw, _] = dst.size();
+
+    let info = UpscaleInfo::new(src.size(), dst.size());
+    let UpscaleInfo {
+        factor,
+        offset,
+        size,
+    } = info;
+
+    let dst = dst.raw_mut();
+    if factor == 0 {
+        dst.fill(background);
+        return info;
+    }
+
+    let (top, rest) = dst.split_at_mut(offset[1] * dw);
+    let (middle, bottom) = rest.split_at_mut(size[1] * dw);
+    top.fill(background);
+    bottom.fill(background);
+
+    // Each chunk of `middle` corresponds to a single source row, replicated `factor` times
+    let upscale_row = |src_row: &[T], dst_rows: &mut [T]| {
+        let (first, others) = dst_rows.split_at_mut(dw);
+        let (left, rest) = first.split_at_mut(offset[0]);
+        let (scaled, right) = rest.split_at_mut(size[0]);
+        left.fill(background);
+        right.fill(background);
+        scaled
+            .chunks_exact_mut(factor)
+            .zip(src_row)
+            .for_each(|(texels, texel)| texels.fill(*texel));
+        others
+            .chunks_exact_mut(dw)
+            .for_each(|row| row.copy_from_slice(first));
+    };
+
+    let rows = src
+        .raw()
+        .chunks_exact(sw)
+        .zip(middle.chunks_exact_mut(dw * factor));
+
+    #[cfg(not(feature = "par"))]
+    rows.for_each(|(src_row, dst_rows)| upscale_row(src_row, dst_rows));
+
+    #[cfg(feature = "par")]
+    crate::par::for_each(rows, |(src_row, dst_rows)| upscale_row(src_row, dst_rows));
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERNAL: [usize; 2] = [320, 180];
+
+    fn window_sizes() -> impl Iterator<Item = [usize; 2]> {
+        (INTERNAL[0]..=1000).flat_map(|w| (INTERNAL[1]..=700).map(move |h| [w, h]))
+    }
+
+    #[test]
+    fn placement() {
+        for [w, h] in window_sizes() {
+            let info = UpscaleInfo::new(INTERNAL, [w, h]);
+            let k = info.factor;
+            assert!(k >= 1);
+            // The factor is the largest that fits
+            assert!(INTERNAL[0] * k <= w && INTERNAL[1] * k <= h);
+            assert!(INTERNAL[0] * (k + 1) > w || INTERNAL[1] * (k + 1) > h);
+            assert_eq!(info.size, INTERNAL.map(|e| e * k));
+            // The image is centred, with any odd pixel of the bars on the right/bottom
+            for (i, dim) in [w, h].into_iter().enumerate() {
+                let before = info.offset[i];
+                let after = dim - info.offset[i] - info.size[i];
+                assert!(after == before || after == before + 1, "{:?}", [w, h]);
+            }
+        }
+    }
+
+    #[test]
+    fn upscale() {
+        let src = Buffer2d::fill_with(INTERNAL, {
+            let mut i = 0;
+            move || {
+                i += 1;
+                i
+            }
+        });
+        const BACKGROUND: u32 = u32::MAX;
+
+        // Checking the contents of every window size is slow, so check a spread of (including odd) sizes
+        for [w, h] in
+            window_sizes().filter(|[w, h]| w % 37 == 0 && h % 29 == 0 || [*w, *h] == [1000, 700])
+        {
+            let mut dst = Buffer2d::fill([w, h], 0);
+            let info = integer_upscale_into(&src, &mut dst, BACKGROUND);
+            assert_eq!(info, UpscaleInfo::new(INTERNAL, [w, h]));
+
+            for y in 0..h {
+                for x in 0..w {
+                    let expected = match info.window_to_internal([x, y]) {
+                        Some(pos) => src.read(pos),
+                        None => BACKGROUND,
+                    };
+                    assert_eq!(dst.read([x, y]), expected, "{:?} in {:?}", [x, y], [w, h]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coordinate_mapping() {
+        for [w, h] in window_sizes().filter(|[w, h]| w % 7 == 0 && h % 5 == 0) {
+            let info = UpscaleInfo::new(INTERNAL, [w, h]);
+            let k = info.factor;
+
+            // Every texel round-trips
+            for y in 0..INTERNAL[1] {
+                for x in 0..INTERNAL[0] {
+                    let win = info.internal_to_window([x, y]);
+                    assert_eq!(info.window_to_internal(win), Some([x, y]));
+                    assert_eq!(
+                        info.window_to_internal([win[0] + k - 1, win[1] + k - 1]),
+                        Some([x, y])
+                    );
+                }
+            }
+
+            // Positions in the letterbox bars map to nothing
+            let [ox, oy] = info.offset;
+            let [sx, sy] = info.size;
+            for pos in [
+                [ox.wrapping_sub(1), oy],
+                [ox, oy.wrapping_sub(1)],
+                [ox + sx, oy],
+                [ox, oy + sy],
+            ] {
+                assert_eq!(
+                    info.window_to_internal(pos),
+                    None,
+                    "{:?} in {:?}",
+                    pos,
+                    [w, h]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn too_small() {
+        let src = Buffer2d::fill(INTERNAL, 1u32);
+        let mut dst = Buffer2d::fill([319, 700], 0);
+        let info = integer_upscale_into(&src, &mut dst, 7);
+        assert_eq!(info.factor, 0);
+        assert!(dst.raw().iter().all(|px| *px == 7));
+    }
+}
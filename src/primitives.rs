@@ -1,8 +1,14 @@
-use crate::rasterizer::{Lines, Rasterizer, Triangles};
+use crate::rasterizer::{Lines, Points, Quads, Rasterizer, Triangles};
+use core::marker::PhantomData;
 
 pub trait PrimitiveKind<V> {
+    /// The rasterizer used for the primitives emitted by the geometry stage (those of [`PrimitiveKind::Output`]).
     type Rasterizer: Rasterizer;
     type Primitive;
+    /// The kind of primitive that [`Pipeline::geometry`](crate::Pipeline::geometry) turns primitives of this kind into.
+    ///
+    /// This is `Self` for every kind other than [`Geometry`].
+    type Output: PrimitiveKind<V>;
 
     /// Collect a single primitive from an iterator of vertices.
     fn collect_primitive<I>(iter: I) -> Option<Self::Primitive>
@@ -13,6 +19,29 @@ pub trait PrimitiveKind<V> {
     fn primitive_vertices<O>(primitive: Self::Primitive, output: O)
     where
         O: FnMut(([f32; 4], V));
+
+    /// Emit a primitive as primitives of kind [`PrimitiveKind::Output`], as the geometry stage does by default.
+    fn passthrough<O>(primitive: Self::Primitive, output: O)
+    where
+        O: FnMut(<Self::Output as PrimitiveKind<V>>::Primitive);
+
+    /// Assemble a stream of vertices into primitives of this kind, in the same way as the vertices given to
+    /// [`Pipeline::render`](crate::Pipeline::render), emitting each one.
+    ///
+    /// This is useful for emitting primitives one vertex at a time from
+    /// [`Pipeline::geometry`](crate::Pipeline::geometry), such as `LineList::assemble([a, b, b, c], output)`. Vertices
+    /// that do not make up a whole primitive at the end of the stream are ignored.
+    #[inline]
+    fn assemble<I, O>(vertices: I, mut output: O)
+    where
+        I: IntoIterator<Item = ([f32; 4], V)>,
+        O: FnMut(Self::Primitive),
+    {
+        let mut vertices = vertices.into_iter();
+        while let Some(primitive) = Self::collect_primitive(&mut vertices) {
+            output(primitive);
+        }
+    }
 }
 
 /// A list of triangles.
@@ -23,6 +52,7 @@ pub struct TriangleList(());
 impl<V> PrimitiveKind<V> for TriangleList {
     type Rasterizer = Triangles;
     type Primitive = [([f32; 4], V); 3];
+    type Output = Self;
 
     #[inline]
     fn collect_primitive<I>(mut iter: I) -> Option<Self::Primitive>
@@ -41,6 +71,54 @@ impl<V> PrimitiveKind<V> for TriangleList {
         output(b);
         output(c);
     }
+
+    #[inline]
+    fn passthrough<O>(primitive: Self::Primitive, mut output: O)
+    where
+        O: FnMut(Self::Primitive),
+    {
+        output(primitive);
+    }
+}
+
+/// A list of quads.
+///
+/// `0 1 2 3 4 5 6 7` produces quads `0 1 2 3` and `4 5 6 7`. Each quad is drawn as the triangles `0 1 2` and `0 2 3`,
+/// covering exactly the same fragments as they would in a [`TriangleList`], but with fewer vertices to shade (the
+/// triangles are still rasterized separately, see [`Quads`](crate::rasterizer::Quads)).
+pub struct QuadList(());
+
+impl<V> PrimitiveKind<V> for QuadList {
+    type Rasterizer = Quads;
+    type Primitive = [([f32; 4], V); 4];
+    type Output = Self;
+
+    #[inline]
+    fn collect_primitive<I>(mut iter: I) -> Option<Self::Primitive>
+    where
+        I: Iterator<Item = ([f32; 4], V)>,
+    {
+        Some([iter.next()?, iter.next()?, iter.next()?, iter.next()?])
+    }
+
+    #[inline]
+    fn primitive_vertices<O>([a, b, c, d]: Self::Primitive, mut output: O)
+    where
+        O: FnMut(([f32; 4], V)),
+    {
+        output(a);
+        output(b);
+        output(c);
+        output(d);
+    }
+
+    #[inline]
+    fn passthrough<O>(primitive: Self::Primitive, mut output: O)
+    where
+        O: FnMut(Self::Primitive),
+    {
+        output(primitive);
+    }
 }
 
 /// A list of triangles, rasterised as lines.
@@ -51,6 +129,7 @@ pub struct LineTriangleList(());
 impl<V: Clone> PrimitiveKind<V> for LineTriangleList {
     type Rasterizer = Lines;
     type Primitive = [([f32; 4], V); 3];
+    type Output = Self;
 
     #[inline]
     fn collect_primitive<I>(mut iter: I) -> Option<Self::Primitive>
@@ -74,6 +153,14 @@ impl<V: Clone> PrimitiveKind<V> for LineTriangleList {
         output(c);
         output(a);
     }
+
+    #[inline]
+    fn passthrough<O>(primitive: Self::Primitive, mut output: O)
+    where
+        O: FnMut(Self::Primitive),
+    {
+        output(primitive);
+    }
 }
 
 /// A list of lines.
@@ -84,6 +171,7 @@ pub struct LineList(());
 impl<V> PrimitiveKind<V> for LineList {
     type Rasterizer = Lines;
     type Primitive = [([f32; 4], V); 2];
+    type Output = Self;
 
     #[inline]
     fn collect_primitive<I>(mut iter: I) -> Option<Self::Primitive>
@@ -101,4 +189,87 @@ impl<V> PrimitiveKind<V> for LineList {
         output(a);
         output(b);
     }
+
+    #[inline]
+    fn passthrough<O>(primitive: Self::Primitive, mut output: O)
+    where
+        O: FnMut(Self::Primitive),
+    {
+        output(primitive);
+    }
+}
+
+/// A list of points.
+///
+/// `0 1 2` produces points `0`, `1`, and `2`.
+pub struct PointList(());
+
+impl<V> PrimitiveKind<V> for PointList {
+    type Rasterizer = Points;
+    type Primitive = ([f32; 4], V);
+    type Output = Self;
+
+    #[inline]
+    fn collect_primitive<I>(mut iter: I) -> Option<Self::Primitive>
+    where
+        I: Iterator<Item = ([f32; 4], V)>,
+    {
+        iter.next()
+    }
+
+    #[inline]
+    fn primitive_vertices<O>(point: Self::Primitive, mut output: O)
+    where
+        O: FnMut(([f32; 4], V)),
+    {
+        output(point);
+    }
+
+    #[inline]
+    fn passthrough<O>(primitive: Self::Primitive, mut output: O)
+    where
+        O: FnMut(Self::Primitive),
+    {
+        output(primitive);
+    }
+}
+
+/// Primitives of kind `In` that the geometry stage turns into primitives of kind `Out`, which are then rasterized.
+///
+/// For example, a pipeline with `Geometry<PointList, TriangleList>` as its [`Pipeline::Primitives`] can expand each
+/// point into a sprite made of triangles in [`Pipeline::geometry`].
+///
+/// [`Pipeline::Primitives`]: crate::Pipeline::Primitives
+/// [`Pipeline::geometry`]: crate::Pipeline::geometry
+pub struct Geometry<In, Out>(PhantomData<(In, Out)>);
+
+impl<V, In: PrimitiveKind<V>, Out: PrimitiveKind<V>> PrimitiveKind<V> for Geometry<In, Out> {
+    type Rasterizer = Out::Rasterizer;
+    type Primitive = In::Primitive;
+    type Output = Out;
+
+    #[inline]
+    fn collect_primitive<I>(iter: I) -> Option<Self::Primitive>
+    where
+        I: Iterator<Item = ([f32; 4], V)>,
+    {
+        In::collect_primitive(iter)
+    }
+
+    #[inline]
+    fn primitive_vertices<O>(primitive: Self::Primitive, output: O)
+    where
+        O: FnMut(([f32; 4], V)),
+    {
+        In::primitive_vertices(primitive, output)
+    }
+
+    /// Primitives of kind `In` can't be turned into those of kind `Out` without knowing what they represent, so nothing
+    /// is emitted: pipelines using this kind should implement [`Pipeline::geometry`](crate::Pipeline::geometry).
+    #[inline]
+    fn passthrough<O>(_: Self::Primitive, _: O)
+    where
+        O: FnMut(Out::Primitive),
+    {
+    }
 }
@@ -0,0 +1,297 @@
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// Parameters of a single wave used by [`gerstner_wave`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GerstnerParams {
+    /// The direction of travel of the wave on the xz plane. This need not be normalised.
+    pub direction: [f32; 2],
+    /// The distance between successive crests of the wave.
+    pub wavelength: f32,
+    /// The height of the wave's crests above the rest plane.
+    pub amplitude: f32,
+    /// How sharp the crests of the wave are. `0.0` produces a pure sine wave, `1.0` produces cusped crests. Values
+    /// above `1.0` cause the surface to loop over itself.
+    pub steepness: f32,
+    /// The speed at which the wave's crests travel, in units per unit of time.
+    pub speed: f32,
+}
+
+/// Generate 3-dimensional gradient noise (roughly in the range -1 to 1) at the given position, along with its analytic
+/// gradient.
+///
+/// The gradient is exact, which makes it suitable for computing the normals of noise-displaced surfaces without
+/// needing to sample neighbouring vertices.
+pub fn noise3_with_gradient(p: [f32; 3], seed: u32) -> (f32, [f32; 3]) {
+    let i = p.map(|e| e.floor());
+    let f = [p[0] - i[0], p[1] - i[1], p[2] - i[2]];
+    let [ix, iy, iz] = i.map(|e| e as i32);
+
+    // Quintic interpolation curve and its derivative
+    let u = f.map(|e| e * e * e * (e * (e * 6.0 - 15.0) + 10.0));
+    let du = f.map(|e| 30.0 * e * e * (e * (e - 2.0) + 1.0));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let g = GRADIENTS[(hash(ix + dx, iy + dy, iz + dz, seed) % 12) as usize];
+        let v = dot3(g, [f[0] - dx as f32, f[1] - dy as f32, f[2] - dz as f32]);
+        (v, g)
+    };
+    let (va, ga) = corner(0, 0, 0);
+    let (vb, gb) = corner(1, 0, 0);
+    let (vc, gc) = corner(0, 1, 0);
+    let (vd, gd) = corner(1, 1, 0);
+    let (ve, ge) = corner(0, 0, 1);
+    let (vf, gf) = corner(1, 0, 1);
+    let (vg, gg) = corner(0, 1, 1);
+    let (vh, gh) = corner(1, 1, 1);
+
+    // Trilinear interpolation, expanded into polynomial coefficients
+    let k0 = va;
+    let k1 = vb - va;
+    let k2 = vc - va;
+    let k3 = ve - va;
+    let k4 = va - vb - vc + vd;
+    let k5 = va - vc - ve + vg;
+    let k6 = va - vb - ve + vf;
+    let k7 = -va + vb + vc - vd + ve - vf - vg + vh;
+
+    let value = k0
+        + k1 * u[0]
+        + k2 * u[1]
+        + k3 * u[2]
+        + k4 * u[0] * u[1]
+        + k5 * u[1] * u[2]
+        + k6 * u[2] * u[0]
+        + k7 * u[0] * u[1] * u[2];
+
+    let gradient = core::array::from_fn(|j| {
+        // Contribution from the corner gradients themselves...
+        let g = ga[j]
+            + u[0] * (gb[j] - ga[j])
+            + u[1] * (gc[j] - ga[j])
+            + u[2] * (ge[j] - ga[j])
+            + u[0] * u[1] * (ga[j] - gb[j] - gc[j] + gd[j])
+            + u[1] * u[2] * (ga[j] - gc[j] - ge[j] + gg[j])
+            + u[2] * u[0] * (ga[j] - gb[j] - ge[j] + gf[j])
+            + u[0] * u[1] * u[2] * (-ga[j] + gb[j] + gc[j] - gd[j] + ge[j] - gf[j] - gg[j] + gh[j]);
+        // ...and from the interpolation curve
+        let k = match j {
+            0 => k1 + k4 * u[1] + k6 * u[2] + k7 * u[1] * u[2],
+            1 => k2 + k5 * u[2] + k4 * u[0] + k7 * u[2] * u[0],
+            _ => k3 + k6 * u[0] + k5 * u[1] + k7 * u[0] * u[1],
+        };
+        g + du[j] * k
+    });
+
+    (value, gradient)
+}
+
+/// Generate fractal brownian motion by summing `octaves` layers of [`noise3_with_gradient`], along with the analytic
+/// gradient of the sum.
+///
+/// Each successive octave has its frequency multiplied by `lacunarity` and its amplitude multiplied by `gain`
+/// (typical values are `2.0` and `0.5` respectively). The result is not normalised.
+pub fn fbm_with_gradient(
+    p: [f32; 3],
+    seed: u32,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+) -> (f32, [f32; 3]) {
+    let mut value = 0.0;
+    let mut gradient = [0.0; 3];
+    let mut freq = 1.0;
+    let mut amp = 1.0;
+    for i in 0..octaves {
+        let (v, g) = noise3_with_gradient(p.map(|e| e * freq), seed.wrapping_add(i));
+        value += v * amp;
+        // Chain rule: d/dp n(p * freq) = freq * n'(p * freq)
+        (0..3).for_each(|j| gradient[j] += g[j] * amp * freq);
+        freq *= lacunarity;
+        amp *= gain;
+    }
+    (value, gradient)
+}
+
+/// Evaluate a sum of Gerstner (trochoidal) waves at the given position on the xz plane and at time `t`.
+///
+/// Returns the offset that should be applied to the rest position (`[x, 0, z]`) of the surface, and the unit normal of
+/// the displaced surface (with +y as up).
+pub fn gerstner_wave(pos_xz: [f32; 2], t: f32, params: &[GerstnerParams]) -> ([f32; 3], [f32; 3]) {
+    let mut offset = [0.0; 3];
+    // Partial derivatives of the displaced position with respect to x and z
+    let mut dpdx = [1.0, 0.0, 0.0];
+    let mut dpdz = [0.0, 0.0, 1.0];
+
+    for wave in params {
+        let len =
+            (wave.direction[0] * wave.direction[0] + wave.direction[1] * wave.direction[1]).sqrt();
+        if len == 0.0 || wave.wavelength == 0.0 {
+            continue;
+        }
+        let [dx, dz] = wave.direction.map(|e| e / len);
+        let k = core::f32::consts::TAU / wave.wavelength;
+        let theta = k * (dx * pos_xz[0] + dz * pos_xz[1] - wave.speed * t);
+        let (s, c) = (theta.sin(), theta.cos());
+        let q = wave.steepness / k;
+
+        offset[0] += q * dx * c;
+        offset[1] += wave.amplitude * s;
+        offset[2] += q * dz * c;
+
+        let ak = wave.amplitude * k;
+        dpdx[0] -= wave.steepness * dx * dx * s;
+        dpdx[1] += ak * dx * c;
+        dpdx[2] -= wave.steepness * dx * dz * s;
+        dpdz[0] -= wave.steepness * dx * dz * s;
+        dpdz[1] += ak * dz * c;
+        dpdz[2] -= wave.steepness * dz * dz * s;
+    }
+
+    let n = cross3(dpdz, dpdx);
+    let rlen = dot3(n, n).sqrt().recip();
+    (offset, n.map(|e| e * rlen))
+}
+
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+pub(crate) fn hash(x: i32, y: i32, z: i32, seed: u32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^= h >> 15;
+    h
+}
+
+fn dot3([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> f32 {
+    a0 * b0 + a1 * b1 + a2 * b2
+}
+
+fn cross3([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
+    [a1 * b2 - a2 * b1, a2 * b0 - a0 * b2, a0 * b1 - a1 * b0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo-random points spread over several lattice cells, including negative coordinates.
+    fn points(n: u32) -> impl Iterator<Item = [f32; 3]> {
+        (0..n).map(|i| {
+            [0, 1, 2].map(|j| hash(i as i32, j, 0, 42) as f32 / u32::MAX as f32 * 20.0 - 10.0)
+        })
+    }
+
+    fn central_difference(f: impl Fn([f32; 3]) -> f32, p: [f32; 3]) -> [f32; 3] {
+        const H: f32 = 1.0e-3;
+        core::array::from_fn(|j| {
+            let (mut lo, mut hi) = (p, p);
+            lo[j] -= H;
+            hi[j] += H;
+            (f(hi) - f(lo)) / (2.0 * H)
+        })
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tolerance: f32, at: [f32; 3]) {
+        assert!(
+            (0..3).all(|j| (a[j] - b[j]).abs() <= tolerance),
+            "{:?} != {:?} at {:?}",
+            a,
+            b,
+            at,
+        );
+    }
+
+    #[test]
+    fn noise_gradient_matches_central_difference() {
+        for p in points(2000) {
+            let (_, grad) = noise3_with_gradient(p, 7);
+            let approx = central_difference(|p| noise3_with_gradient(p, 7).0, p);
+            assert_close(grad, approx, 1.0e-2, p);
+        }
+    }
+
+    #[test]
+    fn fbm_accumulates_gradients() {
+        for p in points(500) {
+            // A single octave is just noise
+            assert_eq!(
+                fbm_with_gradient(p, 3, 1, 2.0, 0.5),
+                noise3_with_gradient(p, 3)
+            );
+
+            let (value, grad) = fbm_with_gradient(p, 3, 4, 2.0, 0.5);
+            let (expected_value, expected_grad) =
+                (0..4).fold((0.0, [0.0; 3]), |(value, grad), i: u32| {
+                    let (freq, amp) = (2.0f32.powi(i as i32), 0.5f32.powi(i as i32));
+                    let (v, g) = noise3_with_gradient(p.map(|e| e * freq), 3 + i);
+                    (
+                        value + v * amp,
+                        core::array::from_fn(|j| grad[j] + g[j] * amp * freq),
+                    )
+                });
+            assert!((value - expected_value).abs() < 1.0e-5);
+            assert_close(grad, expected_grad, 1.0e-4, p);
+
+            let approx = central_difference(|p| fbm_with_gradient(p, 3, 4, 2.0, 0.5).0, p);
+            assert_close(grad, approx, 5.0e-2, p);
+        }
+    }
+
+    #[test]
+    fn gerstner_normals_are_unit_and_match_surface() {
+        let waves = [
+            GerstnerParams {
+                direction: [1.0, 0.3],
+                wavelength: 7.0,
+                amplitude: 0.4,
+                steepness: 0.6,
+                speed: 1.5,
+            },
+            GerstnerParams {
+                direction: [-0.2, 1.0],
+                wavelength: 3.0,
+                amplitude: 0.15,
+                steepness: 0.4,
+                speed: 0.8,
+            },
+        ];
+        let surface = |[x, z]: [f32; 2]| {
+            let (offset, _) = gerstner_wave([x, z], 2.5, &waves);
+            [x + offset[0], offset[1], z + offset[2]]
+        };
+
+        for [x, _, z] in points(1000) {
+            let (_, normal) = gerstner_wave([x, z], 2.5, &waves);
+            assert!((dot3(normal, normal) - 1.0).abs() < 1.0e-5);
+            assert!(normal[1] > 0.0, "Normal should face upwards");
+
+            const H: f32 = 1.0e-3;
+            let d = |[dx, dz]: [f32; 2]| {
+                let (hi, lo) = (surface([x + dx, z + dz]), surface([x - dx, z - dz]));
+                [0, 1, 2].map(|j| (hi[j] - lo[j]) / (2.0 * H))
+            };
+            let n = cross3(d([0.0, H]), d([H, 0.0]));
+            let rlen = dot3(n, n).sqrt().recip();
+            assert_close(normal, n.map(|e| e * rlen), 1.0e-2, [x, 0.0, z]);
+        }
+    }
+}
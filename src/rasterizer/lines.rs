@@ -1,12 +1,39 @@
 use super::*;
-use crate::{CoordinateMode, YAxisDirection};
+use crate::CoordinateMode;
 
-/// A rasterizer that produces filled triangles.
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// A rasterizer that produces lines.
+///
+/// Lines are aliased by default, but may be anti-aliased instead (see [`LineMode`]).
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Lines;
 
 impl Rasterizer for Lines {
-    type Config = ();
+    type Config = LineMode;
+
+    const VERTICES_PER_PRIMITIVE: Option<usize> = Some(2);
+
+    #[inline]
+    fn screen_bounds(
+        &self,
+        verts_hom: &[[f32; 4]],
+        coords: &CoordinateMode,
+        tgt_size: [usize; 2],
+    ) -> Option<([usize; 2], [usize; 2])> {
+        let size = tgt_size.map(|e| e as f32);
+        let verts_screen = [verts_hom[0], verts_hom[1]].map(|v| {
+            let [a0, a1, _a2, a3] = to_clip(coords, tgt_size, v);
+            let w = a3.max(0.0001);
+            let [a0, a1] = [a0 / w, a1 / w];
+            [size[0] * (a0 * 0.5 + 0.5), size[1] * (a1 * -0.5 + 0.5)]
+        });
+        let (min, max) = bounds_clamped(verts_screen, [0.0; 2], size);
+        // Anti-aliased lines may cover the pixels just beyond the end of the line
+        let max = [0, 1].map(|i| (max[i] as usize + 1).min(tgt_size[i]));
+        Some((min.map(|e| e as usize), max))
+    }
 
     #[inline]
     unsafe fn rasterize<V, I, B>(
@@ -14,7 +41,7 @@ impl Rasterizer for Lines {
         mut vertices: I,
         _principal_x: bool,
         coords: CoordinateMode,
-        _config: (),
+        mode: LineMode,
         mut blitter: B,
     ) where
         V: Clone + WeightedSum,
@@ -25,11 +52,6 @@ impl Rasterizer for Lines {
         let tgt_min = blitter.target_min();
         let tgt_max = blitter.target_max();
 
-        let flip = match coords.y_axis_direction {
-            YAxisDirection::Down => [1.0f32, 1.0],
-            YAxisDirection::Up => [1.0f32, -1.0],
-        };
-
         let size = tgt_size.map(|e| e as f32);
 
         let verts_hom_out = core::iter::from_fn(move || Some([vertices.next()?, vertices.next()?]));
@@ -40,8 +62,9 @@ impl Rasterizer for Lines {
             // Calculate vertex shader outputs and vertex homogeneous coordinates
             let verts_hom = [verts_hom_out[0].0, verts_hom_out[1].0];
             let verts_out = verts_hom_out.map(|(_, v)| v);
+            blitter.inspect_primitive(&verts_hom);
 
-            let verts_hom = verts_hom.map(|[a0, a1, a2, a3]| [a0 * flip[0], a1 * flip[1], a2, a3]);
+            let verts_hom = verts_hom.map(|v| to_clip(&coords, tgt_size, v));
 
             // Convert homogenous to euclidean coordinates
             let verts_euc = verts_hom.map(|[a0, a1, a2, a3]| {
@@ -57,21 +80,32 @@ impl Rasterizer for Lines {
             let screen_min = tgt_min.map(|e| e as f32);
             let screen_max = tgt_max.map(|e| e as f32);
 
-            let [x1, y1] = [verts_screen[0][0] as isize, verts_screen[0][1] as isize];
-            let [x2, y2] = [verts_screen[1][0] as isize, verts_screen[1][1] as isize];
-
-            let [wx1, wy1] = [
-                (verts_screen[0][0].min(verts_screen[1][0]) + 0.)
-                    .clamp(screen_min[0], screen_max[0]) as isize,
-                (verts_screen[0][1].min(verts_screen[1][1]) + 0.)
-                    .clamp(screen_min[1], screen_max[1]) as isize,
-            ];
-            let [wx2, wy2] = [
-                (verts_screen[0][0].max(verts_screen[1][0]) + 1.)
-                    .clamp(screen_min[0], screen_max[0]) as isize,
-                (verts_screen[0][1].max(verts_screen[1][1]) + 1.)
-                    .clamp(screen_min[1], screen_max[1]) as isize,
-            ];
+            // Clip the line to just beyond the target, such that distant vertices can't overflow integer coordinates
+            let Some(verts_clipped) = clip_segment(
+                verts_screen,
+                screen_min.map(|e| e - 1.0),
+                screen_max.map(|e| e + 1.0),
+            ) else {
+                blitter.primitive_culled(CullReason::OffScreen);
+                return;
+            };
+
+            // Skip lines that lie entirely beyond the same z clip plane (see `Triangles`)
+            if let Some(clip) = &coords.z_clip_range {
+                let [near, far] = [clip.start.min(clip.end), clip.start.max(clip.end)];
+                if verts_hom.iter().all(|v| v[3] > 0.0)
+                    && (verts_euc.iter().all(|v| v[2] < near)
+                        || verts_euc.iter().all(|v| v[2] > far))
+                {
+                    blitter.primitive_culled(CullReason::ZClip);
+                    return;
+                }
+            }
+
+            let [x1, y1] = [verts_clipped[0][0] as isize, verts_clipped[0][1] as isize];
+            let [x2, y2] = [verts_clipped[1][0] as isize, verts_clipped[1][1] as isize];
+
+            let ([wx1, wy1], [wx2, wy2]) = bounds_clamped(verts_screen, screen_min, screen_max);
 
             let use_x = (x1 - x2).abs() > (y1 - y2).abs();
             let norm = 1.0
@@ -81,41 +115,140 @@ impl Rasterizer for Lines {
                     verts_screen[1][1] - verts_screen[0][1]
                 };
 
-            clipline::clipline(
-                ((x1, y1), (x2, y2)),
-                ((wx1, wy1), (wx2 - 1, wy2 - 1)),
-                |x, y| {
-                    let (x, y) = (x as usize, y as usize);
-
-                    let frac = if use_x {
-                        x as f32 - verts_screen[0][0]
-                    } else {
-                        y as f32 - verts_screen[0][1]
-                    } * norm;
-
-                    // Calculate the interpolated z coordinate for the depth target
-                    let z = verts_euc[0][2] + frac * (verts_euc[1][2] - verts_euc[0][2]);
-
-                    if coords.passes_z_clip(z) && blitter.test_fragment(x, y, z) {
-                        let get_v_data = |x: f32, y: f32| {
-                            let frac = if use_x {
-                                x - verts_screen[0][0]
-                            } else {
-                                y - verts_screen[0][1]
-                            } * norm;
-
-                            V::weighted_sum2(
-                                verts_out[0].clone(),
-                                verts_out[1].clone(),
-                                1.0 - frac,
-                                frac,
-                            )
-                        };
-
-                        blitter.emit_fragment(x, y, get_v_data, z);
+            let get_v_data = |x: f32, y: f32| {
+                let frac = if use_x {
+                    x - verts_screen[0][0]
+                } else {
+                    y - verts_screen[0][1]
+                } * norm;
+
+                (
+                    [1.0 - frac, frac, 0.0],
+                    // Lines are interpolated linearly in screen space
+                    V::weighted_sum_linear(
+                        [verts_out[0].clone(), verts_out[1].clone()],
+                        [1.0 - frac, frac],
+                        [1.0 - frac, frac],
+                    ),
+                )
+            };
+
+            if mode == LineMode::AntiAliased {
+                // Iterate along the principal axis of the line, covering the two pixels either side of it on the
+                // other axis in proportion to their distance from it
+                let [major, minor] = if use_x { [0, 1] } else { [1, 0] };
+                let [start, end] = if verts_clipped[0][major] <= verts_clipped[1][major] {
+                    [verts_clipped[0], verts_clipped[1]]
+                } else {
+                    [verts_clipped[1], verts_clipped[0]]
+                };
+                let slope = (end[minor] - start[minor]) / (end[major] - start[major]);
+                let i_min = (start[major].round() as isize).max(tgt_min[major] as isize);
+                let i_max = (end[major].round() as isize).min(tgt_max[major] as isize - 1);
+
+                for i in i_min..=i_max {
+                    // Pixels at either end of the line are only partially covered along the principal axis
+                    let i_cov = ((i as f32 + 0.5).min(end[major])
+                        - (i as f32 - 0.5).max(start[major]))
+                    .min(1.0);
+                    let m = start[minor] + slope * (i as f32 - start[major]);
+                    let j = m.floor();
+
+                    for (j, cov) in [(j, 1.0 - (m - j)), (j + 1.0, m - j)] {
+                        let coverage = i_cov * cov;
+                        // Written so that degenerate lines, with NaN coordinates, are skipped
+                        let in_bounds = (tgt_min[minor] as f32..tgt_max[minor] as f32).contains(&j);
+                        if !(in_bounds && coverage > 0.0) {
+                            continue;
+                        }
+
+                        let mut pos = [0; 2];
+                        pos[major] = i as usize;
+                        pos[minor] = j as usize;
+                        let [x, y] = pos;
+
+                        let frac = (i as f32 - verts_screen[0][major]) * norm;
+                        let z = verts_euc[0][2] + frac * (verts_euc[1][2] - verts_euc[0][2]);
+
+                        if coords.passes_z_clip(z) && blitter.test_fragment(x, y, z) {
+                            blitter.emit_fragment_with_coverage(x, y, get_v_data, z, coverage);
+                        }
                     }
-                },
-            );
+                }
+            } else {
+                clipline::clipline(
+                    ((x1, y1), (x2, y2)),
+                    ((wx1, wy1), (wx2 - 1, wy2 - 1)),
+                    |x, y| {
+                        let (x, y) = (x as usize, y as usize);
+
+                        let frac = if use_x {
+                            x as f32 - verts_screen[0][0]
+                        } else {
+                            y as f32 - verts_screen[0][1]
+                        } * norm;
+
+                        // Calculate the interpolated z coordinate for the depth target
+                        let z = verts_euc[0][2] + frac * (verts_euc[1][2] - verts_euc[0][2]);
+
+                        if coords.passes_z_clip(z) && blitter.test_fragment(x, y, z) {
+                            blitter.emit_fragment(x, y, get_v_data, z);
+                        }
+                    },
+                );
+            }
         });
     }
 }
+
+/// Clip a line in screen space to the given bounds (using the Liang-Barsky algorithm), or return `None` if no part of it
+/// lies within them or it has non-finite coordinates.
+fn clip_segment([p, q]: [[f32; 2]; 2], min: [f32; 2], max: [f32; 2]) -> Option<[[f32; 2]; 2]> {
+    let d = [q[0] - p[0], q[1] - p[1]];
+    if !p.iter().chain(&q).chain(&d).all(|e| e.is_finite()) {
+        return None;
+    }
+    let [mut t0, mut t1] = [0.0f32, 1.0f32];
+    for i in 0..2 {
+        // The distance of the start of the line within each bound, and the rate at which the line approaches it
+        for (dist, rate) in [(p[i] - min[i], -d[i]), (max[i] - p[i], d[i])] {
+            if rate == 0.0 {
+                if dist < 0.0 {
+                    return None;
+                }
+            } else if rate > 0.0 {
+                t1 = t1.min(dist / rate);
+            } else {
+                t0 = t0.max(dist / rate);
+            }
+        }
+    }
+    // Unclipped ends are returned exactly
+    let at = |t: f32| match t {
+        0.0 => p,
+        1.0 => q,
+        t => [p[0] + d[0] * t, p[1] + d[1] * t],
+    };
+    (t0 <= t1).then(|| [at(t0), at(t1)])
+}
+
+/// Calculate the bounding box of a line in screen space, clamped to the given bounds.
+fn bounds_clamped(
+    verts_screen: [[f32; 2]; 2],
+    screen_min: [f32; 2],
+    screen_max: [f32; 2],
+) -> ([isize; 2], [isize; 2]) {
+    let min = [
+        (verts_screen[0][0].min(verts_screen[1][0]) + 0.).clamp(screen_min[0], screen_max[0])
+            as isize,
+        (verts_screen[0][1].min(verts_screen[1][1]) + 0.).clamp(screen_min[1], screen_max[1])
+            as isize,
+    ];
+    let max = [
+        (verts_screen[0][0].max(verts_screen[1][0]) + 1.).clamp(screen_min[0], screen_max[0])
+            as isize,
+        (verts_screen[0][1].max(verts_screen[1][1]) + 1.).clamp(screen_min[1], screen_max[1])
+            as isize,
+    ];
+    (min, max)
+}
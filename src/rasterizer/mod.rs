@@ -1,22 +1,53 @@
 pub mod lines;
+pub mod points;
+pub mod quads;
 pub mod triangles;
 
-pub use self::{lines::Lines, triangles::Triangles};
+pub use self::{lines::Lines, points::Points, quads::Quads, triangles::Triangles};
 
-use crate::{math::WeightedSum, CoordinateMode};
+use crate::{
+    debug::CullReason, math::WeightedSum, pipeline::DepthBias, CoordinateMode, YAxisDirection,
+};
 
 /// The face culling strategy used during rendering.
-#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+///
+/// Triangles face towards the camera when their vertices appear in clockwise order on the screen in a left-handed
+/// coordinate space, or in counter-clockwise order in a right-handed coordinate space (see [`CoordinateMode`]).
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
 pub enum CullMode {
     /// Do not cull triangles regardless of their winding order
     None,
-    /// Cull clockwise triangles
+    /// Cull triangles facing away from the camera
     #[default]
     Back,
-    /// Cull counter-clockwise triangles
+    /// Cull triangles facing towards the camera
     Front,
+    /// Cull triangles that cover less than the given area on the screen (in square pixels), whichever way they face.
+    ///
+    /// This can be used to cull degenerate and sliver triangles, which cover few (if any) fragments and are prone to
+    /// aliasing. For example, `CullMode::MinArea(0.5)` culls triangles that cover less than half a pixel. The area is
+    /// that of the whole triangle, even when only part of it is rasterized (see [`Triangles`]), and triangles with
+    /// vertices behind the camera are never culled by their area.
+    MinArea(f32),
+}
+
+/// The line drawing strategy used during rendering.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LineMode {
+    /// Draw lines one pixel thick, with each pixel either fully covered or not covered at all
+    #[default]
+    Aliased,
+    /// Draw anti-aliased lines (using Xiaolin Wu's algorithm), where the pixels on either side of the line are
+    /// partially covered (see [`Pipeline::blend_coverage`](crate::Pipeline::blend_coverage)).
+    ///
+    /// The pixels at the ends of each line are only covered up to the end of the line, so lines that share a vertex
+    /// don't cover the pixel where they meet more than once.
+    AntiAliased,
 }
 
+/// The maximum number of samples per fragment that a [`Blitter`] may request (see [`Blitter::sample_offsets`]).
+pub const MAX_SAMPLES: usize = 8;
+
 /// A trait for types that define an interface for blitting fragments to surfaces
 #[doc(hidden)]
 pub trait Blitter<V>: Sized {
@@ -24,9 +55,53 @@ pub trait Blitter<V>: Sized {
     fn target_min(&self) -> [usize; 2];
     fn target_max(&self) -> [usize; 2];
 
+    /// The offset that should be applied to the depth of fragments, if supported by the rasterizer.
+    fn depth_bias(&self) -> DepthBias {
+        DepthBias::NONE
+    }
+
+    /// The positions at which rasterizers that support multisampling should evaluate the coverage of each fragment,
+    /// relative to the position of the fragment.
+    ///
+    /// There must be between 1 and [`MAX_SAMPLES`] offsets, each no more than half a pixel from the fragment position.
+    fn sample_offsets(&self) -> &[[f32; 2]] {
+        &[[0.0; 2]]
+    }
+
+    /// Whether rasterizers that support multisampling should evaluate the vertex data of partially covered fragments
+    /// at the centroid of their covered samples, rather than at the position of the fragment.
+    ///
+    /// By default, this returns `false`.
+    fn centroid_sampling(&self) -> bool {
+        false
+    }
+
+    /// Test whether any fragment within the given region (`min` inclusive, `max` exclusive), with a depth in the range
+    /// `z`, could pass the depth test.
+    ///
+    /// Rasterizers may skip primitives for which this returns `false`. By default, this always returns `true`.
+    fn test_region(&self, min: [usize; 2], max: [usize; 2], z: [f32; 2]) -> bool {
+        let _ = (min, max, z);
+        true
+    }
+
     // Indicate to the blitter that a new primitive is now being rasterized.
     fn begin_primitive(&mut self);
 
+    /// Inspect the clip-space positions of the vertices of the primitive being rasterized, for debugging.
+    ///
+    /// Rasterizers call this after [`Blitter::begin_primitive`]. By default, this does nothing.
+    fn inspect_primitive(&mut self, verts_hom: &[[f32; 4]]) {
+        let _ = verts_hom;
+    }
+
+    /// Indicate to the blitter that the primitive being rasterized (or part of it) was culled, for debugging.
+    ///
+    /// By default, this does nothing.
+    fn primitive_culled(&mut self, reason: CullReason) {
+        let _ = reason;
+    }
+
     /// Test whether a fragment should be emitted with the given attributes.
     ///
     /// # Safety
@@ -34,18 +109,56 @@ pub trait Blitter<V>: Sized {
     /// This function *must* be called with a position that is valid for size and bounds that this type provides.
     unsafe fn test_fragment(&mut self, x: usize, y: usize, z: f32) -> bool;
 
+    /// Test whether a fragment should be emitted, given which of its samples are covered by the primitive.
+    ///
+    /// Bit `i` of `coverage` is set if the `i`th sample (see [`Blitter::sample_offsets`]) is covered, and `z` holds the
+    /// depth at each sample. By default, this tests the first sample with [`Blitter::test_fragment`].
+    ///
+    /// # Safety
+    ///
+    /// This function *must* be called with a position that is valid for size and bounds that this type provides.
+    unsafe fn test_samples(&mut self, x: usize, y: usize, coverage: u32, z: &[f32]) -> bool {
+        coverage & 1 != 0 && self.test_fragment(x, y, z[0])
+    }
+
     /// Emit a fragment with the given attributes.
     ///
+    /// This must only be called after [`Blitter::test_fragment`] or [`Blitter::test_samples`] has passed for the same
+    /// position, and the fragment is written to the samples that passed.
+    ///
+    /// `get_v_data` gives the barycentric weights of the vertices of the primitive at a position (see
+    /// [`Pipeline::fragment_bary`](crate::Pipeline::fragment_bary)), along with the vertex data interpolated with them.
+    ///
     /// # Safety
     ///
     /// This function *must* be called with a position that is valid for size and bounds that this type provides.
-    unsafe fn emit_fragment<F: FnMut(f32, f32) -> V>(
+    unsafe fn emit_fragment<F: FnMut(f32, f32) -> ([f32; 3], V)>(
         &mut self,
         x: usize,
         y: usize,
         get_v_data: F,
         z: f32,
     );
+
+    /// Like [`Blitter::emit_fragment`], but for a fragment that only partially covers its pixel.
+    ///
+    /// `coverage` is the proportion of the pixel that is covered, between `0.0` and `1.0`. By default, this ignores the
+    /// coverage.
+    ///
+    /// # Safety
+    ///
+    /// This function *must* be called with a position that is valid for size and bounds that this type provides.
+    unsafe fn emit_fragment_with_coverage<F: FnMut(f32, f32) -> ([f32; 3], V)>(
+        &mut self,
+        x: usize,
+        y: usize,
+        get_v_data: F,
+        z: f32,
+        coverage: f32,
+    ) {
+        let _ = coverage;
+        self.emit_fragment(x, y, get_v_data, z)
+    }
 }
 
 /// A trait that represents types that turn vertex streams into fragment coordinates.
@@ -55,6 +168,30 @@ pub trait Blitter<V>: Sized {
 pub trait Rasterizer: Default {
     type Config: Default + Send + Sync;
 
+    /// The number of vertices that make up each primitive consumed by this rasterizer, if every primitive has the same
+    /// number of vertices.
+    ///
+    /// When this is `None` (the default), primitives cannot be divided between workers ahead of rasterization and the
+    /// entire vertex stream is given to every worker instead.
+    const VERTICES_PER_PRIMITIVE: Option<usize> = None;
+
+    /// Determine the screen-space bounding box (`min` inclusive, `max` exclusive) of the fragments that the given
+    /// primitive may produce, clamped to the target.
+    ///
+    /// This is used to divide primitives between workers ahead of rasterization. The bounds must be conservative: no
+    /// fragment may be emitted outside of them. Returning `None` is always valid, and indicates that the primitive may
+    /// cover any part of the target.
+    #[inline]
+    fn screen_bounds(
+        &self,
+        verts_hom: &[[f32; 4]],
+        coordinate_mode: &CoordinateMode,
+        target_size: [usize; 2],
+    ) -> Option<([usize; 2], [usize; 2])> {
+        let _ = (verts_hom, coordinate_mode, target_size);
+        None
+    }
+
     /// Rasterize the given vertices into fragments.
     ///
     /// - `target_size`: The size of the render target(s) in pixels
@@ -80,3 +217,25 @@ pub trait Rasterizer: Default {
         I: Iterator<Item = ([f32; 4], V)>,
         B: Blitter<V>;
 }
+
+/// Map the position of a vertex onto the homogeneous coordinates used by rasterizers, in which the x and y coordinates
+/// (after perspective division) run from -1 to 1 and +y points towards the first row of the target.
+///
+/// This flips y according to the [`YAxisDirection`], or maps pixel coordinates onto this range when
+/// [`CoordinateMode::pixel_coordinates`] is enabled.
+#[inline]
+pub(crate) fn to_clip(
+    coords: &CoordinateMode,
+    tgt_size: [usize; 2],
+    [a0, a1, a2, a3]: [f32; 4],
+) -> [f32; 4] {
+    if coords.pixel_coordinates {
+        let [size_x, size_y] = tgt_size.map(|e| e as f32);
+        [a0 * (2.0 / size_x) - a3, a3 - a1 * (2.0 / size_y), a2, a3]
+    } else {
+        match coords.y_axis_direction {
+            YAxisDirection::Down => [a0, a1, a2, a3],
+            YAxisDirection::Up => [a0, -a1, a2, a3],
+        }
+    }
+}
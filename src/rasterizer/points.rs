@@ -0,0 +1,90 @@
+use super::*;
+use crate::CoordinateMode;
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// A rasterizer that produces a single fragment for each point, at the pixel that contains it.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Points;
+
+impl Rasterizer for Points {
+    type Config = ();
+
+    const VERTICES_PER_PRIMITIVE: Option<usize> = Some(1);
+
+    #[inline]
+    fn screen_bounds(
+        &self,
+        verts_hom: &[[f32; 4]],
+        coords: &CoordinateMode,
+        tgt_size: [usize; 2],
+    ) -> Option<([usize; 2], [usize; 2])> {
+        let size = tgt_size.map(|e| e as f32);
+        let [a0, a1, _a2, a3] = to_clip(coords, tgt_size, verts_hom[0]);
+        let w = a3.max(0.0001);
+        let pos = [
+            size[0] * (a0 / w * 0.5 + 0.5),
+            size[1] * (a1 / w * -0.5 + 0.5),
+        ];
+        // The pixel containing the point (NaN coordinates clamp to the first pixel, and are skipped during rasterization)
+        let min = [0, 1].map(|i| pos[i].floor().clamp(0.0, size[i]) as usize);
+        let max = [0, 1].map(|i| (min[i] + 1).min(tgt_size[i]));
+        Some((min, max))
+    }
+
+    #[inline]
+    unsafe fn rasterize<V, I, B>(
+        &self,
+        vertices: I,
+        _principal_x: bool,
+        coords: CoordinateMode,
+        _config: (),
+        mut blitter: B,
+    ) where
+        V: Clone + WeightedSum,
+        I: Iterator<Item = ([f32; 4], V)>,
+        B: Blitter<V>,
+    {
+        let tgt_size = blitter.target_size();
+        let tgt_min = blitter.target_min();
+        let tgt_max = blitter.target_max();
+
+        let size = tgt_size.map(|e| e as f32);
+
+        vertices.for_each(|([a0, a1, a2, a3], v)| {
+            blitter.begin_primitive();
+            blitter.inspect_primitive(&[[a0, a1, a2, a3]]);
+
+            // Points behind the camera are never visible
+            if a3 <= 0.0 {
+                blitter.primitive_culled(CullReason::OffScreen);
+                return;
+            }
+
+            let [a0, a1, a2, a3] = to_clip(&coords, tgt_size, [a0, a1, a2, a3]);
+            let [x, y, z] = [a0 / a3, a1 / a3, a2 / a3];
+            let [x, y] = [
+                (size[0] * (x * 0.5 + 0.5)).floor(),
+                (size[1] * (y * -0.5 + 0.5)).floor(),
+            ];
+
+            // Written so that degenerate points, with NaN coordinates, are skipped
+            let in_bounds = (tgt_min[0] as f32..tgt_max[0] as f32).contains(&x)
+                && (tgt_min[1] as f32..tgt_max[1] as f32).contains(&y);
+            if !in_bounds {
+                blitter.primitive_culled(CullReason::OffScreen);
+                return;
+            }
+            let [x, y] = [x as usize, y as usize];
+
+            if !coords.passes_z_clip(z) {
+                blitter.primitive_culled(CullReason::ZClip);
+                return;
+            }
+            if blitter.test_fragment(x, y, z) {
+                blitter.emit_fragment(x, y, |_, _| ([1.0, 0.0, 0.0], v.clone()), z);
+            }
+        });
+    }
+}
@@ -0,0 +1,49 @@
+use super::*;
+
+/// A rasterizer that produces filled quads.
+///
+/// Each quad is drawn as two triangles, `0 1 2` and `0 2 3`, which are rasterized exactly as [`Triangles`] would
+/// rasterize them (so the quad should be planar and convex), but which only count as a single primitive.
+///
+/// This is not a faster way to rasterize the quad: each of its triangles is set up (culled, bounded, and given its own
+/// edges, including the shared diagonal) separately, just as it would be in a triangle list. Using quads only saves
+/// fetching and shading the two vertices that the triangles share.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Quads;
+
+impl Rasterizer for Quads {
+    type Config = CullMode;
+
+    const VERTICES_PER_PRIMITIVE: Option<usize> = Some(4);
+
+    #[inline]
+    fn screen_bounds(
+        &self,
+        verts_hom: &[[f32; 4]],
+        coords: &CoordinateMode,
+        tgt_size: [usize; 2],
+    ) -> Option<([usize; 2], [usize; 2])> {
+        // The quad is convex, so it covers exactly the area of its outline
+        Some(triangles::polygon_screen_bounds(
+            [verts_hom[0], verts_hom[1], verts_hom[2], verts_hom[3]],
+            coords,
+            tgt_size,
+        ))
+    }
+
+    #[inline]
+    unsafe fn rasterize<V, I, B>(
+        &self,
+        vertices: I,
+        principal_x: bool,
+        coords: CoordinateMode,
+        cull_mode: CullMode,
+        blitter: B,
+    ) where
+        V: Clone + WeightedSum,
+        I: Iterator<Item = ([f32; 4], V)>,
+        B: Blitter<V>,
+    {
+        Triangles.rasterize_fans::<_, _, _, 4>(vertices, principal_x, coords, cull_mode, blitter)
+    }
+}
@@ -1,21 +1,69 @@
 use super::*;
-use crate::{CoordinateMode, YAxisDirection};
+use crate::{
+    math::{add, cross, dot, lerp, magnitude_squared, mat3_mul_vec3, matmul, sub, Unit},
+    CoordinateMode, Handedness,
+};
+use alloc::vec::Vec;
 
 #[cfg(feature = "micromath")]
 use micromath::F32Ext;
 
 /// A rasterizer that produces filled triangles.
+///
+/// Fragments that lie exactly on the edge of a triangle are only covered if it is a top or left edge, so triangles that
+/// share an edge never both cover the fragments along it.
+///
+/// Triangles that extend far beyond the target are clipped to a guard band around it before being rasterized.
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Triangles;
 
 impl Rasterizer for Triangles {
     type Config = CullMode;
 
+    const VERTICES_PER_PRIMITIVE: Option<usize> = Some(3);
+
+    #[inline]
+    fn screen_bounds(
+        &self,
+        verts_hom: &[[f32; 4]],
+        coords: &CoordinateMode,
+        tgt_size: [usize; 2],
+    ) -> Option<([usize; 2], [usize; 2])> {
+        Some(polygon_screen_bounds(
+            [verts_hom[0], verts_hom[1], verts_hom[2]],
+            coords,
+            tgt_size,
+        ))
+    }
+
     #[inline]
     unsafe fn rasterize<V, I, B>(
+        &self,
+        vertices: I,
+        principal_x: bool,
+        coords: CoordinateMode,
+        cull_mode: CullMode,
+        blitter: B,
+    ) where
+        V: Clone + WeightedSum,
+        I: Iterator<Item = ([f32; 4], V)>,
+        B: Blitter<V>,
+    {
+        self.rasterize_fans::<_, _, _, 3>(vertices, principal_x, coords, cull_mode, blitter)
+    }
+}
+
+impl Triangles {
+    /// Rasterize primitives made up of `N` vertices each (such as triangles, or quads), drawing each one as a fan of
+    /// triangles around its first vertex.
+    ///
+    /// The triangles of each primitive are rasterized exactly as they would be if they were given to this rasterizer
+    /// individually, but they only count as a single primitive.
+    #[inline]
+    pub(super) unsafe fn rasterize_fans<V, I, B, const N: usize>(
         &self,
         mut vertices: I,
-        _principal_x: bool,
+        principal_x: bool,
         coords: CoordinateMode,
         cull_mode: CullMode,
         mut blitter: B,
@@ -28,18 +76,32 @@ impl Rasterizer for Triangles {
         let tgt_min = blitter.target_min();
         let tgt_max = blitter.target_max();
 
-        let cull_dir = match cull_mode {
-            CullMode::None => None,
-            CullMode::Back => Some(1.0),
-            CullMode::Front => Some(-1.0),
+        // Winding is determined after flipping y such that it points down the screen, so only the handedness of the
+        // coordinate space decides which winding faces the camera
+        let front_dir = match coords.handedness {
+            Handedness::Left => 1.0,
+            Handedness::Right => -1.0,
         };
+        let [size_x, size_y] = tgt_size.map(|e| e as f32);
 
-        let flip = match coords.y_axis_direction {
-            YAxisDirection::Down => [1.0f32, 1.0],
-            YAxisDirection::Up => [1.0f32, -1.0],
+        // The winding is the cross product of two edges in normalised device coordinates, which span 2 units on each
+        // axis, and the area of the triangle is half of that
+        let winding_to_area = size_x * size_y / 8.0;
+        let culled = |winding: f32| match cull_mode {
+            CullMode::None | CullMode::MinArea(_) => false,
+            CullMode::Back => winding * front_dir < 0.0,
+            CullMode::Front => winding * front_dir > 0.0,
         };
 
-        let [size_x, size_y] = tgt_size.map(|e| e as f32);
+        // Copy the sample offsets so that the blitter is free to be mutated during rasterization
+        let mut sample_offsets = [[0.0; 2]; MAX_SAMPLES];
+        let samples = {
+            let offsets = blitter.sample_offsets();
+            sample_offsets[..offsets.len()].copy_from_slice(offsets);
+            offsets.len()
+        };
+        let sample_offsets = &sample_offsets[..samples];
+        let sample_margin = if samples > 1 { 0.5 } else { 0.0 };
 
         let to_ndc = [
             [2.0 / size_x, 0.0, -1.0],
@@ -47,19 +109,20 @@ impl Rasterizer for Triangles {
             [0.0, 0.0, 1.0],
         ];
 
-        let verts_hom_out = core::iter::from_fn(move || {
-            Some([vertices.next()?, vertices.next()?, vertices.next()?])
+        let primitives = core::iter::from_fn(move || {
+            let verts: [_; N] = core::array::from_fn(|_| vertices.next());
+            if verts.iter().all(Option::is_some) {
+                Some(verts.map(Option::unwrap))
+            } else {
+                None
+            }
         });
 
-        verts_hom_out.for_each(|verts_hom_out: [([f32; 4], V); 3]| {
-            blitter.begin_primitive();
-
+        let draw = |blitter: &mut B, verts_hom_out: [([f32; 4], WithBary<V>); 3]| {
             // Calculate vertex shader outputs and vertex homogeneous coordinates
             let verts_hom = [verts_hom_out[0].0, verts_hom_out[1].0, verts_hom_out[2].0];
             let verts_out = verts_hom_out.map(|(_, v)| v);
 
-            let verts_hom = verts_hom.map(|[a0, a1, a2, a3]| [a0 * flip[0], a1 * flip[1], a2, a3]);
-
             // Convert homogenous to euclidean coordinates
             let verts_euc = verts_hom.map(|[a0, a1, a2, a3]| [a0 / a3, a1 / a3, a2 / a3]);
 
@@ -69,19 +132,58 @@ impl Rasterizer for Triangles {
                 sub(verts_euc[2], verts_euc[0]),
             )[2];
 
-            // Culling and correcting for winding
-            let (verts_hom, verts_euc, verts_out) = if cull_dir
-                .map(|cull_dir| winding * cull_dir < 0.0)
-                .unwrap_or(false)
-            {
+            // Culling and correcting for winding. The vertex data is kept in its original order, so that its provoking
+            // vertex (the first) is the same whichever way the triangle faces: `order` tracks where each vertex went.
+            let (verts_hom, verts_euc, order) = if culled(winding) {
+                blitter.primitive_culled(CullReason::Facing);
                 return; // Cull the triangle
             } else if winding >= 0.0 {
                 // Reverse vertex order
-                (rev(verts_hom), rev(verts_euc), rev(verts_out))
+                (rev(verts_hom), rev(verts_euc), rev([0, 1, 2]))
             } else {
-                (verts_hom, verts_euc, verts_out)
+                (verts_hom, verts_euc, [0, 1, 2])
             };
 
+            // Skip triangles that lie entirely beyond the same z clip plane. When every vertex is in front of the
+            // camera, the depth of each fragment lies within the range of the depths of the vertices, so none of them
+            // would pass the z clip test. NaN depths are never skipped here, and get handled per-fragment instead.
+            if let Some(clip) = &coords.z_clip_range {
+                let [near, far] = [clip.start.min(clip.end), clip.start.max(clip.end)];
+                if verts_hom.iter().all(|v| v[3] > 0.0)
+                    && (verts_euc.iter().all(|v| v[2] < near)
+                        || verts_euc.iter().all(|v| v[2] > far))
+                {
+                    blitter.primitive_culled(CullReason::ZClip);
+                    return;
+                }
+            }
+
+            // Convert vertex coordinates to screen space
+            let verts_screen = verts_euc
+                .map(|[a0, a1, _a2]| [size_x * (a0 * 0.5 + 0.5), size_y * (a1 * -0.5 + 0.5)]);
+
+            // Calculate the triangle bounds as a bounding box, skipping triangles that cover none of the target before
+            // doing any more work
+            let (bounds_clamped_min, bounds_clamped_max) =
+                bounds_clamped(&verts_screen, tgt_min, tgt_max, sample_margin);
+            if (0..2).any(|i| bounds_clamped_min[i] >= bounds_clamped_max[i]) {
+                blitter.primitive_culled(CullReason::OffScreen);
+                return;
+            }
+
+            // The weight of the last vertex is found by subtracting the others from their sum, so make it the vertex
+            // nearest to the camera (which has the largest weight) to minimise the loss of precision. Rotating the
+            // vertices preserves their winding order.
+            let nearest = (0..3)
+                .min_by(|&i, &j| verts_hom[i][3].total_cmp(&verts_hom[j][3]))
+                .unwrap_or(0);
+            let (verts_hom, verts_euc, verts_screen, order) = (
+                rotate(verts_hom, nearest + 1),
+                rotate(verts_euc, nearest + 1),
+                rotate(verts_screen, nearest + 1),
+                rotate(order, nearest + 1),
+            );
+
             // Create a matrix that allows conversion between screen coordinates and interpolation weights
             let coords_to_weights = {
                 let [a, b, c] = [verts_hom[0], verts_hom[1], verts_hom[2]];
@@ -89,8 +191,12 @@ impl Rasterizer for Triangles {
                 let ca = sub([a[0], a[1], a[3]], c);
                 let cb = sub([b[0], b[1], b[3]], c);
                 let n = cross(ca, cb);
-                let rec_det = if magnitude_squared(n) > 0.0 {
-                    1.0 / dot(n, c).min(-core::f32::EPSILON)
+                let rec_det = if verts_hom.iter().all(|v| v[3] == 1.0) {
+                    // Without perspective, the determinant is exactly (twice) the area of the triangle, so it doesn't
+                    // need to be clamped: triangles with no area produce infinities, and get skipped below
+                    1.0 / dot(n, c)
+                } else if magnitude_squared(n) > 0.0 {
+                    1.0 / dot(n, c).min(-f32::EPSILON)
                 } else {
                     1.0
                 };
@@ -101,42 +207,24 @@ impl Rasterizer for Triangles {
                 )
             };
 
-            // Ensure we didn't accidentally end up with infinities or NaNs
-            debug_assert!(coords_to_weights
+            // Degenerate triangles (or those with non-finite vertices) produce infinities or NaNs, and cover nothing
+            if !coords_to_weights
                 .iter()
-                .all(|v| v.iter().all(|e| e.is_finite())));
-
-            // Convert vertex coordinates to screen space
-            let verts_screen = verts_euc
-                .map(|[a0, a1, _a2]| [size_x * (a0 * 0.5 + 0.5), size_y * (a1 * -0.5 + 0.5)]);
+                .all(|v| v.iter().all(|e| e.is_finite()))
+            {
+                blitter.primitive_culled(CullReason::ZeroArea);
+                return;
+            }
 
-            // Calculate the triangle bounds as a bounding box
-            let screen_min = tgt_min.map(|e| e as usize);
-            let screen_max = tgt_max.map(|e| e as usize);
-            let bounds_clamped_min = [
-                ((verts_screen[0][0]
-                    .min(verts_screen[1][0])
-                    .min(verts_screen[2][0])
-                    + 0.) as usize)
-                    .clamp(screen_min[0], screen_max[0]),
-                ((verts_screen[0][1]
-                    .min(verts_screen[1][1])
-                    .min(verts_screen[2][1])
-                    + 0.) as usize)
-                    .clamp(screen_min[1], screen_max[1]),
-            ];
-            let bounds_clamped_max = [
-                ((verts_screen[0][0]
-                    .max(verts_screen[1][0])
-                    .max(verts_screen[2][0])
-                    + 1.) as usize)
-                    .clamp(screen_min[0], screen_max[0]),
-                ((verts_screen[0][1]
-                    .max(verts_screen[1][1])
-                    .max(verts_screen[2][1])
-                    + 1.) as usize)
-                    .clamp(screen_min[1], screen_max[1]),
-            ];
+            // Fragments that lie exactly on an edge shared by two triangles should only be covered by one of them. The
+            // triangles have the same winding order, so the edge runs in opposite directions for each of them: only
+            // cover fragments on the edges (opposite each vertex) that run up the screen, or along it to the right.
+            // After correcting the winding order, these are the left and top edges of the triangle respectively (the
+            // 'top-left' convention).
+            let edges = [0, 1, 2].map(|i| {
+                let [p, q] = [verts_screen[(i + 1) % 3], verts_screen[(i + 2) % 3]];
+                Edge::new(p, q, q[1] < p[1] || (q[1] == p[1] && q[0] > p[0]))
+            });
 
             // Calculate change in vertex weights for each pixel
             let weights_at = |[p0, p1]: [f32; 2]| mat3_mul_vec3(coords_to_weights, [p0, p1, 1.0]);
@@ -144,30 +232,65 @@ impl Rasterizer for Triangles {
             let w_hom_dx = sub(weights_at([1000.0, 0.]), w_hom_origin).map(|e| e * (1.0 / 1000.0));
             let w_hom_dy = sub(weights_at([0., 1000.0]), w_hom_origin).map(|e| e * (1.0 / 1000.0));
 
-            // First, order vertices by height
-            let min_y = {
-                let y = verts_screen.map(|v| v[1]);
-                y[0].min(y[1]).min(y[2])
+            // Depth is affine in screen space, so its slope (and hence the depth bias) is constant across the triangle
+            let z_bias = {
+                let bias = blitter.depth_bias();
+                let z_at =
+                    |[w0, w1, w2]: [f32; 3]| dot(verts_hom.map(|v| v[2]), [w0, w1, w2 - w0 - w1]);
+                let slope = z_at(w_hom_dx).abs().max(z_at(w_hom_dy).abs());
+                bias.constant + bias.slope_scale * slope
             };
-            let verts_by_y = if verts_screen[0][1] == min_y {
-                if verts_screen[1][1] < verts_screen[2][1] {
-                    [verts_screen[0], verts_screen[1], verts_screen[2]]
-                } else {
-                    [verts_screen[0], verts_screen[2], verts_screen[1]]
-                }
-            } else if verts_screen[1][1] == min_y {
-                if verts_screen[0][1] < verts_screen[2][1] {
-                    [verts_screen[1], verts_screen[0], verts_screen[2]]
-                } else {
-                    [verts_screen[1], verts_screen[2], verts_screen[0]]
+
+            // Skip triangles that are entirely hidden. When every vertex is in front of the camera, the depth of each
+            // fragment lies within the range of the depths of the vertices (give or take some rounding error).
+            if verts_hom.iter().all(|v| v[3] > 0.0) {
+                let z = verts_euc.map(|v| v[2] + z_bias);
+                let [z_min, z_max] = [z[0].min(z[1]).min(z[2]), z[0].max(z[1]).max(z[2])];
+                let margin = (z_min.abs().max(z_max.abs()) + 1.0) * 1.0e-4;
+                if !blitter.test_region(
+                    bounds_clamped_min,
+                    bounds_clamped_max,
+                    [z_min - margin, z_max + margin],
+                ) {
+                    blitter.primitive_culled(CullReason::Occluded);
+                    return;
                 }
+            }
+
+            // Fragments are visited in rows along the principal axis (or in columns, if the principal axis is y)
+            let axes = if principal_x { [0, 1] } else { [1, 0] };
+            let outer = axes[1];
+
+            // Tiny triangles have so few candidate fragments that it's quicker to test all of them than to find the
+            // range of each line that they cover. Otherwise, order the vertices along the outer axis to find it.
+            let extent = [0, 1].map(|i| bounds_clamped_max[i] - bounds_clamped_min[i]);
+            let verts_by_outer = if extent[0] * extent[1] < 128 {
+                None
             } else {
-                #[allow(clippy::collapsible_else_if)]
-                if verts_screen[0][1] < verts_screen[1][1] {
-                    [verts_screen[2], verts_screen[0], verts_screen[1]]
+                let min_outer = {
+                    let o = verts_screen.map(|v| v[outer]);
+                    o[0].min(o[1]).min(o[2])
+                };
+                Some(if verts_screen[0][outer] == min_outer {
+                    if verts_screen[1][outer] < verts_screen[2][outer] {
+                        [verts_screen[0], verts_screen[1], verts_screen[2]]
+                    } else {
+                        [verts_screen[0], verts_screen[2], verts_screen[1]]
+                    }
+                } else if verts_screen[1][outer] == min_outer {
+                    if verts_screen[0][outer] < verts_screen[2][outer] {
+                        [verts_screen[1], verts_screen[0], verts_screen[2]]
+                    } else {
+                        [verts_screen[1], verts_screen[2], verts_screen[0]]
+                    }
                 } else {
-                    [verts_screen[2], verts_screen[1], verts_screen[0]]
-                }
+                    #[allow(clippy::collapsible_else_if)]
+                    if verts_screen[0][outer] < verts_screen[1][outer] {
+                        [verts_screen[2], verts_screen[0], verts_screen[1]]
+                    } else {
+                        [verts_screen[2], verts_screen[1], verts_screen[0]]
+                    }
+                })
             };
 
             if let [true, true, true] = verts_euc.map(|v| coords.passes_z_clip(v[2])) {
@@ -175,31 +298,42 @@ impl Rasterizer for Triangles {
                     coords.clone(),
                     bounds_clamped_min,
                     bounds_clamped_max,
-                    verts_by_y,
+                    axes,
+                    verts_by_outer,
                     verts_hom,
                     w_hom_origin,
                     w_hom_dx,
                     w_hom_dy,
+                    z_bias,
+                    sample_offsets,
+                    edges,
                     verts_out,
-                    &mut blitter,
+                    order,
+                    blitter,
                 );
             } else {
                 rasterize::<_, _, false>(
                     coords.clone(),
                     bounds_clamped_min,
                     bounds_clamped_max,
-                    verts_by_y,
+                    axes,
+                    verts_by_outer,
                     verts_hom,
                     w_hom_origin,
                     w_hom_dx,
                     w_hom_dy,
+                    z_bias,
+                    sample_offsets,
+                    edges,
                     verts_out,
-                    &mut blitter,
+                    order,
+                    blitter,
                 );
             }
 
             // Iterate over fragment candidates within the triangle's bounding box
             #[inline]
+            #[allow(clippy::too_many_arguments)]
             unsafe fn rasterize<
                 V: Clone + WeightedSum,
                 B: Blitter<V>,
@@ -208,160 +342,633 @@ impl Rasterizer for Triangles {
                 coords: CoordinateMode,
                 bounds_clamped_min: [usize; 2],
                 bounds_clamped_max: [usize; 2],
-                verts_by_y: [[f32; 2]; 3],
+                [inner, outer]: [usize; 2],
+                verts_by_outer: Option<[[f32; 2]; 3]>,
                 verts_hom: [[f32; 4]; 3],
                 w_hom_origin: [f32; 3],
                 w_hom_dx: [f32; 3],
                 w_hom_dy: [f32; 3],
-                verts_out: [V; 3],
+                z_bias: f32,
+                sample_offsets: &[[f32; 2]],
+                edges: [Edge; 3],
+                verts_out: [WithBary<V>; 3],
+                order: [usize; 3],
                 blitter: &mut B,
             ) {
-                (bounds_clamped_min[1]..bounds_clamped_max[1]).for_each(|y| {
-                    let extent = [
-                        bounds_clamped_max[0] - bounds_clamped_min[0],
-                        bounds_clamped_max[1] - bounds_clamped_min[1],
-                    ];
-                    let row_range = if extent.iter().product::<usize>() < 128 {
-                        // Stupid version
-                        [bounds_clamped_min[0], bounds_clamped_max[0]]
-                    } else {
-                        let [a, b, c] = verts_by_y;
-
-                        // For each of the lines, calculate the point at which our row intersects it
-                        let ac = lerp(a[0], c[0], (y as f32 - a[1]) / (c[1] - a[1])); // Longest side
-                                                                                      // Then, depending on the half of the triangle we're in, we need to check different lines
-                        let row_bounds = if (y as f32) < b[1] {
-                            let ab = lerp(a[0], b[0], (y as f32 - a[1]) / (b[1] - a[1]));
-                            [ab.min(ac), ab.max(ac)]
-                        } else {
-                            let bc = lerp(b[0], c[0], (y as f32 - b[1]) / (c[1] - b[1]));
-                            [bc.min(ac), bc.max(ac)]
-                        };
+                let samples = sample_offsets.len();
+                let centroid_sampling = blitter.centroid_sampling();
+                let inside = |p: [f32; 2]| edges.iter().all(|e| e.covers(p));
+                // Find the vertex data of the fragment at the given position
+                let get_v_data = |x: f32, y: f32| {
+                    let w_hom = add(
+                        add(w_hom_origin, w_hom_dy.map(|e| e * y)),
+                        w_hom_dx.map(|e| e * x),
+                    );
+
+                    // Calculate vertex weights to determine vs_out lerping and intersection
+                    let w_unbalanced = [w_hom[0], w_hom[1], w_hom[2] - w_hom[0] - w_hom[1]];
+                    let r = w_hom[2].recip();
+                    let w = w_unbalanced.map(|e| e * r);
+
+                    // Weights without perspective correction, which are proportional to the product of the
+                    // perspective-correct weights and the w coordinates of the vertices
+                    let w_linear = [0, 1, 2].map(|i| w_unbalanced[i] * verts_hom[i][3]);
+                    let r_linear = (w_linear[0] + w_linear[1] + w_linear[2]).recip();
+                    let w_linear = w_linear.map(|e| e * r_linear);
+
+                    // Put the weights back in the original order of the vertex data
+                    let unorder = |w: [f32; 3]| {
+                        let mut out = [0.0; 3];
+                        for (i, w) in order.into_iter().zip(w) {
+                            out[i] = w;
+                        }
+                        out
+                    };
+                    let WithBary(v_data, bary) = WithBary::weighted_sum_linear(
+                        verts_out.clone(),
+                        unorder(w),
+                        unorder(w_linear),
+                    );
+                    (bary, v_data)
+                };
 
-                        // Now we have screen-space bounds for the row. Clean it up and clamp it to the screen bounds
-                        let screen_clamp = |e, b| {
-                            if e >= bounds_clamped_min[0] as f32 && e < bounds_clamped_max[0] as f32
-                            {
-                                e as usize
+                (bounds_clamped_min[outer]..bounds_clamped_max[outer]).for_each(|o| {
+                    let line_range = if let Some([a, b, c]) = verts_by_outer {
+                        // For each of the edges, calculate the point at which a line (a row, or a column if the
+                        // principal axis is y) intersects it
+                        let line_bounds_at = |o: f32| {
+                            let t = |p: [f32; 2], q: [f32; 2]| {
+                                ((o - p[outer]) / (q[outer] - p[outer])).clamp(0.0, 1.0)
+                            };
+                            let ac = lerp(a[inner], c[inner], t(a, c)); // Longest side
+                                                                        // Then, depending on the half of the triangle we're in, we need to check different lines
+                            if o < b[outer] {
+                                let ab = lerp(a[inner], b[inner], t(a, b));
+                                [ab.min(ac), ab.max(ac)]
                             } else {
-                                b
+                                let bc = lerp(b[inner], c[inner], t(b, c));
+                                [bc.min(ac), bc.max(ac)]
                             }
                         };
+                        let line_bounds = if samples > 1 {
+                            // Samples may lie up to half a pixel from the line in any direction. The triangle is
+                            // convex, so its widest point within that band lies on one of its edges or at a vertex.
+                            let [min0, max0] = line_bounds_at(o as f32 - 0.5);
+                            let [min1, max1] = line_bounds_at(o as f32 + 0.5);
+                            let [min, max] = if (b[outer] - o as f32).abs() <= 0.5 {
+                                [min0.min(min1).min(b[inner]), max0.max(max1).max(b[inner])]
+                            } else {
+                                [min0.min(min1), max0.max(max1)]
+                            };
+                            [min - 0.5, max + 0.5]
+                        } else {
+                            line_bounds_at(o as f32)
+                        };
+                        // Rounding error may move the ends of the line past fragments that lie exactly on the edges of
+                        // the triangle, so widen it slightly (fragments are still tested against the edges below)
+                        let line_bounds =
+                            [line_bounds[0] - 1.0 / 16.0, line_bounds[1] + 1.0 / 16.0];
+
+                        // Now we have screen-space bounds for the line. Convert them to a range of fragments in the same way
+                        // as the bounds of the triangle, and clamp it to them. NaNs (from edges parallel to the line) fall
+                        // back to the bounds of the triangle.
+                        let [min, max] = [line_bounds[0], line_bounds[1]];
                         [
-                            screen_clamp(row_bounds[0].floor(), bounds_clamped_min[0]),
-                            screen_clamp(row_bounds[1].ceil(), bounds_clamped_max[0]),
+                            if min.is_nan() {
+                                bounds_clamped_min[inner]
+                            } else {
+                                screen_clamp(
+                                    min.floor(),
+                                    bounds_clamped_min[inner],
+                                    bounds_clamped_max[inner],
+                                )
+                            },
+                            if max.is_nan() {
+                                bounds_clamped_max[inner]
+                            } else {
+                                screen_clamp(
+                                    max.floor() + 1.0,
+                                    bounds_clamped_min[inner],
+                                    bounds_clamped_max[inner],
+                                )
+                            },
                         ]
+                    } else {
+                        // Stupid version
+                        [bounds_clamped_min[inner], bounds_clamped_max[inner]]
                     };
 
-                    // Find the barycentric weights for the start of this row
-                    let mut w_hom = add(
-                        add(w_hom_origin, w_hom_dy.map(|e| e * y as f32)),
-                        w_hom_dx.map(|e| e * row_range[0] as f32),
-                    );
+                    #[allow(unused_mut)]
+                    let mut line_start = line_range[0];
+
+                    // Single-sampled fragments are tested in groups, with the weights of every fragment in a group
+                    // being evaluated at once. Any that remain at the end of the line are handled individually below.
+                    #[cfg(feature = "simd")]
+                    if samples == 1 {
+                        while line_start + lanes::LANES <= line_range[1] {
+                            let pos = core::array::from_fn(|axis| {
+                                core::array::from_fn(|lane| {
+                                    if axis == inner {
+                                        (line_start + lane) as f32
+                                    } else {
+                                        o as f32
+                                    }
+                                })
+                            });
+                            let (mask, z) = lanes::eval(
+                                pos,
+                                w_hom_origin,
+                                w_hom_dx,
+                                w_hom_dy,
+                                verts_hom.map(|v| v[2]),
+                                &edges,
+                            );
+                            (0..lanes::LANES)
+                                .filter(|lane| mask & (1 << lane) != 0)
+                                .for_each(|lane| {
+                                    let mut pos = [0; 2];
+                                    pos[inner] = line_start + lane;
+                                    pos[outer] = o;
+                                    let [x, y] = pos;
+
+                                    let z_biased = z[lane] + z_bias;
+                                    if (NO_VERTS_CLIPPED || coords.passes_z_clip(z[lane]))
+                                        && blitter.test_fragment(x, y, z_biased)
+                                    {
+                                        blitter.emit_fragment(x, y, &get_v_data, z_biased);
+                                    }
+                                });
+                            line_start += lanes::LANES;
+                        }
+                    }
+
+                    // Weights are evaluated directly rather than accumulated along the line so that fragments do
+                    // not depend on where the line started (and hence on which region of the target is being
+                    // rasterized, or in which order). When iterating by row, the row term is hoisted out of the line.
+                    // The column term isn't hoisted when iterating by column, since summing the terms in a different
+                    // order would round differently from the grouped (`simd`) path above.
+                    let w_hom_row =
+                        (outer == 1).then(|| add(w_hom_origin, w_hom_dy.map(|e| e * o as f32)));
+
+                    (line_start..line_range[1]).for_each(|i| {
+                        let mut pos = [0; 2];
+                        pos[inner] = i;
+                        pos[outer] = o;
+                        let [x, y] = pos;
+
+                        let w_hom_row = w_hom_row
+                            .unwrap_or_else(|| add(w_hom_origin, w_hom_dy.map(|e| e * y as f32)));
+                        let w_hom = add(w_hom_row, w_hom_dx.map(|e| e * x as f32));
 
-                    (row_range[0]..row_range[1]).for_each(|x| {
                         // Calculate vertex weights to determine vs_out lerping and intersection
                         let w_unbalanced = [w_hom[0], w_hom[1], w_hom[2] - w_hom[0] - w_hom[1]];
 
-                        // Test the weights to determine whether the fragment is inside the triangle
-                        if let [true, true, true] = w_unbalanced.map(|e| e >= 0.0) {
-                            // Calculate the interpolated z coordinate for the depth target
-                            let z = dot(verts_hom.map(|v| v[2]), w_unbalanced);
-
-                            if (NO_VERTS_CLIPPED || coords.passes_z_clip(z))
-                                && blitter.test_fragment(x, y, z)
-                            {
-                                let get_v_data = |x: f32, y: f32| {
-                                    let w_hom = add(
-                                        add(w_hom_origin, w_hom_dy.map(|e| e * y)),
-                                        w_hom_dx.map(|e| e * x),
-                                    );
-
-                                    // Calculate vertex weights to determine vs_out lerping and intersection
-                                    let w_unbalanced =
-                                        [w_hom[0], w_hom[1], w_hom[2] - w_hom[0] - w_hom[1]];
-                                    let r = w_hom[2].recip();
-                                    let w = w_unbalanced.map(|e| e * r);
-
-                                    V::weighted_sum3(
-                                        verts_out[0].clone(),
-                                        verts_out[1].clone(),
-                                        verts_out[2].clone(),
-                                        w[0],
-                                        w[1],
-                                        w[2],
-                                    )
-                                };
-
-                                blitter.emit_fragment(x, y, get_v_data, z);
+                        // Calculate the interpolated z coordinate for the depth target
+                        let z = dot(verts_hom.map(|v| v[2]), w_unbalanced);
+                        let z_biased = z + z_bias;
+
+                        // The offset from the fragment position at which its vertex data is evaluated
+                        let mut centroid = [0.0; 2];
+
+                        let passed = if samples > 1 {
+                            // Determine which samples lie inside the triangle. The fragment is only shaded once,
+                            // but each sample is tested with its own depth.
+                            let mut coverage = 0u32;
+                            let mut sample_z = [0.0; MAX_SAMPLES];
+                            for (i, [ox, oy]) in sample_offsets.iter().enumerate() {
+                                let w_hom = add(
+                                    add(w_hom, w_hom_dx.map(|e| e * ox)),
+                                    w_hom_dy.map(|e| e * oy),
+                                );
+                                let w_unbalanced =
+                                    [w_hom[0], w_hom[1], w_hom[2] - w_hom[0] - w_hom[1]];
+                                if inside([x as f32 + ox, y as f32 + oy]) {
+                                    let z = dot(verts_hom.map(|v| v[2]), w_unbalanced);
+                                    if NO_VERTS_CLIPPED || coords.passes_z_clip(z) {
+                                        coverage |= 1 << i;
+                                        sample_z[i] = z + z_bias;
+                                    }
+                                }
                             }
-                        }
+                            // With centroid sampling, partially covered fragments are shaded at the centroid of
+                            // their covered samples rather than at their position, which may lie outside the
+                            // triangle: this keeps the vertex data from being extrapolated beyond the vertices.
+                            let covered = coverage.count_ones();
+                            if centroid_sampling && covered > 0 && covered < samples as u32 {
+                                let sum = sample_offsets
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(i, _)| coverage & (1 << i) != 0)
+                                    .fold([0.0; 2], |[sx, sy], (_, [ox, oy])| [sx + ox, sy + oy]);
+                                centroid = sum.map(|e| e / covered as f32);
+                            }
+                            coverage != 0
+                                && blitter.test_samples(x, y, coverage, &sample_z[..samples])
+                        } else {
+                            inside([x as f32, y as f32])
+                                && (NO_VERTS_CLIPPED || coords.passes_z_clip(z))
+                                && blitter.test_fragment(x, y, z_biased)
+                        };
 
-                        // Update barycentric weight ready for the next fragment
-                        w_hom = add(w_hom, w_hom_dx);
+                        if passed {
+                            let [cx, cy] = centroid;
+                            blitter.emit_fragment(
+                                x,
+                                y,
+                                |x, y| get_v_data(x + cx, y + cy),
+                                z_biased,
+                            );
+                        }
                     });
                 });
             }
+        };
+
+        primitives.for_each(|verts: [([f32; 4], V); N]| {
+            blitter.begin_primitive();
+            blitter.inspect_primitive(&verts.each_ref().map(|(pos, _)| *pos));
+
+            // Vertices shared with later triangles of the fan are cloned, so the vertices of lone triangles never are
+            let mut verts = verts.map(Some);
+            for i in 2..N {
+                let last = i == N - 1;
+                let mut take = |i: usize, shared: bool| {
+                    if shared && !last {
+                        verts[i].clone()
+                    } else {
+                        verts[i].take()
+                    }
+                    .unwrap()
+                };
+                let [a, b, c] = [take(0, true), take(i - 1, false), take(i, true)];
+
+                let verts_hom_out = [
+                    (
+                        to_clip(&coords, tgt_size, a.0),
+                        WithBary(a.1, [1.0, 0.0, 0.0]),
+                    ),
+                    (
+                        to_clip(&coords, tgt_size, b.0),
+                        WithBary(b.1, [0.0, 1.0, 0.0]),
+                    ),
+                    (
+                        to_clip(&coords, tgt_size, c.0),
+                        WithBary(c.1, [0.0, 0.0, 1.0]),
+                    ),
+                ];
+
+                // Small triangles are culled by the area of the whole triangle, rather than that of the parts of it
+                // drawn below (which may be small even when the triangle isn't). The area of triangles with vertices
+                // behind the camera isn't meaningful, so they're left alone.
+                if let CullMode::MinArea(min_area) = cull_mode {
+                    let verts_hom = verts_hom_out.each_ref().map(|(v, _)| *v);
+                    if verts_hom.iter().all(|v| v[3] > 0.0) {
+                        let verts_euc =
+                            verts_hom.map(|[a0, a1, a2, a3]| [a0 / a3, a1 / a3, a2 / a3]);
+                        let winding = cross(
+                            sub(verts_euc[1], verts_euc[0]),
+                            sub(verts_euc[2], verts_euc[0]),
+                        )[2];
+                        if (winding * winding_to_area).abs() < min_area {
+                            blitter.primitive_culled(CullReason::ZeroArea);
+                            continue;
+                        }
+                    }
+                }
+
+                if verts_hom_out.iter().all(|(v, _)| in_guard_band(v)) {
+                    draw(&mut blitter, verts_hom_out);
+                } else {
+                    // Screen-space coordinates of vertices far outside of the target are large enough to degrade the
+                    // precision of the interpolation weights, so draw the part of the triangle within the guard band
+                    let poly = clip_guard_band(verts_hom_out);
+                    if poly.len() < 3 {
+                        blitter.primitive_culled(CullReason::OffScreen);
+                    }
+                    for i in 2..poly.len() {
+                        draw(
+                            &mut blitter,
+                            [poly[0].clone(), poly[i - 1].clone(), poly[i].clone()],
+                        );
+                    }
+                }
+            }
         });
     }
 }
 
-fn cross([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
-    [
-        a1 * b2 - a2 * b1, // x-component
-        a2 * b0 - a0 * b2, // y-component
-        a0 * b1 - a1 * b0, // z-component
-    ]
+/// An edge of a triangle in screen space, used to determine which fragments the triangle covers.
+///
+/// Edges are evaluated from their endpoints in a consistent order (whichever way around the triangle they run), so
+/// triangles that share an edge make exactly opposite decisions about the fragments near it: no fragment is covered by
+/// both of them, or by neither.
+#[derive(Copy, Clone)]
+struct Edge {
+    origin: [f32; 2],
+    dir: [f32; 2],
+    sign: f32,
+    owned: bool,
 }
 
-fn sub([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
-    [
-        a0 - b0, // x-component
-        a1 - b1, // y-component
-        a2 - b2, // z-component
-    ]
+impl Edge {
+    /// The edge running from `p` to `q` (in the corrected winding order of the triangle), which covers the fragments
+    /// that lie exactly on it if it is `owned`.
+    fn new(p: [f32; 2], q: [f32; 2], owned: bool) -> Self {
+        let (origin, end, sign) = if p[0] < q[0] || (p[0] == q[0] && p[1] < q[1]) {
+            (p, q, 1.0)
+        } else {
+            (q, p, -1.0)
+        };
+        Self {
+            origin,
+            dir: [end[0] - origin[0], end[1] - origin[1]],
+            sign,
+            owned,
+        }
+    }
+
+    /// A value that is positive for points on the inner side of the edge, and zero for those exactly on it.
+    #[inline(always)]
+    fn eval(&self, [x, y]: [f32; 2]) -> f32 {
+        self.sign * (self.dir[0] * (y - self.origin[1]) - self.dir[1] * (x - self.origin[0]))
+    }
+
+    #[inline(always)]
+    fn covers(&self, p: [f32; 2]) -> bool {
+        let e = self.eval(p);
+        e > 0.0 || (e == 0.0 && self.owned)
+    }
 }
 
-fn add([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> [f32; 3] {
+#[derive(Clone)]
+struct WithBary<V>(V, [f32; 3]);
+
+impl<V: WeightedSum> WeightedSum for WithBary<V> {
+    #[inline(always)]
+    fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+        Self::weighted_sum_linear(values, weights, weights)
+    }
+    #[inline(always)]
+    fn weighted_sum_linear<const N: usize>(
+        values: [Self; N],
+        weights: [f32; N],
+        linear_weights: [f32; N],
+    ) -> Self {
+        // Barycentric coordinates are always perspective-correct
+        let bary = values.iter().zip(weights).fold([0.0; 3], |bary, (v, w)| {
+            [0, 1, 2].map(|i| bary[i] + v.1[i] * w)
+        });
+        WithBary(
+            V::weighted_sum_linear(values.map(|v| v.0), weights, linear_weights),
+            bary,
+        )
+    }
+}
+
+/// The extent of the guard band (relative to the extent of the target) that triangles are clipped against.
+const GUARD_BAND: f32 = 8.0;
+
+/// The signed distances of a vertex (in homogeneous coordinates) from each edge of the guard band, which are
+/// non-negative when the vertex lies within it.
+fn guard_band_distances([x, y, _z, w]: [f32; 4]) -> [f32; 4] {
     [
-        a0 + b0, // x-component
-        a1 + b1, // y-component
-        a2 + b2, // z-component
+        GUARD_BAND * w - x,
+        GUARD_BAND * w + x,
+        GUARD_BAND * w - y,
+        GUARD_BAND * w + y,
     ]
 }
 
-fn dot([a0, a1, a2]: [f32; 3], [b0, b1, b2]: [f32; 3]) -> f32 {
-    a0 * b0 + a1 * b1 + a2 * b2
+fn in_guard_band(v: &[f32; 4]) -> bool {
+    v[3] > 0.0 && guard_band_distances(*v).iter().all(|d| *d >= 0.0)
 }
 
-fn rev<T>([a0, a1, a2]: [T; 3]) -> [T; 3] {
-    [a2, a1, a0]
+/// The bounds of the fragments that a convex polygon (such as a triangle) may cover on the screen.
+pub(super) fn polygon_screen_bounds<const N: usize>(
+    verts_hom: [[f32; 4]; N],
+    coords: &CoordinateMode,
+    tgt_size: [usize; 2],
+) -> ([usize; 2], [usize; 2]) {
+    let [size_x, size_y] = tgt_size.map(|e| e as f32);
+    let to_screen = |[a0, a1, _a2, a3]: [f32; 4]| {
+        [
+            size_x * (a0 / a3 * 0.5 + 0.5),
+            size_y * (a1 / a3 * -0.5 + 0.5),
+        ]
+    };
+    // Only the part of the polygon within the guard band gets rasterized. Multisampled fragments may be covered by
+    // samples up to half a pixel away from their position.
+    let verts = verts_hom.map(|v| to_clip(coords, tgt_size, v));
+    if verts.iter().all(in_guard_band) {
+        bounds_clamped(&verts.map(to_screen), [0; 2], tgt_size, 0.5)
+    } else {
+        let poly = clip_guard_band(verts.map(|v| (v, Unit)));
+        let verts_screen = poly
+            .into_iter()
+            .map(|(v, _)| to_screen(v))
+            .collect::<Vec<_>>();
+        bounds_clamped(&verts_screen, [0; 2], tgt_size, 0.5)
+    }
 }
 
-fn magnitude_squared([v0, v1, v2]: [f32; 3]) -> f32 {
-    v0 * v0 + v1 * v1 + v2 * v2
+/// Clip a triangle (or other convex polygon) against each edge of the guard band, producing a convex polygon with the
+/// same winding order.
+///
+/// Vertices behind the camera lie outside of the guard band, so the polygon only has vertices in front of it.
+///
+/// The polygon begins with the first vertex of the triangle or, if that gets clipped, a new vertex (unless the next
+/// vertex lies exactly on an edge of the guard band). New vertices take
+/// the values of [`Flat`](crate::math::Flat) data from the first vertex of the triangle, so that the provoking vertex of
+/// each triangle fanned out from the start of the polygon matches that of the original triangle.
+fn clip_guard_band<V: Clone + WeightedSum, const N: usize>(
+    verts: [([f32; 4], V); N],
+) -> Vec<([f32; 4], V)> {
+    let provoking = verts[0].1.clone();
+    let mut poly = Vec::from(verts);
+    for edge in 0..4 {
+        let dist = |(v, _): &([f32; 4], V)| guard_band_distances(*v)[edge];
+        let input = core::mem::take(&mut poly);
+        for (i, a) in input.iter().enumerate() {
+            let b = &input[(i + 1) % input.len()];
+            let (da, db) = (dist(a), dist(b));
+            if da >= 0.0 {
+                poly.push(a.clone());
+            }
+            // Vertices that lie on the edge are kept as they are, rather than producing a duplicate vertex
+            if (da > 0.0 && db < 0.0) || (da < 0.0 && db > 0.0) {
+                // Interpolate from the same end of the edge regardless of its direction, so that the triangles that
+                // share it produce exactly the same vertex
+                let ((p, p_out), dp, (q, q_out), dq) = if a.0 < b.0 {
+                    (a, da, b, db)
+                } else {
+                    (b, db, a, da)
+                };
+                let t = dp / (dp - dq);
+                // The position of the new vertex along the edge on the screen, for attributes that are interpolated
+                // linearly in screen space (this is undefined when the edge crosses behind the camera)
+                let s = t * q[3] / ((1.0 - t) * p[3] + t * q[3]);
+                let s = if s.is_finite() { s } else { t };
+                poly.push((
+                    core::array::from_fn(|i| p[i] + (q[i] - p[i]) * t),
+                    // The provoking vertex has no weight, but comes first
+                    V::weighted_sum_linear(
+                        [provoking.clone(), p_out.clone(), q_out.clone()],
+                        [0.0, 1.0 - t, t],
+                        [0.0, 1.0 - s, s],
+                    ),
+                ));
+            }
+        }
+    }
+    poly
 }
 
-fn matmul(a: [[f32; 3]; 3], b: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
-    let mut result = [[0.0; 3]; 3]; // Initialize a 3x3 matrix to store the result
+/// Calculate the bounding box of a triangle in screen space, expanded by `margin` and clamped to the given bounds.
+fn bounds_clamped(
+    verts_screen: &[[f32; 2]],
+    screen_min: [usize; 2],
+    screen_max: [usize; 2],
+    margin: f32,
+) -> ([usize; 2], [usize; 2]) {
+    let [min, max] = verts_screen.iter().fold(
+        [[f32::INFINITY; 2], [f32::NEG_INFINITY; 2]],
+        |[min, max], v| {
+            [
+                [min[0].min(v[0]), min[1].min(v[1])],
+                [max[0].max(v[0]), max[1].max(v[1])],
+            ]
+        },
+    );
+    // Fragments are positioned at integer screen coordinates, so those at `min` and `max` are covered when they are
+    // integers. Bounds are clamped before being converted to integers, so they're never truncated towards zero.
+    let bounds_clamped_min =
+        [0, 1].map(|i| screen_clamp((min[i] - margin).floor(), screen_min[i], screen_max[i]));
+    let bounds_clamped_max = [0, 1].map(|i| {
+        screen_clamp(
+            (max[i] + margin).floor() + 1.0,
+            screen_min[i],
+            screen_max[i],
+        )
+    });
+    (bounds_clamped_min, bounds_clamped_max)
+}
 
-    for i in 0..3 {
-        for j in 0..3 {
-            result[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
-        }
-    }
+/// Clamp a screen coordinate to the given range, then convert it to an index.
+#[inline(always)]
+fn screen_clamp(e: f32, min: usize, max: usize) -> usize {
+    e.clamp(min as f32, max as f32) as usize
+}
 
-    result
+fn rev<T>([a0, a1, a2]: [T; 3]) -> [T; 3] {
+    [a2, a1, a0]
 }
 
-fn mat3_mul_vec3(m: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
-    [
-        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2], // x-component
-        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2], // y-component
-        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2], // z-component
-    ]
+/// Rotate the vertices of a triangle such that vertex `n` comes first.
+fn rotate<T>([a0, a1, a2]: [T; 3], n: usize) -> [T; 3] {
+    match n % 3 {
+        0 => [a0, a1, a2],
+        1 => [a1, a2, a0],
+        _ => [a2, a0, a1],
+    }
 }
 
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    a + t * (b - a)
+/// Evaluation of the weights of several fragments at once, for the rasterizer's inner loop.
+#[cfg(feature = "simd")]
+mod lanes {
+    use super::Edge;
+    use wide::{f32x8, CmpGe, CmpGt};
+
+    /// The number of fragments evaluated at once.
+    pub const LANES: usize = 8;
+
+    /// Evaluate the weights of a group of fragments at the given positions (x coordinates, then y coordinates) exactly
+    /// as the scalar path does, returning a mask of the fragments that lie inside the triangle and their z coordinates.
+    #[inline(always)]
+    pub fn eval(
+        [x, y]: [[f32; LANES]; 2],
+        w_hom_origin: [f32; 3],
+        w_hom_dx: [f32; 3],
+        w_hom_dy: [f32; 3],
+        verts_z: [f32; 3],
+        edges: &[Edge; 3],
+    ) -> (u32, [f32; LANES]) {
+        let [x, y] = [f32x8::from(x), f32x8::from(y)];
+        let w_hom: [f32x8; 3] = core::array::from_fn(|i| {
+            (f32x8::splat(w_hom_origin[i]) + f32x8::splat(w_hom_dy[i]) * y)
+                + f32x8::splat(w_hom_dx[i]) * x
+        });
+        let w_unbalanced = [w_hom[0], w_hom[1], w_hom[2] - w_hom[0] - w_hom[1]];
+
+        // Like the scalar path, fragments exactly on an edge are only inside if the triangle owns the edge
+        let covers = |e: &Edge| {
+            let d = f32x8::splat(e.sign)
+                * (f32x8::splat(e.dir[0]) * (y - f32x8::splat(e.origin[1]))
+                    - f32x8::splat(e.dir[1]) * (x - f32x8::splat(e.origin[0])));
+            if e.owned {
+                d.cmp_ge(f32x8::ZERO)
+            } else {
+                d.cmp_gt(f32x8::ZERO)
+            }
+        };
+        let inside = covers(&edges[0]) & covers(&edges[1]) & covers(&edges[2]);
+        let z = f32x8::splat(verts_z[0]) * w_unbalanced[0]
+            + f32x8::splat(verts_z[1]) * w_unbalanced[1]
+            + f32x8::splat(verts_z[2]) * w_unbalanced[2];
+
+        (inside.move_mask() as u32, z.to_array())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::math::{add, dot};
+
+        #[test]
+        fn matches_scalar() {
+            // A simple generator of arbitrary values, including some that lie exactly on edges
+            let mut state = 0x2545_f491_u32;
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                match state % 16 {
+                    0 => 0.0,
+                    1 => 1.0,
+                    _ => (state >> 8) as f32 / (1 << 20) as f32 - 8.0,
+                }
+            };
+
+            for _ in 0..10_000 {
+                let w_hom_origin = [next(), next(), next()];
+                let w_hom_dx = [next(), next(), next()];
+                let w_hom_dy = [next(), next(), next()];
+                let verts_z = [next(), next(), next()];
+                let edges =
+                    [0; 3].map(|_| Edge::new([next(), next()], [next(), next()], next() > 0.0));
+                let [x0, y] = [next().abs() * 100.0, next().abs() * 100.0].map(|e| e.floor());
+                let x = core::array::from_fn(|lane| x0 + lane as f32);
+
+                let (mask, z) = eval(
+                    [x, [y; LANES]],
+                    w_hom_origin,
+                    w_hom_dx,
+                    w_hom_dy,
+                    verts_z,
+                    &edges,
+                );
+                for lane in 0..LANES {
+                    let w_hom = add(
+                        add(w_hom_origin, w_hom_dy.map(|e| e * y)),
+                        w_hom_dx.map(|e| e * x[lane]),
+                    );
+                    let w_unbalanced = [w_hom[0], w_hom[1], w_hom[2] - w_hom[0] - w_hom[1]];
+                    let inside = edges.iter().all(|e| e.covers([x[lane], y]));
+                    assert_eq!(mask & (1 << lane) != 0, inside);
+                    if inside {
+                        assert_eq!(z[lane].to_bits(), dot(verts_z, w_unbalanced).to_bits());
+                    }
+                }
+            }
+        }
+    }
 }
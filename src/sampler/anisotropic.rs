@@ -0,0 +1,149 @@
+use super::*;
+use core::ops::{Add, Mul};
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// A sampler that may be sampled with the derivatives of its index across the screen (like `textureGrad` in GLSL),
+/// which describe the footprint of a fragment on the texture.
+pub trait SamplerGrad: Sampler<2, Index = f32> {
+    /// Sample the texture at the given index, filtering over the footprint given by the change in the index between
+    /// neighbouring pixels along x (`ddx`) and y (`ddy`).
+    fn sample_grad(&self, index: [f32; 2], ddx: [f32; 2], ddy: [f32; 2]) -> Self::Sample;
+}
+
+/// A sampler that filters anisotropically, by averaging several samples of another sampler (usually [`Linear`]) along
+/// the major axis of each fragment's footprint.
+///
+/// This keeps textures sharp when they are viewed at grazing angles, where the footprint of a fragment is long and
+/// thin. Footprints that cover no more than a texel along their major axis are sampled just once.
+///
+/// See [`Sampler::anisotropic`].
+#[derive(Copy, Clone)]
+pub struct Anisotropic<S> {
+    sampler: S,
+    max_taps: usize,
+}
+
+impl<S: Sampler<2, Index = f32>> Anisotropic<S> {
+    pub(crate) fn new(sampler: S, max_taps: usize) -> Self {
+        assert!(
+            max_taps >= 1,
+            "Anisotropic sampler must take at least one sample"
+        );
+        Self { sampler, max_taps }
+    }
+
+    /// The maximum number of samples taken along the footprint of a fragment.
+    pub fn max_taps(&self) -> usize {
+        self.max_taps
+    }
+}
+
+impl<S: Sampler<2, Index = f32>> Sampler<2> for Anisotropic<S> {
+    type Index = f32;
+    type Sample = S::Sample;
+    type Texture = S::Texture;
+
+    fn raw_texture(&self) -> &Self::Texture {
+        self.sampler.raw_texture()
+    }
+    /// Without derivatives, the footprint of the sample is unknown, so the inner sampler is sampled just once.
+    fn sample(&self, index: [Self::Index; 2]) -> Self::Sample {
+        self.sampler.sample(index)
+    }
+    unsafe fn sample_unchecked(&self, index: [Self::Index; 2]) -> Self::Sample {
+        self.sampler.sample_unchecked(index)
+    }
+}
+
+impl<S> SamplerGrad for Anisotropic<S>
+where
+    S: Sampler<2, Index = f32>,
+    S::Texture: Texture<2, Index = usize>,
+    S::Sample: Mul<f32, Output = S::Sample> + Add<Output = S::Sample>,
+{
+    fn sample_grad(&self, index: [f32; 2], ddx: [f32; 2], ddy: [f32; 2]) -> Self::Sample {
+        let size = self.raw_texture().size().map(|e| e as f32);
+        let texels = |[u, v]: [f32; 2]| (u * size[0]).hypot(v * size[1]);
+        let [len_x, len_y] = [texels(ddx), texels(ddy)];
+        let (major, major_len, minor_len) = if len_x >= len_y {
+            (ddx, len_x, len_y)
+        } else {
+            (ddy, len_y, len_x)
+        };
+
+        // Take enough samples along the major axis to cover it in steps no longer than the minor axis (or a texel)
+        let taps = (major_len / minor_len.max(1.0))
+            .ceil()
+            .clamp(1.0, self.max_taps as f32);
+        // Written such that non-finite footprints are sampled once
+        if !(major_len > 1.0 && taps > 1.0) {
+            return self.sampler.sample(index);
+        }
+        let taps = taps as usize;
+
+        // Samples are spread evenly across the footprint, centred on the index
+        let tap = |i: usize| {
+            let t = (i as f32 + 0.5) / taps as f32 - 0.5;
+            self.sampler
+                .sample([index[0] + major[0] * t, index[1] + major[1] * t])
+        };
+        let w = 1.0 / taps as f32;
+        (1..taps).fold(tap(0) * w, |sum, i| sum + tap(i) * w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer2d;
+
+    /// A 16x16 texture with stripes along x, alternating between 0 and 1 with each texel.
+    fn stripes() -> Buffer2d<f32> {
+        let mut tex = Buffer2d::fill([16, 16], 0.0);
+        for (i, t) in tex.raw_mut().iter_mut().enumerate() {
+            *t = (i % 2) as f32;
+        }
+        tex
+    }
+
+    #[test]
+    fn small_footprint_samples_once() {
+        let tex = stripes();
+        let sampler = (&tex).linear().anisotropic(8);
+        let index = [0.3, 0.6];
+        let texel = 1.0 / 16.0;
+        // Footprints up to a texel long
+        for (ddx, ddy) in [([0.0; 2], [0.0; 2]), ([texel, 0.0], [0.0, texel * 0.5])] {
+            assert_eq!(
+                sampler.sample_grad(index, ddx, ddy),
+                (&tex).linear().sample(index)
+            );
+        }
+        assert_eq!(sampler.sample(index), (&tex).linear().sample(index));
+        assert_eq!(
+            sampler.sample_grad(index, [f32::NAN; 2], [0.0; 2]),
+            (&tex).linear().sample(index)
+        );
+    }
+
+    #[test]
+    fn long_footprint_averages() {
+        let tex = stripes();
+        let texel = 1.0 / 16.0;
+        // A footprint 8 texels long across the stripes, and a texel wide along them
+        let (ddx, ddy) = ([0.0, texel], [texel * 8.0, 0.0]);
+        let index = [5.0 * texel, 0.5];
+        // A single sample lands on a stripe...
+        assert_eq!((&tex).linear().sample(index), 1.0);
+        // ...while 8 samples, a texel apart, average them out
+        let sampler = (&tex).linear().anisotropic(8);
+        assert_eq!(sampler.sample_grad(index, ddx, ddy), 0.5);
+        // With fewer samples, they're spread further apart: these 2 are 4 texels apart, and both land on a stripe
+        let sampler = (&tex).linear().anisotropic(2);
+        let sample = sampler.sample_grad(index, ddx, ddy);
+        assert_eq!(sample, 1.0);
+        assert_eq!(sampler.max_taps(), 2);
+    }
+}
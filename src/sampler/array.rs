@@ -0,0 +1,376 @@
+use super::*;
+use crate::buffer::Buffer3d;
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    ops::{Add, Mul},
+};
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// A stack of equally-sized 2D textures (layers), like a `sampler2DArray` in GLSL.
+///
+/// As a [`Texture`], a texture array is a 3-dimensional texture whose third axis selects the layer. Texture arrays are
+/// most useful when sampled with [`NearestLayer`] or [`LinearLayer`], which filter within a layer but never blend
+/// between layers. This makes them well-suited to sprite sheets and tilemaps, where each layer is an unrelated image.
+#[derive(Debug)]
+pub struct TextureArray<T> {
+    buffer: Buffer3d<T>,
+}
+
+/// The error produced when constructing a [`TextureArray`] from layers that are not all the same size.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LayerSizeMismatch {
+    /// The index of the first layer with a different size to the layers before it.
+    pub layer: usize,
+    /// The size of the first layer.
+    pub expected: [usize; 2],
+    /// The size of the mismatched layer.
+    pub found: [usize; 2],
+}
+
+impl fmt::Display for LayerSizeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "layer {} has size {:?}, but the texture array has layers of size {:?}",
+            self.layer, self.found, self.expected,
+        )
+    }
+}
+
+impl core::error::Error for LayerSizeMismatch {}
+
+impl<T: Clone> TextureArray<T> {
+    /// Create a texture array by copying the texels of each of the given layers.
+    ///
+    /// Returns an error if the layers are not all the same size. An empty iterator produces an array with no layers.
+    pub fn from_layers<I, L>(layers: I) -> Result<Self, LayerSizeMismatch>
+    where
+        I: IntoIterator<Item = L>,
+        L: Texture<2, Index = usize, Texel = T>,
+    {
+        let mut size = None;
+        let mut texels = Vec::new();
+        let mut count = 0;
+        for (i, layer) in layers.into_iter().enumerate() {
+            let [w, h] = layer.size();
+            match size {
+                None => size = Some([w, h]),
+                Some(expected) if expected != [w, h] => {
+                    return Err(LayerSizeMismatch {
+                        layer: i,
+                        expected,
+                        found: [w, h],
+                    })
+                }
+                Some(_) => {}
+            }
+            for y in 0..h {
+                // Safety: `x` and `y` are within the bounds of the layer
+                texels.extend((0..w).map(|x| unsafe { layer.read_unchecked([x, y]) }));
+            }
+            count += 1;
+        }
+
+        let [w, h] = size.unwrap_or([0; 2]);
+        // Texels were collected in the same order as `Buffer::linear_index`
+        let mut texels = texels.into_iter();
+        Ok(Self {
+            buffer: Buffer3d::fill_with([w, h, count], || texels.next().unwrap()),
+        })
+    }
+
+    /// The number of layers in the texture array.
+    pub fn layers(&self) -> usize {
+        self.buffer.size()[2]
+    }
+
+    /// The size of each layer of the texture array.
+    pub fn layer_size(&self) -> [usize; 2] {
+        let [w, h, _] = self.buffer.size();
+        [w, h]
+    }
+
+    /// Access the underlying buffer, in which the third axis selects the layer.
+    pub fn as_buffer(&self) -> &Buffer3d<T> {
+        &self.buffer
+    }
+}
+
+impl<T> From<Buffer3d<T>> for TextureArray<T> {
+    fn from(buffer: Buffer3d<T>) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<T: Clone> Texture<3> for TextureArray<T> {
+    type Index = usize;
+    type Texel = T;
+
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 3] {
+        self.buffer.size()
+    }
+
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; 3]> {
+        Some([0, 1, 2])
+    }
+
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; 3]) -> Self::Texel {
+        self.buffer.read(index)
+    }
+
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; 3]) -> Self::Texel {
+        self.buffer.read_unchecked(index)
+    }
+}
+
+/// Find the layer selected by the third component of an index, rounded to the nearest layer and clamped to the
+/// layers of the texture (as in GLSL).
+#[inline(always)]
+fn select_layer(layer: f32, layers: usize) -> usize {
+    (layer.round().max(0.0) as usize).min(layers - 1)
+}
+
+/// A sampler that uses nearest-neighbour sampling within a layer of a 3D texture, such as a [`TextureArray`].
+///
+/// The index is `[u, v, layer]`, where `u` and `v` are normalised coordinates within the layer and `layer` is the
+/// (unnormalised) index of the layer, which is rounded to the nearest layer. Indices outside the texture are clamped to
+/// its edges.
+///
+/// See [`Texture::nearest_layer`].
+#[derive(Copy, Clone)]
+pub struct NearestLayer<T>(pub(crate) T);
+
+impl<T> Sampler<3> for NearestLayer<T>
+where
+    T: Texture<3, Index = usize>,
+{
+    type Index = f32;
+
+    type Sample = T::Texel;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.0
+    }
+
+    #[inline(always)]
+    fn sample(&self, [u, v, layer]: [Self::Index; 3]) -> Self::Sample {
+        let [w, h, layers] = self.0.size();
+        let x = ((u * w as f32).max(0.0) as usize).min(w - 1);
+        let y = ((v * h as f32).max(0.0) as usize).min(h - 1);
+        // Safety: all components have been clamped to the bounds of the texture, which cannot have no size
+        unsafe { self.0.read_unchecked([x, y, select_layer(layer, layers)]) }
+    }
+}
+
+/// A sampler that uses bilinear sampling within a layer of a 3D texture, such as a [`TextureArray`].
+///
+/// The index is `[u, v, layer]`, where `u` and `v` are normalised coordinates within the layer and `layer` is the
+/// (unnormalised) index of the layer, which is rounded to the nearest layer. Texels are only ever blended with texels
+/// of the same layer. Like [`Linear`], texel `i` lies at `i / size`, and neighbours beyond the edge of the layer are
+/// clamped to the edge.
+///
+/// See [`Texture::linear_layer`].
+#[derive(Copy, Clone)]
+pub struct LinearLayer<T>(pub(crate) T);
+
+impl<T> Sampler<3> for LinearLayer<T>
+where
+    T: Texture<3, Index = usize>,
+    T::Texel: Mul<f32, Output = T::Texel> + Add<Output = T::Texel>,
+{
+    type Index = f32;
+
+    type Sample = T::Texel;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.0
+    }
+
+    #[inline(always)]
+    fn sample(&self, [u, v, layer]: [Self::Index; 3]) -> Self::Sample {
+        let [w, h, layers] = self.0.size();
+        let layer = select_layer(layer, layers);
+        // Index in texture coordinates, clamped such that both neighbours lie within the layer
+        let tex_x = (u * w as f32).clamp(0.0, (w - 1) as f32);
+        let tex_y = (v * h as f32).clamp(0.0, (h - 1) as f32);
+        let [p0x, p0y] = [tex_x as usize, tex_y as usize];
+        let [p1x, p1y] = [(p0x + 1).min(w - 1), (p0y + 1).min(h - 1)];
+        let [fract_x, fract_y] = [tex_x.fract(), tex_y.fract()];
+
+        // Safety: all components have been clamped to the bounds of the texture, which cannot have no size
+        let [t00, t10, t01, t11] = unsafe {
+            [
+                self.0.read_unchecked([p0x, p0y, layer]),
+                self.0.read_unchecked([p1x, p0y, layer]),
+                self.0.read_unchecked([p0x, p1y, layer]),
+                self.0.read_unchecked([p1x, p1y, layer]),
+            ]
+        };
+
+        let t0 = t00 * (1.0 - fract_y) + t01 * fract_y;
+        let t1 = t10 * (1.0 - fract_y) + t11 * fract_y;
+
+        t0 * (1.0 - fract_x) + t1 * fract_x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::Buffer2d, math::WeightedSum, pipeline::Pipeline, primitives::TriangleList,
+        rasterizer::CullMode, texture::Empty,
+    };
+
+    /// Three 2 x 2 layers, where the texels of layer `i` are `10 * i + [0, 1, 2, 3]`.
+    fn array() -> TextureArray<f32> {
+        TextureArray::from_layers((0..3).map(|i| {
+            let mut layer = Buffer2d::fill([2, 2], 0.0);
+            let base = i as f32 * 10.0;
+            layer
+                .raw_mut()
+                .copy_from_slice(&[base, base + 1.0, base + 2.0, base + 3.0]);
+            layer
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn construction() {
+        let array = array();
+        assert_eq!(array.size(), [2, 2, 3]);
+        assert_eq!(array.layers(), 3);
+        assert_eq!(array.layer_size(), [2, 2]);
+        assert_eq!(array.read([1, 0, 2]), 21.0);
+
+        let empty = TextureArray::<f32>::from_layers(core::iter::empty::<Buffer2d<f32>>());
+        assert_eq!(empty.unwrap().size(), [0; 3]);
+    }
+
+    #[test]
+    fn mismatched_layers() {
+        let layers = [[2, 2], [2, 2], [3, 2]].map(|size| Buffer2d::fill(size, 0.0));
+        assert_eq!(
+            TextureArray::from_layers(&layers).unwrap_err(),
+            LayerSizeMismatch {
+                layer: 2,
+                expected: [2, 2],
+                found: [3, 2],
+            },
+        );
+    }
+
+    #[test]
+    fn nearest_selects_layer() {
+        let sampler = array().nearest_layer();
+        assert_eq!(sampler.sample([0.25, 0.75, 0.0]), 2.0);
+        assert_eq!(sampler.sample([0.75, 0.25, 1.0]), 11.0);
+        // Layers are rounded, and clamped to the array
+        assert_eq!(sampler.sample([0.75, 0.75, 1.6]), 23.0);
+        assert_eq!(sampler.sample([0.0, 0.0, -4.0]), 0.0);
+        assert_eq!(sampler.sample([2.0, 2.0, 9.0]), 23.0);
+    }
+
+    #[test]
+    fn linear_never_blends_layers() {
+        let sampler = array().linear_layer();
+        // Midway between all four texels of a layer
+        assert_eq!(sampler.sample([0.25, 0.25, 1.0]), 11.5);
+        // Midway between layers, the nearest layer is used rather than a blend of both
+        assert_eq!(sampler.sample([0.25, 0.25, 1.4]), 11.5);
+        assert_eq!(sampler.sample([0.25, 0.25, 1.5]), 21.5);
+        // Neighbours beyond the edge of the layer are clamped to the edge
+        assert_eq!(sampler.sample([0.75, 0.75, 0.0]), 3.0);
+        assert_eq!(sampler.sample([-1.0, 0.0, 2.0]), 20.0);
+    }
+
+    /// A tile of a tilemap, interpolated across a quad.
+    #[derive(Clone)]
+    struct Tile {
+        uv: [f32; 2],
+        layer: f32,
+    }
+
+    impl WeightedSum for Tile {
+        fn weighted_sum<const N: usize>(values: [Self; N], weights: [f32; N]) -> Self {
+            Tile {
+                uv: [0, 1].map(|i| f32::weighted_sum(values.clone().map(|t| t.uv[i]), weights)),
+                layer: f32::weighted_sum(values.map(|t| t.layer), weights),
+            }
+        }
+    }
+
+    struct Tilemap<'a> {
+        tiles: NearestLayer<&'a TextureArray<f32>>,
+    }
+
+    impl<'r> Pipeline<'r> for Tilemap<'_> {
+        type Vertex = ([f32; 2], Tile);
+        type VertexData = Tile;
+        type Primitives = TriangleList;
+        type Fragment = f32;
+        type Pixel = f32;
+
+        fn rasterizer_config(&self) -> CullMode {
+            CullMode::None
+        }
+        fn vertex(&self, ([x, y], tile): &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+            ([*x, *y, 0.5, 1.0], tile.clone())
+        }
+        fn fragment(&self, Tile { uv: [u, v], layer }: Self::VertexData) -> Self::Fragment {
+            self.tiles.sample([u, v, layer])
+        }
+        fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+            new
+        }
+    }
+
+    #[test]
+    fn render_tilemap() {
+        let array = array();
+        let map = [[2, 0], [1, 2]];
+
+        // Emit a quad covering each cell of a 2 x 2 tilemap, with the layer of the tile given by the map
+        let vertices = (0..2)
+            .flat_map(|y| (0..2).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let layer = map[y][x] as f32;
+                let corner = |cx: usize, cy: usize| {
+                    // The y axis of normalised device coordinates points up the screen
+                    let pos = [(x + cx) as f32 - 1.0, 1.0 - (y + cy) as f32];
+                    let uv = [cx as f32, cy as f32];
+                    (pos, Tile { uv, layer })
+                };
+                let [a, b, c, d] = [corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)];
+                [a.clone(), b, c.clone(), a, c, d]
+            })
+            .collect::<Vec<_>>();
+
+        let mut color = Buffer2d::fill([8, 8], -1.0);
+        Tilemap {
+            tiles: (&array).nearest_layer(),
+        }
+        .render(&vertices, &mut color, &mut Empty::depth());
+
+        // Each tile is 4 x 4 pixels and shows the top-left texel of its layer in its top-left quadrant
+        for (ty, row) in map.iter().enumerate() {
+            for (tx, layer) in row.iter().enumerate() {
+                let px = color.read([tx * 4 + 1, ty * 4 + 1]);
+                assert_eq!(px, *layer as f32 * 10.0, "tile {:?}", (tx, ty));
+            }
+        }
+    }
+}
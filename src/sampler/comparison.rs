@@ -0,0 +1,260 @@
+use super::*;
+use core::cmp::Ordering;
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// A sampler that compares a reference depth against the texels of a depth texture, producing the proportion of
+/// texels that pass the comparison (percentage-closer filtering).
+///
+/// This is typically used for shadow mapping: sampling a shadow map with the light-space depth of a fragment as the
+/// reference, using [`Ordering::Less`], produces `1.0` where the fragment is fully lit and `0.0` where it is fully in
+/// shadow.
+///
+/// Each component of the index is a `(coordinate, reference)` pair. The reference depth is taken from the first
+/// component; [`Comparison::sample_compare`] may be used to pass the reference separately instead.
+///
+/// The reference is compared against each texel of a `kernel` x `kernel` grid centred on the sampled position, and
+/// the results are averaged. Texels are compared before any filtering takes place, so a kernel size of 1 produces hard
+/// shadows while larger kernels produce progressively softer edges. Texels outside the texture are clamped to its
+/// edge.
+///
+/// If the sampler is [`filtered`](Comparison::filtered), each position of the grid instead compares the reference
+/// against the four texels nearest to it and bilinearly filters the results of the comparisons (like a `samplerShadow`
+/// with linear filtering on a GPU), with texel centres at `(i + 0.5) / size`. This produces smooth shadow edges even
+/// with a kernel size of 1.
+///
+/// See [`Texture::compare`].
+#[derive(Copy, Clone)]
+pub struct Comparison<T> {
+    pub(crate) texture: T,
+    pub(crate) test: Ordering,
+    pub(crate) kernel: usize,
+    pub(crate) filtered: bool,
+}
+
+impl<T> Comparison<T> {
+    /// Bilinearly filter the results of the comparisons (see [`Comparison`]).
+    pub fn filtered(self) -> Self {
+        Self {
+            filtered: true,
+            ..self
+        }
+    }
+}
+
+impl<T> Comparison<T>
+where
+    T: Texture<2, Index = usize>,
+    T::Texel: PartialOrd<f32>,
+{
+    /// Compare `reference` against the depth texels around `index`, returning the proportion (between 0 and 1) of
+    /// texels for which `reference` has the ordering `test` relative to the texel.
+    pub fn sample_compare(&self, index: [f32; 2], reference: f32) -> f32 {
+        self.sample([(index[0], reference), (index[1], reference)])
+    }
+
+    /// Whether `reference` has the ordering `test` relative to the texel at `[x, y]`, as `1.0` or `0.0`.
+    ///
+    /// # Safety
+    ///
+    /// The texel must lie within the bounds of the texture.
+    #[inline(always)]
+    unsafe fn passes(&self, [x, y]: [usize; 2], reference: f32) -> f32 {
+        let depth = self.texture.read_unchecked([x, y]);
+        if depth.partial_cmp(&reference).map(Ordering::reverse) == Some(self.test) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<T> Sampler<2> for Comparison<T>
+where
+    T: Texture<2, Index = usize>,
+    T::Texel: PartialOrd<f32>,
+{
+    type Index = (f32, f32);
+
+    type Sample = f32;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.texture
+    }
+
+    fn sample(&self, [(u, reference), (v, _)]: [Self::Index; 2]) -> Self::Sample {
+        let kernel = self.kernel.max(1);
+        let size = self.texture.size();
+        if size[0] == 0 || size[1] == 0 {
+            return 0.0;
+        }
+
+        let offset = (kernel - 1) as f32 * 0.5;
+        let mut passed = 0.0;
+        if self.filtered {
+            // Positions are measured from the centre of the first texel, and the kernel is centred on the position
+            let start = [
+                u * size[0] as f32 - 0.5 - offset,
+                v * size[1] as f32 - 0.5 - offset,
+            ];
+            for j in 0..kernel {
+                for i in 0..kernel {
+                    // Find the texel before the position and the position's distance past its centre along each axis
+                    let [(x0, x1, fract_x), (y0, y1, fract_y)] = [0, 1].map(|axis| {
+                        let pos =
+                            (start[axis] + [i, j][axis] as f32).clamp(0.0, (size[axis] - 1) as f32);
+                        let p0 = pos.floor();
+                        let p0i = p0 as usize;
+                        (p0i, (p0i + 1).min(size[axis] - 1), pos - p0)
+                    });
+                    // Safety: all texel positions have been clamped to the bounds of the texture
+                    let [p00, p01, p10, p11] = unsafe {
+                        [[x0, y0], [x0, y1], [x1, y0], [x1, y1]]
+                            .map(|xy| self.passes(xy, reference))
+                    };
+                    let t0 = p00 * (1.0 - fract_y) + p01 * fract_y;
+                    let t1 = p10 * (1.0 - fract_y) + p11 * fract_y;
+                    passed += t0 * (1.0 - fract_x) + t1 * fract_x;
+                }
+            }
+        } else {
+            // Find the first texel of the kernel, such that the kernel is centred on the sampled texel
+            let start = [
+                (u * size[0] as f32 - offset).floor(),
+                (v * size[1] as f32 - offset).floor(),
+            ];
+            for j in 0..kernel {
+                let y = (start[1] + j as f32).clamp(0.0, (size[1] - 1) as f32) as usize;
+                for i in 0..kernel {
+                    let x = (start[0] + i as f32).clamp(0.0, (size[0] - 1) as f32) as usize;
+                    // Safety: `x` and `y` have been clamped to the bounds of the texture
+                    passed += unsafe { self.passes([x, y], reference) };
+                }
+            }
+        }
+        passed / (kernel * kernel) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::Buffer2d, texture::Target};
+
+    /// A 16 x 1 depth texture with a step from depth 0.25 to depth 0.75 between the 8th and 9th texels.
+    fn step() -> Buffer2d<f32> {
+        let mut tex = Buffer2d::fill([16, 1], 0.25);
+        (8..16).for_each(|x| tex.write(x, 0, 0.75));
+        tex
+    }
+
+    #[test]
+    fn hard_shadow() {
+        let shadow = step().compare(Ordering::Less, 1);
+        for x in 0..16 {
+            let u = (x as f32 + 0.5) / 16.0;
+            let lit = shadow.sample([(u, 0.5), (0.5, 0.5)]);
+            assert_eq!(lit, if x < 8 { 0.0 } else { 1.0 }, "texel {}", x);
+        }
+    }
+
+    #[test]
+    fn transition_is_fractional() {
+        let shadow = step().compare(Ordering::Less, 3);
+        let lit = (0..16)
+            .map(|x| shadow.sample_compare([(x as f32 + 0.5) / 16.0, 0.5], 0.5))
+            .collect::<alloc::vec::Vec<_>>();
+
+        // Far from the step, the result is the same as a hard shadow
+        assert!(lit[..7].iter().all(|&l| l == 0.0), "{:?}", lit);
+        assert!(lit[9..].iter().all(|&l| l == 1.0), "{:?}", lit);
+        // Either side of the step, the kernel straddles both depths
+        assert!((lit[7] - 1.0 / 3.0).abs() < 1.0e-6, "{:?}", lit);
+        assert!((lit[8] - 2.0 / 3.0).abs() < 1.0e-6, "{:?}", lit);
+    }
+
+    #[test]
+    fn compares_raw_texels() {
+        // Halfway between the two depths, a filtered depth sampler would produce exactly the reference depth and so
+        // fail every comparison. Comparing texels individually produces partial occlusion instead.
+        let shadow = step().compare(Ordering::Less, 2);
+        assert_eq!(shadow.sample_compare([0.5, 0.5], 0.5), 0.5);
+        assert_eq!(shadow.sample_compare([0.5, 0.5], 0.1), 1.0);
+        assert_eq!(shadow.sample_compare([0.5, 0.5], 0.9), 0.0);
+    }
+
+    #[test]
+    fn clamps_to_edge() {
+        let shadow = step().compare(Ordering::Less, 5);
+        assert_eq!(shadow.sample_compare([-10.0, 0.5], 0.5), 0.0);
+        assert_eq!(shadow.sample_compare([10.0, -3.0], 0.5), 1.0);
+
+        let shadow = step().pcf(Ordering::Less);
+        assert_eq!(shadow.sample_compare([-10.0, 0.5], 0.5), 0.0);
+        assert_eq!(shadow.sample_compare([10.0, -3.0], 0.5), 1.0);
+        assert_eq!(shadow.sample_compare([1.0, 1.0], 0.5), 1.0);
+    }
+
+    #[test]
+    fn pcf_texel_centres_are_hard() {
+        let shadow = step().pcf(Ordering::Less);
+        for x in 0..16 {
+            let u = (x as f32 + 0.5) / 16.0;
+            let lit = shadow.sample_compare([u, 0.5], 0.5);
+            assert_eq!(lit, if x < 8 { 0.0 } else { 1.0 }, "texel {}", x);
+        }
+    }
+
+    #[test]
+    fn pcf_edges_are_filtered() {
+        let shadow = step().pcf(Ordering::Less);
+        // Between the centres of the texels either side of the step, the result varies linearly
+        for t in [0.0, 0.1, 0.5, 0.75, 1.0] {
+            let u = (7.5 + t) / 16.0;
+            let lit = shadow.sample_compare([u, 0.5], 0.5);
+            assert!((lit - t).abs() < 1.0e-6, "{}: {}", t, lit);
+        }
+    }
+
+    #[test]
+    fn pcf_ordering_is_configurable() {
+        let lit = step().pcf(Ordering::Less).sample_compare([0.49, 0.5], 0.5);
+        let greater = step()
+            .pcf(Ordering::Greater)
+            .sample_compare([0.49, 0.5], 0.5);
+        assert!(lit > 0.0 && lit < 1.0);
+        assert!((lit + greater - 1.0).abs() < 1.0e-6);
+        // Equal depths pass neither test
+        assert_eq!(
+            step().pcf(Ordering::Less).sample_compare([0.1, 0.5], 0.25),
+            0.0
+        );
+        assert_eq!(
+            step()
+                .pcf(Ordering::Greater)
+                .sample_compare([0.1, 0.5], 0.25),
+            0.0
+        );
+    }
+
+    #[test]
+    fn filtered_kernel() {
+        // A filtered kernel is as soft as its unfiltered counterpart at texel centres, but varies smoothly between them
+        let hard = step().compare(Ordering::Less, 3);
+        let soft = step().compare(Ordering::Less, 3).filtered();
+        for x in 0..16 {
+            let u = (x as f32 + 0.5) / 16.0;
+            assert_eq!(
+                soft.sample_compare([u, 0.5], 0.5),
+                hard.sample_compare([u, 0.5], 0.5)
+            );
+        }
+        // A quarter of the way past the centre of the last texel before the step, only the middle tap straddles it
+        let between = soft.sample_compare([7.75 / 16.0, 0.5], 0.5);
+        assert!((between - 1.25 / 3.0).abs() < 1.0e-6, "{}", between);
+    }
+}
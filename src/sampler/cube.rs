@@ -0,0 +1,231 @@
+use super::*;
+
+/// The largest `f32` that is less than `1.0`.
+const BELOW_ONE: f32 = 1.0 - f32::EPSILON * 0.5;
+
+/// A cube map, made up of six square faces, that may be sampled with a direction vector (like a `samplerCube` in
+/// GLSL).
+///
+/// Each face is itself a sampler (such as [`Nearest`] or [`Linear`]), which determines how texels within the face are
+/// filtered. Faces are given in the order `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`, and are oriented as they are in Vulkan
+/// (with the first row of each face at the top): `+Y` points towards the top of the `±X` and `±Z` faces, while `+Z`
+/// points towards the bottom of the `+Y` face and the top of the `-Y` face.
+///
+/// Sampling finds the axis along which the direction is largest, projects the direction onto the face at the end of
+/// that axis, and samples that face. Directions do not need to be normalised. Samples are clamped to the edges of each
+/// face rather than being blended with neighbouring faces, so seams may be visible when sampling low-resolution cube
+/// maps with a linear filter.
+///
+/// As a [`Texture`], a cube map is a 3-dimensional texture whose third axis selects the face.
+#[derive(Copy, Clone)]
+pub struct CubeMap<S> {
+    faces: [S; 6],
+}
+
+impl<S> CubeMap<S> {
+    /// The index of the `+X` face.
+    pub const POS_X: usize = 0;
+    /// The index of the `-X` face.
+    pub const NEG_X: usize = 1;
+    /// The index of the `+Y` face.
+    pub const POS_Y: usize = 2;
+    /// The index of the `-Y` face.
+    pub const NEG_Y: usize = 3;
+    /// The index of the `+Z` face.
+    pub const POS_Z: usize = 4;
+    /// The index of the `-Z` face.
+    pub const NEG_Z: usize = 5;
+
+    /// Create a cube map from samplers of each of its faces, in the order `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`.
+    pub fn new(faces: [S; 6]) -> Self {
+        Self { faces }
+    }
+
+    /// Access the sampler of each face of the cube map.
+    pub fn faces(&self) -> &[S; 6] {
+        &self.faces
+    }
+
+    /// Find the face that the given direction points towards, along with the normalised coordinates of the point on
+    /// that face that the direction points at.
+    pub fn face_coords([x, y, z]: [f32; 3]) -> (usize, [f32; 2]) {
+        let [ax, ay, az] = [x.abs(), y.abs(), z.abs()];
+        // The face, the major axis, and the (unscaled) coordinates on the face
+        let (face, ma, [sc, tc]) = if ax >= ay && ax >= az {
+            if x >= 0.0 {
+                (Self::POS_X, ax, [-z, -y])
+            } else {
+                (Self::NEG_X, ax, [z, -y])
+            }
+        } else if ay >= az {
+            if y >= 0.0 {
+                (Self::POS_Y, ay, [x, z])
+            } else {
+                (Self::NEG_Y, ay, [x, -z])
+            }
+        } else if z >= 0.0 {
+            (Self::POS_Z, az, [x, -y])
+        } else {
+            (Self::NEG_Z, az, [-x, -y])
+        };
+
+        if ma > 0.0 {
+            let coord = |c: f32| (c / ma * 0.5 + 0.5).clamp(0.0, BELOW_ONE);
+            (face, [coord(sc), coord(tc)])
+        } else {
+            // Zero-length (or NaN) directions don't point anywhere, so pick the centre of a face
+            (face, [0.5; 2])
+        }
+    }
+}
+
+impl<S> Texture<3> for CubeMap<S>
+where
+    S: Sampler<2>,
+    S::Texture: Texture<2, Index = usize>,
+{
+    type Index = usize;
+    type Texel = <S::Texture as Texture<2>>::Texel;
+
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 3] {
+        let [w, h] = self.faces[0].raw_texture().size();
+        [w, h, 6]
+    }
+
+    #[inline(always)]
+    fn read(&self, [x, y, face]: [Self::Index; 3]) -> Self::Texel {
+        self.faces[face].raw_texture().read([x, y])
+    }
+}
+
+impl<S> Sampler<3> for CubeMap<S>
+where
+    S: Sampler<2, Index = f32>,
+    S::Texture: Texture<2, Index = usize>,
+{
+    type Index = f32;
+
+    type Sample = S::Sample;
+
+    type Texture = Self;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        self
+    }
+
+    #[inline(always)]
+    fn sample(&self, dir: [Self::Index; 3]) -> Self::Sample {
+        let (face, [s, t]) = Self::face_coords(dir);
+        self.faces[face].sample([s, t])
+    }
+
+    #[inline(always)]
+    unsafe fn sample_unchecked(&self, dir: [Self::Index; 3]) -> Self::Sample {
+        let (face, [s, t]) = Self::face_coords(dir);
+        // Safety: `face_coords` always produces a valid face and coordinates within the bounds of that face
+        self.faces.get_unchecked(face).sample_unchecked([s, t])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer2d;
+    use alloc::vec::Vec;
+
+    /// A face in which each texel is a unique `(face, x, y)` triple.
+    type Face = Buffer2d<(usize, usize, usize)>;
+
+    fn faces() -> Vec<Face> {
+        (0..6)
+            .map(|face| {
+                let mut tex = Buffer2d::fill([4, 4], (0, 0, 0));
+                for y in 0..4 {
+                    for x in 0..4 {
+                        *tex.get_mut([x, y]) = (face, x, y);
+                    }
+                }
+                tex
+            })
+            .collect()
+    }
+
+    fn cube(faces: &[Face]) -> CubeMap<Nearest<&Face>> {
+        CubeMap::new(core::array::from_fn(|i| (&faces[i]).nearest()))
+    }
+
+    #[test]
+    fn selects_dominant_axis() {
+        let faces = faces();
+        let cube = cube(&faces);
+        for (dir, face) in [
+            ([1.0, 0.2, -0.3], CubeMap::<()>::POS_X),
+            ([-5.0, 0.2, -0.3], CubeMap::<()>::NEG_X),
+            ([0.1, 2.0, 0.3], CubeMap::<()>::POS_Y),
+            ([0.1, -2.0, 0.0], CubeMap::<()>::NEG_Y),
+            ([0.1, 0.2, 0.3], CubeMap::<()>::POS_Z),
+            ([0.1, 0.2, -30.0], CubeMap::<()>::NEG_Z),
+        ] {
+            assert_eq!(cube.sample(dir).0, face, "{:?}", dir);
+        }
+    }
+
+    #[test]
+    fn vulkan_orientation() {
+        let faces = faces();
+        let cube = cube(&faces);
+        // Looking along each axis, find the texels in the directions of the other two axes
+        let corner = |dir: [f32; 3]| {
+            let (_, x, y) = cube.sample(dir);
+            [x, y]
+        };
+        // +X: +Z is towards -s, +Y is towards -t
+        assert_eq!(corner([1.0, 0.9, 0.9]), [0, 0]);
+        assert_eq!(corner([1.0, -0.9, -0.9]), [3, 3]);
+        // -X: +Z is towards +s
+        assert_eq!(corner([-1.0, 0.9, 0.9]), [3, 0]);
+        // +Y: +X is towards +s, +Z is towards +t
+        assert_eq!(corner([0.9, 1.0, 0.9]), [3, 3]);
+        // -Y: +Z is towards -t
+        assert_eq!(corner([0.9, -1.0, 0.9]), [3, 0]);
+        // +Z: +X is towards +s, +Y is towards -t
+        assert_eq!(corner([0.9, 0.9, 1.0]), [3, 0]);
+        // -Z: +X is towards -s
+        assert_eq!(corner([0.9, 0.9, -1.0]), [0, 0]);
+    }
+
+    #[test]
+    fn direction_length_is_irrelevant() {
+        let faces = faces();
+        let cube = cube(&faces);
+        for dir in [[0.3, -0.7, 0.2], [-0.9, 0.1, 0.5], [0.0, 0.0, -1.0]] {
+            let sample = cube.sample(dir);
+            for scale in [1.0e-3, 0.5, 7.0, 1.0e4] {
+                assert_eq!(cube.sample(dir.map(|e| e * scale)), sample);
+            }
+        }
+        // Degenerate directions still produce a sample
+        cube.sample([0.0; 3]);
+        cube.sample([f32::NAN; 3]);
+    }
+
+    #[test]
+    fn edges_are_clamped() {
+        let tex = Buffer2d::fill([2, 2], 1.0);
+        let cube = CubeMap::new(core::array::from_fn(|_| (&tex).linear()));
+        // Exactly along an edge or corner, linear samplers must not wrap around to the opposite edge of the face
+        for dir in [
+            [1.0, 1.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [-1.0, -1.0, -1.0],
+            [0.0, -1.0, 1.0],
+        ] {
+            assert_eq!(cube.sample(dir), 1.0);
+        }
+        let (_, coords) = CubeMap::<()>::face_coords([1.0, -1.0, -1.0]);
+        assert!(coords.iter().all(|c| (0.0..1.0).contains(c)));
+        assert_eq!(cube.size(), [2, 2, 6]);
+    }
+}
@@ -0,0 +1,184 @@
+use super::*;
+use core::ops::{Add, Mul};
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
+
+/// The curve used by a [`Cubic`] sampler to interpolate between texels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CubicFilter {
+    /// A Catmull-Rom spline, which passes through every texel.
+    ///
+    /// The spline has negative lobes, so samples may undershoot or overshoot the texels around them near sharp edges
+    /// (for example, colours may fall below `0.0` or rise above `1.0`).
+    #[default]
+    CatmullRom,
+    /// A cubic B-spline, which is smoother than [`CubicFilter::CatmullRom`] but blurs the texture slightly, since it
+    /// does not pass through the texels. Samples always lie within the range of the texels around them.
+    BSpline,
+}
+
+impl CubicFilter {
+    /// The weights of the 4 texels around a position `t` (between `0.0` and `1.0`) of the way from the second texel to
+    /// the third.
+    #[inline(always)]
+    fn weights(self, t: f32) -> [f32; 4] {
+        let (t2, t3) = (t * t, t * t * t);
+        match self {
+            Self::CatmullRom => [
+                (-t3 + 2.0 * t2 - t) * 0.5,
+                (3.0 * t3 - 5.0 * t2 + 2.0) * 0.5,
+                (-3.0 * t3 + 4.0 * t2 + t) * 0.5,
+                (t3 - t2) * 0.5,
+            ],
+            Self::BSpline => [
+                (1.0 - t) * (1.0 - t) * (1.0 - t) / 6.0,
+                (3.0 * t3 - 6.0 * t2 + 4.0) / 6.0,
+                (-3.0 * t3 + 3.0 * t2 + 3.0 * t + 1.0) / 6.0,
+                t3 / 6.0,
+            ],
+        }
+    }
+}
+
+/// A sampler that uses bicubic interpolation over the 4x4 texels around each sample (see [`CubicFilter`]).
+///
+/// Texels are positioned as they are for [`Linear`], and neighbours beyond the edge of the texture are clamped to the
+/// edge.
+pub struct Cubic<T> {
+    pub(crate) texture: T,
+    pub(crate) filter: CubicFilter,
+}
+
+impl<T: Texture<2, Index = usize>> Cubic<T> {
+    /// Create a new bicubic sampler from a texture, using the given filter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture has no size.
+    pub fn new(texture: T, filter: CubicFilter) -> Self {
+        assert!(
+            texture.size().iter().all(|e| *e >= 1),
+            "Cubic-interpolated texture cannot have no size",
+        );
+        Self { texture, filter }
+    }
+}
+
+impl<T> Sampler<2> for Cubic<T>
+where
+    T: Texture<2, Index = usize>,
+    T::Texel: Mul<f32, Output = T::Texel> + Add<Output = T::Texel>,
+{
+    type Index = f32;
+
+    type Sample = T::Texel;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.texture
+    }
+
+    #[inline]
+    fn sample(&self, index: [Self::Index; 2]) -> Self::Sample {
+        let size = self.texture.size();
+        // Index in texture coordinates, following the same conventions as `Linear`
+        let index_tex = [0, 1].map(|i| index[i].fract() * size[i] as f32);
+        let posi = index_tex.map(|e| e.trunc() as usize);
+        let [wx, wy] = index_tex.map(|e| self.filter.weights(e.fract()));
+
+        // The 4 texels around the sample along each axis, clamped to the edges
+        let [xs, ys] =
+            [0, 1].map(|i| [0, 1, 2, 3].map(|j| (posi[i] + j).saturating_sub(1).min(size[i] - 1)));
+
+        let weighted_sum = |texels: [T::Texel; 4], [w0, w1, w2, w3]: [f32; 4]| {
+            let [t0, t1, t2, t3] = texels;
+            t0 * w0 + t1 * w1 + t2 * w2 + t3 * w3
+        };
+        // SAFETY: the `min` above ensures we're in-bounds. Also, this type cannot be created with an underlying
+        // texture with a zero size.
+        let rows = ys.map(|y| {
+            weighted_sum(
+                xs.map(|x| unsafe { self.texture.read_unchecked([x, y]) }),
+                wx,
+            )
+        });
+        weighted_sum(rows, wy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer2d;
+
+    /// An 8x8 texture with the given value in every texel of each column.
+    fn columns(f: impl Fn(usize) -> f32) -> Buffer2d<f32> {
+        let mut tex = Buffer2d::fill([8, 8], 0.0);
+        for (i, t) in tex.raw_mut().iter_mut().enumerate() {
+            *t = f(i % 8);
+        }
+        tex
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1.0e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let mut tex = Buffer2d::fill([8, 8], 0.0);
+        for y in 0..8 {
+            for x in 0..8 {
+                *tex.get_mut([x, y]) = x as f32 + 2.0 * y as f32;
+            }
+        }
+        // Both filters reproduce linear gradients exactly, away from the edges
+        for filter in [CubicFilter::CatmullRom, CubicFilter::BSpline] {
+            let sampler = Cubic::new(&tex, filter);
+            assert_close(sampler.sample([0.3, 0.45]), 2.4 + 2.0 * 3.6);
+            assert_close(sampler.sample([0.5, 0.25]), 4.0 + 2.0 * 2.0);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_spike() {
+        let tex = columns(|x| if x == 3 { 1.0 } else { 0.0 });
+        let sampler = (&tex).cubic();
+        // Catmull-Rom splines pass through every texel...
+        assert_close(sampler.sample([3.0 / 8.0, 0.5]), 1.0);
+        assert_close(sampler.sample([4.0 / 8.0, 0.5]), 0.0);
+        // ...with weights of -1/16 and 9/16 for the texels either side of the midpoint between two texels, which
+        // undershoots beside the spike
+        assert_close(sampler.sample([2.5 / 8.0, 0.5]), 9.0 / 16.0);
+        assert_close(sampler.sample([4.5 / 8.0, 0.5]), -1.0 / 16.0);
+    }
+
+    #[test]
+    fn b_spline_spike() {
+        let tex = columns(|x| if x == 3 { 1.0 } else { 0.0 });
+        let sampler = Cubic::new(&tex, CubicFilter::BSpline);
+        // B-splines weigh each texel and its neighbours 4:1, and never undershoot
+        assert_close(sampler.sample([3.0 / 8.0, 0.5]), 4.0 / 6.0);
+        assert_close(sampler.sample([4.0 / 8.0, 0.5]), 1.0 / 6.0);
+        assert_close(sampler.sample([4.5 / 8.0, 0.5]), 1.0 / 48.0);
+        assert_close(sampler.sample([5.0 / 8.0, 0.5]), 0.0);
+    }
+
+    #[test]
+    fn clamps_to_edge() {
+        let tex = columns(|x| x as f32);
+        let sampler = (&tex).cubic();
+        // Beyond the last texel, the texture is flat
+        assert_close(sampler.sample([7.0 / 8.0, 0.5]), 7.0);
+        assert_close(sampler.sample([7.0 / 8.0, 0.999]), 7.0);
+        // Before the second texel, the clamped neighbour flattens the gradient
+        assert_close(sampler.sample([0.0, 0.0]), 0.0);
+        assert_close(sampler.sample([0.5 / 8.0, 0.0]), 0.4375);
+
+        let single = Buffer2d::fill([1, 1], 2.5);
+        assert_close((&single).cubic().sample([0.3, 0.8]), 2.5);
+    }
+}
@@ -10,25 +10,25 @@ use micromath::F32Ext;
 /// A sampler that uses nearest-neighbor sampling.
 pub struct Linear<T, I = f32>(pub(crate) T, pub(crate) PhantomData<I>);
 
-impl<T> Sampler<2> for Linear<T, f32>
+impl<T> Linear<T, f32>
 where
     T: Texture<2, Index = usize>,
-    T::Texel: Mul<f32, Output = T::Texel> + Add<Output = T::Texel>,
 {
-    type Index = f32;
-
-    type Sample = T::Texel;
-
-    type Texture = T;
-
+    /// Fetch the four texels that would be blended together when sampling at the given index, without blending them.
+    ///
+    /// Texels are returned in the order `[texel00, texel10, texel01, texel11]`, where `texel00` is the texel at the
+    /// lowest x and y coordinates, `texel10` is its neighbour along +x, and `texel01` is its neighbour along +y.
+    /// Neighbours beyond the edge of the texture are clamped to the edge. This is equivalent to `textureGather` in
+    /// GLSL, and is useful for implementing custom filters.
     #[inline(always)]
-    fn raw_texture(&self) -> &Self::Texture {
-        &self.0
+    pub fn gather(&self, index: [f32; 2]) -> [T::Texel; 4] {
+        self.gather_fract(index).0
     }
 
+    /// Like [`Linear::gather`], but also returns the position of the index between the texels.
     #[inline(always)]
-    fn sample(&self, [x, y]: [Self::Index; 2]) -> Self::Sample {
-        let [w, h] = self.raw_texture().size();
+    fn gather_fract(&self, [x, y]: [f32; 2]) -> ([T::Texel; 4], [f32; 2]) {
+        let [w, h] = self.0.size();
         // Index in texture coordinates
         let index_tex_x = x.fract() * w as f32;
         let index_tex_y = y.fract() * h as f32;
@@ -42,27 +42,87 @@ where
         debug_assert!(posi_x < w, "pos: {:?}, w: {:?}", posi_x, w,);
         debug_assert!(posi_y < h, "pos: {:?}, h: {:?}", posi_y, h,);
 
-        let p0x = (posi_x + 0).min(w - 1);
-        let p0y = (posi_y + 0).min(h - 1);
+        let p0x = posi_x.min(w - 1);
+        let p0y = posi_y.min(h - 1);
         let p1x = (posi_x + 1).min(w - 1);
         let p1y = (posi_y + 1).min(h - 1);
 
-        let (t00, t10, t01, t11);
         // SAFETY: the `min` above ensures we're in-bounds. Also, this type cannot be created with an underlying
         // texture with a zero size.
-        unsafe {
-            t00 = self.raw_texture().read_unchecked([p0x, p0y]);
-            t10 = self.raw_texture().read_unchecked([p1x, p0y]);
-            t01 = self.raw_texture().read_unchecked([p0x, p1y]);
-            t11 = self.raw_texture().read_unchecked([p1x, p1y]);
-        }
+        let texels = unsafe {
+            [
+                self.0.read_unchecked([p0x, p0y]),
+                self.0.read_unchecked([p1x, p0y]),
+                self.0.read_unchecked([p0x, p1y]),
+                self.0.read_unchecked([p1x, p1y]),
+            ]
+        };
+        (texels, [fract_x, fract_y])
+    }
+}
+
+impl<T> Sampler<1> for Linear<T, f32>
+where
+    T: Texture<1, Index = usize>,
+    T::Texel: Mul<f32, Output = T::Texel> + Add<Output = T::Texel>,
+{
+    type Index = f32;
+
+    type Sample = T::Texel;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.0
+    }
 
-        let t0 = t00 * (1.0 - fract_y) + t01 * fract_y;
-        let t1 = t10 * (1.0 - fract_y) + t11 * fract_y;
+    #[inline(always)]
+    fn sample(&self, [x]: [Self::Index; 1]) -> Self::Sample {
+        let [w] = self.0.size();
+        // Index in texture coordinates, following the same conventions as the 2D sampler
+        let index_tex = x.fract() * w as f32;
+        let posi = index_tex.trunc() as usize;
+        let fract = index_tex.fract();
+
+        debug_assert!(posi < w, "pos: {:?}, w: {:?}", posi, w,);
 
-        let t = t0 * (1.0 - fract_x) + t1 * fract_x;
+        let p0 = posi.min(w - 1);
+        let p1 = (posi + 1).min(w - 1);
 
-        t
+        // SAFETY: the `min` above ensures we're in-bounds. Also, this type cannot be created with an underlying
+        // texture with a zero size.
+        let (t0, t1) = unsafe { (self.0.read_unchecked([p0]), self.0.read_unchecked([p1])) };
+        t0 * (1.0 - fract) + t1 * fract
+    }
+
+    #[inline(always)]
+    unsafe fn sample_unchecked(&self, index: [Self::Index; 1]) -> Self::Sample {
+        // TODO: Not this
+        self.sample(index)
+    }
+}
+
+impl<T> Sampler<2> for Linear<T, f32>
+where
+    T: Texture<2, Index = usize>,
+    T::Texel: Mul<f32, Output = T::Texel> + Add<Output = T::Texel>,
+{
+    type Index = f32;
+
+    type Sample = T::Texel;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.0
+    }
+
+    #[inline(always)]
+    fn sample(&self, index: [Self::Index; 2]) -> Self::Sample {
+        let (texels, fract) = self.gather_fract(index);
+        bilinear(texels, fract)
     }
 
     #[inline(always)]
@@ -71,3 +131,164 @@ where
         self.sample(index)
     }
 }
+
+impl<T> Sampler<3> for Linear<T, f32>
+where
+    T: Texture<3, Index = usize>,
+    T::Texel: Mul<f32, Output = T::Texel> + Add<Output = T::Texel>,
+{
+    type Index = f32;
+
+    type Sample = T::Texel;
+
+    type Texture = T;
+
+    #[inline(always)]
+    fn raw_texture(&self) -> &Self::Texture {
+        &self.0
+    }
+
+    #[inline(always)]
+    fn sample(&self, index: [Self::Index; 3]) -> Self::Sample {
+        let size = self.0.size();
+        // Index in texture coordinates, following the same conventions as the 2D sampler
+        let index_tex = [0, 1, 2].map(|i| index[i].fract() * size[i] as f32);
+        let posi = index_tex.map(|e| e.trunc() as usize);
+        let [fract_x, fract_y, fract_z] = index_tex.map(|e| e.fract());
+
+        debug_assert!(
+            (0..3).all(|i| posi[i] < size[i]),
+            "pos: {:?}, size: {:?}",
+            posi,
+            size,
+        );
+
+        let p0 = [0, 1, 2].map(|i| posi[i].min(size[i] - 1));
+        let p1 = [0, 1, 2].map(|i| (posi[i] + 1).min(size[i] - 1));
+
+        // SAFETY: the `min` above ensures we're in-bounds. Also, this type cannot be created with an underlying
+        // texture with a zero size.
+        let layer = |z| unsafe {
+            bilinear(
+                [
+                    self.0.read_unchecked([p0[0], p0[1], z]),
+                    self.0.read_unchecked([p1[0], p0[1], z]),
+                    self.0.read_unchecked([p0[0], p1[1], z]),
+                    self.0.read_unchecked([p1[0], p1[1], z]),
+                ],
+                [fract_x, fract_y],
+            )
+        };
+
+        // Textures with a depth of 1 (or samples at the far edge) only have one layer to sample, which is returned
+        // exactly as the 2D sampler would
+        if p0[2] == p1[2] {
+            layer(p0[2])
+        } else {
+            layer(p0[2]) * (1.0 - fract_z) + layer(p1[2]) * fract_z
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn sample_unchecked(&self, index: [Self::Index; 3]) -> Self::Sample {
+        // TODO: Not this
+        self.sample(index)
+    }
+}
+
+/// Blend the texels `[texel00, texel10, texel01, texel11]` (as produced by [`Linear::gather`]) at the given position
+/// between them.
+#[inline(always)]
+fn bilinear<T>([t00, t10, t01, t11]: [T; 4], [fract_x, fract_y]: [f32; 2]) -> T
+where
+    T: Mul<f32, Output = T> + Add<Output = T>,
+{
+    let t0 = t00 * (1.0 - fract_y) + t01 * fract_y;
+    let t1 = t10 * (1.0 - fract_y) + t11 * fract_y;
+
+    t0 * (1.0 - fract_x) + t1 * fract_x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::{Buffer1d, Buffer2d, Buffer3d};
+
+    fn texture() -> Buffer2d<f32> {
+        let mut tex = Buffer2d::fill([2, 2], 0.0);
+        tex.raw_mut().copy_from_slice(&[0.0, 1.0, 2.0, 3.0]);
+        tex
+    }
+
+    #[test]
+    fn gather_order() {
+        let tex = texture();
+        let sampler = (&tex).linear();
+        // Texel `i` lies at `i / size`, so this is midway between all four texels
+        assert_eq!(sampler.gather([0.25, 0.25]), [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(sampler.sample([0.25, 0.25]), 1.5);
+    }
+
+    #[test]
+    fn linear_1d() {
+        // A lookup table, such as a tone-mapping curve
+        let mut lut = Buffer1d::fill([4], 0.0);
+        lut.raw_mut().copy_from_slice(&[0.0, 0.5, 2.0, 4.0]);
+        let sampler = (&lut).linear();
+        assert_eq!(sampler.sample([0.5]), 2.0);
+        // Texel `i` lies at `i / size`, so this is midway between the middle two texels
+        assert_eq!(sampler.sample([0.375]), 1.25);
+        assert_eq!(sampler.sample([0.0625]), 0.125);
+        // Beyond the last texel, samples clamp to it
+        assert_eq!(sampler.sample([0.875]), 4.0);
+    }
+
+    #[test]
+    fn trilinear() {
+        // Each texel holds its own coordinates
+        let mut tex = Buffer3d::fill([2, 3, 4], vek::Vec3::zero());
+        for z in 0..4 {
+            for y in 0..3 {
+                for x in 0..2 {
+                    *tex.get_mut([x, y, z]) = vek::Vec3::new(x as f32, y as f32, z as f32);
+                }
+            }
+        }
+        let sampler = (&tex).linear();
+        let sample = |index| sampler.sample(index).into_array();
+        // Texel `i` lies at `i / size`, so the sample interpolates between each pair of texels
+        assert_eq!(sample([0.25, 0.5, 0.375]), [0.5, 1.5, 1.5]);
+        assert_eq!(sample([0.0, 0.25, 0.5]), [0.0, 0.75, 2.0]);
+        // Beyond the last texel along each axis, samples clamp to it
+        assert_eq!(sample([0.75, 0.9, 0.9]), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn trilinear_flat_matches_bilinear() {
+        let flat = Buffer2d::fill_with([3, 2], {
+            let mut i = 0.0;
+            move || {
+                i += 1.7;
+                i
+            }
+        });
+        let mut volume = Buffer3d::fill([3, 2, 1], 0.0);
+        volume.raw_mut().copy_from_slice(flat.raw());
+        let (s2, s3) = ((&flat).linear(), (&volume).linear());
+        for [x, y, z] in [[0.1, 0.2, 0.3], [0.5, 0.9, 0.0], [0.33, 0.77, 0.99]] {
+            assert_eq!(s2.sample([x, y]), s3.sample([x, y, z]));
+        }
+        // Textures with a size of 1 along every axis sample their only texel
+        let single = Buffer3d::fill([1, 1, 1], 4.0);
+        assert_eq!((&single).linear().sample([0.7, 0.2, 0.9]), 4.0);
+    }
+
+    #[test]
+    fn gather_clamps_to_edge() {
+        let tex = texture();
+        let sampler = (&tex).linear();
+        assert_eq!(sampler.gather([0.75, 0.25]), [1.0, 1.0, 3.0, 3.0]);
+        assert_eq!(sampler.gather([0.25, 0.75]), [2.0, 3.0, 2.0, 3.0]);
+        assert_eq!(sampler.gather([0.75, 0.75]), [3.0; 4]);
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+use alloc::vec::Vec;
+use core::ops::{Add, Mul};
+
+/// A sampler that selects between the levels of a mip chain (usually [`Linear`] samplers of the levels of a
+/// [`MipChain`](crate::MipChain)), blending between the two levels either side of the level of detail given to
+/// [`Sampler::sample_lod`].
+///
+/// When sampled without a level of detail, the full-resolution level (level `0`) is sampled.
+#[derive(Clone)]
+pub struct Mipmapped<S> {
+    levels: Vec<S>,
+}
+
+impl<S> Mipmapped<S> {
+    /// Create a sampler from the samplers of each level, starting with the full-resolution level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no levels.
+    pub fn new(levels: Vec<S>) -> Self {
+        assert!(!levels.is_empty(), "Mipmapped sampler must have a level");
+        Self { levels }
+    }
+
+    /// The samplers of each level, starting with the full-resolution level.
+    pub fn levels(&self) -> &[S] {
+        &self.levels
+    }
+}
+
+impl<S, const N: usize> Sampler<N> for Mipmapped<S>
+where
+    S: Sampler<N>,
+    S::Sample: Mul<f32, Output = S::Sample> + Add<Output = S::Sample>,
+{
+    type Index = S::Index;
+    type Sample = S::Sample;
+    type Texture = S::Texture;
+
+    fn raw_texture(&self) -> &Self::Texture {
+        self.levels[0].raw_texture()
+    }
+    fn sample(&self, index: [Self::Index; N]) -> Self::Sample {
+        self.levels[0].sample(index)
+    }
+    unsafe fn sample_unchecked(&self, index: [Self::Index; N]) -> Self::Sample {
+        self.levels[0].sample_unchecked(index)
+    }
+    /// Levels of detail beyond the ends of the chain are clamped to them (as are NaNs, to level `0`).
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        let lod = lod.max(0.0).min((self.levels.len() - 1) as f32);
+        let level = lod as usize;
+        let t = lod - level as f32;
+        if t == 0.0 {
+            self.levels[level].sample_lod(index, 0.0)
+        } else {
+            self.levels[level].sample_lod(index.clone(), 0.0) * (1.0 - t)
+                + self.levels[level + 1].sample_lod(index, 0.0) * t
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::{Buffer2d, MipChain};
+
+    #[test]
+    fn select_level() {
+        // A checkerboard, which averages to grey
+        let mut tex = Buffer2d::fill([8, 8], 0.0);
+        for y in 0..8 {
+            for x in 0..8 {
+                *tex.get_mut([x, y]) = ((x + y) % 2) as f32;
+            }
+        }
+        let chain = MipChain::generate(tex);
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain.level(3).size(), [1, 1]);
+
+        let sampler = chain.linear();
+        let index = [0.0, 0.0];
+        assert_eq!(sampler.sample_lod(index, 0.0), 0.0);
+        assert_eq!(sampler.sample(index), 0.0);
+        assert_eq!(sampler.sample_lod(index, 1.0), 0.5);
+        // Between levels, the levels either side are blended together
+        assert_eq!(sampler.sample_lod(index, 0.5), 0.25);
+        // ...and beyond the ends of the chain, the nearest level is used
+        assert_eq!(sampler.sample_lod(index, 10.0), 0.5);
+        assert_eq!(sampler.sample_lod(index, -1.0), 0.0);
+
+        // Samplers without levels ignore the level of detail
+        assert_eq!(chain.level(0).linear().sample_lod(index, 1.0), 0.0);
+    }
+}
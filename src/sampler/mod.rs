@@ -1,13 +1,25 @@
+pub mod anisotropic;
+pub mod array;
+pub mod comparison;
+pub mod cube;
+pub mod cubic;
 pub mod linear;
+pub mod mipmap;
 pub mod nearest;
 
-pub use self::{linear::Linear, nearest::Nearest};
+pub use self::{
+    anisotropic::{Anisotropic, SamplerGrad},
+    array::{LayerSizeMismatch, LinearLayer, NearestLayer, TextureArray},
+    comparison::Comparison,
+    cube::CubeMap,
+    cubic::{Cubic, CubicFilter},
+    linear::Linear,
+    mipmap::Mipmapped,
+    nearest::Nearest,
+};
 
 use crate::{math::*, texture::Texture};
 
-#[cfg(feature = "micromath")]
-use micromath::F32Ext;
-
 /// A trait that describes a sampler of a texture.
 ///
 /// Samplers use normalised coordinates (between 0 and 1) to sample textures. Often, samplers will combine this with
@@ -48,6 +60,16 @@ pub trait Sampler<const N: usize> {
         self.sample(index)
     }
 
+    /// Sample the texture at the given index with the given level of detail (like `textureLod` in GLSL).
+    ///
+    /// The level of detail selects between the levels of a mip chain, where `0.0` is the full-resolution texture and
+    /// each level after it has half the resolution of the last (see [`Mipmapped`]). Samplers without a mip chain ignore
+    /// it, so by default this is the same as [`Sampler::sample`].
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        let _ = lod;
+        self.sample(index)
+    }
+
     /// Create a version of this sampler that clamps the index to the bounds of the sampler.
     ///
     /// See [`Clamped`].
@@ -77,9 +99,39 @@ pub trait Sampler<const N: usize> {
     {
         Mirrored(self)
     }
+
+    /// Create a version of this sampler whose indices, between `0.0` and `1.0`, map to the region between `uv_min` and
+    /// `uv_max` of the underlying sampler (such as a sprite within a texture atlas).
+    ///
+    /// See [`Atlas`].
+    fn atlas(self, uv_min: [f32; N], uv_max: [f32; N]) -> Atlas<Self, N>
+    where
+        Self: Sized,
+    {
+        Atlas {
+            sampler: self,
+            uv_min,
+            uv_max,
+        }
+    }
+
+    /// Create a version of this 2D sampler that filters anisotropically when sampled with derivatives, taking up to
+    /// `max_taps` samples (such as 4, 8, or 16) along the footprint of each fragment.
+    ///
+    /// See [`Anisotropic`] and [`SamplerGrad`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_taps` is `0`.
+    fn anisotropic(self, max_taps: usize) -> Anisotropic<Self>
+    where
+        Self: Sampler<2, Index = f32> + Sized,
+    {
+        Anisotropic::new(self, max_taps)
+    }
 }
 
-impl<'a, S: Sampler<N>, const N: usize> Sampler<N> for &'a S {
+impl<S: Sampler<N>, const N: usize> Sampler<N> for &S {
     type Index = S::Index;
     type Sample = S::Sample;
     type Texture = S::Texture;
@@ -93,13 +145,19 @@ impl<'a, S: Sampler<N>, const N: usize> Sampler<N> for &'a S {
     unsafe fn sample_unchecked(&self, index: [Self::Index; N]) -> Self::Sample {
         (*self).sample_unchecked(index)
     }
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        (*self).sample_lod(index, lod)
+    }
 }
 
 /// A sampler that clamps the index's components to the 0.0 <= x <= 1.0 range.
 #[derive(Copy, Clone)]
 pub struct Clamped<S>(S);
 
-impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Clamped<S> {
+impl<S: Sampler<N>, const N: usize> Sampler<N> for Clamped<S>
+where
+    S::Index: Float,
+{
     type Index = S::Index;
     type Sample = S::Sample;
     type Texture = S::Texture;
@@ -108,13 +166,17 @@ impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Clamped<S> {
         self.0.raw_texture()
     }
     fn sample(&self, index: [Self::Index; N]) -> Self::Sample {
-        let index = index.map(|e| e.max(0.0).min(1.0));
+        let index = index.map(|e| e.clamp_to(S::Index::ZERO, S::Index::ONE));
         self.0.sample(index)
     }
     unsafe fn sample_unchecked(&self, index: [Self::Index; N]) -> Self::Sample {
-        let index = index.map(|e| e.max(0.0).min(1.0));
+        let index = index.map(|e| e.clamp_to(S::Index::ZERO, S::Index::ONE));
         self.0.sample_unchecked(index)
     }
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        let index = index.map(|e| e.clamp_to(S::Index::ZERO, S::Index::ONE));
+        self.0.sample_lod(index, lod)
+    }
 }
 
 /// A sampler that tiles the index's components, repeating the sampler when sampling out-of-bounds.
@@ -123,7 +185,10 @@ impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Clamped<S> {
 #[derive(Copy, Clone)]
 pub struct Tiled<S>(S);
 
-impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Tiled<S> {
+impl<S: Sampler<N>, const N: usize> Sampler<N> for Tiled<S>
+where
+    S::Index: Float,
+{
     type Index = S::Index;
     type Sample = S::Sample;
     type Texture = S::Texture;
@@ -132,13 +197,17 @@ impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Tiled<S> {
         self.0.raw_texture()
     }
     fn sample(&self, index: [Self::Index; N]) -> Self::Sample {
-        let index = index.map(|e| e.rem_euclid(1.0));
+        let index = index.map(|e| e.rem_euclid_by(S::Index::ONE));
         self.0.sample(index)
     }
     unsafe fn sample_unchecked(&self, index: [Self::Index; N]) -> Self::Sample {
-        let index = index.map(|e| e.rem_euclid(1.0));
+        let index = index.map(|e| e.rem_euclid_by(S::Index::ONE));
         self.0.sample_unchecked(index)
     }
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        let index = index.map(|e| e.rem_euclid_by(S::Index::ONE));
+        self.0.sample_lod(index, lod)
+    }
 }
 
 /// A sampler that tiles the index's components, repeating the sampler when sampling out-of-bounds, but mirroring the
@@ -148,7 +217,10 @@ impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Tiled<S> {
 #[derive(Copy, Clone)]
 pub struct Mirrored<S>(S);
 
-impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Mirrored<S> {
+impl<S: Sampler<N>, const N: usize> Sampler<N> for Mirrored<S>
+where
+    S::Index: Float,
+{
     type Index = S::Index;
     type Sample = S::Sample;
     type Texture = S::Texture;
@@ -158,22 +230,170 @@ impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Mirrored<S> {
     }
     fn sample(&self, index: [Self::Index; N]) -> Self::Sample {
         let index = index.map(|e| {
-            if e.rem_euclid(2.0) >= 1.0 {
-                1.0 - e.rem_euclid(1.0)
+            if e.rem_euclid_by(S::Index::TWO) >= S::Index::ONE {
+                S::Index::ONE - e.rem_euclid_by(S::Index::ONE)
             } else {
-                e.rem_euclid(1.0)
+                e.rem_euclid_by(S::Index::ONE)
             }
         });
         self.0.sample(index)
     }
     unsafe fn sample_unchecked(&self, index: [Self::Index; N]) -> Self::Sample {
         let index = index.map(|e| {
-            if e.rem_euclid(2.0) >= 1.0 {
-                1.0 - e.rem_euclid(1.0)
+            if e.rem_euclid_by(S::Index::TWO) >= S::Index::ONE {
+                S::Index::ONE - e.rem_euclid_by(S::Index::ONE)
             } else {
-                e.rem_euclid(1.0)
+                e.rem_euclid_by(S::Index::ONE)
             }
         });
         self.0.sample_unchecked(index)
     }
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        let index = index.map(|e| {
+            if e.rem_euclid_by(S::Index::TWO) >= S::Index::ONE {
+                S::Index::ONE - e.rem_euclid_by(S::Index::ONE)
+            } else {
+                e.rem_euclid_by(S::Index::ONE)
+            }
+        });
+        self.0.sample_lod(index, lod)
+    }
+}
+
+/// A sampler that maps indices between `0.0` and `1.0` to a region of another sampler, such as a sprite within a
+/// texture atlas.
+///
+/// Indices are remapped before being passed on, so wrapping this sampler with [`Tiled`] or [`Clamped`] tiles or clamps
+/// within the region, while wrapping them with this sampler does so across the whole texture.
+///
+/// See [`Sampler::atlas`].
+#[derive(Copy, Clone)]
+pub struct Atlas<S, const N: usize> {
+    sampler: S,
+    uv_min: [f32; N],
+    uv_max: [f32; N],
+}
+
+impl<S: Sampler<N, Index = f32>, const N: usize> Atlas<S, N> {
+    #[inline(always)]
+    fn remap(&self, index: [f32; N]) -> [f32; N] {
+        core::array::from_fn(|i| self.uv_min[i] + index[i] * (self.uv_max[i] - self.uv_min[i]))
+    }
+}
+
+impl<S: Sampler<N, Index = f32>, const N: usize> Sampler<N> for Atlas<S, N> {
+    type Index = S::Index;
+    type Sample = S::Sample;
+    type Texture = S::Texture;
+
+    fn raw_texture(&self) -> &Self::Texture {
+        self.sampler.raw_texture()
+    }
+    fn sample(&self, index: [Self::Index; N]) -> Self::Sample {
+        self.sampler.sample(self.remap(index))
+    }
+    unsafe fn sample_unchecked(&self, index: [Self::Index; N]) -> Self::Sample {
+        self.sampler.sample_unchecked(self.remap(index))
+    }
+    fn sample_lod(&self, index: [Self::Index; N], lod: f32) -> Self::Sample {
+        self.sampler.sample_lod(self.remap(index), lod)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer2d;
+
+    /// An 8x8 texture whose texels are their own indices.
+    fn coords() -> Buffer2d<[usize; 2]> {
+        let mut tex = Buffer2d::fill([8, 8], [0; 2]);
+        for y in 0..8 {
+            for x in 0..8 {
+                *tex.get_mut([x, y]) = [x, y];
+            }
+        }
+        tex
+    }
+
+    #[test]
+    fn atlas_region() {
+        let tex = coords();
+        // The 4x2 texels starting at [4, 2]
+        let sprite = (&tex).nearest().atlas([0.5, 0.25], [1.0, 0.5]);
+        assert_eq!(sprite.sample([0.5, 0.5]), [6, 3]);
+        assert_eq!(sprite.sample([0.0, 0.0]), [4, 2]);
+        assert_eq!(sprite.sample([0.99, 0.99]), [7, 3]);
+        assert_eq!((&tex).nearest().sample([0.5, 0.5]), [4, 4]);
+    }
+
+    #[test]
+    fn atlas_composes() {
+        let tex = coords();
+        let sprite = (&tex).nearest().atlas([0.5, 0.25], [1.0, 0.5]);
+        // Tiling and clamping apply within the region
+        assert_eq!((&sprite).tiled().sample([1.5, -0.5]), [6, 3]);
+        assert_eq!((&sprite).clamped().sample([-1.0, 0.0]), [4, 2]);
+        // ...or across the whole texture, if they wrap the underlying sampler instead
+        let sprite = (&tex).nearest().tiled().atlas([0.5, 0.25], [1.5, 0.5]);
+        assert_eq!(sprite.sample([0.75, 0.0]), [2, 2]);
+    }
+
+    #[test]
+    fn f64_indices() {
+        let tex = coords();
+        let sampler = Nearest::<_, f64>::new(&tex).tiled();
+        assert_eq!(sampler.sample([0.5, 0.25]), [4, 2]);
+        // Indices beyond `1.0` (or below `0.0`) wrap around
+        assert_eq!(sampler.sample([1.5, 3.25]), [4, 2]);
+        assert_eq!(sampler.sample([-0.5, -1.75]), [4, 2]);
+        // Even far from the origin, where `f32` can't distinguish between neighbouring texels
+        assert_eq!(sampler.sample([10_000_000.5, 1.0e9 + 0.125]), [4, 1]);
+        assert_eq!(
+            (&tex).nearest().tiled().sample([10_000_000.5, 0.125]),
+            [0, 1]
+        );
+
+        let sampler = Nearest::<_, f64>::new(&tex);
+        assert_eq!((&sampler).mirrored().sample([1.25, 0.25]), [6, 2]);
+        assert_eq!(sampler.clamped().sample([2.0, -1.0]), [7, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "half")]
+    fn half_texels() {
+        use crate::math::Half;
+        use half::f16;
+
+        // A smooth HDR gradient, beyond the range of `[0, 1]`
+        let reference = Buffer2d::fill_with([16, 16], {
+            let mut i = 0;
+            move || {
+                i += 1;
+                let [x, y] = [(i - 1) % 16, (i - 1) / 16].map(|e| e as f32);
+                x * 4.0 + x * y * 0.25
+            }
+        });
+        let half = Buffer2d::from_texture(&(&reference).map(Half::<f16>::from));
+        let half_rgb = Buffer2d::from_texture(&(&reference).map(|e| Half::from([e, -e, 0.5])));
+
+        for (x, y) in [(0.0, 0.0), (0.37, 0.81), (0.5, 0.5), (0.93, 0.12)] {
+            let expected = (&reference).linear().sample([x, y]);
+            // `f16` has 11 bits of precision, and several roundings happen while blending
+            let close = |texel: f32, expected: f32| {
+                assert!(
+                    (texel - expected).abs() <= expected.abs().max(1.0) * 4.0e-3,
+                    "{} at {:?}, expected {}",
+                    texel,
+                    [x, y],
+                    expected
+                )
+            };
+            close((&half).linear().sample([x, y]).into(), expected);
+            let [r, g, b]: [f32; 3] = (&half_rgb).linear().sample([x, y]).into();
+            close(r, expected);
+            close(g, -expected);
+            close(b, 0.5);
+        }
+    }
 }
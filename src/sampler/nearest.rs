@@ -7,6 +7,17 @@ pub struct Nearest<T, I = f32> {
     pub(crate) phantom: PhantomData<I>,
 }
 
+impl<T, I> Nearest<T, I> {
+    /// Create a new nearest-neighbour sampler from the given texture, indexed with `I` (such as `f64`, for more
+    /// precision than [`Texture::nearest`] offers).
+    pub fn new(texture: T) -> Self {
+        Self {
+            texture,
+            phantom: PhantomData,
+        }
+    }
+}
+
 impl<T, I, const N: usize> Sampler<N> for Nearest<T, I>
 where
     T: Texture<N>,
@@ -1,5 +1,11 @@
-use super::sampler::{Linear, Nearest};
-use core::marker::PhantomData;
+use super::sampler::{Comparison, Cubic, CubicFilter, Linear, LinearLayer, Nearest, NearestLayer};
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "micromath")]
+use micromath::F32Ext;
 
 /// A trait implemented by types that may be treated as textures.
 pub trait Texture<const N: usize> {
@@ -58,15 +64,25 @@ pub trait Texture<const N: usize> {
     /// See [`Linear`].
     fn linear(self) -> Linear<Self>
     where
-        Self: Texture<2, Index = usize> + Sized,
+        Self: Texture<N, Index = usize> + Sized,
     {
         assert!(
-            <Self as Texture<2>>::size(&self)[0] >= 1 && <Self as Texture<2>>::size(&self)[1] >= 1,
+            self.size().iter().all(|e| *e >= 1),
             "Linearly-interpolated texture cannot have no size",
         );
         Linear(self, PhantomData)
     }
 
+    /// Create a bicubically interpolated sampler from this 2D texture, using a Catmull-Rom spline.
+    ///
+    /// See [`Cubic`]. Use [`Cubic::new`] to choose a different [`CubicFilter`].
+    fn cubic(self) -> Cubic<Self>
+    where
+        Self: Texture<2, Index = usize> + Sized,
+    {
+        Cubic::new(self, CubicFilter::CatmullRom)
+    }
+
     /// Create a nearest-neighbour (i.e: unfiltered) sampler from this texture.
     ///
     /// See [`Nearest`].
@@ -80,6 +96,63 @@ pub trait Texture<const N: usize> {
         }
     }
 
+    /// Create a comparison sampler from this depth texture that performs percentage-closer filtering with a
+    /// `kernel` x `kernel` grid of texels.
+    ///
+    /// See [`Comparison`].
+    fn compare(self, test: core::cmp::Ordering, kernel: usize) -> Comparison<Self>
+    where
+        Self: Texture<2, Index = usize> + Sized,
+    {
+        Comparison {
+            texture: self,
+            test,
+            kernel,
+            filtered: false,
+        }
+    }
+
+    /// Create a comparison sampler from this depth texture that compares against the four texels nearest to the sampled
+    /// position and bilinearly filters the results (like a `samplerShadow` with linear filtering on a GPU).
+    ///
+    /// This is equivalent to `self.compare(test, 1).filtered()`. See [`Comparison`].
+    fn pcf(self, test: core::cmp::Ordering) -> Comparison<Self>
+    where
+        Self: Texture<2, Index = usize> + Sized,
+    {
+        Texture::<2>::compare(self, test, 1).filtered()
+    }
+
+    /// Create a nearest-neighbour sampler that samples within the layers of this 3D texture (such as a
+    /// [`crate::TextureArray`]).
+    ///
+    /// See [`NearestLayer`].
+    fn nearest_layer(self) -> NearestLayer<Self>
+    where
+        Self: Texture<3, Index = usize> + Sized,
+    {
+        assert!(
+            <Self as Texture<3>>::size(&self).iter().all(|e| *e >= 1),
+            "Layered texture cannot have no size",
+        );
+        NearestLayer(self)
+    }
+
+    /// Create a bilinearly interpolated sampler that samples within the layers of this 3D texture (such as a
+    /// [`crate::TextureArray`]), without blending between layers.
+    ///
+    /// See [`LinearLayer`].
+    fn linear_layer(self) -> LinearLayer<Self>
+    where
+        Self: Texture<3, Index = usize> + Sized,
+    {
+        assert!(
+            <Self as Texture<3>>::size(&self).iter().all(|e| *e >= 1),
+            "Layered texture cannot have no size",
+        );
+        LinearLayer(self)
+    }
+
     /// Map the texels of this texture to another type using a mapping function.
     fn map<F, U>(self, f: F) -> Map<Self, F, U>
     where
@@ -92,9 +165,115 @@ pub trait Texture<const N: usize> {
             phantom: PhantomData,
         }
     }
+
+    /// Decode the sRGB-encoded texels of this texture into linear space.
+    ///
+    /// Because texels are decoded as they are read, samplers created from the result (such as with
+    /// [`Texture::linear`]) filter in linear space.
+    ///
+    /// See [`Srgb`].
+    fn srgb(self) -> Srgb<Self>
+    where
+        Self::Texel: AsMut<[f32]>,
+        Self: Sized,
+    {
+        Srgb(self)
+    }
+
+    /// Decode the 8-bit sRGB-encoded texels of this texture (such as those of a PNG image) into linear colours of type
+    /// `C`.
+    ///
+    /// Choosing a colour type that supports arithmetic (such as `vek::Rgba<f32>`) allows samplers created from the
+    /// result (such as with [`Texture::linear`]) to filter in linear space.
+    ///
+    /// See [`Srgb8`].
+    fn srgb8<C>(self) -> Srgb8<Self, C>
+    where
+        Self::Texel: Into<[u8; 4]>,
+        C: From<[f32; 4]>,
+        Self: Sized,
+    {
+        Srgb8(self, PhantomData)
+    }
+
+    /// Reorder the first 4 channels of each texel of this texture as they are read (for example, from BGRA to RGBA
+    /// with `[2, 1, 0, 3]`), such that channel `i` of each texel is channel `order[i]` of the original texel.
+    ///
+    /// Texels with fewer than 4 channels (such as `[u8; 3]`) only reorder the channels they have, using the leading
+    /// components of `order`.
+    ///
+    /// See [`Swizzle`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if any component of `order` is not less than `4`. Reading a texel with fewer than 4 channels panics if
+    /// the components of `order` used for it refer to channels the texel doesn't have.
+    fn swizzle<C>(self, order: [usize; 4]) -> Swizzle<Self, C>
+    where
+        Self::Texel: AsMut<[C]>,
+        C: Copy,
+        Self: Sized,
+    {
+        assert!(
+            order.iter().all(|i| *i < 4),
+            "Swizzle order {:?} refers to channels beyond the 4th",
+            order
+        );
+        Swizzle(self, order, PhantomData)
+    }
+
+    /// Look up the index held by each texel of this texture (such as a `u8` of an indexed-colour image) in a palette
+    /// of colours, as it is read.
+    ///
+    /// Indices are never interpolated, even by filtering samplers like [`Texture::linear`], which instead blend the
+    /// colours that the indices refer to.
+    ///
+    /// See [`Palette`].
+    fn palette<P, C>(self, palette: P) -> Palette<Self, P, C>
+    where
+        Self::Texel: Into<usize>,
+        P: AsRef<[C]>,
+        C: Clone,
+        Self: Sized,
+    {
+        Palette(self, palette, PhantomData)
+    }
+
+    /// Borrow a rectangular region of this texture (such as a single sprite of a sprite sheet) as a texture in its own
+    /// right, without copying its texels.
+    ///
+    /// The texel at `offset` becomes the first texel of the slice, so samplers created from the result (such as with
+    /// [`Texture::nearest`]) map the whole `0..1` range of texture coordinates onto the region alone. Unlike
+    /// [`crate::Atlas`], filtering samplers never blend in texels from outside of the region.
+    ///
+    /// See [`Slice`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region does not lie entirely within the texture.
+    fn slice(self, offset: [usize; N], size: [usize; N]) -> Slice<Self, N>
+    where
+        Self: Texture<N, Index = usize> + Sized,
+    {
+        let tex_size = self.size();
+        assert!(
+            (0..N).all(|i| offset[i]
+                .checked_add(size[i])
+                .is_some_and(|end| end <= tex_size[i])),
+            "Slice at {:?} with size {:?} does not lie within a texture of size {:?}",
+            offset,
+            size,
+            tex_size,
+        );
+        Slice {
+            texture: self,
+            offset,
+            size,
+        }
+    }
 }
 
-impl<'a, T: Texture<N>, const N: usize> Texture<N> for &'a T {
+impl<T: Texture<N>, const N: usize> Texture<N> for &T {
     type Index = T::Index;
     type Texel = T::Texel;
     #[inline(always)]
@@ -115,7 +294,7 @@ impl<'a, T: Texture<N>, const N: usize> Texture<N> for &'a T {
     }
 }
 
-impl<'a, T: Texture<N>, const N: usize> Texture<N> for &'a mut T {
+impl<T: Texture<N>, const N: usize> Texture<N> for &mut T {
     type Index = T::Index;
     type Texel = T::Texel;
     #[inline(always)]
@@ -175,6 +354,291 @@ impl<T: Texture<N>, U: Clone, F: Fn(T::Texel) -> U, const N: usize> Texture<N> f
     }
 }
 
+/// Convert a single sRGB-encoded colour channel (in the range 0 to 1) to linear space.
+///
+/// This uses the exact piecewise sRGB transfer function rather than a gamma approximation.
+#[inline]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a single linear colour channel (in the range 0 to 1) to sRGB-encoded space.
+///
+/// This is the inverse of [`srgb_to_linear`].
+#[inline]
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// A texture that decodes the sRGB-encoded texels of another texture into linear space.
+///
+/// The first three channels of each texel (red, green, and blue) have the sRGB transfer function removed, and any
+/// further channels (such as alpha) are left untouched. Texels are expected to be normalised to the 0 to 1 range.
+///
+/// See [`Texture::srgb`].
+#[derive(Copy, Clone, Debug)]
+pub struct Srgb<T>(T);
+
+impl<T, const N: usize> Texture<N> for Srgb<T>
+where
+    T: Texture<N>,
+    T::Texel: AsMut<[f32]>,
+{
+    type Index = T::Index;
+    type Texel = T::Texel;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; N] {
+        self.0.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; N]> {
+        self.0.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; N]) -> Self::Texel {
+        decode_srgb(self.0.read(index))
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; N]) -> Self::Texel {
+        decode_srgb(self.0.read_unchecked(index))
+    }
+}
+
+#[inline(always)]
+fn decode_srgb<T: AsMut<[f32]>>(mut texel: T) -> T {
+    texel
+        .as_mut()
+        .iter_mut()
+        .take(3)
+        .for_each(|c| *c = srgb_to_linear(*c));
+    texel
+}
+
+/// A texture that decodes the 8-bit sRGB-encoded texels of another texture into linear colours.
+///
+/// The red, green, and blue channels of each texel have the sRGB transfer function removed (using a lookup table, see
+/// [`crate::pixel::from_srgb8`]), and the alpha channel is normalised to the 0 to 1 range. Texels of
+/// [`image::Rgba<u8>`] images can be converted to `[u8; 4]` with `texture.map(|px| px.0)`.
+///
+/// See [`Texture::srgb8`].
+pub struct Srgb8<T, C = [f32; 4]>(T, PhantomData<fn() -> C>);
+
+impl<T: Clone, C> Clone for Srgb8<T, C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<T: Copy, C> Copy for Srgb8<T, C> {}
+
+impl<T, C, const N: usize> Texture<N> for Srgb8<T, C>
+where
+    T: Texture<N>,
+    T::Texel: Into<[u8; 4]>,
+    C: Clone + From<[f32; 4]>,
+{
+    type Index = T::Index;
+    type Texel = C;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; N] {
+        self.0.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; N]> {
+        self.0.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; N]) -> Self::Texel {
+        C::from(decode_srgb8(self.0.read(index).into()))
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; N]) -> Self::Texel {
+        C::from(decode_srgb8(self.0.read_unchecked(index).into()))
+    }
+}
+
+/// A texture that reorders the first 4 channels (or fewer, if they have fewer) of the texels of another texture
+/// (such as `[u8; 4]` or `vek::Rgba<u8>` texels) as they are read.
+///
+/// See [`Texture::swizzle`].
+pub struct Swizzle<T, C = u8>(T, [usize; 4], PhantomData<fn() -> C>);
+
+impl<T: Clone, C> Clone for Swizzle<T, C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1, PhantomData)
+    }
+}
+
+impl<T: Copy, C> Copy for Swizzle<T, C> {}
+
+impl<T, C, const N: usize> Texture<N> for Swizzle<T, C>
+where
+    T: Texture<N>,
+    T::Texel: AsMut<[C]>,
+    C: Copy,
+{
+    type Index = T::Index;
+    type Texel = T::Texel;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; N] {
+        self.0.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; N]> {
+        self.0.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; N]) -> Self::Texel {
+        swizzle(self.0.read(index), self.1)
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; N]) -> Self::Texel {
+        swizzle(self.0.read_unchecked(index), self.1)
+    }
+}
+
+#[inline(always)]
+fn swizzle<T: AsMut<[C]>, C: Copy>(mut texel: T, order: [usize; 4]) -> T {
+    let channels = texel.as_mut();
+    // Texels with fewer than 4 channels only reorder the channels they have
+    let n = channels.len().min(4);
+    if let Some(&first) = channels.first() {
+        let mut original = [first; 4];
+        original[..n].copy_from_slice(&channels[..n]);
+        channels[..n].iter_mut().zip(order).for_each(|(c, i)| {
+            assert!(
+                i < n,
+                "Swizzle order {:?} refers to channels beyond the texel's {} channels",
+                order,
+                n
+            );
+            *c = original[i]
+        });
+    }
+    texel
+}
+
+/// A texture whose texels are the colours of a palette (such as a `[C; 256]` or a `Vec<C>`), chosen by the indices
+/// held by the texels of another texture.
+///
+/// See [`Texture::palette`].
+///
+/// # Panics
+///
+/// Reading a texel panics if its index lies beyond the end of the palette.
+pub struct Palette<T, P, C>(T, P, PhantomData<fn() -> C>);
+
+impl<T: Clone, P: Clone, C> Clone for Palette<T, P, C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), self.1.clone(), PhantomData)
+    }
+}
+
+impl<T: Copy, P: Copy, C> Copy for Palette<T, P, C> {}
+
+impl<T, P, C, const N: usize> Texture<N> for Palette<T, P, C>
+where
+    T: Texture<N>,
+    T::Texel: Into<usize>,
+    P: AsRef<[C]>,
+    C: Clone,
+{
+    type Index = T::Index;
+    type Texel = C;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; N] {
+        self.0.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; N]> {
+        self.0.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; N]) -> Self::Texel {
+        self.1.as_ref()[self.0.read(index).into()].clone()
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; N]) -> Self::Texel {
+        // The indices held by the texture aren't covered by the safety contract, so they are still checked
+        self.1.as_ref()[self.0.read_unchecked(index).into()].clone()
+    }
+}
+
+/// A texture made up of a rectangular region of the texels of another texture.
+///
+/// See [`Texture::slice`].
+///
+/// # Panics
+///
+/// Reading a texel with [`Texture::read`] panics if its index lies beyond the bounds of the slice.
+#[derive(Copy, Clone, Debug)]
+pub struct Slice<T, const N: usize> {
+    texture: T,
+    offset: [usize; N],
+    size: [usize; N],
+}
+
+impl<T, const N: usize> Slice<T, N> {
+    /// Get the position of the first texel of the slice within the underlying texture.
+    pub fn offset(&self) -> [usize; N] {
+        self.offset
+    }
+
+    /// Unwrap the underlying texture.
+    pub fn into_inner(self) -> T {
+        self.texture
+    }
+}
+
+impl<T: Texture<N, Index = usize>, const N: usize> Texture<N> for Slice<T, N> {
+    type Index = usize;
+    type Texel = T::Texel;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; N] {
+        self.size
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; N]> {
+        self.texture.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; N]) -> Self::Texel {
+        assert!(
+            (0..N).all(|i| index[i] < self.size[i]),
+            "Index {:?} is out of bounds of a slice of size {:?}",
+            index,
+            self.size,
+        );
+        // Safe, because the slice lies within the texture (see `Texture::slice`)
+        unsafe { self.read_unchecked(index) }
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; N]) -> Self::Texel {
+        self.texture
+            .read_unchecked(core::array::from_fn(|i| self.offset[i] + index[i]))
+    }
+}
+
+#[inline(always)]
+fn decode_srgb8([r, g, b, a]: [u8; 4]) -> [f32; 4] {
+    use crate::pixel::{from_srgb8, from_unorm8};
+    [from_srgb8(r), from_srgb8(g), from_srgb8(b), from_unorm8(a)]
+}
+
+#[inline(always)]
+fn encode_srgb8([r, g, b, a]: [f32; 4]) -> [u8; 4] {
+    use crate::pixel::{to_srgb8, to_unorm8};
+    [to_srgb8(r), to_srgb8(g), to_srgb8(b), to_unorm8(a)]
+}
+
 // impl<'a, T: Clone, F: Fn([usize; N]) -> T, const N: usize> Texture<N> for (F, [usize; N], PhantomData<T>) {
 //     type Index = usize;
 //     type Texel = T;
@@ -256,6 +720,16 @@ pub trait Target: Texture<2, Index = usize> {
             }
         }
     }
+
+    /// Conservative bounds (lowest, then highest) on the texels within the given region (`min` inclusive, `max`
+    /// exclusive), if the target keeps track of them.
+    ///
+    /// This is used for hierarchical depth testing (see [`crate::HiZ`]). The default implementation returns `None`.
+    #[inline]
+    fn texel_bounds(&self, min: [usize; 2], max: [usize; 2]) -> Option<[Self::Texel; 2]> {
+        let _ = (min, max);
+        None
+    }
 }
 
 impl<T: Target> Target for &mut T {
@@ -279,10 +753,271 @@ impl<T: Target> Target for &mut T {
     fn clear(&mut self, texel: Self::Texel) {
         T::clear(self, texel);
     }
+    #[inline(always)]
+    fn texel_bounds(&self, min: [usize; 2], max: [usize; 2]) -> Option<[Self::Texel; 2]> {
+        T::texel_bounds(self, min, max)
+    }
+}
+
+/// A target that encodes linear colours as 8-bit sRGB when they are written to another target with 8-bit texels.
+///
+/// This is the counterpart of [`Srgb8`]: pipelines that render to it work entirely with linear colours (of type `C`,
+/// such as `[f32; 4]`), and texels are decoded back into linear space when read (such as for blending). The red,
+/// green, and blue channels are encoded with the exact sRGB transfer function (see [`crate::pixel::to_srgb8`]), and
+/// the alpha channel is left linear.
+pub struct SrgbWrite<T, C = [f32; 4]>(T, PhantomData<fn() -> C>);
+
+impl<T, C> SrgbWrite<T, C> {
+    /// Wrap a target with 8-bit texels, such as a `Buffer2d<[u8; 4]>`.
+    pub fn new(target: T) -> Self {
+        Self(target, PhantomData)
+    }
+
+    /// Unwrap the underlying target.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, C> Texture<2> for SrgbWrite<T, C>
+where
+    T: Target,
+    T::Texel: Into<[u8; 4]> + From<[u8; 4]>,
+    C: Into<[f32; 4]> + From<[f32; 4]> + Clone,
+{
+    type Index = usize;
+    type Texel = C;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 2] {
+        self.0.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; 2]> {
+        self.0.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; 2]) -> Self::Texel {
+        C::from(decode_srgb8(self.0.read(index).into()))
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; 2]) -> Self::Texel {
+        C::from(decode_srgb8(self.0.read_unchecked(index).into()))
+    }
+}
+
+impl<T, C> Target for SrgbWrite<T, C>
+where
+    T: Target,
+    T::Texel: Into<[u8; 4]> + From<[u8; 4]>,
+    C: Into<[f32; 4]> + From<[f32; 4]> + Clone,
+{
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        C::from(decode_srgb8(self.0.read_exclusive_unchecked(x, y).into()))
+    }
+    #[inline(always)]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        self.0
+            .write_exclusive_unchecked(x, y, encode_srgb8(texel.into()).into())
+    }
+    #[inline(always)]
+    unsafe fn write_unchecked(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        self.0
+            .write_unchecked(x, y, encode_srgb8(texel.into()).into())
+    }
+    #[inline(always)]
+    fn write(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        self.0.write(x, y, encode_srgb8(texel.into()).into());
+    }
+    #[inline(always)]
+    fn clear(&mut self, texel: Self::Texel) {
+        self.0.clear(encode_srgb8(texel.into()).into());
+    }
+}
+
+/// A target that keeps track of the region of another target that has been written to since it was last checked.
+///
+/// This is useful for presenting only the part of a target that changed, such as when redrawing a terminal. Writes
+/// grow a bounding box of dirty texels, which is fetched (and reset) with [`DirtyTracked::take_dirty`]. The bounding box
+/// is updated with relaxed atomic operations, and only when a write lies outside of it, so rendering to the target
+/// from several threads at once does not contend on it.
+pub struct DirtyTracked<T> {
+    target: T,
+    /// The dirty region, as `[min_x, min_y, max_x, max_y]` (the maximum being exclusive). The region is empty while
+    /// the minimum is greater than the maximum.
+    dirty: [AtomicUsize; 4],
+}
+
+impl<T> DirtyTracked<T> {
+    /// Wrap a target, with none of it being dirty.
+    pub fn new(target: T) -> Self {
+        Self {
+            target,
+            dirty: [usize::MAX, usize::MAX, 0, 0].map(AtomicUsize::new),
+        }
+    }
+
+    /// Get a reference to the underlying target.
+    pub fn inner(&self) -> &T {
+        &self.target
+    }
+
+    /// Unwrap the underlying target.
+    pub fn into_inner(self) -> T {
+        self.target
+    }
+
+    /// Fetch the region that has been written to since the last call, as `[min_x, min_y, max_x, max_y]` (where the
+    /// maximum is exclusive), or `None` if nothing has been written to. The region is then reset.
+    pub fn take_dirty(&mut self) -> Option<[usize; 4]> {
+        let [min_x, min_y, max_x, max_y] = [0, 1, 2, 3].map(|i| {
+            core::mem::replace(self.dirty[i].get_mut(), if i < 2 { usize::MAX } else { 0 })
+        });
+        (min_x < max_x && min_y < max_y).then_some([min_x, min_y, max_x, max_y])
+    }
+
+    /// Grow the dirty region to include the texels within the given region (`min` inclusive, `max` exclusive).
+    #[inline(always)]
+    fn mark(&self, min: [usize; 2], max: [usize; 2]) {
+        // Only update the bounds when they grow: writes within the dirty region (by far the most common case) then
+        // only ever read them, so threads don't contend over them.
+        for i in 0..2 {
+            if min[i] < self.dirty[i].load(Ordering::Relaxed) {
+                self.dirty[i].fetch_min(min[i], Ordering::Relaxed);
+            }
+            if max[i] > self.dirty[2 + i].load(Ordering::Relaxed) {
+                self.dirty[2 + i].fetch_max(max[i], Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<T: Texture<2>> Texture<2> for DirtyTracked<T> {
+    type Index = T::Index;
+    type Texel = T::Texel;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 2] {
+        self.target.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; 2]> {
+        self.target.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; 2]) -> Self::Texel {
+        self.target.read(index)
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; 2]) -> Self::Texel {
+        self.target.read_unchecked(index)
+    }
+}
+
+impl<T: Target> Target for DirtyTracked<T> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        self.target.read_exclusive_unchecked(x, y)
+    }
+    #[inline(always)]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        self.mark([x, y], [x + 1, y + 1]);
+        self.target.write_exclusive_unchecked(x, y, texel)
+    }
+    #[inline(always)]
+    unsafe fn write_unchecked(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        self.mark([x, y], [x + 1, y + 1]);
+        self.target.write_unchecked(x, y, texel)
+    }
+    #[inline(always)]
+    fn write(&mut self, x: usize, y: usize, texel: Self::Texel) {
+        let [w, h] = self.target.size();
+        if x < w && y < h {
+            self.mark([x, y], [x + 1, y + 1]);
+        }
+        self.target.write(x, y, texel)
+    }
+    #[inline(always)]
+    fn clear(&mut self, texel: Self::Texel) {
+        self.mark([0, 0], self.target.size());
+        self.target.clear(texel)
+    }
+    #[inline(always)]
+    fn texel_bounds(&self, min: [usize; 2], max: [usize; 2]) -> Option<[Self::Texel; 2]> {
+        self.target.texel_bounds(min, max)
+    }
+}
+
+/// A target that may be read from, but not written to, wrapping any 2D texture.
+///
+/// This allows a target to be tested against while it is also being sampled by the pipeline, without copying it. For
+/// example, soft particles need to read the depth of the scene in their fragment shader while being depth tested
+/// against it:
+///
+/// ```ignore
+/// let particles = Particles { scene_depth: (&depth).nearest() };
+/// particles.render(vertices, &mut color, &mut ReadOnly::new(&depth));
+/// ```
+///
+/// # Panics
+///
+/// Writing to the target panics, so pipelines that render to it must not write to it (i.e: [`DepthMode::write`] must
+/// be `false` for depth targets).
+///
+/// [`DepthMode::write`]: crate::DepthMode::write
+#[derive(Copy, Clone, Debug)]
+pub struct ReadOnly<T>(T);
+
+impl<T> ReadOnly<T> {
+    /// Wrap a texture, such as a `&Buffer2d<f32>`.
+    pub fn new(texture: T) -> Self {
+        Self(texture)
+    }
+
+    /// Unwrap the underlying texture.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Texture<2>> Texture<2> for ReadOnly<T> {
+    type Index = T::Index;
+    type Texel = T::Texel;
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 2] {
+        self.0.size()
+    }
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; 2]> {
+        self.0.preferred_axes()
+    }
+    #[inline(always)]
+    fn read(&self, index: [Self::Index; 2]) -> Self::Texel {
+        self.0.read(index)
+    }
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, index: [Self::Index; 2]) -> Self::Texel {
+        self.0.read_unchecked(index)
+    }
+}
+
+impl<T: Texture<2, Index = usize>> Target for ReadOnly<T> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        // Nothing can write to the texture while it's borrowed, so shared reads are enough
+        self.0.read_unchecked([x, y])
+    }
+    #[inline]
+    unsafe fn write_exclusive_unchecked(&self, _: usize, _: usize, _: Self::Texel) {
+        panic!("Cannot write to a read-only target");
+    }
 }
 
 /// An always-empty texture. Useful as a placeholder for an unused target.
-pub struct Empty<T>(core::marker::PhantomData<T>);
+///
+/// The texel type defaults to `f32`, so `Empty` on its own names a placeholder for an unused depth target. Since the
+/// depth target may have any [`DepthTexel`](crate::DepthTexel), a placeholder passed as the depth target needs its
+/// texel type to be spelled out, either as `<Empty>::default()` or with [`Empty::depth`].
+pub struct Empty<T = f32>(core::marker::PhantomData<T>);
 
 impl<T> Empty<T> {
     pub const fn new() -> Self {
@@ -290,6 +1025,13 @@ impl<T> Empty<T> {
     }
 }
 
+impl Empty {
+    /// Create a placeholder for an unused `f32` depth target.
+    pub const fn depth() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Default for Empty<T> {
     fn default() -> Self {
         Self::new()
@@ -343,17 +1085,419 @@ where
     }
 }
 
-// #[cfg(feature = "image")]
-// impl<P, C> Target for image::ImageBuffer<P, C>
-// where
-//     P: image::Pixel + 'static,
-//     C: core::ops::DerefMut<Target = [P::Subpixel]>,
-// {
-//     fn write(&mut self, [x, y]: [usize; 2], texel: Self::Texel) {
-//         self.put_pixel(x as u32, y as u32, texel);
-//     }
+/// A render target that writes directly into an [`image::ImageBuffer`].
+///
+/// Image buffers cannot implement [`Target`] themselves because targets are written to through shared references
+/// while rendering (potentially from several threads at once), which the storage of an image buffer does not permit.
+/// This type borrows the image mutably for as long as it exists, upholding the invariants that [`Target`] requires.
+#[cfg(feature = "image")]
+pub struct ImageTarget<'a, P: image::Pixel> {
+    ptr: *mut P::Subpixel,
+    size: [usize; 2],
+    phantom: PhantomData<&'a mut [P::Subpixel]>,
+}
 
-//     unsafe fn write_unchecked(&mut self, [x, y]: [usize; 2], texel: Self::Texel) {
-//         image::GenericImage::unsafe_put_pixel(self, x as u32, y as u32, texel);
-//     }
-// }
+#[cfg(feature = "image")]
+impl<'a, P: image::Pixel> ImageTarget<'a, P> {
+    /// Create a target that writes into the given image.
+    pub fn new<C>(image: &'a mut image::ImageBuffer<P, C>) -> Self
+    where
+        C: core::ops::DerefMut<Target = [P::Subpixel]>,
+    {
+        let size = [image.width() as usize, image.height() as usize];
+        let channels = P::CHANNEL_COUNT as usize;
+        let subpixels: &mut [P::Subpixel] = image;
+        assert!(subpixels.len() >= size[0] * size[1] * channels);
+        Self {
+            ptr: subpixels.as_mut_ptr(),
+            size,
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    fn offset(&self, x: usize, y: usize) -> usize {
+        (y * self.size[0] + x) * P::CHANNEL_COUNT as usize
+    }
+}
+
+// SAFETY: Same behaviour as a mutable slice upheld
+#[cfg(feature = "image")]
+unsafe impl<P: image::Pixel> Send for ImageTarget<'_, P> where P::Subpixel: Send {}
+#[cfg(feature = "image")]
+unsafe impl<P: image::Pixel> Sync for ImageTarget<'_, P> where P::Subpixel: Sync {}
+
+#[cfg(feature = "image")]
+impl<P: image::Pixel> Texture<2> for ImageTarget<'_, P> {
+    type Index = usize;
+    type Texel = P;
+
+    #[inline(always)]
+    fn size(&self) -> [Self::Index; 2] {
+        self.size
+    }
+
+    #[inline(always)]
+    fn preferred_axes(&self) -> Option<[usize; 2]> {
+        Some([0, 1])
+    }
+
+    #[inline(always)]
+    fn read(&self, [x, y]: [Self::Index; 2]) -> Self::Texel {
+        assert!(
+            x < self.size[0] && y < self.size[1],
+            "Attempted to read image of size {:?} at out-of-bounds location {:?}",
+            self.size,
+            [x, y],
+        );
+        // SAFETY: Bounds checked above
+        unsafe { self.read_exclusive_unchecked(x, y) }
+    }
+
+    #[inline(always)]
+    unsafe fn read_unchecked(&self, [x, y]: [Self::Index; 2]) -> Self::Texel {
+        self.read_exclusive_unchecked(x, y)
+    }
+}
+
+#[cfg(feature = "image")]
+impl<P: image::Pixel> Target for ImageTarget<'_, P> {
+    #[inline(always)]
+    unsafe fn read_exclusive_unchecked(&self, x: usize, y: usize) -> Self::Texel {
+        let channels = P::CHANNEL_COUNT as usize;
+        *P::from_slice(core::slice::from_raw_parts(
+            self.ptr.add(self.offset(x, y)),
+            channels,
+        ))
+    }
+
+    #[inline(always)]
+    unsafe fn write_exclusive_unchecked(&self, x: usize, y: usize, texel: Self::Texel) {
+        // This is safe to do provided the caller has guaranteed exclusive access to the texels being written to, as
+        // per the contractual obligations of this method. The pointer was derived from a mutable borrow of the image,
+        // so writing through it is permitted.
+        let channels = P::CHANNEL_COUNT as usize;
+        *P::from_slice_mut(core::slice::from_raw_parts_mut(
+            self.ptr.add(self.offset(x, y)),
+            channels,
+        )) = texel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::Buffer2d, sampler::Sampler};
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn render_into_image() {
+        use crate::{math::Unit, pipeline::Pipeline, primitives::TriangleList};
+
+        struct Red;
+
+        impl<'r> Pipeline<'r> for Red {
+            type Vertex = [f32; 2];
+            type VertexData = Unit;
+            type Primitives = TriangleList;
+            type Fragment = Unit;
+            type Pixel = image::Rgba<u8>;
+
+            fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                ([pos[0], pos[1], 0.0, 1.0], Unit)
+            }
+            fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+                Unit
+            }
+            fn blend(&self, _: Self::Pixel, _: Self::Fragment) -> Self::Pixel {
+                image::Rgba([255, 0, 0, 255])
+            }
+        }
+
+        let mut img = image::RgbaImage::from_pixel(64, 48, image::Rgba([0, 0, 255, 255]));
+        Red.render(
+            &[[-1.0, -1.0], [1.0, -1.0], [0.0, 1.0]],
+            &mut ImageTarget::new(&mut img),
+            &mut Empty::depth(),
+        );
+
+        assert_eq!(*img.get_pixel(32, 24), image::Rgba([255, 0, 0, 255]));
+        assert_eq!(*img.get_pixel(0, 0), image::Rgba([0, 0, 255, 255]));
+        assert_eq!(*img.get_pixel(63, 0), image::Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn swizzle_channels() {
+        let tex = Buffer2d::fill([2, 2], [10u8, 20, 30, 40]);
+        assert_eq!((&tex).swizzle([1, 2, 3, 0]).read([1, 0]), [20, 30, 40, 10]);
+        // BGRA to RGBA
+        assert_eq!((&tex).swizzle([2, 1, 0, 3]).read([0, 1]), [30, 20, 10, 40]);
+        // Channels may be repeated
+        assert_eq!((&tex).swizzle([0, 0, 0, 3]).read([0, 0]), [10, 10, 10, 40]);
+
+        let tex = Buffer2d::fill([1, 1], vek::Rgba::new(0.25f32, 0.5, 0.75, 1.0));
+        let px = (&tex).swizzle([3, 2, 1, 0]).linear().sample([0.0, 0.0]);
+        assert_eq!(px, vek::Rgba::new(1.0, 0.75, 0.5, 0.25));
+
+        // Texels with fewer than 4 channels only reorder the channels they have
+        let tex = Buffer2d::fill([1, 1], [10u8, 20, 30]);
+        assert_eq!((&tex).swizzle([2, 1, 0, 3]).read([0, 0]), [30, 20, 10]);
+        let tex = Buffer2d::fill([1, 1], [0.25f32, 0.5]);
+        assert_eq!((&tex).swizzle([1, 0, 2, 3]).read([0, 0]), [0.5, 0.25]);
+    }
+
+    #[test]
+    fn palette_lookup() {
+        let mut indices = Buffer2d::fill([2, 2], 0u8);
+        indices.raw_mut().copy_from_slice(&[2, 0, 1, 2]);
+        let palette = [[0u8, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 128]];
+
+        let colors = (&indices).palette(palette);
+        assert_eq!(colors.size(), [2, 2]);
+        assert_eq!(colors.read([0, 0]), palette[2]);
+        assert_eq!(colors.read([1, 0]), palette[0]);
+        assert_eq!(colors.read([0, 1]), palette[1]);
+        assert_eq!(colors.read([1, 1]), palette[2]);
+
+        // Samplers read the colour of the index nearest to the sample
+        let sampler = colors.nearest();
+        assert_eq!(sampler.sample([0.75, 0.25]), palette[0]);
+        assert_eq!(sampler.sample([0.25, 0.75]), palette[1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn palette_out_of_range() {
+        Buffer2d::fill([1, 1], 3u8)
+            .palette([0.0f32; 3])
+            .read([0, 0]);
+    }
+
+    #[test]
+    fn slice_region() {
+        let mut tex = Buffer2d::fill([4, 4], 0u32);
+        tex.raw_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, e)| *e = i as u32);
+
+        let slice = (&tex).slice([1, 2], [3, 2]);
+        assert_eq!(slice.size(), [3, 2]);
+        assert_eq!(slice.read([0, 0]), 9);
+        assert_eq!(slice.read([2, 1]), 15);
+        // Slices of slices offset reads further
+        assert_eq!(slice.slice([1, 1], [2, 1]).read([1, 0]), 15);
+
+        // Samplers span the slice alone, and filtering samplers don't blend texels from beyond its edges
+        let slice = (&tex).map(|e| e as f32).slice([2, 0], [2, 2]);
+        assert_eq!(slice.nearest().sample([0.75, 0.75]), 7.0);
+        assert_eq!(slice.nearest().clamped().sample([-1.0, 2.0]), 6.0);
+        assert_eq!(slice.nearest().tiled().sample([1.25, 0.25]), 2.0);
+        assert_eq!(slice.linear().sample([0.75, 0.75]), 7.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_out_of_range() {
+        Buffer2d::fill([4, 4], 0u8).slice([2, 1], [3, 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_read_out_of_range() {
+        Buffer2d::fill([4, 4], 0u8)
+            .slice([0, 0], [2, 2])
+            .read([2, 0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swizzle_out_of_range() {
+        Buffer2d::fill([1, 1], [0u8; 4]).swizzle([0, 1, 2, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swizzle_missing_channel() {
+        Buffer2d::fill([1, 1], [0u8; 3])
+            .swizzle([0, 1, 3, 2])
+            .read([0, 0]);
+    }
+
+    #[test]
+    fn srgb_decoding() {
+        // Reference values from the sRGB specification
+        for (encoded, linear) in [(0u8, 0.0), (128, 0.215_860_5), (255, 1.0)] {
+            let c = encoded as f32 / 255.0;
+            assert!((srgb_to_linear(c) - linear).abs() < 1.0e-4);
+            assert!((linear_to_srgb(linear) - c).abs() < 1.0e-4);
+
+            let tex = Buffer2d::fill([1, 1], [c, c, c, c]).srgb();
+            let [r, g, b, a] = tex.read([0, 0]);
+            assert!([r, g, b].iter().all(|e| (e - linear).abs() < 1.0e-4));
+            // Alpha is untouched
+            assert_eq!(a, c);
+        }
+    }
+
+    #[test]
+    fn srgb_filters_in_linear_space() {
+        let black = vek::Rgba::<f32>::zero();
+        let white = vek::Rgba::<f32>::one();
+        let tex = Buffer2d::fill_with([2, 1], {
+            let mut texels = [black, white].into_iter();
+            move || texels.next().unwrap()
+        });
+
+        // Halfway between black and white is 0.5 in linear space, not the decoded value of an sRGB 0.5 (about 0.214)
+        // `Linear` places texel `i` at `i / width`, so this is halfway between the two texels
+        let px = tex.srgb().linear().sample([0.25, 0.0]);
+        assert!((px.r - 0.5).abs() < 1.0e-4, "{}", px.r);
+        assert!((px.a - 0.5).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn srgb8_filters_in_linear_space() {
+        let tex = Buffer2d::fill_with([2, 1], {
+            let mut texels = [[0u8; 4], [255; 4]].into_iter();
+            move || texels.next().unwrap()
+        });
+
+        let px = tex.srgb8::<vek::Rgba<f32>>().linear().sample([0.25, 0.0]);
+        assert!((px.r - 0.5).abs() < 1.0e-4, "{}", px.r);
+        assert!((px.a - 0.5).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn srgb_write_round_trip() {
+        let mut target = SrgbWrite::<_>::new(Buffer2d::fill([256, 1], [0u8; 4]));
+        for c in 0..=255u8 {
+            let texel = decode_srgb8([c; 4]);
+            target.write(c as usize, 0, texel);
+            assert_eq!(target.read([c as usize, 0]), texel);
+        }
+        let buffer = target.into_inner();
+        assert!((0..=255u8).all(|c| buffer.read([c as usize, 0]) == [c; 4]));
+
+        // Linear mid-grey is noticeably lighter than 50% once encoded
+        let mut target = SrgbWrite::<_>::new(Buffer2d::fill([1, 1], [0u8; 4]));
+        target.clear([0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(target.into_inner().read([0, 0]), [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn read_only_depth() {
+        use crate::{
+            pipeline::{DepthMode, Pipeline},
+            primitives::TriangleList,
+            sampler::Nearest,
+        };
+
+        /// Reads the depth of the scene behind each fragment, as soft particles do.
+        struct Soft<'a> {
+            scene: Nearest<&'a Buffer2d<f32>>,
+        }
+
+        impl<'r> Pipeline<'r> for Soft<'_> {
+            type Vertex = [f32; 2];
+            type VertexData = vek::Vec2<f32>;
+            type Primitives = TriangleList;
+            type Fragment = f32;
+            type Pixel = f32;
+
+            fn depth_mode(&self) -> DepthMode {
+                DepthMode::LESS_PASS
+            }
+            fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                ([pos[0], pos[1], 0.5, 1.0], vek::Vec2::from(*pos))
+            }
+            fn fragment(&self, pos: Self::VertexData) -> Self::Fragment {
+                // The distance between the fragment and the scene behind it
+                self.scene.sample((pos * 0.5 + 0.5).into_array()) - 0.5
+            }
+            fn blend(&self, _: Self::Pixel, new: Self::Fragment) -> Self::Pixel {
+                new
+            }
+        }
+
+        // The depths of the scene in three columns, the first of which lies in front of the quad
+        let mut depth = Buffer2d::fill([12, 8], 0.3);
+        for y in 0..8 {
+            (4..12).for_each(|x| depth.write(x, y, if x < 8 { 0.6 } else { 0.9 }));
+        }
+        let quad = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+
+        // The depth target is sampled by the pipeline while it's depth tested against, without a copy
+        let mut color = Buffer2d::fill([12, 8], -1.0);
+        Soft {
+            scene: (&depth).nearest(),
+        }
+        .render(
+            [0, 1, 2, 0, 2, 3].map(|i| quad[i]),
+            &mut color,
+            &mut ReadOnly::new(&depth),
+        );
+        // Fragments on the boundaries between columns may sample either side
+        for ([x, _], c) in color.enumerate_pixels().filter(|([x, _], _)| x % 4 != 0) {
+            let expected = [-1.0, 0.1, 0.4][x / 4];
+            assert!((c - expected).abs() < 1.0e-6, "{}, {}", x, c);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot write to a read-only target")]
+    fn read_only_write() {
+        let depth = Buffer2d::fill([4, 4], 1.0);
+        ReadOnly::new(&depth).write(1, 1, 0.5);
+    }
+
+    #[test]
+    fn dirty_tracking() {
+        use crate::{math::Unit, pipeline::Pipeline, primitives::TriangleList};
+
+        struct Fill;
+
+        impl<'r> Pipeline<'r> for Fill {
+            type Vertex = [f32; 2];
+            type VertexData = Unit;
+            type Primitives = TriangleList;
+            type Fragment = Unit;
+            type Pixel = u8;
+
+            fn vertex(&self, pos: &Self::Vertex) -> ([f32; 4], Self::VertexData) {
+                ([pos[0], pos[1], 0.0, 1.0], Unit)
+            }
+            fn fragment(&self, _: Self::VertexData) -> Self::Fragment {
+                Unit
+            }
+            fn blend(&self, _: Self::Pixel, _: Self::Fragment) -> Self::Pixel {
+                1
+            }
+        }
+
+        let mut target = DirtyTracked::new(Buffer2d::fill([64, 48], 0u8));
+        assert_eq!(target.take_dirty(), None);
+
+        let tri = [[-0.5, -0.5], [0.25, -0.5], [0.0, 0.75]];
+        let bounds = Fill.render_bounded(
+            &tri,
+            &mut Buffer2d::fill([64, 48], 0u8),
+            &mut Empty::depth(),
+        );
+        Fill.render(&tri, &mut target, &mut Empty::depth());
+        assert!(bounds.is_some());
+        assert_eq!(target.take_dirty(), bounds);
+        // Fetching the dirty region resets it
+        assert_eq!(target.take_dirty(), None);
+
+        target.write(3, 5, 2);
+        target.write(10, 1, 2);
+        // Out of bounds writes don't touch the target
+        target.write(64, 0, 2);
+        assert_eq!(target.take_dirty(), Some([3, 1, 11, 6]));
+
+        target.clear(0);
+        assert_eq!(target.take_dirty(), Some([0, 0, 64, 48]));
+        assert!(target.into_inner().raw().iter().all(|e| *e == 0));
+    }
+}